@@ -80,7 +80,7 @@ impl ExtractProgramIds for ParsedProgram {
                 continue;
             }
             let parameter1: &InstructionParameter = &instruction.parameter_vec[1];
-            if parameter1.parameter_type != ParameterType::Constant {
+            if parameter1.parameter_type != ParameterType::Constant || parameter1.is_big_constant() {
                 continue;
             }
             let parameter_value_raw: i64 = parameter1.parameter_value;
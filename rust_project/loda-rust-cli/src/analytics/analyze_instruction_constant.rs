@@ -73,7 +73,7 @@ impl AnalyzeInstructionConstant {
                 continue;
             }
             let source_parameter: &InstructionParameter = instruction.parameter_vec.last().unwrap();
-            if source_parameter.parameter_type != ParameterType::Constant {
+            if source_parameter.parameter_type != ParameterType::Constant || source_parameter.is_big_constant() {
                 continue;
             }
             let value: i64 = source_parameter.parameter_value;
@@ -164,6 +164,11 @@ impl AnalyzeLineNgram {
                 if instruction.parameter_vec.len() == 2 {
                     if let Some(parameter) = instruction.parameter_vec.last() {
                         if parameter.parameter_type == ParameterType::Constant {
+                            if parameter.is_big_constant() {
+                                debug!("Encountered a magic value that is beyond 64bit. Ignoring program. Instruction: {}", instruction);
+                                self.ignore_count += 1;
+                                return vec!();
+                            }
                             if parameter.parameter_value < self.limit_lower {
                                 debug!("Encountered a magic value that is lower than {}. Ignoring program. Instruction: {}", self.limit_lower, instruction);
                                 self.ignore_count += 1;
@@ -0,0 +1,421 @@
+use crate::common::{find_asm_files_recursively, load_program_ids_csv_file, oeis_id_from_path, parse_csv_file};
+use loda_rust_core::oeis::OeisId;
+use loda_rust_core::parser::ParsedProgram;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DependencyDirection {
+    /// The programs that are called by a program, via `seq` instructions.
+    Callees,
+
+    /// The programs that call a program. These are the programs that break, when the program changes.
+    Callers,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordDependency {
+    #[serde(rename = "caller program id")]
+    caller_program_id: u32,
+    #[serde(rename = "callee program id")]
+    callee_program_id: u32,
+}
+
+/// The `seq` dependencies between all the programs in the repo.
+///
+/// Can be loaded from the `dependencies.csv` file, created by `AnalyzeDependencies`,
+/// together with the `programs_valid.csv` file, since programs without dependencies are not in the `dependencies.csv` file.
+/// Or from the programs dir, in case the analytics hasn't been run.
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraph {
+    callees: BTreeMap<u32, BTreeSet<u32>>,
+    callers: BTreeMap<u32, BTreeSet<u32>>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the `dependencies.csv` file, with the columns `caller program id` and `callee program id`.
+    pub fn load_csv(path: &Path) -> anyhow::Result<Self> {
+        let records: Vec<RecordDependency> = parse_csv_file(path)
+            .map_err(|e| anyhow::anyhow!("Unable to load dependencies file: {:?} error: {:?}", path, e))?;
+        let mut instance = Self::new();
+        for record in &records {
+            instance.add_dependency(record.caller_program_id, record.callee_program_id);
+        }
+        Ok(instance)
+    }
+
+    /// Register the programs in a csv file with the column `program id`, such as the `programs_valid.csv` file.
+    ///
+    /// This is for programs that neither calls other programs, nor are called by other programs.
+    pub fn add_programs_from_csv(&mut self, path: &Path) -> anyhow::Result<()> {
+        let program_ids: Vec<u32> = load_program_ids_csv_file(path)
+            .map_err(|e| anyhow::anyhow!("Unable to load program ids file: {:?} error: {:?}", path, e))?;
+        for program_id in program_ids {
+            self.add_program(program_id);
+        }
+        Ok(())
+    }
+
+    /// Parse all the programs inside the dir, and extract their direct dependencies.
+    ///
+    /// Programs that cannot be parsed are ignored.
+    pub fn scan_programs(programs_dir: &Path) -> anyhow::Result<Self> {
+        if !programs_dir.is_dir() {
+            return Err(anyhow::anyhow!("Expected dir: {:?}, but it's missing.", programs_dir));
+        }
+        let paths: Vec<PathBuf> = find_asm_files_recursively(programs_dir);
+        let mut instance = Self::new();
+        for path in &paths {
+            let oeis_id: OeisId = match oeis_id_from_path(path) {
+                Some(value) => value,
+                None => continue
+            };
+            let contents: String = match fs::read_to_string(path) {
+                Ok(value) => value,
+                Err(error) => {
+                    debug!("Unable to read program: {:?} error: {:?}", path, error);
+                    continue;
+                }
+            };
+            let parsed_program: ParsedProgram = match ParsedProgram::parse_program(&contents) {
+                Ok(value) => value,
+                Err(error) => {
+                    debug!("Unable to parse program: {:?} error: {:?}", path, error);
+                    continue;
+                }
+            };
+            instance.add_program(oeis_id.raw());
+            for callee_program_id in parsed_program.direct_dependencies() {
+                if let Ok(callee_program_id) = u32::try_from(callee_program_id) {
+                    instance.add_dependency(oeis_id.raw(), callee_program_id);
+                }
+            }
+        }
+        Ok(instance)
+    }
+
+    /// Register a program that may not have any dependencies.
+    pub fn add_program(&mut self, program_id: u32) {
+        self.callees.entry(program_id).or_default();
+        self.callers.entry(program_id).or_default();
+    }
+
+    pub fn add_dependency(&mut self, caller_program_id: u32, callee_program_id: u32) {
+        self.add_program(caller_program_id);
+        self.add_program(callee_program_id);
+        self.callees.entry(caller_program_id).or_default().insert(callee_program_id);
+        self.callers.entry(callee_program_id).or_default().insert(caller_program_id);
+    }
+
+    pub fn contains(&self, program_id: u32) -> bool {
+        self.callees.contains_key(&program_id)
+    }
+
+    /// The programs that are directly adjacent to the program.
+    pub fn neighbors(&self, program_id: u32, direction: DependencyDirection) -> Vec<u32> {
+        let map = match direction {
+            DependencyDirection::Callees => &self.callees,
+            DependencyDirection::Callers => &self.callers,
+        };
+        match map.get(&program_id) {
+            Some(program_ids) => program_ids.iter().cloned().collect(),
+            None => vec!()
+        }
+    }
+
+    /// All the programs that can be reached from the program, with the smallest number of hops to get there.
+    ///
+    /// The program itself is not included, unless it's part of a cycle.
+    ///
+    /// A `max_depth` of `1` is the same as the direct neighbors. A `max_depth` of `None` follows the entire graph.
+    pub fn transitive_closure(&self, program_id: u32, direction: DependencyDirection, max_depth: Option<usize>) -> BTreeMap<u32, usize> {
+        let mut result = BTreeMap::<u32, usize>::new();
+        let mut queue = VecDeque::<(u32, usize)>::new();
+        queue.push_back((program_id, 0));
+        while let Some((current_program_id, depth)) = queue.pop_front() {
+            if max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                continue;
+            }
+            for neighbor_program_id in self.neighbors(current_program_id, direction) {
+                if result.contains_key(&neighbor_program_id) {
+                    continue;
+                }
+                result.insert(neighbor_program_id, depth + 1);
+                queue.push_back((neighbor_program_id, depth + 1));
+            }
+        }
+        result
+    }
+
+    /// Find groups of programs that depend on each other, using Tarjan's strongly connected components algorithm.
+    ///
+    /// Each cycle is sorted by program id, and a program that calls itself is a cycle with a single program.
+    pub fn cycles(&self) -> Vec<Vec<u32>> {
+        let mut tarjan = Tarjan {
+            graph: self,
+            index_counter: 0,
+            indexes: BTreeMap::new(),
+            lowlinks: BTreeMap::new(),
+            stack: vec!(),
+            on_stack: BTreeSet::new(),
+            components: vec!(),
+        };
+        for program_id in self.callees.keys() {
+            if !tarjan.indexes.contains_key(program_id) {
+                tarjan.strongconnect(*program_id);
+            }
+        }
+        let mut cycles: Vec<Vec<u32>> = vec!();
+        for mut component in tarjan.components {
+            let is_cycle: bool = component.len() > 1 || self.neighbors(component[0], DependencyDirection::Callees).contains(&component[0]);
+            if !is_cycle {
+                continue;
+            }
+            component.sort_unstable();
+            cycles.push(component);
+        }
+        cycles.sort();
+        cycles
+    }
+
+    /// The dependencies between the program and the programs in its closure.
+    fn subgraph_edges(&self, program_ids: &BTreeSet<u32>) -> Vec<(u32, u32)> {
+        let mut edges: Vec<(u32, u32)> = vec!();
+        for caller_program_id in program_ids {
+            for callee_program_id in self.neighbors(*caller_program_id, DependencyDirection::Callees) {
+                if program_ids.contains(&callee_program_id) {
+                    edges.push((*caller_program_id, callee_program_id));
+                }
+            }
+        }
+        edges
+    }
+
+    /// Graphviz representation of the program and its closure, with arrows pointing from caller to callee.
+    pub fn to_dot(&self, program_id: u32, closure: &BTreeMap<u32, usize>) -> String {
+        let mut program_ids: BTreeSet<u32> = closure.keys().cloned().collect();
+        program_ids.insert(program_id);
+        let mut rows: Vec<String> = vec!();
+        rows.push("digraph dependencies {".to_string());
+        for id in &program_ids {
+            let name: String = OeisId::from(*id).a_number();
+            if *id == program_id {
+                rows.push(format!("    {} [style=bold];", name));
+            } else {
+                rows.push(format!("    {};", name));
+            }
+        }
+        for (caller_program_id, callee_program_id) in self.subgraph_edges(&program_ids) {
+            rows.push(format!("    {} -> {};", OeisId::from(caller_program_id).a_number(), OeisId::from(callee_program_id).a_number()));
+        }
+        rows.push("}".to_string());
+        rows.join("\n")
+    }
+
+    /// JSON representation of the program and its closure.
+    pub fn to_json(&self, program_id: u32, direction: DependencyDirection, max_depth: Option<usize>, closure: &BTreeMap<u32, usize>) -> anyhow::Result<String> {
+        let mut program_ids: BTreeSet<u32> = closure.keys().cloned().collect();
+        program_ids.insert(program_id);
+        let nodes: Vec<JsonNode> = closure.iter().map(|(id, depth)| {
+            JsonNode {
+                program_id: *id,
+                depth: *depth,
+            }
+        }).collect();
+        let edges: Vec<JsonEdge> = self.subgraph_edges(&program_ids).iter().map(|(caller, callee)| {
+            JsonEdge {
+                caller_program_id: *caller,
+                callee_program_id: *callee,
+            }
+        }).collect();
+        let direction: &str = match direction {
+            DependencyDirection::Callees => "callees",
+            DependencyDirection::Callers => "callers",
+        };
+        let root = JsonRoot {
+            program_id,
+            direction,
+            max_depth,
+            nodes,
+            edges,
+        };
+        Ok(serde_json::to_string_pretty(&root)?)
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRoot<'a> {
+    program_id: u32,
+    direction: &'a str,
+    max_depth: Option<usize>,
+    nodes: Vec<JsonNode>,
+    edges: Vec<JsonEdge>,
+}
+
+#[derive(Serialize)]
+struct JsonNode {
+    program_id: u32,
+    depth: usize,
+}
+
+#[derive(Serialize)]
+struct JsonEdge {
+    caller_program_id: u32,
+    callee_program_id: u32,
+}
+
+struct Tarjan<'a> {
+    graph: &'a DependencyGraph,
+    index_counter: usize,
+    indexes: BTreeMap<u32, usize>,
+    lowlinks: BTreeMap<u32, usize>,
+    stack: Vec<u32>,
+    on_stack: BTreeSet<u32>,
+    components: Vec<Vec<u32>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn strongconnect(&mut self, program_id: u32) {
+        self.indexes.insert(program_id, self.index_counter);
+        self.lowlinks.insert(program_id, self.index_counter);
+        self.index_counter += 1;
+        self.stack.push(program_id);
+        self.on_stack.insert(program_id);
+
+        for callee_program_id in self.graph.neighbors(program_id, DependencyDirection::Callees) {
+            if !self.indexes.contains_key(&callee_program_id) {
+                self.strongconnect(callee_program_id);
+                let lowlink: usize = self.lowlinks[&program_id].min(self.lowlinks[&callee_program_id]);
+                self.lowlinks.insert(program_id, lowlink);
+            } else if self.on_stack.contains(&callee_program_id) {
+                let lowlink: usize = self.lowlinks[&program_id].min(self.indexes[&callee_program_id]);
+                self.lowlinks.insert(program_id, lowlink);
+            }
+        }
+
+        if self.lowlinks[&program_id] != self.indexes[&program_id] {
+            return;
+        }
+        let mut component: Vec<u32> = vec!();
+        while let Some(id) = self.stack.pop() {
+            self.on_stack.remove(&id);
+            component.push(id);
+            if id == program_id {
+                break;
+            }
+        }
+        self.components.push(component);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_graph() -> DependencyGraph {
+        // A000045 (fibonacci) is used by A000071 and A001911.
+        // A000071 is used by A001911.
+        let mut graph = DependencyGraph::new();
+        graph.add_dependency(71, 45);
+        graph.add_dependency(1911, 71);
+        graph.add_dependency(1911, 45);
+        graph.add_dependency(5000, 1911);
+        graph.add_program(40);
+        graph
+    }
+
+    #[test]
+    fn test_10000_transitive_closure_callers() {
+        let graph = mock_graph();
+        let closure: BTreeMap<u32, usize> = graph.transitive_closure(45, DependencyDirection::Callers, None);
+        let expected: BTreeMap<u32, usize> = [(71, 1), (1911, 1), (5000, 2)].into_iter().collect();
+        assert_eq!(closure, expected);
+    }
+
+    #[test]
+    fn test_10001_transitive_closure_max_depth() {
+        let graph = mock_graph();
+        let closure: BTreeMap<u32, usize> = graph.transitive_closure(5000, DependencyDirection::Callees, Some(2));
+        let expected: BTreeMap<u32, usize> = [(1911, 1), (71, 2), (45, 2)].into_iter().collect();
+        assert_eq!(closure, expected);
+        assert!(graph.transitive_closure(5000, DependencyDirection::Callees, Some(0)).is_empty());
+        assert!(graph.transitive_closure(40, DependencyDirection::Callers, None).is_empty());
+    }
+
+    #[test]
+    fn test_20000_cycles() {
+        let mut graph = mock_graph();
+        assert!(graph.cycles().is_empty());
+        graph.add_dependency(45, 1911);
+        graph.add_dependency(7, 7);
+        assert_eq!(graph.cycles(), vec![vec![7], vec![45, 71, 1911]]);
+    }
+
+    #[test]
+    fn test_30000_to_dot() {
+        let graph = mock_graph();
+        let closure: BTreeMap<u32, usize> = graph.transitive_closure(1911, DependencyDirection::Callees, None);
+        let expected = "digraph dependencies {\n    A000045;\n    A000071;\n    A001911 [style=bold];\n    A000071 -> A000045;\n    A001911 -> A000045;\n    A001911 -> A000071;\n}";
+        assert_eq!(graph.to_dot(1911, &closure), expected);
+    }
+
+    #[test]
+    fn test_30001_to_json() {
+        let graph = mock_graph();
+        let closure: BTreeMap<u32, usize> = graph.transitive_closure(71, DependencyDirection::Callers, Some(1));
+        let json: String = graph.to_json(71, DependencyDirection::Callers, Some(1), &closure).expect("json");
+        let value: serde_json::Value = serde_json::from_str(&json).expect("valid json");
+        assert_eq!(value["direction"], "callers");
+        assert_eq!(value["max_depth"], 1);
+        assert_eq!(value["nodes"], serde_json::json!([{"program_id": 1911, "depth": 1}]));
+        assert_eq!(value["edges"], serde_json::json!([{"caller_program_id": 1911, "callee_program_id": 71}]));
+    }
+
+    #[test]
+    fn test_40000_scan_programs() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir: PathBuf = PathBuf::from(&tempdir.path()).join("test_40000_scan_programs");
+        fs::create_dir_all(dir.join("000")).unwrap();
+        fs::write(dir.join("000/A000045.asm"), "mov $1,1\nlpb $0\n  sub $0,1\n  add $1,$2\n  mov $2,$1\nlpe\nmov $0,$2\n").unwrap();
+        fs::write(dir.join("000/A000071.asm"), "add $0,1\nseq $0,45\nsub $0,1\n").unwrap();
+        fs::write(dir.join("000/A000072.asm"), "junk\n").unwrap();
+
+        // Act
+        let graph = DependencyGraph::scan_programs(&dir).expect("graph");
+
+        // Assert
+        assert!(graph.contains(45));
+        assert!(!graph.contains(72));
+        assert_eq!(graph.neighbors(45, DependencyDirection::Callers), vec![71]);
+        assert_eq!(graph.neighbors(71, DependencyDirection::Callees), vec![45]);
+    }
+
+    #[test]
+    fn test_40001_load_csv_with_isolated_programs() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir: PathBuf = PathBuf::from(&tempdir.path()).join("test_40001_load_csv_with_isolated_programs");
+        fs::create_dir_all(&dir).unwrap();
+        let dependencies_file: PathBuf = dir.join("dependencies.csv");
+        let programs_valid_file: PathBuf = dir.join("programs_valid.csv");
+        fs::write(&dependencies_file, "caller program id;callee program id\n71;45\n").unwrap();
+        fs::write(&programs_valid_file, "program id\n40\n45\n71\n").unwrap();
+
+        // Act
+        let mut graph = DependencyGraph::load_csv(&dependencies_file).expect("graph");
+        assert!(!graph.contains(40));
+        graph.add_programs_from_csv(&programs_valid_file).expect("ok");
+
+        // Assert
+        assert!(graph.contains(40));
+        assert!(graph.transitive_closure(40, DependencyDirection::Callers, None).is_empty());
+        assert_eq!(graph.neighbors(71, DependencyDirection::Callees), vec![45]);
+    }
+}
@@ -15,6 +15,7 @@ mod analyze_program_modified;
 mod analyze_target_ngram;
 mod batch_program_analyzer;
 mod deny_file;
+mod dependency_graph;
 mod dont_mine;
 mod histogram_stripped_file;
 mod program_rank;
@@ -36,6 +37,7 @@ pub use analyze_source_ngram::AnalyzeSourceNgram;
 pub use analyze_target_ngram::AnalyzeTargetNgram;
 pub use batch_program_analyzer::{BatchProgramAnalyzer, BatchProgramAnalyzerContext, BatchProgramAnalyzerPlugin, BatchProgramAnalyzerPluginItem};
 pub use deny_file::load_program_ids_from_deny_file;
+pub use dependency_graph::{DependencyDirection, DependencyGraph};
 pub use dont_mine::DontMine;
 pub use histogram_stripped_file::HistogramStrippedFile;
 pub use program_rank::compute_program_rank;
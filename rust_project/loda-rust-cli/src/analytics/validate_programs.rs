@@ -10,7 +10,7 @@ use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
 use std::path::PathBuf;
 use std::collections::HashSet;
 use std::time::Instant;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::fs::File;
 use std::io::Write;
 use std::io::LineWriter;
@@ -132,7 +132,7 @@ impl ValidatePrograms {
         let pb = ProgressBar::new(oeis_ids_len as u64);
         for oeis_id in oeis_ids {
             let program_id64 = oeis_id.raw() as u64;
-            let program_runner: Rc::<ProgramRunner> = match dm.load(program_id64) {
+            let program_runner: Arc::<ProgramRunner> = match dm.load(program_id64) {
                 Ok(value) => value,
                 Err(error) => {
                     // error!("Cannot load program {:?}: {:?}", oeis_id, error);
@@ -1320,7 +1320,7 @@ impl ComputedImages for ProgramState {
         let mut images = Vec::<Image>::with_capacity(number_of_images);
         for index in 0..number_of_images {
             let address: u64 = (index as u64) * 100 + 100 + (MemoryLayoutItem::ComputedOutputImage as u64);
-            let computed_int: BigInt = self.get_u64(address).clone();
+            let computed_int: BigInt = self.get_u64(address).into_owned();
             if computed_int.is_negative() {
                 return Err(anyhow::anyhow!("computed_images. output[{}]. Expected non-negative number, but got {:?}", address, computed_int));
             }
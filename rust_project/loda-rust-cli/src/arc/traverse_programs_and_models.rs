@@ -10,7 +10,7 @@ use super::SolveLogisticRegression;
 use crate::config::Config;
 use crate::common::{find_json_files_recursively, parse_csv_file, create_csv_file};
 use crate::common::find_asm_files_recursively;
-use crate::mine::{Genome, GenomeItem, ToGenomeItemVec, CreateGenomeMutateContextMode, create_genome_mutate_context, GenomeMutateContext};
+use crate::mine::{Genome, GenomeItem, MutateGenome, ToGenomeItemVec, CreateGenomeMutateContextMode, create_genome_mutate_context, GenomeMutateContext};
 use bloomfilter::*;
 use anyhow::{Context, bail};
 use loda_rust_core::control::DependencyManager;
@@ -29,6 +29,7 @@ use console::Style;
 use indicatif::{HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
 use rand::SeedableRng;
 use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
 use serde::{Serialize, Deserialize};
 
 #[allow(unused_imports)]
@@ -990,11 +991,13 @@ impl TraverseProgramsAndModels {
             // The random seed is unaffected by how many threads are running in parallel.
             // However there are still several non-deterministic things that may affect the outcome,
             // Such as the analytics file on disk, how are the rows arranged in the csv file. Bloomfilter.
-            // Such as the way the Genome::mutate() picks a mutation strategy.
+            // Such as the way the mutation strategy is picked.
             let random_seed: u64 = mutation_index * 0x10000 + iteration + ARC_COMPETITION_INITIAL_RANDOM_SEED;
             let mut rng: StdRng = StdRng::seed_from_u64(random_seed);
 
-            let mutate_success: bool = genome.mutate(&mut rng, &self.context);
+            let mutation_vec: Vec<(MutateGenome,usize)> = MutateGenome::default_weights();
+            let mutation: MutateGenome = mutation_vec.choose_weighted(&mut rng, |item| item.1).unwrap().0;
+            let mutate_success: bool = genome.mutate_with(&mut rng, &self.context, mutation);
             if !mutate_success {
                 continue;
             }
@@ -0,0 +1,20 @@
+use loda_rust_core::execute::{PersistentProgramCache, ProgramCache};
+use std::path::Path;
+
+/// Make the `ProgramCache` fall back to the terms saved on disk, when the persistent term cache is enabled.
+/// 
+/// When the persistent term cache cannot be used, then it stays a plain in-memory cache.
+pub fn attach_persistent_term_cache(cache: &mut ProgramCache, persistent_term_cache_dir: Option<&Path>) {
+    let dir: &Path = match persistent_term_cache_dir {
+        Some(value) => value,
+        None => return
+    };
+    match PersistentProgramCache::new(dir) {
+        Ok(persistent_cache) => {
+            cache.set_persistent_cache(persistent_cache);
+        },
+        Err(error) => {
+            error!("Unable to use the persistent term cache. {:?}", error);
+        }
+    }
+}
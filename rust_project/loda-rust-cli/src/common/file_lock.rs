@@ -0,0 +1,130 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Advisory lock for a file that is updated by several processes, such as the miner and `postmine`.
+///
+/// The lock is a file next to the locked file, like this: `mutation-stats.csv.lock`.
+/// It's created exclusively, so only one process or thread can hold the lock at a time.
+/// The lock file is removed when the `FileLock` is dropped.
+///
+/// A process that crashes while holding the lock, leaves the lock file behind.
+/// The lock is held for a short time, so a lock file that is older than `STALE_AGE` is removed.
+pub struct FileLock {
+    lock_path: PathBuf,
+}
+
+impl FileLock {
+    const TIMEOUT: Duration = Duration::from_secs(30);
+    const STALE_AGE: Duration = Duration::from_secs(300);
+    const RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+    /// Blocks until the lock is acquired, or fails after `TIMEOUT`.
+    pub fn acquire(path: &Path) -> anyhow::Result<Self> {
+        let mut lock_path_os_string = path.as_os_str().to_os_string();
+        lock_path_os_string.push(".lock");
+        let lock_path = PathBuf::from(lock_path_os_string);
+        let start = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+                Ok(mut file) => {
+                    // The process id is for humans inspecting a lock file that was left behind.
+                    let _ = writeln!(file, "{}", std::process::id());
+                    return Ok(Self { lock_path });
+                },
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {},
+                Err(error) => {
+                    return Err(anyhow::anyhow!("Unable to create lock file: {:?} error: {:?}", lock_path, error));
+                }
+            }
+            if Self::is_stale(&lock_path) {
+                error!("Removing stale lock file: {:?}", lock_path);
+                let _ = fs::remove_file(&lock_path);
+                continue;
+            }
+            if start.elapsed() > Self::TIMEOUT {
+                return Err(anyhow::anyhow!("Timeout waiting for lock file: {:?}", lock_path));
+            }
+            thread::sleep(Self::RETRY_INTERVAL);
+        }
+    }
+
+    fn is_stale(lock_path: &Path) -> bool {
+        let modified = match fs::metadata(lock_path).and_then(|metadata| metadata.modified()) {
+            Ok(value) => value,
+            Err(_) => return false
+        };
+        match modified.elapsed() {
+            Ok(age) => age > Self::STALE_AGE,
+            Err(_) => false
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.lock_path) {
+            error!("Unable to remove lock file: {:?} error: {:?}", self.lock_path, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_10000_acquire_release() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let path: PathBuf = PathBuf::from(&tempdir.path()).join("manifest.csv");
+        let lock_path: PathBuf = PathBuf::from(&tempdir.path()).join("manifest.csv.lock");
+
+        // Act
+        let lock = FileLock::acquire(&path).expect("lock");
+        assert!(lock_path.is_file());
+        drop(lock);
+
+        // Assert
+        assert!(!lock_path.exists());
+        let _lock = FileLock::acquire(&path).expect("lock");
+    }
+
+    #[test]
+    fn test_10001_mutual_exclusion() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let path: PathBuf = PathBuf::from(&tempdir.path()).join("counter.txt");
+        fs::write(&path, "0").unwrap();
+        let events = Arc::new(Mutex::new(Vec::<&str>::new()));
+
+        // Act
+        let handles: Vec<thread::JoinHandle<()>> = (0..4).map(|_| {
+            let path: PathBuf = path.clone();
+            let events = events.clone();
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    let _lock = FileLock::acquire(&path).expect("lock");
+                    events.lock().unwrap().push("begin");
+                    let value: u32 = fs::read_to_string(&path).unwrap().parse().unwrap();
+                    thread::sleep(Duration::from_millis(1));
+                    fs::write(&path, (value + 1).to_string()).unwrap();
+                    events.lock().unwrap().push("end");
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Assert
+        assert_eq!(fs::read_to_string(&path).unwrap(), "40");
+        let events = events.lock().unwrap();
+        for pair in events.chunks(2) {
+            assert_eq!(pair, ["begin", "end"]);
+        }
+    }
+}
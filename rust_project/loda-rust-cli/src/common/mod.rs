@@ -1,5 +1,7 @@
 //! Read/write CSV files. CSV row types. Obtain filenames. Logging.
+mod attach_persistent_term_cache;
 mod create_csv_file;
+mod file_lock;
 mod find_files_recursively;
 mod mine_event_directory_scan;
 mod oeis_ids_from_paths;
@@ -14,9 +16,13 @@ mod record_program_id;
 mod record_skipgram;
 mod record_trigram;
 mod record_unigram;
+mod save_file_atomic;
 mod simple_log;
+mod trace_observer;
 
+pub use attach_persistent_term_cache::attach_persistent_term_cache;
 pub use create_csv_file::create_csv_file;
+pub use file_lock::FileLock;
 
 #[allow(unused_imports)]
 pub use find_files_recursively::{find_asm_files_recursively, find_csv_files_recursively, find_json_files_recursively};
@@ -35,4 +41,6 @@ pub use record_program_id::{load_program_ids_csv_file, save_program_ids_csv_file
 pub use record_skipgram::RecordSkipgram;
 pub use record_trigram::RecordTrigram;
 pub use record_unigram::RecordUnigram;
+pub use save_file_atomic::save_file_atomic;
 pub use simple_log::SimpleLog;
+pub use trace_observer::TraceObserver;
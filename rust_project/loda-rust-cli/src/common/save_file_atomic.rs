@@ -0,0 +1,72 @@
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// Replaces the file, so a crash never leaves a partially written file behind,
+/// and processes that are reading the old file are not interfered with.
+///
+/// The `write` callback populates a uniquely named temporary file in the same dir,
+/// so concurrent writers, in other processes or threads, never share the temporary file.
+/// When several writers save the same file, then the last rename wins.
+pub fn save_file_atomic<F>(path: &Path, write: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&Path) -> anyhow::Result<()>
+{
+    let parent: &Path = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+    let tmp_file = NamedTempFile::new_in(parent)
+        .map_err(|e| anyhow::anyhow!("Unable to create temporary file in dir: {:?} error: {:?}", parent, e))?;
+    write(tmp_file.path())?;
+    tmp_file.persist(path)
+        .map_err(|e| anyhow::anyhow!("Unable to replace file: {:?} error: {:?}", path, e.error))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_10000_save_file_atomic() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let basedir = PathBuf::from(&tempdir.path()).join("test_10000_save_file_atomic");
+        fs::create_dir(&basedir).expect("ok");
+        let path: PathBuf = basedir.join("file.txt");
+        fs::write(&path, "old").expect("ok");
+
+        // Act
+        save_file_atomic(&path, |tmp_path| {
+            fs::write(tmp_path, "new")?;
+            Ok(())
+        }).expect("ok");
+
+        // Assert
+        assert_eq!(fs::read_to_string(&path).expect("ok"), "new");
+        assert_eq!(fs::read_dir(&basedir).expect("ok").count(), 1);
+    }
+
+    #[test]
+    fn test_10001_failed_write_keeps_old_file() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let basedir = PathBuf::from(&tempdir.path()).join("test_10001_failed_write_keeps_old_file");
+        fs::create_dir(&basedir).expect("ok");
+        let path: PathBuf = basedir.join("file.txt");
+        fs::write(&path, "old").expect("ok");
+
+        // Act
+        let result = save_file_atomic(&path, |tmp_path| {
+            fs::write(tmp_path, "partial")?;
+            Err(anyhow::anyhow!("boom"))
+        });
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&path).expect("ok"), "old");
+        assert_eq!(fs::read_dir(&basedir).expect("ok").count(), 1);
+    }
+}
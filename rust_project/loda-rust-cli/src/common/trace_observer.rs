@@ -0,0 +1,188 @@
+use loda_rust_core::execute::{ObserverAction, ObserverEvent, ProgramId, ProgramObserver};
+use num_bigint::BigInt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TraceRegisterDiff {
+    pub register: u64,
+    pub before: String,
+    pub after: String,
+}
+
+/// One record per executed instruction.
+///
+/// For loops, the record is written after the records of the loop body,
+/// and the diff is the net effect of the entire loop.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct TraceRecord {
+    pub term_index: u64,
+    pub step_count: u64,
+
+    /// The OEIS id of the program that executed the instruction.
+    /// Is `None` for the program being evaluated when it has no id.
+    pub program_id: Option<u64>,
+
+    /// Number of nested `seq` calls. The evaluated program has depth 0.
+    pub call_depth: usize,
+    pub loop_depth: usize,
+    pub line_number: usize,
+    pub instruction: String,
+    pub diff: Vec<TraceRegisterDiff>,
+}
+
+/// Writes one JSON record per line for every executed instruction.
+///
+/// The output can be compared with another trace using ordinary diff tools.
+pub struct TraceObserver<W: Write> {
+    writer: W,
+    term_index: u64,
+    snapshot_stack: Vec<BTreeMap<u64, BigInt>>,
+    write_error: Option<std::io::Error>,
+    record_count: u64,
+}
+
+impl<W: Write> TraceObserver<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            term_index: 0,
+            snapshot_stack: vec!(),
+            write_error: None,
+            record_count: 0,
+        }
+    }
+
+    pub fn set_term_index(&mut self, term_index: u64) {
+        self.term_index = term_index;
+        self.snapshot_stack.clear();
+    }
+
+    pub fn record_count(&self) -> u64 {
+        self.record_count
+    }
+
+    /// Flush the writer and report if anything went wrong while writing the records.
+    pub fn finish(&mut self) -> anyhow::Result<()> {
+        if let Some(error) = self.write_error.take() {
+            return Err(anyhow::anyhow!("TraceObserver unable to write record. error: {:?}", error));
+        }
+        self.writer.flush()
+            .map_err(|e| anyhow::anyhow!("TraceObserver unable to flush. error: {:?}", e))?;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    fn diff(before: &BTreeMap<u64, BigInt>, after: &BTreeMap<u64, BigInt>) -> Vec<TraceRegisterDiff> {
+        let zero = BigInt::from(0);
+        let mut registers: Vec<u64> = before.keys().chain(after.keys()).copied().collect();
+        registers.sort();
+        registers.dedup();
+        let mut result: Vec<TraceRegisterDiff> = vec!();
+        for register in registers {
+            let value_before: &BigInt = before.get(&register).unwrap_or(&zero);
+            let value_after: &BigInt = after.get(&register).unwrap_or(&zero);
+            if value_before == value_after {
+                continue;
+            }
+            let item = TraceRegisterDiff {
+                register,
+                before: value_before.to_string(),
+                after: value_after.to_string(),
+            };
+            result.push(item);
+        }
+        result
+    }
+}
+
+impl<W: Write> ProgramObserver for TraceObserver<W> {
+    fn before_eval(&mut self, event: &ObserverEvent) -> ObserverAction {
+        self.snapshot_stack.push(event.state.memory_full_snapshot());
+        ObserverAction::Continue
+    }
+
+    fn after_eval(&mut self, event: &ObserverEvent) -> ObserverAction {
+        let before: BTreeMap<u64, BigInt> = self.snapshot_stack.pop().unwrap_or_default();
+        let after: BTreeMap<u64, BigInt> = event.state.memory_full_snapshot();
+        let program_id: Option<u64> = match event.program_id {
+            ProgramId::ProgramOEIS(value) => Some(value),
+            ProgramId::ProgramWithoutId => None,
+        };
+        let record = TraceRecord {
+            term_index: self.term_index,
+            step_count: event.state.step_count(),
+            program_id,
+            call_depth: event.call_depth,
+            loop_depth: event.loop_depth,
+            line_number: event.line_number,
+            instruction: event.node.formatted_instruction(),
+            diff: Self::diff(&before, &after),
+        };
+        let json: String = match serde_json::to_string(&record) {
+            Ok(value) => value,
+            Err(error) => {
+                error!("TraceObserver unable to serialize record. error: {:?}", error);
+                return ObserverAction::Abort;
+            }
+        };
+        if let Err(error) = writeln!(self.writer, "{}", json) {
+            self.write_error = Some(error);
+            return ObserverAction::Abort;
+        }
+        self.record_count += 1;
+        ObserverAction::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loda_rust_core::control::{DependencyManager, DependencyManagerFileSystemMode};
+    use loda_rust_core::execute::{ProgramCache, ProgramRunner, RegisterValue};
+    use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_10000_trace_records() {
+        // Arrange
+        let program: &str = "mov $1,2\nlpb $0\n  sub $0,1\n  mul $1,3\nlpe\nmov $0,$1";
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            UnofficialFunctionRegistry::new(),
+        );
+        let runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, program).expect("runner");
+        let observer = Rc::new(RefCell::new(TraceObserver::new(Vec::<u8>::new())));
+        observer.borrow_mut().set_term_index(1);
+        let mut cache = ProgramCache::new();
+        let mut step_count: u64 = 0;
+
+        // Act
+        let output: RegisterValue = runner.run_observed(
+            RegisterValue::from_i64(1),
+            &mut step_count,
+            1000,
+            &mut cache,
+            observer.clone(),
+        ).expect("output");
+        observer.borrow_mut().finish().expect("ok");
+
+        // Assert
+        assert_eq!(output, RegisterValue::from_i64(6));
+        let content: String = String::from_utf8(observer.borrow().writer().clone()).expect("utf8");
+        let rows: Vec<&str> = content.lines().collect();
+        assert_eq!(rows.len(), 7);
+        assert_eq!(rows[0], r#"{"term_index":1,"step_count":1,"program_id":null,"call_depth":0,"loop_depth":0,"line_number":1,"instruction":"mov $1,2","diff":[{"register":1,"before":"0","after":"2"}]}"#);
+        assert_eq!(rows[1], r#"{"term_index":1,"step_count":2,"program_id":null,"call_depth":0,"loop_depth":1,"line_number":3,"instruction":"sub $0,1","diff":[{"register":0,"before":"1","after":"0"}]}"#);
+        assert_eq!(rows[5], r#"{"term_index":1,"step_count":6,"program_id":null,"call_depth":0,"loop_depth":0,"line_number":2,"instruction":"lpb $0","diff":[{"register":0,"before":"1","after":"0"},{"register":1,"before":"2","after":"6"}]}"#);
+        assert_eq!(observer.borrow().record_count(), 7);
+    }
+}
@@ -1,3 +1,4 @@
+use super::MinerTuning;
 use std::path::{Path,PathBuf};
 use serde::Deserialize;
 use std::fs;
@@ -56,6 +57,15 @@ pub struct Config {
     miner_cpu_strategy: MinerCPUStrategy,
     arc_repository_data: PathBuf,
     loda_arc_challenge_repository: PathBuf,
+    persistent_term_cache: bool,
+    miner_tuning: MinerTuning,
+    miner_offline: bool,
+    miner_offline_import_dir: Option<PathBuf>,
+    miner_coordinator_listen_address: Option<String>,
+    miner_coordinator_shard_count: u32,
+    miner_coordinator_address: Option<String>,
+    miner_coordinator_secret: String,
+    miner_coordinator_worker_name: Option<String>,
 }
 
 impl Config {
@@ -97,6 +107,44 @@ impl Config {
         path
     }
 
+    /// The dir "~/.loda-rust/term-cache" holds the terms computed by programs, so they can be reused across runs.
+    /// 
+    /// Returns `None` when the persistent term cache is disabled.
+    pub fn persistent_term_cache_dir(&self) -> Option<PathBuf> {
+        if !self.persistent_term_cache {
+            return None;
+        }
+        let path = self.basedir.join("term-cache");
+        assert!(path.is_absolute());
+        Some(path)
+    }
+
+    /// The file "~/.loda-rust/mutation-stats.csv" holds how well each mutation performs, so the miner can adapt.
+    pub fn mutation_stats_file(&self) -> PathBuf {
+        let path = self.basedir.join("mutation-stats.csv");
+        assert!(path.is_absolute());
+        path
+    }
+
+    /// The dir "~/.loda-rust/upload-outbox" holds the mined programs, until they have been uploaded.
+    /// 
+    /// When mining without network access, the programs are uploaded later with the `loda-rust upload-outbox` command.
+    pub fn upload_outbox_dir(&self) -> PathBuf {
+        let path = self.basedir.join("upload-outbox");
+        assert!(path.is_absolute());
+        path
+    }
+
+    /// The dir "~/.loda-rust/oeis-bfile" holds b-files, such as `b000045.txt`, that have been downloaded from oeis.org.
+    /// 
+    /// When postmine finds a b-file here, then it checks the program without using `loda-cpp`.
+    pub fn oeis_bfile_dir(&self) -> PathBuf {
+        let name = Path::new("oeis-bfile");
+        let path = self.basedir.join(name);
+        assert!(path.is_absolute());
+        path
+    }
+
     pub fn loda_programs_repository(&self) -> PathBuf {
         let path = &self.loda_programs_repository;
         assert!(path.is_absolute());
@@ -219,6 +267,55 @@ impl Config {
         self.miner_filter_mode
     }
 
+    pub fn miner_tuning(&self) -> &MinerTuning {
+        &self.miner_tuning
+    }
+
+    /// Mine without network access.
+    /// - The mined programs are saved to the `upload_outbox_dir`, instead of being uploaded.
+    /// - The `miner_sync_executable` is not run.
+    pub fn miner_offline(&self) -> bool {
+        self.miner_offline
+    }
+
+    /// When mining offline, the programs in this dir are imported into the "loda-programs" repository, instead of running the sync script.
+    /// 
+    /// Returns `None` when there is nothing to import.
+    pub fn miner_offline_import_dir(&self) -> Option<PathBuf> {
+        self.miner_offline_import_dir.clone()
+    }
+
+    /// Act as the head node for distributed mining, by listening on this address.
+    /// 
+    /// Returns `None` when not acting as a head node.
+    pub fn miner_coordinator_listen_address(&self) -> Option<String> {
+        self.miner_coordinator_listen_address.clone()
+    }
+
+    /// The number of shards that the head node splits the target sequences into.
+    pub fn miner_coordinator_shard_count(&self) -> u32 {
+        self.miner_coordinator_shard_count
+    }
+
+    /// Act as a worker node for distributed mining, by connecting to the head node at this address.
+    /// 
+    /// Returns `None` when mining on this machine alone.
+    pub fn miner_coordinator_address(&self) -> Option<String> {
+        self.miner_coordinator_address.clone()
+    }
+
+    /// The shared secret that the worker nodes present to the head node, when mining distributed.
+    pub fn miner_coordinator_secret(&self) -> String {
+        self.miner_coordinator_secret.clone()
+    }
+
+    /// Identifies the worker node to the head node, so it gets the same shard after a restart.
+    /// 
+    /// Returns `None` when the hostname is to be used.
+    pub fn miner_coordinator_worker_name(&self) -> Option<String> {
+        self.miner_coordinator_worker_name.clone()
+    }
+
     pub fn miner_cpu_strategy(&self) -> MinerCPUStrategy {
         self.miner_cpu_strategy
     }
@@ -266,6 +363,15 @@ struct ConfigFallback {
     miner_cpu_strategy: MinerCPUStrategy,
     arc_repository_data: String,
     loda_arc_challenge_repository: String,
+    persistent_term_cache: bool,
+    miner_tuning: Option<MinerTuning>,
+    miner_offline: bool,
+    miner_offline_import_dir: String,
+    miner_coordinator_listen_address: String,
+    miner_coordinator_shard_count: u32,
+    miner_coordinator_address: String,
+    miner_coordinator_secret: String,
+    miner_coordinator_worker_name: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -287,6 +393,15 @@ struct ConfigCustom {
     miner_cpu_strategy: Option<MinerCPUStrategy>,
     arc_repository_data: Option<String>,
     loda_arc_challenge_repository: Option<String>,
+    persistent_term_cache: Option<bool>,
+    miner_tuning: Option<MinerTuning>,
+    miner_offline: Option<bool>,
+    miner_offline_import_dir: Option<String>,
+    miner_coordinator_listen_address: Option<String>,
+    miner_coordinator_shard_count: Option<u32>,
+    miner_coordinator_address: Option<String>,
+    miner_coordinator_secret: Option<String>,
+    miner_coordinator_worker_name: Option<String>,
 }
 
 fn load_config_from_home_dir() -> Config {
@@ -363,6 +478,31 @@ pub fn config_from_toml_content(toml_content: String, basedir: PathBuf, homedir:
     let miner_cpu_strategy: MinerCPUStrategy = custom.miner_cpu_strategy.unwrap_or(fallback.miner_cpu_strategy);
     let arc_repository_data: String = custom.arc_repository_data.unwrap_or(fallback.arc_repository_data);
     let loda_arc_challenge_repository: String = custom.loda_arc_challenge_repository.unwrap_or(fallback.loda_arc_challenge_repository);
+    let persistent_term_cache: bool = custom.persistent_term_cache.unwrap_or(fallback.persistent_term_cache);
+    let miner_tuning: MinerTuning = custom.miner_tuning.or(fallback.miner_tuning).unwrap_or_default();
+    let miner_offline: bool = custom.miner_offline.unwrap_or(fallback.miner_offline);
+    let miner_offline_import_dir: String = custom.miner_offline_import_dir.unwrap_or(fallback.miner_offline_import_dir);
+    let miner_offline_import_dir: Option<PathBuf> = match miner_offline_import_dir.is_empty() {
+        true => None,
+        false => Some(simpleenv.resolve_path(&miner_offline_import_dir))
+    };
+    let miner_coordinator_listen_address: String = custom.miner_coordinator_listen_address.unwrap_or(fallback.miner_coordinator_listen_address);
+    let miner_coordinator_listen_address: Option<String> = match miner_coordinator_listen_address.is_empty() {
+        true => None,
+        false => Some(miner_coordinator_listen_address)
+    };
+    let miner_coordinator_shard_count: u32 = custom.miner_coordinator_shard_count.unwrap_or(fallback.miner_coordinator_shard_count);
+    let miner_coordinator_address: String = custom.miner_coordinator_address.unwrap_or(fallback.miner_coordinator_address);
+    let miner_coordinator_address: Option<String> = match miner_coordinator_address.is_empty() {
+        true => None,
+        false => Some(miner_coordinator_address)
+    };
+    let miner_coordinator_secret: String = custom.miner_coordinator_secret.unwrap_or(fallback.miner_coordinator_secret);
+    let miner_coordinator_worker_name: String = custom.miner_coordinator_worker_name.unwrap_or(fallback.miner_coordinator_worker_name);
+    let miner_coordinator_worker_name: Option<String> = match miner_coordinator_worker_name.is_empty() {
+        true => None,
+        false => Some(miner_coordinator_worker_name)
+    };
     Config {
         basedir: basedir,
         loda_programs_repository: simpleenv.resolve_path(&loda_programs_repository),
@@ -382,6 +522,15 @@ pub fn config_from_toml_content(toml_content: String, basedir: PathBuf, homedir:
         miner_cpu_strategy: miner_cpu_strategy,
         arc_repository_data: simpleenv.resolve_path(&arc_repository_data),
         loda_arc_challenge_repository: simpleenv.resolve_path(&loda_arc_challenge_repository),
+        persistent_term_cache,
+        miner_tuning,
+        miner_offline,
+        miner_offline_import_dir,
+        miner_coordinator_listen_address,
+        miner_coordinator_shard_count,
+        miner_coordinator_address,
+        miner_coordinator_secret,
+        miner_coordinator_worker_name,
     }
 }
 
@@ -503,6 +652,15 @@ mod tests {
         assert_has_suffix(&config.loda_arc_challenge_repository, "/git/loda-arc-challenge")?;
         assert_eq!(config.miner_filter_mode, MinerFilterMode::New);
         assert_eq!(config.miner_cpu_strategy, MinerCPUStrategy::Max);
+        assert!(!config.persistent_term_cache);
+        assert_eq!(config.miner_tuning, MinerTuning::default());
+        assert!(!config.miner_offline);
+        assert_eq!(config.miner_offline_import_dir, None);
+        assert_eq!(config.miner_coordinator_listen_address, None);
+        assert_eq!(config.miner_coordinator_shard_count, 1);
+        assert_eq!(config.miner_coordinator_address, None);
+        assert_eq!(config.miner_coordinator_secret, "");
+        assert_eq!(config.miner_coordinator_worker_name, None);
         Ok(())
     }
 
@@ -593,4 +751,102 @@ mod tests {
         assert_eq!(config.miner_cpu_strategy, MinerCPUStrategy::CPU {count: 8});
         Ok(())
     }
+
+    #[test]
+    fn test_40004_override_persistent_term_cache() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_40004_override_persistent_term_cache");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        persistent_term_cache = true
+        "#;
+        let basedir = PathBuf::from(&tempdir.path()).join(".loda-rust");
+
+        // Act
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+
+        // Assert
+        let path: PathBuf = config.persistent_term_cache_dir().expect("dir");
+        assert_has_suffix(&path, "/.loda-rust/term-cache")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_40005_override_miner_tuning() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_40005_override_miner_tuning");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        [miner_tuning]
+        cache_capacity = 3000
+        [miner_tuning.mutation_weights]
+        CallRecentProgram = 100
+        "#;
+        let basedir = PathBuf::from(Path::new("non-existing-basedir"));
+
+        // Act
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+
+        // Assert
+        assert_eq!(config.miner_tuning().cache_capacity, 3000);
+        assert_eq!(config.miner_tuning().minimum_program_length, 6);
+        assert_eq!(config.miner_tuning().mutation_weights.get("CallRecentProgram"), Some(&100));
+        Ok(())
+    }
+
+    #[test]
+    fn test_40006_override_miner_offline() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_40006_override_miner_offline");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        miner_offline = true
+        miner_offline_import_dir = "$HOME/usb/programs"
+        "#;
+        let basedir = PathBuf::from(Path::new("/non-existing-basedir"));
+
+        // Act
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+
+        // Assert
+        assert!(config.miner_offline());
+        let path: PathBuf = config.miner_offline_import_dir().expect("dir");
+        assert_has_suffix(&path, "/test_40006_override_miner_offline/usb/programs")?;
+        assert_has_suffix(&config.upload_outbox_dir(), "/non-existing-basedir/upload-outbox")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_40007_override_miner_coordinator() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_40007_override_miner_coordinator");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        miner_coordinator_listen_address = "0.0.0.0:8091"
+        miner_coordinator_shard_count = 4
+        miner_coordinator_address = "192.168.1.10:8091"
+        miner_coordinator_secret = "abc"
+        miner_coordinator_worker_name = "worker7"
+        "#;
+        let basedir = PathBuf::from(Path::new("/non-existing-basedir"));
+
+        // Act
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+
+        // Assert
+        assert_eq!(config.miner_coordinator_listen_address(), Some("0.0.0.0:8091".to_string()));
+        assert_eq!(config.miner_coordinator_shard_count(), 4);
+        assert_eq!(config.miner_coordinator_address(), Some("192.168.1.10:8091".to_string()));
+        assert_eq!(config.miner_coordinator_secret(), "abc");
+        assert_eq!(config.miner_coordinator_worker_name(), Some("worker7".to_string()));
+        Ok(())
+    }
 }
@@ -0,0 +1,139 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Limits for computing a range of terms.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Eq)]
+pub struct MinerTermLimit {
+    /// Max number of iterations a loop may do.
+    pub loop_limit: u32,
+
+    /// Max number of instructions executed while computing a single term.
+    pub step_limit: u64,
+}
+
+/// The limits for the 3 ranges of terms, without the rest of the `MinerTuning`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinerTermLimits {
+    pub terms10: MinerTermLimit,
+    pub terms20: MinerTermLimit,
+    pub terms40: MinerTermLimit,
+}
+
+impl MinerTermLimits {
+    /// The limits to use when computing the term at `index`.
+    pub fn term_limit(&self, index: usize) -> MinerTermLimit {
+        if index <= 10 {
+            return self.terms10;
+        }
+        if index <= 20 {
+            return self.terms20;
+        }
+        self.terms40
+    }
+}
+
+/// The optional `[miner_tuning]` section of the config file.
+///
+/// Makes it possible to run different mining strategies on different machines.
+/// Keys that are not specified, uses the built-in defaults.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MinerTuning {
+    /// Reject mutated programs that have fewer instructions than this.
+    pub minimum_program_length: usize,
+
+    /// Number of computed terms that are kept in memory by each miner worker.
+    pub cache_capacity: usize,
+
+    /// Number of iterations before mining starts over with another program.
+    pub iterations_between_picking_a_new_initial_genome: usize,
+
+    /// Number of iterations before the mutations gets discarded, and the original program gets mutated again.
+    pub iterations_between_reloading_current_genome: usize,
+
+    /// Number of genomes that are being mutated at the same time.
+    ///
+    /// With `1` there is a single current genome and crossover is disabled.
+    pub population_size: usize,
+
+    /// How often, in percent, that a reload combines two genomes from the population,
+    /// instead of reusing a single genome.
+    pub crossover_percent: usize,
+
+    /// Limits for computing the terms 0..=10.
+    pub terms10: MinerTermLimit,
+
+    /// Limits for computing the terms 11..=20.
+    pub terms20: MinerTermLimit,
+
+    /// Limits for computing the terms 21 and beyond.
+    pub terms40: MinerTermLimit,
+
+    /// Override the weight of a mutation, such as `CallRecentProgram = 300`.
+    ///
+    /// A weight of `0` disables the mutation.
+    pub mutation_weights: HashMap<String, usize>,
+}
+
+impl Default for MinerTuning {
+    fn default() -> Self {
+        Self {
+            minimum_program_length: 6,
+            cache_capacity: 1500,
+            iterations_between_picking_a_new_initial_genome: 300,
+            iterations_between_reloading_current_genome: 5,
+            population_size: 1,
+            crossover_percent: 20,
+            terms10: MinerTermLimit { loop_limit: 4000, step_limit: 40000 },
+            terms20: MinerTermLimit { loop_limit: 8000, step_limit: 80000 },
+            terms40: MinerTermLimit { loop_limit: 32000, step_limit: 320000 },
+            mutation_weights: HashMap::new(),
+        }
+    }
+}
+
+impl MinerTuning {
+    pub fn term_limits(&self) -> MinerTermLimits {
+        MinerTermLimits {
+            terms10: self.terms10,
+            terms20: self.terms20,
+            terms40: self.terms40,
+        }
+    }
+
+    /// The limits to use when computing the term at `index`.
+    pub fn term_limit(&self, index: usize) -> MinerTermLimit {
+        self.term_limits().term_limit(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_10000_partial_section_uses_defaults() {
+        // Arrange
+        let content = r#"
+        minimum_program_length = 8
+
+        [terms20]
+        loop_limit = 100
+        step_limit = 200
+
+        [mutation_weights]
+        CopyLine = 0
+        "#;
+
+        // Act
+        let tuning: MinerTuning = toml::from_str(content).expect("toml");
+
+        // Assert
+        assert_eq!(tuning.minimum_program_length, 8);
+        assert_eq!(tuning.cache_capacity, 1500);
+        assert_eq!(tuning.term_limit(10), MinerTermLimit { loop_limit: 4000, step_limit: 40000 });
+        assert_eq!(tuning.term_limit(11), MinerTermLimit { loop_limit: 100, step_limit: 200 });
+        assert_eq!(tuning.term_limit(39).loop_limit, 32000);
+        assert_eq!(tuning.mutation_weights.get("CopyLine"), Some(&0));
+    }
+}
@@ -1,10 +1,12 @@
 //! Load the `~/.loda-rust/config.toml` file.
 mod config;
+mod miner_tuning;
 mod number_of_workers;
 mod validate_config;
 
 #[allow(unused_imports)]
 pub use config::{config_from_toml_content, Config, MinerCPUStrategy, MinerFilterMode};
 
+pub use miner_tuning::{MinerTermLimit, MinerTermLimits, MinerTuning};
 pub use number_of_workers::NumberOfWorkers;
 pub use validate_config::{ValidateConfigTask, ValidateConfig};
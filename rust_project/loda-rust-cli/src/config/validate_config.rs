@@ -1,4 +1,5 @@
-use super::Config;
+use super::{Config, MinerTermLimit, MinerTuning};
+use crate::mine::MutateGenome;
 use std::path::PathBuf;
 
 pub enum ValidateConfigTask {
@@ -35,7 +36,13 @@ impl ValidateConfigInner {
     }
 
     fn validate_config_for_task_oeis_mine(&mut self) -> anyhow::Result<()> {
-        self.miner_sync_executable()?;
+        if self.config.miner_offline() {
+            self.miner_offline_import_dir()?;
+        } else {
+            self.miner_sync_executable()?;
+        }
+        self.miner_tuning()?;
+        self.miner_coordinator()?;
         self.to_result()
     }
 
@@ -61,6 +68,96 @@ impl ValidateConfigInner {
         }
         Ok(())
     }
+
+    fn miner_offline_import_dir(&mut self) -> anyhow::Result<()> {
+        let path: PathBuf = match self.config.miner_offline_import_dir() {
+            Some(value) => value,
+            None => {
+                return Ok(());
+            }
+        };
+        if !path.is_absolute() {
+            let s = format!("The 'miner_offline_import_dir' must be an absolute path, but got: {:?}", path);
+            self.messages.push(s);
+            return Ok(());
+        }
+        if !path.is_dir() {
+            let s = format!("The 'miner_offline_import_dir' must be an absolute path to an existing dir, but got: {:?}", path);
+            self.messages.push(s);
+            return Ok(());
+        }
+        Ok(())
+    }
+
+    fn miner_coordinator(&mut self) -> anyhow::Result<()> {
+        if self.config.miner_coordinator_shard_count() == 0 {
+            self.messages.push("The 'miner_coordinator_shard_count' must be 1 or greater".to_string());
+        }
+        let is_distributed: bool = self.config.miner_coordinator_address().is_some() || self.config.miner_coordinator_listen_address().is_some();
+        if is_distributed && self.config.miner_coordinator_secret().is_empty() {
+            self.messages.push("The 'miner_coordinator_secret' must be set, when acting as a head node or as a worker node".to_string());
+        }
+        if self.config.miner_coordinator_address().is_none() {
+            return Ok(());
+        }
+        if self.config.miner_coordinator_listen_address().is_some() {
+            self.messages.push("The 'miner_coordinator_address' and the 'miner_coordinator_listen_address' cannot both be set. Either be a head node or a worker node".to_string());
+        }
+        if self.config.miner_offline() {
+            self.messages.push("The 'miner_coordinator_address' cannot be used together with 'miner_offline'".to_string());
+        }
+        Ok(())
+    }
+
+    fn miner_tuning(&mut self) -> anyhow::Result<()> {
+        let tuning: MinerTuning = self.config.miner_tuning().clone();
+        if tuning.minimum_program_length == 0 {
+            self.messages.push("The 'miner_tuning.minimum_program_length' must be 1 or greater".to_string());
+        }
+        if tuning.cache_capacity == 0 {
+            self.messages.push("The 'miner_tuning.cache_capacity' must be 1 or greater".to_string());
+        }
+        if tuning.iterations_between_picking_a_new_initial_genome == 0 {
+            self.messages.push("The 'miner_tuning.iterations_between_picking_a_new_initial_genome' must be 1 or greater".to_string());
+        }
+        if tuning.iterations_between_reloading_current_genome == 0 {
+            self.messages.push("The 'miner_tuning.iterations_between_reloading_current_genome' must be 1 or greater".to_string());
+        }
+        if tuning.population_size == 0 {
+            self.messages.push("The 'miner_tuning.population_size' must be 1 or greater".to_string());
+        }
+        if tuning.crossover_percent > 100 {
+            let s = format!("The 'miner_tuning.crossover_percent' must be in the range 0..=100, but got: {}", tuning.crossover_percent);
+            self.messages.push(s);
+        }
+        let term_limits: [(&str, MinerTermLimit); 3] = [
+            ("terms10", tuning.terms10),
+            ("terms20", tuning.terms20),
+            ("terms40", tuning.terms40),
+        ];
+        for (name, term_limit) in &term_limits {
+            if term_limit.loop_limit == 0 || term_limit.step_limit == 0 {
+                let s = format!("The 'miner_tuning.{}' limits must be 1 or greater, but got: {:?}", name, term_limit);
+                self.messages.push(s);
+            }
+        }
+        let mut names: Vec<&String> = tuning.mutation_weights.keys().collect();
+        names.sort();
+        for name in names {
+            if MutateGenome::from_name(name).is_none() {
+                let s = format!("The 'miner_tuning.mutation_weights' contains an unknown mutation: {:?}", name);
+                self.messages.push(s);
+            }
+        }
+        let has_enabled_mutation: bool = MutateGenome::default_weights().iter().any(|(mutation, default_weight)| {
+            let weight: usize = tuning.mutation_weights.get(&mutation.name()).cloned().unwrap_or(*default_weight);
+            weight > 0
+        });
+        if !has_enabled_mutation {
+            self.messages.push("The 'miner_tuning.mutation_weights' disables all the mutations".to_string());
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -123,4 +220,131 @@ mod tests {
         instance.to_result().expect("no error");
         Ok(())
     }
+
+    #[test]
+    fn test_20000_miner_tuning_error() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_20000_miner_tuning_error");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        [miner_tuning]
+        cache_capacity = 0
+        crossover_percent = 101
+        [miner_tuning.terms20]
+        loop_limit = 0
+        step_limit = 1000
+        [miner_tuning.mutation_weights]
+        NoSuchMutation = 10
+        "#;
+        let basedir = PathBuf::from(Path::new("non-existing-basedir"));
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+        let mut instance = ValidateConfigInner::new(config);
+
+        // Act
+        instance.miner_tuning()?;
+
+        // Assert
+        let error_message: String = instance.to_result().expect_err("error").to_string();
+        assert!(error_message.contains("'miner_tuning.cache_capacity' must be 1 or greater"));
+        assert!(error_message.contains("'miner_tuning.crossover_percent' must be in the range 0..=100"));
+        assert!(error_message.contains("'miner_tuning.terms20' limits must be 1 or greater"));
+        assert!(error_message.contains("unknown mutation: \"NoSuchMutation\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_20001_miner_tuning_ok() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_20001_miner_tuning_ok");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        [miner_tuning.mutation_weights]
+        CallRecentProgram = 0
+        CopyLine = 20
+        "#;
+        let basedir = PathBuf::from(Path::new("non-existing-basedir"));
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+        let mut instance = ValidateConfigInner::new(config);
+
+        // Act
+        instance.miner_tuning()?;
+
+        // Assert
+        instance.to_result().expect("no error");
+        Ok(())
+    }
+
+    #[test]
+    fn test_30000_miner_coordinator_error() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_30000_miner_coordinator_error");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        miner_coordinator_listen_address = "0.0.0.0:8091"
+        miner_coordinator_shard_count = 0
+        miner_coordinator_address = "192.168.1.10:8091"
+        "#;
+        let basedir = PathBuf::from(Path::new("non-existing-basedir"));
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+        let mut instance = ValidateConfigInner::new(config);
+
+        // Act
+        instance.miner_coordinator()?;
+
+        // Assert
+        let error_message: String = instance.to_result().expect_err("error").to_string();
+        assert!(error_message.contains("'miner_coordinator_shard_count' must be 1 or greater"));
+        assert!(error_message.contains("cannot both be set"));
+        assert!(error_message.contains("'miner_coordinator_secret' must be set"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_40000_miner_offline_import_dir_error() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_40000_miner_offline_import_dir_error");
+        fs::create_dir(&homedir)?;
+        let content = 
+        r#"
+        miner_offline_import_dir = "/non-existing-dir/import"
+        "#;
+        let basedir = PathBuf::from(Path::new("non-existing-basedir"));
+        let config: Config = config_from_toml_content(content.to_string(), basedir, homedir);
+        let mut instance = ValidateConfigInner::new(config);
+
+        // Act
+        instance.miner_offline_import_dir()?;
+
+        // Assert
+        let error_message: String = instance.to_result().expect_err("error").to_string();
+        assert!(error_message.contains("The 'miner_offline_import_dir' must be an absolute path to an existing dir"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_40001_miner_offline_import_dir_ok() -> Result<(), Box<dyn Error>> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let homedir = PathBuf::from(&tempdir.path()).join("test_40001_miner_offline_import_dir_ok");
+        let import_dir: PathBuf = homedir.join("import");
+        fs::create_dir_all(&import_dir)?;
+        let content = format!("miner_offline_import_dir = \"{}\"", import_dir.to_string_lossy());
+        let basedir = PathBuf::from(Path::new("non-existing-basedir"));
+        let config: Config = config_from_toml_content(content, basedir, homedir);
+        let mut instance = ValidateConfigInner::new(config);
+
+        // Act
+        instance.miner_offline_import_dir()?;
+
+        // Assert
+        instance.to_result().expect("no error");
+        Ok(())
+    }
 }
@@ -7,7 +7,6 @@ mod lodacpp_eval_steps_execute;
 mod lodacpp_eval_steps;
 mod lodacpp_eval_terms_execute;
 mod lodacpp_eval_terms;
-mod lodacpp_minimize;
 
 pub use lodacpp::LodaCpp;
 pub use lodacpp_check_execute::LodaCppCheck;
@@ -17,4 +16,3 @@ pub use lodacpp_eval_steps_execute::LodaCppEvalStepsExecute;
 pub use lodacpp_eval_steps::LodaCppEvalSteps;
 pub use lodacpp_eval_terms_execute::LodaCppEvalTermsExecute;
 pub use lodacpp_eval_terms::LodaCppEvalTerms;
-pub use lodacpp_minimize::LodaCppMinimize;
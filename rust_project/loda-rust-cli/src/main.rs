@@ -35,10 +35,13 @@ mod subcommand_mine;
 mod subcommand_pattern;
 mod subcommand_similar;
 mod subcommand_test;
+mod subcommand_upload_outbox;
 
 use subcommand_analytics::SubcommandAnalytics;
 use subcommand_arc::{SubcommandARC, SubcommandARCMode};
-use subcommand_dependencies::subcommand_dependencies;
+use subcommand_dependencies::{subcommand_dependencies, SubcommandDependenciesFormat, SubcommandDependenciesMode};
+use analytics::DependencyDirection;
+use mine::MinerTarget;
 use subcommand_evaluate::{subcommand_evaluate,SubcommandEvaluateMode};
 use subcommand_export_dataset::SubcommandExportDataset;
 use subcommand_install::subcommand_install;
@@ -46,6 +49,7 @@ use subcommand_mine::{SubcommandMine,SubcommandMineMetricsMode};
 use subcommand_pattern::SubcommandPattern;
 use subcommand_similar::subcommand_similar;
 use subcommand_test::SubcommandTest;
+use subcommand_upload_outbox::SubcommandUploadOutbox;
 
 extern crate clap;
 extern crate num_bigint;
@@ -89,14 +93,51 @@ async fn main() -> anyhow::Result<()> {
                         .help("Inspect the internal state during execute")
                         .long("debug")
                 )
+                .arg(
+                    Arg::new("trace")
+                        .help("Write a JSON lines file with one record per executed instruction")
+                        .takes_value(true)
+                        .long("trace")
+                )
+                .arg(
+                    Arg::new("unofficial-functions")
+                        .help("Dir with LODA programs to be registered as unofficial functions, named like 'f21_1100.asm'")
+                        .takes_value(true)
+                        .long("unofficial-functions")
+                )
         )
         .subcommand(
             Command::new("dependencies")
                 .alias("deps")
-                .about("Print all direct/indirect dependencies of a program")
+                .about("Print all direct/indirect dependencies of a program, or the programs that depend on it")
                 .arg(
                     Arg::new("programid")
-                        .required(true)
+                        .required_unless_present("cycles")
+                )
+                .arg(
+                    Arg::new("reverse")
+                        .help("Print the programs that depend on the program, across the entire repo")
+                        .long("reverse")
+                )
+                .arg(
+                    Arg::new("depth")
+                        .help("Only follow this many levels of dependencies")
+                        .takes_value(true)
+                        .long("depth")
+                )
+                .arg(
+                    Arg::new("format")
+                        .help("Output format")
+                        .takes_value(true)
+                        .possible_values(["text", "dot", "json"])
+                        .default_value("text")
+                        .long("format")
+                )
+                .arg(
+                    Arg::new("cycles")
+                        .help("Print groups of programs that depend on each other, across the entire repo")
+                        .long("cycles")
+                        .conflicts_with_all(&["programid", "reverse", "depth"])
                 )
         )
         .subcommand(
@@ -121,6 +162,23 @@ async fn main() -> anyhow::Result<()> {
                         .long("metrics")
                         .help("Run a metrics server on localhost:8090 (can be overwritten in the config file)")
                 )
+                .arg(
+                    Arg::new("target")
+                        .help("Only mine for these sequences, comma separated, such as 'A000045,A000040'")
+                        .takes_value(true)
+                        .long("target")
+                        .conflicts_with("target-terms")
+                )
+                .arg(
+                    Arg::new("target-terms")
+                        .help("Only mine for the sequences that contains these terms, comma separated, such as '1,1,2,3,5,8'")
+                        .takes_value(true)
+                        .long("target-terms")
+                )
+        )
+        .subcommand(
+            Command::new("upload-outbox")
+                .about("Upload the programs that have been mined offline, from the ~/.loda-rust/upload-outbox dir.")
         )
         .subcommand(
             Command::new("similar")
@@ -256,23 +314,49 @@ async fn main() -> anyhow::Result<()> {
         // Eval mode
         let show_steps: bool = sub_m.is_present("steps");
         let show_debug: bool = sub_m.is_present("debug");
-        let mode: SubcommandEvaluateMode = match (show_debug, show_steps) {
-            (false,false) => SubcommandEvaluateMode::PrintTerms,
-            (false,true) => SubcommandEvaluateMode::PrintSteps,
-            (true,false) => SubcommandEvaluateMode::PrintDebug,
-            (true,true) => {
+        let trace_path: Option<PathBuf> = sub_m.value_of("trace").map(PathBuf::from);
+        let mode: SubcommandEvaluateMode = match (show_debug, show_steps, trace_path) {
+            (false,false,None) => SubcommandEvaluateMode::PrintTerms,
+            (false,true,None) => SubcommandEvaluateMode::PrintSteps,
+            (true,false,None) => SubcommandEvaluateMode::PrintDebug,
+            (false,false,Some(path)) => SubcommandEvaluateMode::PrintTrace { path },
+            _ => {
                 panic!("Invalid combo of parameters");
             }
         };
-        subcommand_evaluate(program_id, number_of_terms, mode);
+        let unofficial_functions_dir: Option<PathBuf> = sub_m.value_of("unofficial-functions").map(PathBuf::from);
+        subcommand_evaluate(program_id, number_of_terms, mode, unofficial_functions_dir)?;
         return Ok(());
     }
 
     if let Some(sub_m) = matches.subcommand_matches("dependencies") {
+        if sub_m.is_present("cycles") {
+            subcommand_dependencies(SubcommandDependenciesMode::PrintCycles)?;
+            return Ok(());
+        }
         let program_id_raw: &str = sub_m.value_of("programid").unwrap();
         let program_id: u64 = u64::from_str(program_id_raw)
             .expect("Unable to parse program_id.");
-        subcommand_dependencies(program_id);
+        let mut max_depth: Option<usize> = None;
+        if let Some(max_depth_raw) = sub_m.value_of("depth") {
+            let value: usize = usize::from_str(max_depth_raw)
+                .expect("Unable to parse depth.");
+            max_depth = Some(value);
+        }
+        let format: SubcommandDependenciesFormat = match sub_m.value_of("format") {
+            Some("dot") => SubcommandDependenciesFormat::Dot,
+            Some("json") => SubcommandDependenciesFormat::Json,
+            _ => SubcommandDependenciesFormat::Text,
+        };
+        let reverse: bool = sub_m.is_present("reverse");
+        let mode: SubcommandDependenciesMode = match (reverse, max_depth, format) {
+            (false, None, SubcommandDependenciesFormat::Text) => SubcommandDependenciesMode::PrintDependencies { program_id },
+            _ => {
+                let direction: DependencyDirection = if reverse { DependencyDirection::Callers } else { DependencyDirection::Callees };
+                SubcommandDependenciesMode::Graph { program_id, direction, max_depth, format }
+            }
+        };
+        subcommand_dependencies(mode)?;
         return Ok(());
     }
 
@@ -297,7 +381,19 @@ async fn main() -> anyhow::Result<()> {
             true => SubcommandMineMetricsMode::RunMetricsServer,
             false => SubcommandMineMetricsMode::NoMetricsServer
         };
-        SubcommandMine::run(metrics_mode).await?;
+        let mut miner_target: Option<MinerTarget> = None;
+        if let Some(value) = sub_m.value_of("target") {
+            miner_target = Some(MinerTarget::parse_oeis_ids(value)?);
+        }
+        if let Some(value) = sub_m.value_of("target-terms") {
+            miner_target = Some(MinerTarget::parse_term_pattern(value)?);
+        }
+        SubcommandMine::run(metrics_mode, miner_target).await?;
+        return Ok(());
+    }
+
+    if let Some(_sub_m) = matches.subcommand_matches("upload-outbox") {
+        SubcommandUploadOutbox::run().await?;
         return Ok(());
     }
 
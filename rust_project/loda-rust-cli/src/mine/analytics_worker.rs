@@ -2,16 +2,23 @@ use crate::analytics::{Analytics, AnalyticsDirectory};
 use crate::common::PendingProgramsWithPriority;
 use crate::config::Config;
 use crate::mine::{CoordinatorWorkerMessage, MineEventDirectoryState};
-use crate::oeis::{load_terms_to_program_id_set, TermsToProgramIdSet};
+use crate::oeis::{load_terms_to_program_id_set, retain_program_ids, terms_to_program_id_set_from_rows, StrippedRow, TermsToProgramIdSet};
 use super::{CreateFunnel, Funnel, FunnelConfig};
 use super::{CreateGenomeMutateContextMode, create_genome_mutate_context, GenomeMutateContext};
 use super::MinerWorkerMessageWithAnalytics;
 use super::{create_prevent_flooding, PreventFlooding};
-use super::{MinerSyncExecute, MinerSyncExecuteStatus};
+use super::{MinerSyncExecute, MinerSyncExecuteStatus, MinerSyncImport};
+use super::{DistributedClient, TargetShard};
+use super::MinerTarget;
 use bastion::prelude::*;
 use num_bigint::{BigInt, ToBigInt};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::Duration;
+
+const SNAPSHOT_MAX_ATTEMPTS: usize = 20;
+const SNAPSHOT_RETRY_INTERVAL_SECONDS: u64 = 30;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AnalyticsWorkerMessage {
@@ -22,6 +29,8 @@ pub async fn analytics_worker(
     ctx: BastionContext,
     config: Config,
     prevent_flooding: Arc<Mutex<PreventFlooding>>,
+    analytics_ready: Arc<RwLock<bool>>,
+    miner_target: Option<MinerTarget>,
 ) -> Result<(), ()> {
     loop {
         let message: SignedMessage = match ctx.recv().await {
@@ -40,7 +49,7 @@ pub async fn analytics_worker(
                 );
                 match message {
                     AnalyticsWorkerMessage::PerformSyncAndAnalytics => {
-                        perform_sync_and_analytics(&config, prevent_flooding.clone());
+                        perform_sync_and_analytics(&config, prevent_flooding.clone(), &analytics_ready, miner_target.as_ref());
                     },
                 }
             })
@@ -54,40 +63,98 @@ pub async fn analytics_worker(
     }
 }
 
-fn perform_sync_and_analytics(
-    config: &Config,
-    prevent_flooding: Arc<Mutex<PreventFlooding>>,
-) {
+/// Update the local "loda-programs" repository.
+/// 
+/// When mining offline, the sync script is not run. Instead programs are imported from a dir, if one is configured.
+fn perform_sync(config: &Config) -> anyhow::Result<MinerSyncExecuteStatus> {
+    if config.miner_offline() {
+        let import_dir: PathBuf = match config.miner_offline_import_dir() {
+            Some(value) => value,
+            None => {
+                println!("Mining offline. Skipping sync.");
+                return Ok(MinerSyncExecuteStatus::NoChange);
+            }
+        };
+        return MinerSyncImport::execute(&import_dir, &config.loda_programs_oeis_dir());
+    }
     let command_windows: String = config.miner_sync_executable_command_windows();
     let executable_path: PathBuf = config.miner_sync_executable();
-    let sync_status: MinerSyncExecuteStatus = match MinerSyncExecute::execute(&command_windows, &executable_path) {
-        Ok(value) => value,
-        Err(error) => {
-            Bastion::stop();
-            panic!("Problem executing MinerSyncExecute. config.miner_sync_executable_command_windows: {:?}  config.miner_sync_executable: {:?}  error: {:?}", command_windows, executable_path, error);
+    MinerSyncExecute::execute(&command_windows, &executable_path)
+        .map_err(|e| anyhow::anyhow!("Problem executing MinerSyncExecute. config.miner_sync_executable_command_windows: {:?}  config.miner_sync_executable: {:?}  error: {:?}", command_windows, executable_path, e))
+}
+
+/// Worker nodes obtain the analytics from the head node, instead of generating the analytics.
+fn download_snapshot_from_head_node(config: &Config, address: String) -> anyhow::Result<TargetShard> {
+    let worker_name: String = DistributedClient::worker_name_from_config(config)?;
+    let client = DistributedClient::new(address.clone(), config.miner_coordinator_secret(), worker_name.clone());
+    let target_shard: TargetShard = client.hello()?;
+    println!("Mining as worker {:?} for the head node at {:?}, with shard {} of {}", worker_name, address, target_shard.index, target_shard.count);
+    for attempt in 1..=SNAPSHOT_MAX_ATTEMPTS {
+        if client.download_snapshot(&config.analytics_oeis_dir(), &config.oeis_stripped_file())?.is_some() {
+            return Ok(target_shard);
         }
-    };
-    println!("Successfully executed MinerSyncExecute. status: {:?}", sync_status);
+        println!("The head node is regenerating its analytics. Attempt {} of {}, retrying in {} seconds.", attempt, SNAPSHOT_MAX_ATTEMPTS, SNAPSHOT_RETRY_INTERVAL_SECONDS);
+        thread::sleep(Duration::from_secs(SNAPSHOT_RETRY_INTERVAL_SECONDS));
+    }
+    Err(anyhow::anyhow!("The head node at {:?} didn't have its analytics ready", address))
+}
 
-    let analytics_run_result: anyhow::Result<()> = match sync_status {
+/// Regenerate the analytics if needed.
+/// 
+/// While this is going on, the write lock is held, so the head node doesn't hand out the analytics snapshot to the worker nodes.
+fn perform_analytics(sync_status: MinerSyncExecuteStatus, analytics_ready: &RwLock<bool>) -> anyhow::Result<()> {
+    let mut analytics_ready_guard = analytics_ready.write()
+        .map_err(|e| anyhow::anyhow!("The analytics lock is poisoned. error: {:?}", e))?;
+    *analytics_ready_guard = false;
+    match sync_status {
         MinerSyncExecuteStatus::NoChange => {
             // Data is already uptodate, then skip no need to regenerate analytics.
             println!("BEFORE analytics - run_if_expired");
-            Analytics::oeis_run_if_expired()
+            Analytics::oeis_run_if_expired()?;
         },
         MinerSyncExecuteStatus::Changed => {
             // Data has been modified, then analytics needs to be regenerated.
             println!("BEFORE analytics - run_force");
-            Analytics::oeis_run_force()
+            Analytics::oeis_run_force()?;
         }
-    };
-    match analytics_run_result {
-        Ok(()) => {},
+    }
+    *analytics_ready_guard = true;
+    Ok(())
+}
+
+fn perform_sync_and_analytics(
+    config: &Config,
+    prevent_flooding: Arc<Mutex<PreventFlooding>>,
+    analytics_ready: &RwLock<bool>,
+    miner_target: Option<&MinerTarget>,
+) {
+    let sync_status: MinerSyncExecuteStatus = match perform_sync(config) {
+        Ok(value) => value,
         Err(error) => {
             Bastion::stop();
-            panic!("AFTER analytics. error: {:?}", error);
+            panic!("Problem performing sync. error: {:?}", error);
         }
-    }
+    };
+    println!("Successfully performed sync. status: {:?}", sync_status);
+
+    let target_shard: Option<TargetShard> = match config.miner_coordinator_address() {
+        Some(address) => {
+            match download_snapshot_from_head_node(config, address) {
+                Ok(value) => Some(value),
+                Err(error) => {
+                    Bastion::stop();
+                    panic!("analytics_worker: Unable to obtain snapshot from the head node. error: {:?}", error);
+                }
+            }
+        },
+        None => {
+            if let Err(error) = perform_analytics(sync_status, analytics_ready) {
+                Bastion::stop();
+                panic!("AFTER analytics. error: {:?}", error);
+            }
+            None
+        }
+    };
 
     let prevent_flooding_x: PreventFlooding = match create_prevent_flooding(&config) {
         Ok(value) => value,
@@ -106,26 +173,71 @@ fn perform_sync_and_analytics(
         }
     }
 
-    println!("populating terms_to_program_id");
+    // When mining targeted, only the target sequences go into terms_to_program_id and the funnel.
     let oeis_stripped_file: PathBuf = config.oeis_stripped_file();
+    let target_rows: Option<Vec<StrippedRow>> = match miner_target {
+        Some(miner_target) => {
+            let rows: Vec<StrippedRow> = match miner_target.load_rows(&oeis_stripped_file) {
+                Ok(value) => value,
+                Err(error) => {
+                    Bastion::stop();
+                    panic!("analytics_worker: Unable to find the target sequences. error: {:?}", error);
+                }
+            };
+            miner_target.print_summary(&rows);
+            if rows.is_empty() {
+                Bastion::stop();
+                panic!("analytics_worker: None of the target sequences can be mined for. target: {:?}", miner_target);
+            }
+            Some(rows)
+        },
+        None => None
+    };
+
+    // When mining as a worker node, only the sequences in the shard go into terms_to_program_id and the funnel.
+    // The target sequences are picked by hand and are few, so they are not split into shards.
+    let target_shard: Option<TargetShard> = match (&target_rows, target_shard) {
+        (None, Some(target_shard)) if target_shard.count > 1 => Some(target_shard),
+        _ => None
+    };
+
+    println!("populating terms_to_program_id");
     let padding_value: BigInt = FunnelConfig::WILDCARD_MAGIC_VALUE.to_bigint().unwrap();
-    let terms_to_program_id_result = load_terms_to_program_id_set(
-        &oeis_stripped_file, 
-        FunnelConfig::MINIMUM_NUMBER_OF_REQUIRED_TERMS, 
-        FunnelConfig::TERM_COUNT,
-        &padding_value
-    );
-    let terms_to_program_id: TermsToProgramIdSet = match terms_to_program_id_result {
+    let terms_to_program_id_result = match &target_rows {
+        Some(rows) => Ok(terms_to_program_id_set_from_rows(rows)),
+        None => load_terms_to_program_id_set(
+            &oeis_stripped_file, 
+            FunnelConfig::MINIMUM_NUMBER_OF_REQUIRED_TERMS, 
+            FunnelConfig::TERM_COUNT,
+            &padding_value
+        )
+    };
+    let mut terms_to_program_id: TermsToProgramIdSet = match terms_to_program_id_result {
         Ok(value) => value,
         Err(error) => {
             Bastion::stop();
             panic!("analytics_worker: Unable to load terms for program ids. error: {:?}", error);
         }
     };
+    if let Some(target_shard) = &target_shard {
+        retain_program_ids(&mut terms_to_program_id, |program_id| target_shard.contains(program_id));
+    }
     let terms_to_program_id_arc: Arc<TermsToProgramIdSet> = Arc::new(terms_to_program_id);
 
     println!("populating funnel");
-    let funnel: Funnel = Funnel::create_funnel_with_file_data(&config);
+    let funnel: Funnel = match (&target_rows, &target_shard) {
+        (Some(rows), _) => Funnel::create_funnel_with_rows(rows),
+        (None, Some(target_shard)) => {
+            match Funnel::create_funnel_with_shard(config, target_shard) {
+                Ok(value) => value,
+                Err(error) => {
+                    Bastion::stop();
+                    panic!("analytics_worker: Unable to populate the funnel for the target shard. error: {:?}", error);
+                }
+            }
+        },
+        (None, None) => Funnel::create_funnel_with_file_data(config)
+    };
 
     println!("populating genome_mutate_context");
     let analytics_directory = AnalyticsDirectory::new(
@@ -140,6 +252,7 @@ fn perform_sync_and_analytics(
         funnel,
         genome_mutate_context,
         terms_to_program_id_arc,
+        target_rows.is_some(),
     );
     let arc_instance = Arc::new(instance);
     debug!("analytics_worker: miner_workers.ask_everyone(MinerWorkerMessageWithAnalytics)");
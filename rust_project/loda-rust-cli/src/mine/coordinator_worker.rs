@@ -19,6 +19,10 @@ pub enum CoordinatorWorkerMessage {
     /// This synchronizes the `loda-programs` repository.
     /// And regenerates the `~/.loda-rust/analytics` directory.
     TriggerSync,
+
+    /// Invoked by the `DistributedCoordinator` when a worker node has mined a candidate program,
+    /// that has been saved to the `mine-event` dir.
+    RemoteCandidateProgram { high_priority: bool },
 }
 
 #[derive(Debug, Clone)]
@@ -62,6 +66,15 @@ pub async fn coordinator_worker(
                     },
                     CoordinatorWorkerMessage::PostmineJobComplete => {
                         state_machine.postmine_job_is_complete();
+                    },
+                    CoordinatorWorkerMessage::RemoteCandidateProgram { high_priority } => {
+                        let mut execute_batch_result = ExecuteBatchResult::new();
+                        if high_priority {
+                            execute_batch_result.increment_number_of_mined_high_prio();
+                        } else {
+                            execute_batch_result.increment_number_of_mined_low_prio();
+                        }
+                        state_machine.remote_candidate_program(&execute_batch_result);
                     }
                 }
             })
@@ -144,6 +157,11 @@ trait State: Send {
     fn postmine_job_is_complete(self: Box<Self>) -> Box<dyn State>;
     fn timeout(self: Box<Self>) -> Box<dyn State>;
     fn trigger_sync(self: Box<Self>) -> Box<dyn State>;
+
+    /// A candidate program from a worker node.
+    /// 
+    /// Outside of mining, it stays in the `mine-event` dir until the next time `postmine` runs.
+    fn remote_candidate_program(self: Box<Self>, execute_batch_result: &ExecuteBatchResult) -> Box<dyn State>;
 }
 
 struct InitialState;
@@ -186,6 +204,10 @@ impl State for InitialState {
     fn trigger_sync(self: Box<Self>) -> Box<dyn State> {
         self
     }
+
+    fn remote_candidate_program(self: Box<Self>, _execute_batch_result: &ExecuteBatchResult) -> Box<dyn State> {
+        self
+    }
 }
 
 struct RunLaunchProcedureInProgressState {
@@ -242,6 +264,10 @@ impl State for RunLaunchProcedureInProgressState {
             trigger_sync: true,
         })
     }
+
+    fn remote_candidate_program(self: Box<Self>, _execute_batch_result: &ExecuteBatchResult) -> Box<dyn State> {
+        self
+    }
 }
 
 struct MiningInProgressState {
@@ -302,6 +328,10 @@ impl State for MiningInProgressState {
             self.mineevent_dir_state,
         ))
     }
+
+    fn remote_candidate_program(self: Box<Self>, execute_batch_result: &ExecuteBatchResult) -> Box<dyn State> {
+        self.miner_worker_executed_one_batch(execute_batch_result)
+    }
 }
 
 /// Wait for all miner_worker instances to complete their mining job
@@ -389,6 +419,10 @@ impl State for MiningIsStoppingState {
             start_time: self.start_time, 
         })
     }
+
+    fn remote_candidate_program(self: Box<Self>, execute_batch_result: &ExecuteBatchResult) -> Box<dyn State> {
+        self.miner_worker_executed_one_batch(execute_batch_result)
+    }
 }
 
 struct PostmineInProgressState {
@@ -457,6 +491,10 @@ impl State for PostmineInProgressState {
             mineevent_dir_state: self.mineevent_dir_state,
         })
     }
+
+    fn remote_candidate_program(self: Box<Self>, _execute_batch_result: &ExecuteBatchResult) -> Box<dyn State> {
+        self
+    }
 }
 
 struct StateMachine {
@@ -510,6 +548,12 @@ impl StateMachine {
         }
     }
 
+    fn remote_candidate_program(&mut self, execute_batch_result: &ExecuteBatchResult) {
+        if let Some(state) = self.state.take() {
+            self.state = Some(state.remote_candidate_program(execute_batch_result));
+        }
+    }
+
     fn trigger_sync(&mut self) {
         println!("coordinator_worker: trigger_sync - scheduling \"sync\" as soon as possible");
         if let Some(state) = self.state.take() {
@@ -1,9 +1,20 @@
+use crate::analytics::AnalyticsDirectory;
+use crate::common::load_program_ids_csv_file;
 use crate::config::{Config, MinerFilterMode};
-use super::{CheckFixedLengthSequence, Funnel, NamedCacheFile};
-use std::path::PathBuf;
+use crate::oeis::{ProcessStrippedFile, StrippedRow};
+use super::{CheckFixedLengthSequence, Funnel, FunnelConfig, NamedCacheFile, TargetShard};
+use bloomfilter::*;
+use loda_rust_core::oeis::{OeisId, OeisIdHashSet};
+use loda_rust_core::util::BigIntVec;
+use num_bigint::{BigInt, ToBigInt};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
 
 pub trait CreateFunnel {
     fn create_funnel_with_file_data(config: &Config) -> Funnel;
+    fn create_funnel_with_rows(rows: &[StrippedRow]) -> Funnel;
+    fn create_funnel_with_shard(config: &Config, target_shard: &TargetShard) -> anyhow::Result<Funnel>;
     fn create_empty_funnel() -> Funnel;
 }
 
@@ -30,6 +41,88 @@ impl CreateFunnel for Funnel {
         )
     }
 
+    /// Populate the bloomfilters with only these sequences, such as when mining targeted.
+    /// 
+    /// The rows must be padded to 40 terms.
+    fn create_funnel_with_rows(rows: &[StrippedRow]) -> Funnel {
+        let bloom_items_count: usize = rows.len().max(1);
+        let false_positive_rate: f64 = FunnelConfig::BLOOMFILTER_FALSE_POSITIVE_RATE;
+        let mut bloom10 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let mut bloom20 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let mut bloom30 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let mut bloom40 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        for row in rows {
+            let terms: &BigIntVec = row.terms();
+            assert!(terms.len() >= FunnelConfig::TERM_COUNT);
+            bloom10.set(&terms[0..10].to_vec());
+            bloom20.set(&terms[0..20].to_vec());
+            bloom30.set(&terms[0..30].to_vec());
+            bloom40.set(&terms[0..40].to_vec());
+        }
+        Funnel::new(
+            CheckFixedLengthSequence::new(bloom10),
+            CheckFixedLengthSequence::new(bloom20),
+            CheckFixedLengthSequence::new(bloom30),
+            CheckFixedLengthSequence::new(bloom40),
+        )
+    }
+
+    /// Populate the bloomfilters with only the sequences in the shard, when mining as a worker node.
+    ///
+    /// The sequences are picked from the oeis 'stripped' file the same way as the cached bloomfilters,
+    /// so the `miner_filter_mode` is respected.
+    fn create_funnel_with_shard(config: &Config, target_shard: &TargetShard) -> anyhow::Result<Funnel> {
+        let analytics_directory = AnalyticsDirectory::new(config.analytics_oeis_dir())?;
+        let mut ignore_files: Vec<PathBuf> = vec![analytics_directory.dont_mine_file()];
+        if config.miner_filter_mode() == MinerFilterMode::New {
+            ignore_files.push(analytics_directory.programs_valid_file());
+            ignore_files.push(analytics_directory.programs_invalid_file());
+        }
+        let mut oeis_ids_to_ignore = OeisIdHashSet::new();
+        for path in &ignore_files {
+            oeis_ids_to_ignore.extend(load_oeis_ids(path)?);
+        }
+
+        let oeis_stripped_file: PathBuf = config.oeis_stripped_file();
+        let file = File::open(&oeis_stripped_file)
+            .map_err(|e| anyhow::anyhow!("Unable to open oeis stripped file: {:?} error: {:?}", oeis_stripped_file, e))?;
+        let mut reader = BufReader::new(file);
+        let bloom_items_count: usize = (FunnelConfig::BLOOMFILTER_CAPACITY / (target_shard.count.max(1) as usize)).max(1);
+        let false_positive_rate: f64 = FunnelConfig::BLOOMFILTER_FALSE_POSITIVE_RATE;
+        let mut bloom10 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let mut bloom20 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let mut bloom30 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let mut bloom40 = Bloom::<BigIntVec>::new_for_fp_rate(bloom_items_count, false_positive_rate);
+        let callback = |row: &StrippedRow, _| {
+            if !target_shard.contains(row.oeis_id().raw()) {
+                return;
+            }
+            let terms: &BigIntVec = row.terms();
+            bloom10.set(&terms[0..10].to_vec());
+            bloom20.set(&terms[0..20].to_vec());
+            bloom30.set(&terms[0..30].to_vec());
+            bloom40.set(&terms[0..40].to_vec());
+        };
+        let padding_value: BigInt = FunnelConfig::WILDCARD_MAGIC_VALUE.to_bigint().unwrap();
+        let mut processor = ProcessStrippedFile::new();
+        processor.execute(
+            &mut reader,
+            FunnelConfig::MINIMUM_NUMBER_OF_REQUIRED_TERMS,
+            FunnelConfig::TERM_COUNT,
+            &oeis_ids_to_ignore,
+            &padding_value,
+            true,
+            callback
+        );
+        let funnel = Funnel::new(
+            CheckFixedLengthSequence::new(bloom10),
+            CheckFixedLengthSequence::new(bloom20),
+            CheckFixedLengthSequence::new(bloom30),
+            CheckFixedLengthSequence::new(bloom40),
+        );
+        Ok(funnel)
+    }
+
     fn create_empty_funnel() -> Funnel {
         let checker10: CheckFixedLengthSequence = CheckFixedLengthSequence::new_empty();
         let checker20: CheckFixedLengthSequence = CheckFixedLengthSequence::new_empty();
@@ -43,3 +136,9 @@ impl CreateFunnel for Funnel {
         )
     }
 }
+
+fn load_oeis_ids(path: &Path) -> anyhow::Result<OeisIdHashSet> {
+    let program_ids: Vec<u32> = load_program_ids_csv_file(path)
+        .map_err(|e| anyhow::anyhow!("Unable to load program ids: {:?} error: {:?}", path, e))?;
+    Ok(program_ids.into_iter().map(OeisId::from).collect())
+}
@@ -0,0 +1,217 @@
+use super::{read_message, save_candidate_program, write_message, DistributedRequest, DistributedResponse, TargetShard, MAX_RESPONSE_LENGTH};
+use crate::common::save_file_atomic;
+use crate::config::Config;
+use loda_rust_core::util::{BigIntVec, BigIntVecToString};
+use std::fs;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT_SECONDS: u64 = 10;
+
+/// The head node replies right away to most requests.
+const READ_TIMEOUT_SECONDS: u64 = 30;
+
+/// Transferring the snapshot can take a while, since the oeis `stripped` file is big.
+const SNAPSHOT_READ_TIMEOUT_SECONDS: u64 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistributedCandidateStatus {
+    Accepted,
+    Duplicate,
+}
+
+/// The worker node's connection to the head node, when mining distributed.
+#[derive(Clone, Debug)]
+pub struct DistributedClient {
+    address: String,
+    secret: String,
+    worker_name: String,
+}
+
+impl DistributedClient {
+    /// The `secret` must match the head node's `miner_coordinator_secret`.
+    pub fn new(address: String, secret: String, worker_name: String) -> Self {
+        Self { address, secret, worker_name }
+    }
+
+    /// The `miner_coordinator_worker_name` from the config, otherwise the hostname.
+    pub fn worker_name_from_config(config: &Config) -> anyhow::Result<String> {
+        if let Some(worker_name) = config.miner_coordinator_worker_name() {
+            return Ok(worker_name);
+        }
+        let output = Command::new("hostname").output()
+            .map_err(|e| anyhow::anyhow!("Unable to obtain the hostname. Set 'miner_coordinator_worker_name' in the config. error: {:?}", e))?;
+        let hostname: String = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !output.status.success() || hostname.is_empty() {
+            return Err(anyhow::anyhow!("Unable to obtain the hostname. Set 'miner_coordinator_worker_name' in the config."));
+        }
+        Ok(hostname)
+    }
+
+    fn connect(&self) -> anyhow::Result<TcpStream> {
+        let addresses: Vec<SocketAddr> = self.address.to_socket_addrs()
+            .map_err(|e| anyhow::anyhow!("Unable to resolve the head node address {:?}. error: {:?}", self.address, e))?
+            .collect();
+        let mut last_error: Option<std::io::Error> = None;
+        for address in &addresses {
+            match TcpStream::connect_timeout(address, Duration::from_secs(CONNECT_TIMEOUT_SECONDS)) {
+                Ok(stream) => {
+                    return Ok(stream);
+                },
+                Err(error) => {
+                    last_error = Some(error);
+                }
+            }
+        }
+        Err(anyhow::anyhow!("Unable to connect to the head node at {:?}. error: {:?}", self.address, last_error))
+    }
+
+    fn request(&self, request: &DistributedRequest, read_timeout: Duration) -> anyhow::Result<DistributedResponse> {
+        let stream: TcpStream = self.connect()?;
+        stream.set_read_timeout(Some(read_timeout))?;
+        stream.set_write_timeout(Some(Duration::from_secs(READ_TIMEOUT_SECONDS)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer: TcpStream = stream;
+        write_message(&mut writer, request)?;
+        let response: DistributedResponse = match read_message(&mut reader, MAX_RESPONSE_LENGTH)? {
+            Some(value) => value,
+            None => {
+                return Err(anyhow::anyhow!("The head node at {:?} closed the connection without a response", self.address));
+            }
+        };
+        if let DistributedResponse::Error { message } = response {
+            return Err(anyhow::anyhow!("The head node at {:?} responded with an error: {}", self.address, message));
+        }
+        Ok(response)
+    }
+
+    /// Obtain the target shard. The head node hands out the same shard, every time the same worker name says hello.
+    pub fn hello(&self) -> anyhow::Result<TargetShard> {
+        let request = DistributedRequest::Hello {
+            worker_name: self.worker_name.clone(),
+            secret: self.secret.clone(),
+        };
+        match self.request(&request, Duration::from_secs(READ_TIMEOUT_SECONDS))? {
+            DistributedResponse::Welcome { shard } => Ok(shard),
+            response => Err(anyhow::anyhow!("Unexpected response to hello: {:?}", response))
+        }
+    }
+
+    /// Replace the files in the `analytics_dir` and the `oeis_stripped_file` with the ones from the head node.
+    ///
+    /// Returns `None` when the head node is busy regenerating its analytics.
+    pub fn download_snapshot(&self, analytics_dir: &Path, oeis_stripped_file: &Path) -> anyhow::Result<Option<String>> {
+        let request = DistributedRequest::Snapshot { secret: self.secret.clone() };
+        let (snapshot_id, files, oeis_stripped) = match self.request(&request, Duration::from_secs(SNAPSHOT_READ_TIMEOUT_SECONDS))? {
+            DistributedResponse::Snapshot { snapshot_id, files, oeis_stripped } => (snapshot_id, files, oeis_stripped),
+            DistributedResponse::NotReady => {
+                return Ok(None);
+            },
+            response => {
+                return Err(anyhow::anyhow!("Unexpected response to snapshot: {:?}", response));
+            }
+        };
+        fs::create_dir_all(analytics_dir)?;
+        for file in &files {
+            // Only plain filenames are accepted, so the head node cannot write outside the analytics dir.
+            let is_plain_filename: bool = Path::new(&file.name).file_name().map(|name| name == file.name.as_str()).unwrap_or(false);
+            if !is_plain_filename {
+                return Err(anyhow::anyhow!("The snapshot contains an invalid filename: {:?}", file.name));
+            }
+            save_text_file(&analytics_dir.join(&file.name), &file.content)?;
+        }
+        if let Some(parent) = oeis_stripped_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        save_text_file(oeis_stripped_file, &oeis_stripped)?;
+        println!("DistributedClient: downloaded snapshot {:?} with {} files", snapshot_id, files.len());
+        Ok(Some(snapshot_id))
+    }
+
+    pub fn submit_candidate(&self, terms40: &BigIntVec, program: &str, high_priority: bool) -> anyhow::Result<DistributedCandidateStatus> {
+        let request = DistributedRequest::Candidate {
+            worker_name: self.worker_name.clone(),
+            secret: self.secret.clone(),
+            terms40: terms40.to_compact_comma_string(),
+            program: program.to_string(),
+            high_priority,
+        };
+        match self.request(&request, Duration::from_secs(READ_TIMEOUT_SECONDS))? {
+            DistributedResponse::Accepted => Ok(DistributedCandidateStatus::Accepted),
+            DistributedResponse::Duplicate => Ok(DistributedCandidateStatus::Duplicate),
+            response => Err(anyhow::anyhow!("Unexpected response to candidate: {:?}", response))
+        }
+    }
+}
+
+struct DistributedCandidate {
+    terms40: BigIntVec,
+    program: String,
+    high_priority: bool,
+    iteration: usize,
+}
+
+/// Submits the candidate programs to the head node in the background, so the miner isn't blocked by the network.
+///
+/// If the head node cannot be reached, then the program is saved to the local `mine-event` dir.
+/// Programs that the head node already has, are counted as prevented floodings on the head node.
+///
+/// The background thread stops when the `DistributedCandidateSender` is dropped.
+pub struct DistributedCandidateSender {
+    sender: Sender<DistributedCandidate>,
+}
+
+impl DistributedCandidateSender {
+    pub fn spawn(client: DistributedClient, mine_event_dir: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel::<DistributedCandidate>();
+        thread::spawn(move || {
+            Self::run(client, mine_event_dir, receiver);
+        });
+        Self { sender }
+    }
+
+    pub fn send(&self, terms40: &BigIntVec, program: &str, high_priority: bool, iteration: usize) {
+        let candidate = DistributedCandidate {
+            terms40: terms40.clone(),
+            program: program.to_string(),
+            high_priority,
+            iteration,
+        };
+        if let Err(error) = self.sender.send(candidate) {
+            error!("DistributedCandidateSender: the background thread has stopped. error: {:?}", error);
+        }
+    }
+
+    fn run(client: DistributedClient, mine_event_dir: PathBuf, receiver: Receiver<DistributedCandidate>) {
+        for candidate in receiver {
+            match client.submit_candidate(&candidate.terms40, &candidate.program, candidate.high_priority) {
+                Ok(DistributedCandidateStatus::Accepted) => {
+                    debug!("DistributedCandidateSender: the head node accepted the candidate program");
+                    continue;
+                },
+                Ok(DistributedCandidateStatus::Duplicate) => {
+                    debug!("DistributedCandidateSender: the head node already has a program with the same terms");
+                    continue;
+                },
+                Err(error) => {
+                    error!("Unable to send candidate program to the head node, saving it locally instead. error: {:?}", error);
+                }
+            }
+            if let Err(error) = save_candidate_program(&mine_event_dir, candidate.iteration, &candidate.program) {
+                error!("DistributedCandidateSender: Unable to save candidate program: {:?}", error);
+            }
+        }
+    }
+}
+
+fn save_text_file(path: &Path, content: &str) -> anyhow::Result<()> {
+    save_file_atomic(path, |tmp_path| {
+        fs::write(tmp_path, content)
+            .map_err(|e| anyhow::anyhow!("Unable to write file: {:?} error: {:?}", path, e))
+    })
+}
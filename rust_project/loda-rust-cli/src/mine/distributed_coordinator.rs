@@ -0,0 +1,470 @@
+use super::{save_candidate_program_with_suffix, PreventFlooding};
+use super::{read_message, write_message, DistributedRequest, DistributedResponse, DistributedSnapshotFile, TargetShard, MAX_REQUEST_LENGTH};
+use crate::analytics::AnalyticsDirectory;
+use loda_rust_core::util::BigIntVec;
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of worker connections that are served at the same time. Further connections are closed right away.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Connections that are idle for longer than this are closed.
+const CONNECTION_READ_TIMEOUT_SECONDS: u64 = 60;
+
+/// The worker nodes say hello every time they sync, which is once every 24 hours.
+/// A worker that hasn't been heard from for longer than this, is forgotten, so its shard can be handed out again.
+const WORKER_EXPIRE_SECONDS: u64 = 2 * 24 * 60 * 60;
+
+#[derive(Clone, Debug)]
+pub struct DistributedCoordinatorSettings {
+    pub analytics_dir: PathBuf,
+    pub oeis_stripped_file: PathBuf,
+    pub mine_event_dir: PathBuf,
+    pub shard_count: u32,
+    pub secret: String,
+}
+
+struct DistributedWorker {
+    shard: TargetShard,
+    last_seen: Instant,
+}
+
+struct DistributedCoordinatorState {
+    worker_by_name: HashMap<String, DistributedWorker>,
+    number_of_candidates: usize,
+}
+
+/// The head node, when mining distributed across several machines.
+///
+/// - Hands out the analytics snapshot, so the worker nodes don't have to run analytics.
+/// - Assigns a shard of the target sequences to each worker node.
+/// - Receives the candidate programs from the worker nodes, and saves them to the `mine-event` dir.
+///
+/// Requests that don't carry the `secret` are rejected.
+///
+/// The `PreventFlooding` instance is shared with the miners on the head node,
+/// so duplicates are rejected centrally, no matter which machine mined them.
+pub struct DistributedCoordinator {
+    settings: DistributedCoordinatorSettings,
+    prevent_flooding: Arc<Mutex<PreventFlooding>>,
+    analytics_ready: Arc<RwLock<bool>>,
+    state: Mutex<DistributedCoordinatorState>,
+    candidate_callback: Option<Box<dyn Fn(bool) + Send + Sync>>,
+}
+
+impl DistributedCoordinator {
+    /// The snapshot is only handed out while `analytics_ready` is true.
+    /// The read lock is held while the files are read, and the analytics holds the write lock while regenerating,
+    /// so the worker nodes never obtain files that are in the middle of being regenerated.
+    pub fn new(
+        settings: DistributedCoordinatorSettings,
+        prevent_flooding: Arc<Mutex<PreventFlooding>>,
+        analytics_ready: Arc<RwLock<bool>>,
+    ) -> Self {
+        let state = DistributedCoordinatorState {
+            worker_by_name: HashMap::new(),
+            number_of_candidates: 0,
+        };
+        Self {
+            settings,
+            prevent_flooding,
+            analytics_ready,
+            state: Mutex::new(state),
+            candidate_callback: None,
+        }
+    }
+
+    /// Invoked after a candidate program has been saved. The parameter is `true` for high priority programs.
+    pub fn set_candidate_callback(&mut self, c: impl Fn(bool) + Send + Sync + 'static) {
+        self.candidate_callback = Some(Box::new(c));
+    }
+
+    /// Serve the worker nodes in the background, one thread per connection.
+    ///
+    /// At most `MAX_CONNECTIONS` are served at the same time.
+    pub fn spawn(self, listener: TcpListener) -> thread::JoinHandle<()> {
+        let coordinator = Arc::new(self);
+        let number_of_connections = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream: TcpStream = match stream {
+                    Ok(value) => value,
+                    Err(error) => {
+                        error!("DistributedCoordinator: unable to accept connection. error: {:?}", error);
+                        continue;
+                    }
+                };
+                let connection_guard = match ConnectionGuard::acquire(&number_of_connections) {
+                    Some(value) => value,
+                    None => {
+                        error!("DistributedCoordinator: too many connections, closing connection from {:?}", stream.peer_addr());
+                        continue;
+                    }
+                };
+                let coordinator_clone = coordinator.clone();
+                thread::spawn(move || {
+                    let _connection_guard = connection_guard;
+                    if let Err(error) = coordinator_clone.serve_connection(stream) {
+                        error!("DistributedCoordinator: connection error: {:?}", error);
+                    }
+                });
+            }
+        })
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> anyhow::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(CONNECTION_READ_TIMEOUT_SECONDS)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer: TcpStream = stream;
+        loop {
+            let request: DistributedRequest = match read_message(&mut reader, MAX_REQUEST_LENGTH)? {
+                Some(value) => value,
+                None => {
+                    return Ok(());
+                }
+            };
+            let response: DistributedResponse = self.handle_request(request);
+            write_message(&mut writer, &response)?;
+        }
+    }
+
+    pub fn handle_request(&self, request: DistributedRequest) -> DistributedResponse {
+        let secret: &str = match &request {
+            DistributedRequest::Hello { secret, .. } => secret,
+            DistributedRequest::Snapshot { secret } => secret,
+            DistributedRequest::Candidate { secret, .. } => secret,
+        };
+        if secret != self.settings.secret {
+            error!("DistributedCoordinator: rejected request with wrong secret");
+            return DistributedResponse::Error { message: "Wrong secret".to_string() };
+        }
+        let result: anyhow::Result<DistributedResponse> = match request {
+            DistributedRequest::Hello { worker_name, .. } => {
+                Ok(self.hello(worker_name))
+            },
+            DistributedRequest::Snapshot { .. } => {
+                self.snapshot()
+            },
+            DistributedRequest::Candidate { worker_name, terms40, program, high_priority, .. } => {
+                self.candidate(&worker_name, &terms40, &program, high_priority)
+            }
+        };
+        match result {
+            Ok(response) => response,
+            Err(error) => {
+                error!("DistributedCoordinator: unable to handle request. error: {:?}", error);
+                DistributedResponse::Error { message: error.to_string() }
+            }
+        }
+    }
+
+    /// A worker that reconnects gets the same shard as before.
+    /// A new worker gets the shard with the fewest workers.
+    fn hello(&self, worker_name: String) -> DistributedResponse {
+        let shard_count: u32 = self.settings.shard_count.max(1);
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        state.worker_by_name.retain(|_name, worker| {
+            now.duration_since(worker.last_seen) < Duration::from_secs(WORKER_EXPIRE_SECONDS)
+        });
+        if let Some(worker) = state.worker_by_name.get_mut(&worker_name) {
+            worker.last_seen = now;
+        } else {
+            let mut number_of_workers_by_index: Vec<usize> = vec![0; shard_count as usize];
+            for worker in state.worker_by_name.values() {
+                if let Some(count) = number_of_workers_by_index.get_mut(worker.shard.index as usize) {
+                    *count += 1;
+                }
+            }
+            let index: usize = number_of_workers_by_index.iter().enumerate()
+                .min_by_key(|(_index, count)| **count)
+                .map_or(0, |(index, _count)| index);
+            let shard = TargetShard {
+                index: index as u32,
+                count: shard_count,
+            };
+            state.worker_by_name.insert(worker_name.clone(), DistributedWorker { shard, last_seen: now });
+        }
+        let shard: TargetShard = state.worker_by_name[&worker_name].shard;
+        println!("DistributedCoordinator: worker {:?} is assigned shard {} of {}", worker_name, shard.index, shard.count);
+        DistributedResponse::Welcome { shard }
+    }
+
+    fn snapshot(&self) -> anyhow::Result<DistributedResponse> {
+        // The guard is held until the files have been read, so the analytics cannot regenerate them meanwhile.
+        let analytics_ready: RwLockReadGuard<bool> = match self.analytics_ready.try_read() {
+            Ok(value) => value,
+            Err(TryLockError::WouldBlock) => {
+                return Ok(DistributedResponse::NotReady);
+            },
+            Err(TryLockError::Poisoned(error)) => {
+                return Err(anyhow::anyhow!("The analytics lock is poisoned. error: {:?}", error));
+            }
+        };
+        if !*analytics_ready {
+            return Ok(DistributedResponse::NotReady);
+        }
+        let analytics_directory = AnalyticsDirectory::new(self.settings.analytics_dir.clone())?;
+        let snapshot_id: String = match fs::read_to_string(analytics_directory.last_analytics_timestamp_file()) {
+            Ok(value) => value.trim().to_string(),
+            Err(_) => {
+                return Ok(DistributedResponse::NotReady);
+            }
+        };
+        let mut paths: Vec<PathBuf> = vec!();
+        for entry in fs::read_dir(&self.settings.analytics_dir)? {
+            let path: PathBuf = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let mut files: Vec<DistributedSnapshotFile> = vec!();
+        for path in &paths {
+            let name: String = match path.file_name() {
+                Some(value) => value.to_string_lossy().to_string(),
+                None => continue
+            };
+            let content: String = fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Unable to read analytics file: {:?} error: {:?}", path, e))?;
+            files.push(DistributedSnapshotFile { name, content });
+        }
+        let oeis_stripped: String = fs::read_to_string(&self.settings.oeis_stripped_file)
+            .map_err(|e| anyhow::anyhow!("Unable to read oeis stripped file: {:?} error: {:?}", self.settings.oeis_stripped_file, e))?;
+        drop(analytics_ready);
+        println!("DistributedCoordinator: handing out snapshot {:?} with {} files", snapshot_id, files.len());
+        Ok(DistributedResponse::Snapshot { snapshot_id, files, oeis_stripped })
+    }
+
+    fn candidate(&self, worker_name: &str, terms40: &str, program: &str, high_priority: bool) -> anyhow::Result<DistributedResponse> {
+        let mut terms: BigIntVec = vec!();
+        for item in terms40.split(',') {
+            let value: BigInt = BigInt::from_str(item.trim())
+                .map_err(|e| anyhow::anyhow!("Unable to parse terms40: {:?} error: {:?}", terms40, e))?;
+            terms.push(value);
+        }
+        {
+            let mut prevent_flooding = self.prevent_flooding.lock().unwrap();
+            if prevent_flooding.try_register(&terms).is_err() {
+                debug!("DistributedCoordinator: already contained in prevent flooding dictionary");
+                return Ok(DistributedResponse::Duplicate);
+            }
+        }
+        let iteration: usize = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(worker) = state.worker_by_name.get_mut(worker_name) {
+                worker.last_seen = Instant::now();
+            }
+            state.number_of_candidates += 1;
+            state.number_of_candidates
+        };
+        // The "remote" prefix prevents collisions with the filenames of the miners on the head node.
+        let suffix: String = format!("remote-{}-{}", sanitize_worker_name(worker_name), iteration);
+        save_candidate_program_with_suffix(&self.settings.mine_event_dir, &suffix, &program.to_string())?;
+        if let Some(callback) = &self.candidate_callback {
+            callback(high_priority);
+        }
+        Ok(DistributedResponse::Accepted)
+    }
+}
+
+/// The worker name becomes part of a filename, so only a few characters are allowed.
+fn sanitize_worker_name(worker_name: &str) -> String {
+    worker_name.chars()
+        .take(32)
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// Counts the connections being served, and releases the slot when dropped.
+struct ConnectionGuard {
+    number_of_connections: Arc<AtomicUsize>,
+}
+
+impl ConnectionGuard {
+    fn acquire(number_of_connections: &Arc<AtomicUsize>) -> Option<Self> {
+        let previous: usize = number_of_connections.fetch_add(1, Ordering::SeqCst);
+        let guard = Self {
+            number_of_connections: number_of_connections.clone(),
+        };
+        if previous >= MAX_CONNECTIONS {
+            return None;
+        }
+        Some(guard)
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.number_of_connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mine::DistributedClient;
+    use crate::mine::distributed_client::DistributedCandidateStatus;
+    use loda_rust_core::util::BigIntVecFromI64;
+
+    fn prepare(name: &str) -> anyhow::Result<(tempfile::TempDir, DistributedCoordinatorSettings)> {
+        let tempdir = tempfile::tempdir().unwrap();
+        let basedir = PathBuf::from(&tempdir.path()).join(name);
+        let analytics_dir: PathBuf = basedir.join("analytics-oeis");
+        let mine_event_dir: PathBuf = basedir.join("mine-event");
+        fs::create_dir_all(&analytics_dir)?;
+        fs::create_dir_all(&mine_event_dir)?;
+        fs::write(analytics_dir.join("last_analytics_timestamp.txt"), "2022-01-01T00:00:00Z\n")?;
+        fs::write(analytics_dir.join("dont_mine.csv"), "program id\n45\n")?;
+        let oeis_stripped_file: PathBuf = basedir.join("stripped");
+        fs::write(&oeis_stripped_file, "A000045 ,0,1,1,2,3,5,8,\n")?;
+        let settings = DistributedCoordinatorSettings {
+            analytics_dir,
+            oeis_stripped_file,
+            mine_event_dir,
+            shard_count: 2,
+            secret: "secret".to_string(),
+        };
+        Ok((tempdir, settings))
+    }
+
+    #[test]
+    fn test_10000_snapshot_not_ready() -> anyhow::Result<()> {
+        // Arrange
+        let (_tempdir, settings) = prepare("test_10000_snapshot_not_ready")?;
+        let analytics_ready = Arc::new(RwLock::new(false));
+        let prevent_flooding = Arc::new(Mutex::new(PreventFlooding::new()));
+        let coordinator = DistributedCoordinator::new(settings, prevent_flooding, analytics_ready.clone());
+        let request = DistributedRequest::Snapshot { secret: "secret".to_string() };
+
+        // Act
+        let response0: DistributedResponse = coordinator.handle_request(request.clone());
+        let response1: DistributedResponse = {
+            let mut guard = analytics_ready.write().unwrap();
+            *guard = true;
+            // Regenerating the analytics, while holding the write lock
+            coordinator.handle_request(request.clone())
+        };
+        let response2: DistributedResponse = coordinator.handle_request(request);
+
+        // Assert
+        assert_eq!(response0, DistributedResponse::NotReady);
+        assert_eq!(response1, DistributedResponse::NotReady);
+        assert!(matches!(response2, DistributedResponse::Snapshot { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_10001_wrong_secret() -> anyhow::Result<()> {
+        // Arrange
+        let (_tempdir, settings) = prepare("test_10001_wrong_secret")?;
+        let analytics_ready = Arc::new(RwLock::new(true));
+        let prevent_flooding = Arc::new(Mutex::new(PreventFlooding::new()));
+        let coordinator = DistributedCoordinator::new(settings, prevent_flooding, analytics_ready);
+        let request = DistributedRequest::Hello {
+            worker_name: "a".to_string(),
+            secret: "wrong".to_string(),
+        };
+
+        // Act
+        let response: DistributedResponse = coordinator.handle_request(request);
+
+        // Assert
+        assert_eq!(response, DistributedResponse::Error { message: "Wrong secret".to_string() });
+        Ok(())
+    }
+
+    #[test]
+    fn test_10002_hello_least_loaded_shard() -> anyhow::Result<()> {
+        // Arrange
+        let (_tempdir, mut settings) = prepare("test_10002_hello_least_loaded_shard")?;
+        settings.shard_count = 3;
+        let analytics_ready = Arc::new(RwLock::new(true));
+        let prevent_flooding = Arc::new(Mutex::new(PreventFlooding::new()));
+        let coordinator = DistributedCoordinator::new(settings, prevent_flooding, analytics_ready);
+        let hello = |worker_name: &str| -> u32 {
+            let request = DistributedRequest::Hello {
+                worker_name: worker_name.to_string(),
+                secret: "secret".to_string(),
+            };
+            match coordinator.handle_request(request) {
+                DistributedResponse::Welcome { shard } => shard.index,
+                response => panic!("unexpected response: {:?}", response)
+            }
+        };
+
+        // Act
+        let indexes: Vec<u32> = vec![
+            hello("a"), hello("b"), hello("a"), hello("c"), hello("d"), hello("b"), hello("e")
+        ];
+
+        // Assert
+        assert_eq!(indexes, vec![0, 1, 0, 2, 0, 1, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_10003_sanitize_worker_name() {
+        assert_eq!(sanitize_worker_name("worker-7_a"), "worker-7_a");
+        assert_eq!(sanitize_worker_name("../x y.local"), "___x_y_local");
+        assert_eq!(sanitize_worker_name(&"a".repeat(100)).len(), 32);
+    }
+
+    #[test]
+    fn test_20000_loopback() -> anyhow::Result<()> {
+        // Arrange
+        let (tempdir, settings) = prepare("test_20000_loopback")?;
+        let mine_event_dir: PathBuf = settings.mine_event_dir.clone();
+        let analytics_ready = Arc::new(RwLock::new(true));
+        let prevent_flooding = Arc::new(Mutex::new(PreventFlooding::new()));
+        let mut coordinator = DistributedCoordinator::new(settings, prevent_flooding, analytics_ready);
+        let number_of_callbacks = Arc::new(AtomicUsize::new(0));
+        let number_of_callbacks_clone = number_of_callbacks.clone();
+        coordinator.set_candidate_callback(move |_high_priority| {
+            number_of_callbacks_clone.fetch_add(1, Ordering::SeqCst);
+        });
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address: String = listener.local_addr()?.to_string();
+        coordinator.spawn(listener);
+        let client_a = DistributedClient::new(address.clone(), "secret".to_string(), "a".to_string());
+        let client_b = DistributedClient::new(address, "secret".to_string(), "b/c".to_string());
+        let worker_dir: PathBuf = PathBuf::from(&tempdir.path()).join("worker");
+        let worker_analytics_dir: PathBuf = worker_dir.join("analytics-oeis");
+        let worker_stripped_file: PathBuf = worker_dir.join("oeis").join("stripped");
+        let terms40: BigIntVec = BigIntVec::from_i64array(&[1, 2, 3, 4, 5]);
+
+        // Act
+        let shard0: TargetShard = client_a.hello()?;
+        let shard1: TargetShard = client_b.hello()?;
+        let shard2: TargetShard = client_a.hello()?;
+        let snapshot_id: Option<String> = client_a.download_snapshot(&worker_analytics_dir, &worker_stripped_file)?;
+        let status0: DistributedCandidateStatus = client_b.submit_candidate(&terms40, "mov $0,1\n", true)?;
+        let status1: DistributedCandidateStatus = client_a.submit_candidate(&terms40, "mov $0,2\n", false)?;
+
+        // Assert
+        assert_eq!(shard0, TargetShard { index: 0, count: 2 });
+        assert_eq!(shard1, TargetShard { index: 1, count: 2 });
+        assert_eq!(shard2, shard0);
+        assert_eq!(snapshot_id, Some("2022-01-01T00:00:00Z".to_string()));
+        assert_eq!(fs::read_to_string(worker_analytics_dir.join("dont_mine.csv"))?, "program id\n45\n");
+        assert_eq!(fs::read_to_string(&worker_stripped_file)?, "A000045 ,0,1,1,2,3,5,8,\n");
+        assert_eq!(status0, DistributedCandidateStatus::Accepted);
+        assert_eq!(status1, DistributedCandidateStatus::Duplicate);
+        let filenames: Vec<String> = fs::read_dir(&mine_event_dir)?
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(filenames.len(), 1);
+        assert!(filenames[0].ends_with("-remote-b_c-1.asm"));
+        assert_eq!(number_of_callbacks.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+//! Messages exchanged between the head node and the worker nodes, when mining distributed.
+//!
+//! The protocol runs over TCP. Each message is a single line of JSON.
+//! The worker sends a request, and the head node replies with a response.
+//!
+//! Every request carries the shared secret, so only the worker nodes that know it are served.
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use std::io::{BufRead, Read, Write};
+
+/// The head node rejects requests longer than this, so a misbehaving peer cannot exhaust its memory.
+pub const MAX_REQUEST_LENGTH: u64 = 1024 * 1024;
+
+/// The snapshot response contains the analytics files and the oeis `stripped` file, so it's big.
+pub const MAX_RESPONSE_LENGTH: u64 = 512 * 1024 * 1024;
+
+/// The subset of the target sequences that a worker node is mining for.
+///
+/// A sequence belongs to the shard when `oeis_id % count == index`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TargetShard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl TargetShard {
+    pub fn contains(&self, oeis_id: u32) -> bool {
+        if self.count <= 1 {
+            return true;
+        }
+        oeis_id % self.count == self.index
+    }
+}
+
+/// A file from the head node's `analytics-oeis` dir.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DistributedSnapshotFile {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum DistributedRequest {
+    /// Register the worker node, and obtain its target shard.
+    ///
+    /// The same `worker_name` gets the same shard every time.
+    #[serde(rename = "hello")]
+    Hello { worker_name: String, secret: String },
+
+    /// Obtain the analytics that the head node has generated, and the oeis `stripped` file.
+    #[serde(rename = "snapshot")]
+    Snapshot { secret: String },
+
+    /// A candidate program that the worker has mined.
+    ///
+    /// The `terms40` are the first 40 terms, formatted as a compact comma separated string.
+    #[serde(rename = "candidate")]
+    Candidate { worker_name: String, secret: String, terms40: String, program: String, high_priority: bool },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum DistributedResponse {
+    #[serde(rename = "welcome")]
+    Welcome { shard: TargetShard },
+
+    #[serde(rename = "snapshot")]
+    Snapshot { snapshot_id: String, files: Vec<DistributedSnapshotFile>, oeis_stripped: String },
+
+    /// The head node is regenerating its analytics. Try again later.
+    #[serde(rename = "not ready")]
+    NotReady,
+
+    /// The candidate program has been saved to the head node's `mine-event` dir.
+    #[serde(rename = "accepted")]
+    Accepted,
+
+    /// A program with the same terms has already been mined. The candidate program is ignored.
+    #[serde(rename = "duplicate")]
+    Duplicate,
+
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+pub fn write_message<W: Write, T: Serialize>(writer: &mut W, message: &T) -> anyhow::Result<()> {
+    let mut line: String = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Returns `None` when the other side has closed the connection.
+///
+/// Fails when the line is longer than `max_length` bytes.
+pub fn read_message<R: BufRead, T: DeserializeOwned>(reader: &mut R, max_length: u64) -> anyhow::Result<Option<T>> {
+    let mut line = String::new();
+    let number_of_bytes: usize = reader.by_ref().take(max_length).read_line(&mut line)?;
+    if number_of_bytes == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && number_of_bytes as u64 >= max_length {
+        return Err(anyhow::anyhow!("The message exceeds the maximum length of {} bytes", max_length));
+    }
+    let message: T = serde_json::from_str(line.trim_end())
+        .map_err(|e| anyhow::anyhow!("Unable to parse message. error: {:?}", e))?;
+    Ok(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_10000_target_shard_contains() {
+        let shard = TargetShard { index: 1, count: 3 };
+        assert!(shard.contains(40));
+        assert!(!shard.contains(45));
+        assert!(!shard.contains(47));
+        assert!(shard.contains(1));
+        let single = TargetShard { index: 0, count: 1 };
+        assert!(single.contains(45));
+    }
+
+    #[test]
+    fn test_20000_write_read_roundtrip() -> anyhow::Result<()> {
+        // Arrange
+        let request = DistributedRequest::Candidate {
+            worker_name: "w".to_string(),
+            secret: "s".to_string(),
+            terms40: "1,2,3".to_string(),
+            program: "mov $0,1\n; comment\n".to_string(),
+            high_priority: true,
+        };
+        let mut buffer: Vec<u8> = vec!();
+
+        // Act
+        write_message(&mut buffer, &request)?;
+        write_message(&mut buffer, &DistributedRequest::Snapshot { secret: "s".to_string() })?;
+        let mut reader = BufReader::new(buffer.as_slice());
+        let message0: Option<DistributedRequest> = read_message(&mut reader, MAX_REQUEST_LENGTH)?;
+        let message1: Option<DistributedRequest> = read_message(&mut reader, MAX_REQUEST_LENGTH)?;
+        let message2: Option<DistributedRequest> = read_message(&mut reader, MAX_REQUEST_LENGTH)?;
+
+        // Assert
+        assert_eq!(String::from_utf8(buffer.clone())?.lines().count(), 2);
+        assert_eq!(message0, Some(request));
+        assert_eq!(message1, Some(DistributedRequest::Snapshot { secret: "s".to_string() }));
+        assert_eq!(message2, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_20001_read_message_too_long() -> anyhow::Result<()> {
+        // Arrange
+        let request = DistributedRequest::Hello {
+            worker_name: "x".repeat(100),
+            secret: "s".to_string(),
+        };
+        let mut buffer: Vec<u8> = vec!();
+        write_message(&mut buffer, &request)?;
+        let mut reader = BufReader::new(buffer.as_slice());
+
+        // Act
+        let result: anyhow::Result<Option<DistributedRequest>> = read_message(&mut reader, 50);
+
+        // Assert
+        assert!(result.is_err());
+        Ok(())
+    }
+}
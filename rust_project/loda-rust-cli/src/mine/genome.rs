@@ -3,15 +3,17 @@ use loda_rust_core::control::DependencyManager;
 use loda_rust_core::execute::RegisterType;
 use loda_rust_core::parser::{Instruction, InstructionId, InstructionParameter, ParameterType};
 use loda_rust_core::parser::ParsedProgram;
+use loda_rust_core::parser::validate_loops::validate_loops;
+use num_bigint::BigInt;
 use std::collections::HashSet;
 use std::fmt;
+use std::ops::Range;
 use rand::Rng;
 use rand::seq::SliceRandom;
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum MutateGenome {
     ReplaceInstructionWithHistogram,
     InsertInstructionWithConstant,
@@ -43,6 +45,52 @@ pub enum MutateGenome {
     CallProgramThatUsesIndirectMemoryAccess,
 }
 
+impl MutateGenome {
+    /// The hand tuned weights, used when there are no stats about how well each mutation performs.
+    pub fn default_weights() -> Vec<(MutateGenome,usize)> {
+        vec![
+            (MutateGenome::ReplaceInstructionWithHistogram, 10),
+            (MutateGenome::InsertInstructionWithConstant, 0),
+            (MutateGenome::IncrementSourceValueWhereTypeIsConstant, 10),
+            (MutateGenome::DecrementSourceValueWhereTypeIsConstant, 10),
+            (MutateGenome::ReplaceSourceConstantWithHistogram, 10),
+            (MutateGenome::SetSourceToConstant, 10),
+            (MutateGenome::SetSourceToDirect, 10),
+            (MutateGenome::DisableLoop, 0),
+            (MutateGenome::SwapRegisters, 10),
+            (MutateGenome::IncrementSourceValueWhereTypeIsDirect, 10),
+            (MutateGenome::DecrementSourceValueWhereTypeIsDirect, 10),
+            (MutateGenome::ReplaceSourceWithHistogram, 10),
+            (MutateGenome::IncrementTargetValueWhereTypeIsDirect, 10),
+            (MutateGenome::DecrementTargetValueWhereTypeIsDirect, 10),
+            (MutateGenome::ReplaceTargetWithHistogram, 10),
+            (MutateGenome::ReplaceLineWithHistogram, 50),
+            (MutateGenome::InsertLineWithHistogram, 50),
+            (MutateGenome::CopyLine, 10),
+            (MutateGenome::ToggleEnabled, 10),
+            (MutateGenome::SwapRows, 10),
+            (MutateGenome::SwapAdjacentRows, 10),
+            (MutateGenome::InsertLoopBeginEnd, 0),
+            (MutateGenome::CallProgramWeightedByPopularity, 0),
+            (MutateGenome::CallMostPopularProgram, 10),
+            (MutateGenome::CallMediumPopularProgram, 20),
+            (MutateGenome::CallLeastPopularProgram, 50),
+            (MutateGenome::CallRecentProgram, 300),
+            (MutateGenome::CallProgramThatUsesIndirectMemoryAccess, 0),
+        ]
+    }
+
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<MutateGenome> {
+        Self::default_weights().into_iter()
+            .map(|(mutation, _weight)| mutation)
+            .find(|mutation| mutation.name() == name)
+    }
+}
+
 pub struct Genome {
     genome_vec: Vec<GenomeItem>,
     message_vec: Vec<String>,
@@ -184,9 +232,16 @@ impl Genome {
         // Mutate one of the instructions
         let index: &usize = indexes.choose(rng).unwrap();
         let genome_item: &mut GenomeItem = &mut self.genome_vec[*index];
+        if let Some(big_value) = genome_item.source_big_value() {
+            // The constant is beyond 32bit, so it cannot become zero
+            let new_value: BigInt = big_value + 1;
+            genome_item.set_source_big_value(new_value);
+            return true;
+        }
         let value: i32 = genome_item.source_value();
         if value >= i32::MAX {
-            return false;
+            genome_item.set_source_big_value(BigInt::from(value) + 1);
+            return true;
         }
         let new_value = value + 1;
         if genome_item.instruction_id() == InstructionId::Divide && new_value == 0 {
@@ -237,9 +292,16 @@ impl Genome {
         // Mutate one of the instructions
         let index: &usize = indexes.choose(rng).unwrap();
         let genome_item: &mut GenomeItem = &mut self.genome_vec[*index];
+        if let Some(big_value) = genome_item.source_big_value() {
+            // The constant is beyond 32bit, so it cannot become zero
+            let new_value: BigInt = big_value - 1;
+            genome_item.set_source_big_value(new_value);
+            return true;
+        }
         let value: i32 = genome_item.source_value();
         if value <= i32::MIN {
-            return false;
+            genome_item.set_source_big_value(BigInt::from(value) - 1);
+            return true;
         }
         let new_value = value - 1;
         if genome_item.instruction_id() == InstructionId::Divide && new_value == 0 {
@@ -427,6 +489,10 @@ impl Genome {
         if instruction_id == InstructionId::LoopEnd {
             return SourceValue::None;
         }
+        if genome_item.source_big_value().is_some() {
+            // The histograms only contains constants within 32bit
+            return SourceValue::None;
+        }
         let value: i32 = genome_item.source_value();
         match genome_item.source_type() {
             ParameterType::Constant => {
@@ -1567,6 +1633,90 @@ impl Genome {
         true
     }
 
+    /// Find the blocks of rows where the `lpb` and `lpe` instructions are balanced.
+    ///
+    /// A block can be a single instruction, an entire loop, the body of a loop,
+    /// or the tail of the program.
+    ///
+    /// Disabled rows are ignored, since they are not part of the program.
+    fn balanced_blocks(genome_vec: &[GenomeItem], skip_mutation_locked: bool) -> Vec<Range<usize>> {
+        let mut depth_vec: Vec<i32> = Vec::with_capacity(genome_vec.len() + 1);
+        let mut depth: i32 = 0;
+        depth_vec.push(depth);
+        for genome_item in genome_vec {
+            if genome_item.is_enabled() {
+                match genome_item.instruction_id() {
+                    InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract => {
+                        depth += 1;
+                    },
+                    InstructionId::LoopEnd => {
+                        depth -= 1;
+                    },
+                    _ => {}
+                }
+            }
+            depth_vec.push(depth);
+        }
+        let mut blocks: Vec<Range<usize>> = vec!();
+        for start in 0..genome_vec.len() {
+            let start_depth: i32 = depth_vec[start];
+            for end in (start + 1)..=genome_vec.len() {
+                if skip_mutation_locked && genome_vec[end - 1].is_mutation_locked() {
+                    break;
+                }
+                if depth_vec[end] < start_depth {
+                    break;
+                }
+                if depth_vec[end] == start_depth {
+                    blocks.push(start..end);
+                }
+            }
+        }
+        blocks
+    }
+
+    /// Recombine two programs, by replacing a block of rows with a block of rows from another program.
+    ///
+    /// Such as replacing the body of a loop, with the body of a loop from the other program.
+    /// Or replacing the post-processing tail of the program.
+    ///
+    /// The `lpb` and `lpe` instructions stays balanced, and the result passes `validate_loops`.
+    ///
+    /// Return `true` when the mutation was successful.
+    ///
+    /// Return `false` in case of failure, such as empty genome, or the result is identical to the original.
+    pub fn crossover<R: Rng + ?Sized>(rng: &mut R, genome_vec: &mut Vec<GenomeItem>, donor_genome_vec: &[GenomeItem]) -> bool {
+        let blocks: Vec<Range<usize>> = Self::balanced_blocks(genome_vec, true);
+        let donor_blocks: Vec<Range<usize>> = Self::balanced_blocks(donor_genome_vec, false);
+        let block: Range<usize> = match blocks.choose(rng) {
+            Some(value) => value.clone(),
+            None => {
+                return false;
+            }
+        };
+        let donor_block: Range<usize> = match donor_blocks.choose(rng) {
+            Some(value) => value.clone(),
+            None => {
+                return false;
+            }
+        };
+        let mut child_genome_vec: Vec<GenomeItem> = genome_vec.clone();
+        child_genome_vec.splice(block, donor_genome_vec[donor_block].iter().cloned());
+
+        let before: String = Self::genome_vec_to_formatted_program(genome_vec);
+        let after: String = Self::genome_vec_to_formatted_program(&child_genome_vec);
+        if before == after {
+            return false;
+        }
+        let mut child = Genome::new();
+        child.set_genome_vec(child_genome_vec);
+        if validate_loops(&child.to_parsed_program().instruction_vec).is_err() {
+            return false;
+        }
+        *genome_vec = child.genome_vec;
+        true
+    }
+
     /// Mutate the `seq` instruction, so it invokes a random program.
     /// 
     /// Only impact rows where source_type=Constant and instruct=seq
@@ -1610,44 +1760,12 @@ impl Genome {
         false
     }
 
-    /// Apply a mutation to the genome.
+    /// Apply a specific mutation to the genome.
     /// 
     /// Return `true` when the genome got altered.
     /// 
     /// Return `false` in case the mutation didn't change the genome.
-    pub fn mutate<R: Rng + ?Sized>(&mut self, rng: &mut R, context: &GenomeMutateContext) -> bool {
-        let mutation_vec: Vec<(MutateGenome,usize)> = vec![
-            (MutateGenome::ReplaceInstructionWithHistogram, 10),
-            (MutateGenome::InsertInstructionWithConstant, 0),
-            (MutateGenome::IncrementSourceValueWhereTypeIsConstant, 10),
-            (MutateGenome::DecrementSourceValueWhereTypeIsConstant, 10),
-            (MutateGenome::ReplaceSourceConstantWithHistogram, 10),
-            (MutateGenome::SetSourceToConstant, 10),
-            (MutateGenome::SetSourceToDirect, 10),
-            (MutateGenome::DisableLoop, 0),
-            (MutateGenome::SwapRegisters, 10),
-            (MutateGenome::IncrementSourceValueWhereTypeIsDirect, 10),
-            (MutateGenome::DecrementSourceValueWhereTypeIsDirect, 10),
-            (MutateGenome::ReplaceSourceWithHistogram, 10),
-            (MutateGenome::IncrementTargetValueWhereTypeIsDirect, 10),
-            (MutateGenome::DecrementTargetValueWhereTypeIsDirect, 10),
-            (MutateGenome::ReplaceTargetWithHistogram, 10),
-            (MutateGenome::ReplaceLineWithHistogram, 50),
-            (MutateGenome::InsertLineWithHistogram, 50),
-            (MutateGenome::CopyLine, 10),
-            (MutateGenome::ToggleEnabled, 10),
-            (MutateGenome::SwapRows, 10),
-            (MutateGenome::SwapAdjacentRows, 10),
-            (MutateGenome::InsertLoopBeginEnd, 0),
-            (MutateGenome::CallProgramWeightedByPopularity, 0),
-            (MutateGenome::CallMostPopularProgram, 10),
-            (MutateGenome::CallMediumPopularProgram, 20),
-            (MutateGenome::CallLeastPopularProgram, 50),
-            (MutateGenome::CallRecentProgram, 300),
-            (MutateGenome::CallProgramThatUsesIndirectMemoryAccess, 0),
-        ];
-        let mutation: &MutateGenome = &mutation_vec.choose_weighted(rng, |item| item.1).unwrap().0;
-
+    pub fn mutate_with<R: Rng + ?Sized>(&mut self, rng: &mut R, context: &GenomeMutateContext, mutation: MutateGenome) -> bool {
         let did_mutate_ok: bool = match mutation {
             MutateGenome::ReplaceInstructionWithHistogram => {
                 self.replace_instruction_with_histogram(rng, context)
@@ -1758,3 +1876,45 @@ impl fmt::Display for Genome {
         write!(f, "{}", formatted_program)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn genome_vec_from_program(program: &str) -> Vec<GenomeItem> {
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(program).expect("parse");
+        parsed_program.to_genome_item_vec()
+    }
+
+    #[test]
+    fn test_10000_balanced_blocks() {
+        let genome_vec: Vec<GenomeItem> = genome_vec_from_program("mov $1,2\nlpb $0\nadd $1,1\nlpe\nmov $0,$1");
+        let blocks: Vec<Range<usize>> = Genome::balanced_blocks(&genome_vec, false);
+        assert!(blocks.contains(&(1..4)));
+        assert!(blocks.contains(&(2..3)));
+        assert!(blocks.contains(&(0..5)));
+        assert!(!blocks.contains(&(0..2)));
+        assert!(!blocks.contains(&(2..4)));
+    }
+
+    #[test]
+    fn test_20000_crossover_keeps_loops_balanced() {
+        let genome_vec: Vec<GenomeItem> = genome_vec_from_program("mov $1,2\nlpb $0\nadd $1,1\nsub $0,1\nlpe\nmov $0,$1");
+        let donor_genome_vec: Vec<GenomeItem> = genome_vec_from_program("lpb $0\nmul $1,3\nlpb $2\nsub $2,1\nlpe\nsub $0,2\nlpe\nadd $0,7");
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut number_of_changes: usize = 0;
+        for _ in 0..100 {
+            let mut child_genome_vec: Vec<GenomeItem> = genome_vec.clone();
+            if !Genome::crossover(&mut rng, &mut child_genome_vec, &donor_genome_vec) {
+                continue;
+            }
+            number_of_changes += 1;
+            let mut child = Genome::new();
+            child.set_genome_vec(child_genome_vec);
+            assert!(validate_loops(&child.to_parsed_program().instruction_vec).is_ok());
+        }
+        assert!(number_of_changes > 50);
+    }
+}
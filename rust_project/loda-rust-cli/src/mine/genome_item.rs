@@ -2,6 +2,8 @@ use loda_rust_core::execute::RegisterType;
 use loda_rust_core::parser::{Instruction, InstructionId, InstructionParameter, ParameterType};
 use loda_rust_core::parser::ParsedProgram;
 use super::GenomeMutateContext;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 use rand::Rng;
 use rand::seq::SliceRandom;
 use std::fmt;
@@ -36,6 +38,10 @@ pub struct GenomeItem {
     target_value: i32,
     source_type: ParameterType,
     source_value: i32,
+
+    /// A constant that doesn't fit inside the `source_value`.
+    /// When this is set, then the `source_value` is ignored.
+    source_big_value: Option<BigInt>,
 }
 
 impl GenomeItem {
@@ -48,6 +54,7 @@ impl GenomeItem {
             target_value: target_value,
             source_type: source_type,
             source_value: source_value,
+            source_big_value: None,
         }
     }
 
@@ -115,6 +122,9 @@ impl GenomeItem {
     }
 
     pub fn set_source_type(&mut self, source_type: ParameterType) {
+        if source_type != ParameterType::Constant {
+            self.source_big_value = None;
+        }
         self.source_type = source_type;
     }
 
@@ -124,6 +134,23 @@ impl GenomeItem {
 
     pub fn set_source_value(&mut self, value: i32) {
         self.source_value = value;
+        self.source_big_value = None;
+    }
+
+    /// The constant that doesn't fit inside an `i32`.
+    pub fn source_big_value(&self) -> Option<&BigInt> {
+        self.source_big_value.as_ref()
+    }
+
+    /// Assign a constant of any size. If it fits inside an `i32`, then it's stored as a `source_value`.
+    pub fn set_source_big_value(&mut self, value: BigInt) {
+        match value.to_i32() {
+            Some(value) => self.set_source_value(value),
+            None => {
+                self.source_value = 0;
+                self.source_big_value = Some(value);
+            }
+        }
     }
 
     #[allow(dead_code)]
@@ -131,6 +158,7 @@ impl GenomeItem {
         self.instruction_id = InstructionId::Divide;
         self.source_type = ParameterType::Constant;
         self.source_value = 0;
+        self.source_big_value = None;
     }
 
     pub fn set_instruction(&mut self, new_instruction_id: InstructionId) -> bool {
@@ -168,6 +196,10 @@ impl GenomeItem {
     }
 
     pub fn mutate_swap_source_target_value(&mut self) -> bool {
+        if self.source_big_value.is_some() {
+            // A huge constant cannot be used as a register index
+            return false;
+        }
         if self.target_value == self.source_value {
             // No mutation happened
             return false;
@@ -288,41 +320,26 @@ impl GenomeItem {
                 let parameter0: InstructionParameter;
                 match self.target_type {
                     RegisterType::Direct => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Direct,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Direct, (self.target_value.abs()) as i64);
                     },
                     RegisterType::Indirect => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Indirect,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Indirect, (self.target_value.abs()) as i64);
                     },
                 }
                 if self.source_type == ParameterType::Constant && self.source_value == 1 {
                     return vec![parameter0];
                 }
-                let parameter1 = InstructionParameter {
-                    parameter_type: self.source_type.clone(),
-                    parameter_value: (self.source_value.abs()) as i64,
-                };
+                let parameter1 = InstructionParameter::new(self.source_type, (self.source_value.abs()) as i64);
                 return vec![parameter0, parameter1];
             },
             InstructionId::UnofficialLoopBeginSubtract => {
                 let parameter0: InstructionParameter;
                 match self.target_type {
                     RegisterType::Direct => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Direct,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Direct, (self.target_value.abs()) as i64);
                     },
                     RegisterType::Indirect => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Indirect,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Indirect, (self.target_value.abs()) as i64);
                     },
                 }
                 return vec![parameter0];
@@ -334,59 +351,38 @@ impl GenomeItem {
                 let parameter0: InstructionParameter;
                 match self.target_type {
                     RegisterType::Direct => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Direct,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Direct, (self.target_value.abs()) as i64);
                     },
                     RegisterType::Indirect => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Indirect,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Indirect, (self.target_value.abs()) as i64);
                     },
                 }
-                let parameter1 = InstructionParameter {
-                    parameter_type: ParameterType::Constant,
-                    parameter_value: (self.source_value.abs()) as i64,
-                };
+                let parameter1 = InstructionParameter::new(ParameterType::Constant, (self.source_value.abs()) as i64);
                 return vec![parameter0, parameter1];
             },
             _ => {
                 let parameter0: InstructionParameter;
                 match self.target_type {
                     RegisterType::Direct => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Direct,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Direct, (self.target_value.abs()) as i64);
                     },
                     RegisterType::Indirect => {
-                        parameter0 = InstructionParameter {
-                            parameter_type: ParameterType::Indirect,
-                            parameter_value: (self.target_value.abs()) as i64,
-                        };
+                        parameter0 = InstructionParameter::new(ParameterType::Indirect, (self.target_value.abs()) as i64);
                     },
                 }
                 let parameter1: InstructionParameter;
                 match self.source_type {
                     ParameterType::Constant => {
-                        parameter1 = InstructionParameter {
-                            parameter_type: ParameterType::Constant,
-                            parameter_value: self.source_value as i64,
+                        parameter1 = match &self.source_big_value {
+                            Some(value) => InstructionParameter::new_constant(value.clone()),
+                            None => InstructionParameter::new(ParameterType::Constant, self.source_value as i64)
                         };
                     },
                     ParameterType::Direct => {
-                        parameter1 = InstructionParameter {
-                            parameter_type: ParameterType::Direct,
-                            parameter_value: (self.source_value.abs()) as i64,
-                        };
+                        parameter1 = InstructionParameter::new(ParameterType::Direct, (self.source_value.abs()) as i64);
                     },
                     ParameterType::Indirect => {
-                        parameter1 = InstructionParameter {
-                            parameter_type: ParameterType::Indirect,
-                            parameter_value: (self.source_value.abs()) as i64,
-                        };
+                        parameter1 = InstructionParameter::new(ParameterType::Indirect, (self.source_value.abs()) as i64);
                     },
                 }
                 return vec![parameter0, parameter1];
@@ -403,13 +399,17 @@ impl fmt::Display for GenomeItem {
         } else {
             line_prefix = "; ";
         }
+        let source_value: String = match &self.source_big_value {
+            Some(value) => value.to_string(),
+            None => self.source_value.to_string()
+        };
         write!(f, "{}{} {}{},{}{}", 
             line_prefix,
             self.instruction_id, 
             self.target_type.prefix(),
             self.target_value, 
             self.source_type.prefix(), 
-            self.source_value
+            source_value
         )
     }
 }
@@ -424,6 +424,7 @@ impl ToGenomeItem for Instruction {
         let mut target_value: i32 = 0;
         let mut source_type: ParameterType = ParameterType::Constant;
         let mut source_value: i32 = 0;
+        let mut source_big_value: Option<BigInt> = None;
         if self.instruction_id == InstructionId::LoopBegin {
             // The "lpb" instruction, when there is no source parameter, then its default value is 1.
             source_value = 1;
@@ -438,17 +439,25 @@ impl ToGenomeItem for Instruction {
                 }
             }
             if index == 1 {
-                source_value = parameter.parameter_value as i32;
                 source_type = parameter.parameter_type.clone();
+                if parameter.parameter_type == ParameterType::Constant {
+                    // The constant may not fit inside an `i32`
+                    source_big_value = parameter.constant_value();
+                } else {
+                    source_value = parameter.parameter_value as i32;
+                }
             }
         }
-        let genome_item = GenomeItem::new(
+        let mut genome_item = GenomeItem::new(
             self.instruction_id,
             target_type,
             target_value,
             source_type,
             source_value,
         );
+        if let Some(value) = source_big_value {
+            genome_item.set_source_big_value(value);
+        }
         Some(genome_item)
     }
 }
@@ -473,3 +482,44 @@ impl ToGenomeItemVec for ParsedProgram {
         genome_vec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genome_item(line: &str) -> GenomeItem {
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(line).expect("parsed program");
+        parsed_program.instruction_vec[0].to_genome_item().expect("genome item")
+    }
+
+    #[test]
+    fn test_10000_big_constant_roundtrip() {
+        let item: GenomeItem = genome_item("mul $1,123456789012345678901234567890");
+        assert_eq!(item.source_big_value().map(|value| value.to_string()), Some("123456789012345678901234567890".to_string()));
+        assert_eq!(item.to_line_string(), "mul $1,123456789012345678901234567890");
+        assert_eq!(item.to_string(), "mul $1,123456789012345678901234567890");
+    }
+
+    #[test]
+    fn test_10001_constant_beyond_i32() {
+        // Previously the constant was truncated to 32bit
+        let item: GenomeItem = genome_item("add $0,4294967296");
+        assert_eq!(item.to_line_string(), "add $0,4294967296");
+        let item: GenomeItem = genome_item("add $0,-7");
+        assert_eq!(item.source_big_value(), None);
+        assert_eq!(item.to_line_string(), "add $0,-7");
+    }
+
+    #[test]
+    fn test_20000_mutate_big_constant() {
+        let mut item: GenomeItem = genome_item("mul $1,123456789012345678901234567890");
+        assert!(!item.mutate_swap_source_target_value());
+        item.set_source_big_value(BigInt::from(42));
+        assert_eq!(item.source_big_value(), None);
+        assert_eq!(item.to_line_string(), "mul $1,42");
+        item.set_source_big_value(BigInt::from(u64::MAX));
+        item.set_source_type(ParameterType::Direct);
+        assert_eq!(item.source_big_value(), None);
+        assert_eq!(item.to_line_string(), "mul $1,$0");
+    }
+}
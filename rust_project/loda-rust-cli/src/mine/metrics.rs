@@ -1,3 +1,5 @@
+use super::{MinerTargetStatsItem, MutateGenome, MutationStatsItem};
+
 #[derive(Clone, Debug)]
 pub enum MetricEvent {
     Funnel { 
@@ -16,6 +18,7 @@ pub enum MetricEvent {
         cannot_load: u64,
         cannot_parse: u64,
         too_short: u64,
+        junk: u64,
         no_output: u64,
         no_mutation: u64,
         compute_error: u64,
@@ -29,7 +32,25 @@ pub enum MetricEvent {
         prevent_flooding: u64,
         reject_self_dependency: u64,
         candidate_program: u64,
-    }
+    },
+    Mutation {
+        stats: Vec<(MutateGenome, MutationStatsItem)>,
+        weights: Vec<(MutateGenome, f64)>,
+    },
+    Upload {
+        /// Number of programs waiting to be uploaded.
+        pending: u64,
+
+        /// Number of programs that are no longer retried, after too many failed attempts.
+        failed: u64,
+
+        success: u64,
+        error: u64,
+    },
+    Target {
+        /// Progress for each of the target sequences, when mining targeted.
+        stats: Vec<(u32, MinerTargetStatsItem)>,
+    },
 }
 
 pub trait Recorder: RecorderClone {
@@ -1,7 +1,13 @@
 use super::{MetricEvent, Recorder};
+use loda_rust_core::oeis::OeisId;
 use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
 use prometheus_client::metrics::gauge::Gauge;
 use prometheus_client::registry::Registry;
+use std::sync::atomic::AtomicU64;
+
+type MutationLabels = Vec<(String, String)>;
+type TargetLabels = Vec<(String, String)>;
 
 #[derive(Clone)]
 pub struct MetricsPrometheus {
@@ -14,6 +20,7 @@ pub struct MetricsPrometheus {
     cache_miss_program_without_id: Counter,
     error_genome_load: Counter,
     reject_too_short: Counter,
+    reject_junk: Counter,
     reject_cannot_be_parsed: Counter,
     reject_no_output_register: Counter,
     reject_compute_error: Counter,
@@ -27,6 +34,17 @@ pub struct MetricsPrometheus {
     funnel_false_positive: Counter,
     dependency_manager_read_success: Counter,
     dependency_manager_read_error: Counter,
+    mutation_attempts: Family<MutationLabels, Counter>,
+    mutation_no_change: Family<MutationLabels, Counter>,
+    mutation_funnel: Family<MutationLabels, Counter>,
+    mutation_candidate: Family<MutationLabels, Counter>,
+    mutation_weight: Family<MutationLabels, Gauge<f64, AtomicU64>>,
+    upload_pending: Gauge::<u64>,
+    upload_failed: Gauge::<u64>,
+    upload_success: Counter,
+    upload_error: Counter,
+    target_matches: Family<TargetLabels, Counter>,
+    target_candidates: Family<TargetLabels, Counter>,
 }
 
 impl MetricsPrometheus {
@@ -96,6 +114,13 @@ impl MetricsPrometheus {
             Box::new(reject_too_short.clone()),
         );
 
+        let reject_junk = Counter::default();
+        sub_registry.register(
+            "reject_junk",
+            "Rejected programs because the static analysis found instructions without effect",
+            Box::new(reject_junk.clone()),
+        );
+
         let reject_cannot_be_parsed = Counter::default();
         sub_registry.register(
             "reject_cannot_be_parsed",
@@ -187,6 +212,83 @@ impl MetricsPrometheus {
             Box::new(dependency_manager_read_error.clone()),
         );
 
+        let mutation_attempts = Family::<MutationLabels, Counter>::default();
+        sub_registry.register(
+            "mutation_attempts",
+            "Number of times each mutation has been picked",
+            Box::new(mutation_attempts.clone()),
+        );
+
+        let mutation_no_change = Family::<MutationLabels, Counter>::default();
+        sub_registry.register(
+            "mutation_no_change",
+            "Number of times each mutation had no effect",
+            Box::new(mutation_no_change.clone()),
+        );
+
+        let mutation_funnel = Family::<MutationLabels, Counter>::default();
+        sub_registry.register(
+            "mutation_funnel",
+            "Number of funnel stages passed after each mutation",
+            Box::new(mutation_funnel.clone()),
+        );
+
+        let mutation_candidate = Family::<MutationLabels, Counter>::default();
+        sub_registry.register(
+            "mutation_candidate",
+            "Number of candidate programs found after each mutation",
+            Box::new(mutation_candidate.clone()),
+        );
+
+        let mutation_weight = Family::<MutationLabels, Gauge<f64, AtomicU64>>::default();
+        sub_registry.register(
+            "mutation_weight",
+            "The weight used for picking each mutation",
+            Box::new(mutation_weight.clone()),
+        );
+
+        let upload_pending = Gauge::<u64>::default();
+        sub_registry.register(
+            "upload_pending",
+            "Number of programs waiting to be uploaded",
+            Box::new(upload_pending.clone()),
+        );
+
+        let upload_failed = Gauge::<u64>::default();
+        sub_registry.register(
+            "upload_failed",
+            "Number of programs that have failed to upload too many times",
+            Box::new(upload_failed.clone()),
+        );
+
+        let upload_success = Counter::default();
+        sub_registry.register(
+            "upload_success",
+            "Number of programs uploaded",
+            Box::new(upload_success.clone()),
+        );
+
+        let upload_error = Counter::default();
+        sub_registry.register(
+            "upload_error",
+            "Number of failed upload attempts",
+            Box::new(upload_error.clone()),
+        );
+
+        let target_matches = Family::<TargetLabels, Counter>::default();
+        sub_registry.register(
+            "target_matches",
+            "Number of programs with the same 40 terms as each target sequence",
+            Box::new(target_matches.clone()),
+        );
+
+        let target_candidates = Family::<TargetLabels, Counter>::default();
+        sub_registry.register(
+            "target_candidates",
+            "Number of candidate programs for each target sequence",
+            Box::new(target_candidates.clone()),
+        );
+
         Self {
             number_of_workers: number_of_workers,
             number_of_iterations: number_of_iterations,
@@ -197,6 +299,7 @@ impl MetricsPrometheus {
             cache_miss_program_without_id: cache_miss_program_without_id,
             error_genome_load: error_genome_load,
             reject_too_short: reject_too_short,
+            reject_junk,
             reject_cannot_be_parsed: reject_cannot_be_parsed,
             reject_no_output_register: reject_no_output_register,
             reject_compute_error: reject_compute_error,
@@ -210,6 +313,17 @@ impl MetricsPrometheus {
             funnel_false_positive: funnel_false_positive,
             dependency_manager_read_success: dependency_manager_read_success,
             dependency_manager_read_error: dependency_manager_read_error,
+            mutation_attempts,
+            mutation_no_change,
+            mutation_funnel,
+            mutation_candidate,
+            mutation_weight,
+            upload_pending,
+            upload_failed,
+            upload_success,
+            upload_error,
+            target_matches,
+            target_candidates,
         }
     }
 }
@@ -229,10 +343,11 @@ impl Recorder for MetricsPrometheus {
                 self.cache_miss_program_oeis.inc_by(*miss_program_oeis);
                 self.cache_miss_program_without_id.inc_by(*miss_program_without_id);
             },
-            MetricEvent::Genome { cannot_load, cannot_parse, too_short, no_output, no_mutation, compute_error } => {
+            MetricEvent::Genome { cannot_load, cannot_parse, too_short, junk, no_output, no_mutation, compute_error } => {
                 self.error_genome_load.inc_by(*cannot_load);
                 self.reject_cannot_be_parsed.inc_by(*cannot_parse);
                 self.reject_too_short.inc_by(*too_short);
+                self.reject_junk.inc_by(*junk);
                 self.reject_no_output_register.inc_by(*no_output);
                 self.reject_mutate_without_impact.inc_by(*no_mutation);
                 self.reject_compute_error.inc_by(*compute_error);
@@ -247,6 +362,32 @@ impl Recorder for MetricsPrometheus {
                 self.reject_self_dependency.inc_by(*reject_self_dependency);
                 self.number_of_candidate_programs.inc_by(*candidate_program);
             },
+            MetricEvent::Mutation { stats, weights } => {
+                for (mutation, item) in stats {
+                    let labels: MutationLabels = vec![("mutation".to_string(), mutation.name())];
+                    self.mutation_attempts.get_or_create(&labels).inc_by(item.attempts);
+                    self.mutation_no_change.get_or_create(&labels).inc_by(item.no_change);
+                    self.mutation_funnel.get_or_create(&labels).inc_by(item.funnel10 + item.funnel20 + item.funnel30 + item.funnel40);
+                    self.mutation_candidate.get_or_create(&labels).inc_by(item.candidate);
+                }
+                for (mutation, weight) in weights {
+                    let labels: MutationLabels = vec![("mutation".to_string(), mutation.name())];
+                    self.mutation_weight.get_or_create(&labels).set(*weight);
+                }
+            },
+            MetricEvent::Upload { pending, failed, success, error } => {
+                self.upload_pending.set(*pending);
+                self.upload_failed.set(*failed);
+                self.upload_success.inc_by(*success);
+                self.upload_error.inc_by(*error);
+            },
+            MetricEvent::Target { stats } => {
+                for (program_id, item) in stats {
+                    let labels: TargetLabels = vec![("target".to_string(), OeisId::from(*program_id).a_number())];
+                    self.target_matches.get_or_create(&labels).inc_by(item.matches);
+                    self.target_candidates.get_or_create(&labels).inc_by(item.candidates);
+                }
+            },
         }
     }
 }
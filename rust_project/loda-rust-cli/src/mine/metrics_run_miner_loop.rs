@@ -6,6 +6,7 @@ pub struct MetricsRunMinerLoop {
     pub number_of_failed_genome_loads: u64,
     pub number_of_failed_mutations: u64,
     pub number_of_too_short_programs: u64,
+    pub number_of_junk_programs: u64,
     pub number_of_programs_that_cannot_parse: u64,
     pub number_of_programs_without_output: u64,
     pub number_of_compute_errors: u64,
@@ -22,6 +23,7 @@ impl MetricsRunMinerLoop {
             number_of_failed_genome_loads: 0,
             number_of_failed_mutations: 0,
             number_of_too_short_programs: 0,
+            number_of_junk_programs: 0,
             number_of_programs_that_cannot_parse: 0,
             number_of_programs_without_output: 0,
             number_of_compute_errors: 0,
@@ -36,6 +38,7 @@ impl MetricsRunMinerLoop {
         self.number_of_bloomfilter_false_positive = 0;
         self.number_of_failed_mutations = 0;
         self.number_of_too_short_programs = 0;
+        self.number_of_junk_programs = 0;
         self.number_of_programs_that_cannot_parse = 0;
         self.number_of_programs_without_output = 0;
         self.number_of_compute_errors = 0;
@@ -1,4 +1,5 @@
-use super::{MetricEvent, MetricsPrometheus, MovingAverage, Recorder, CoordinatorWorkerMessage};
+use super::{MetricEvent, MetricsPrometheus, MinerTargetStats, MovingAverage, Recorder, CoordinatorWorkerMessage};
+use loda_rust_core::oeis::OeisId;
 use std::time::{Duration, Instant};
 use prometheus_client::encoding::text::encode;
 use prometheus_client::registry::Registry;
@@ -177,15 +178,32 @@ async fn metrics_worker_server(ctx: BastionContext, metrics: MetricsPrometheus)
     }
 }
 
+const TARGET_PROGRESS_INTERVAL_MILLIS: u128 = 10000;
+
+/// Print the progress for each of the target sequences, when mining targeted.
+fn print_target_progress(target_stats: &MinerTargetStats) {
+    for (program_id, item) in target_stats.to_vec() {
+        println!("target {}: matches: {} candidates: {}", OeisId::from(program_id).a_number(), item.matches, item.candidates);
+    }
+}
+
 /// Inspect metrics from commandline.
 /// 
 /// Prints stats with 1 second interval.
+/// 
+/// When mining targeted, the progress for each target is printed with 10 seconds interval.
 async fn metrics_worker_print(ctx: BastionContext) -> Result<(), ()> {
     debug!("metrics_worker_print is ready");
     let mut progress_time = Instant::now();
+    let mut target_progress_time = Instant::now();
     let mut miner_iteration_count: u64 = 0;
     let mut metric_event_count: u64 = 0;
+    let mut target_stats = MinerTargetStats::new();
     loop {
+        if target_progress_time.elapsed().as_millis() >= TARGET_PROGRESS_INTERVAL_MILLIS {
+            print_target_progress(&target_stats);
+            target_progress_time = Instant::now();
+        }
         let elapsed: u128 = progress_time.elapsed().as_millis();
         if elapsed >= 1000 {
             if miner_iteration_count > 0 {
@@ -213,8 +231,14 @@ async fn metrics_worker_print(ctx: BastionContext) -> Result<(), ()> {
         MessageHandler::new(message)
             .on_tell(|metric_event: MetricEvent, _| {
                 metric_event_count += 1;
-                if let MetricEvent::General { number_of_iterations, .. } = metric_event {
-                    miner_iteration_count += number_of_iterations;
+                match metric_event {
+                    MetricEvent::General { number_of_iterations, .. } => {
+                        miner_iteration_count += number_of_iterations;
+                    },
+                    MetricEvent::Target { stats } => {
+                        target_stats.merge(&stats);
+                    },
+                    _ => {}
                 }
             })
             .on_fallback(|unknown, _sender_addr| {
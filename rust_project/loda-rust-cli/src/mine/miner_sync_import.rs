@@ -0,0 +1,83 @@
+use super::MinerSyncExecuteStatus;
+use crate::common::{find_asm_files_recursively, oeis_id_from_path};
+use loda_rust_core::oeis::OeisId;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Sync without network access, by importing programs from a dir, such as an usb stick.
+///
+/// This is used instead of `MinerSyncExecute`, when mining offline.
+///
+/// The programs are copied into the "loda-programs/oeis" dir, like this: `oeis/045/A045123.asm`.
+/// Programs that are identical to the existing programs are ignored.
+pub struct MinerSyncImport;
+
+impl MinerSyncImport {
+    pub fn execute(import_dir: &Path, loda_programs_oeis_dir: &Path) -> anyhow::Result<MinerSyncExecuteStatus> {
+        if !import_dir.is_dir() {
+            return Err(anyhow::anyhow!("MinerSyncImport expected a dir, but got something else. import_dir: {:?}", import_dir));
+        }
+        let paths: Vec<PathBuf> = find_asm_files_recursively(import_dir);
+        let mut number_of_imported_programs: usize = 0;
+        for path in &paths {
+            let oeis_id: OeisId = match oeis_id_from_path(path) {
+                Some(value) => value,
+                None => {
+                    debug!("MinerSyncImport: ignoring file without oeis id: {:?}", path);
+                    continue;
+                }
+            };
+            let contents: String = fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("MinerSyncImport unable to read file: {:?} error: {:?}", path, e))?;
+            let dir_index: String = format!("{:0>3}", oeis_id.raw() / 1000);
+            let destination_dir: PathBuf = loda_programs_oeis_dir.join(dir_index);
+            let destination_path: PathBuf = destination_dir.join(format!("{}.asm", oeis_id.a_number()));
+            if let Ok(existing_contents) = fs::read_to_string(&destination_path) {
+                if existing_contents == contents {
+                    continue;
+                }
+            }
+            fs::create_dir_all(&destination_dir)
+                .map_err(|e| anyhow::anyhow!("MinerSyncImport unable to create dir: {:?} error: {:?}", destination_dir, e))?;
+            fs::write(&destination_path, contents)
+                .map_err(|e| anyhow::anyhow!("MinerSyncImport unable to write file: {:?} error: {:?}", destination_path, e))?;
+            number_of_imported_programs += 1;
+        }
+        println!("MinerSyncImport: imported {} programs out of {} from {:?}", number_of_imported_programs, paths.len(), import_dir);
+        if number_of_imported_programs > 0 {
+            return Ok(MinerSyncExecuteStatus::Changed);
+        }
+        Ok(MinerSyncExecuteStatus::NoChange)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_10000_import_changed_programs() -> anyhow::Result<()> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let basedir = PathBuf::from(&tempdir.path()).join("test_10000_import_changed_programs");
+        let import_dir: PathBuf = basedir.join("import");
+        let oeis_dir: PathBuf = basedir.join("oeis");
+        fs::create_dir_all(import_dir.join("subdir"))?;
+        fs::create_dir_all(oeis_dir.join("000"))?;
+        fs::write(import_dir.join("subdir").join("A000045.asm"), "mov $0,1\n")?;
+        fs::write(import_dir.join("A123456.asm"), "mov $0,2\n")?;
+        fs::write(import_dir.join("junk.asm"), "mov $0,3\n")?;
+        fs::write(oeis_dir.join("000").join("A000045.asm"), "mov $0,1\n")?;
+
+        // Act
+        let status0: MinerSyncExecuteStatus = MinerSyncImport::execute(&import_dir, &oeis_dir)?;
+        let status1: MinerSyncExecuteStatus = MinerSyncImport::execute(&import_dir, &oeis_dir)?;
+
+        // Assert
+        assert!(matches!(status0, MinerSyncExecuteStatus::Changed));
+        assert!(matches!(status1, MinerSyncExecuteStatus::NoChange));
+        let contents: String = fs::read_to_string(oeis_dir.join("123").join("A123456.asm"))?;
+        assert_eq!(contents, "mov $0,2\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use super::FunnelConfig;
+use crate::oeis::{ProcessStrippedFile, StrippedRow};
+use loda_rust_core::oeis::{OeisId, OeisIdHashSet};
+use loda_rust_core::util::BigIntVec;
+use num_bigint::{BigInt, ToBigInt};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Focus the miner on a few sequences, instead of all the sequences in the oeis `stripped` file.
+///
+/// The `miner_filter_mode` and the `dontmine.csv` file are not used when mining targeted,
+/// since the sequences have been picked by hand.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MinerTarget {
+    /// Mine for these sequences.
+    OeisIds(Vec<OeisId>),
+
+    /// Mine for the sequences where these consecutive terms occur among the first 40 terms.
+    TermPattern(BigIntVec),
+}
+
+impl MinerTarget {
+    /// Parse a comma separated list of OEIS ids, such as `A000045,A000040`. The `A` can be left out.
+    pub fn parse_oeis_ids(input: &str) -> anyhow::Result<Self> {
+        let mut oeis_ids: Vec<OeisId> = vec!();
+        for item in input.split(',') {
+            let item: &str = item.trim();
+            let digits: &str = item.strip_prefix('A').unwrap_or(item);
+            let value: u32 = digits.parse()
+                .map_err(|e| anyhow::anyhow!("Unable to parse OEIS id {:?}, expected A number such as A000040 or A123456. error: {:?}", item, e))?;
+            oeis_ids.push(OeisId::from(value));
+        }
+        Ok(Self::OeisIds(oeis_ids))
+    }
+
+    /// Parse comma separated terms, such as `1,1,2,3,5,8`.
+    pub fn parse_term_pattern(input: &str) -> anyhow::Result<Self> {
+        let mut terms: BigIntVec = vec!();
+        for item in input.split(',') {
+            let item: &str = item.trim();
+            let value: BigInt = BigInt::from_str(item)
+                .map_err(|e| anyhow::anyhow!("Unable to parse term {:?}, expected comma separated integers. error: {:?}", item, e))?;
+            terms.push(value);
+        }
+        if terms.len() > FunnelConfig::TERM_COUNT {
+            return Err(anyhow::anyhow!("The term pattern is too long. It can be at most {} terms, but got {} terms.", FunnelConfig::TERM_COUNT, terms.len()));
+        }
+        Ok(Self::TermPattern(terms))
+    }
+
+    fn is_match(&self, row: &StrippedRow) -> bool {
+        match self {
+            Self::OeisIds(oeis_ids) => oeis_ids.contains(&row.oeis_id()),
+            Self::TermPattern(terms) => row.terms().windows(terms.len()).any(|window| window == terms.as_slice())
+        }
+    }
+
+    /// Find the target sequences in the oeis `stripped` file.
+    ///
+    /// The rows are padded to 40 terms, the same way as the sequences in the bloomfilter `Funnel`.
+    pub fn load_rows(&self, oeis_stripped_file: &Path) -> anyhow::Result<Vec<StrippedRow>> {
+        let file = File::open(oeis_stripped_file)
+            .map_err(|e| anyhow::anyhow!("Unable to open oeis stripped file: {:?} error: {:?}", oeis_stripped_file, e))?;
+        let mut reader = BufReader::new(file);
+        Ok(self.find_rows(&mut reader))
+    }
+
+    fn find_rows(&self, reader: &mut dyn BufRead) -> Vec<StrippedRow> {
+        let mut rows: Vec<StrippedRow> = vec!();
+        let callback = |row: &StrippedRow, _| {
+            if self.is_match(row) {
+                rows.push(StrippedRow::new(row.oeis_id(), row.terms().clone()));
+            }
+        };
+        let padding_value: BigInt = FunnelConfig::WILDCARD_MAGIC_VALUE.to_bigint().unwrap();
+        let mut processor = ProcessStrippedFile::new();
+        let oeis_ids_to_ignore = OeisIdHashSet::new();
+        processor.execute(
+            reader,
+            FunnelConfig::MINIMUM_NUMBER_OF_REQUIRED_TERMS,
+            FunnelConfig::TERM_COUNT,
+            &oeis_ids_to_ignore,
+            &padding_value,
+            true,
+            callback
+        );
+        rows
+    }
+
+    /// Print what sequences are being mined for, and the ids that cannot be mined for.
+    pub fn print_summary(&self, rows: &[StrippedRow]) {
+        println!("targeted mining: {} target sequences", rows.len());
+        for row in rows {
+            println!("target: {}", row.oeis_id().a_number());
+        }
+        if let Self::OeisIds(oeis_ids) = self {
+            for oeis_id in oeis_ids {
+                if !rows.iter().any(|row| row.oeis_id() == *oeis_id) {
+                    println!("target: {} is not in the oeis stripped file, or it has fewer than {} terms. Ignoring it.", oeis_id.a_number(), FunnelConfig::MINIMUM_NUMBER_OF_REQUIRED_TERMS);
+                }
+            }
+        }
+    }
+}
+
+/// Progress of the miner for each of the target sequences.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MinerTargetStatsItem {
+    /// Number of mined programs with the same first 40 terms as the target sequence.
+    pub matches: u64,
+
+    /// Number of candidate programs that have been saved for the target sequence.
+    pub candidates: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MinerTargetStats {
+    items: BTreeMap<u32, MinerTargetStatsItem>,
+}
+
+impl MinerTargetStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn record_match(&mut self, program_ids: &[u32]) {
+        for program_id in program_ids {
+            self.items.entry(*program_id).or_default().matches += 1;
+        }
+    }
+
+    pub fn record_candidate(&mut self, program_ids: &[u32]) {
+        for program_id in program_ids {
+            self.items.entry(*program_id).or_default().candidates += 1;
+        }
+    }
+
+    pub fn merge(&mut self, stats: &[(u32, MinerTargetStatsItem)]) {
+        for (program_id, item) in stats {
+            let entry: &mut MinerTargetStatsItem = self.items.entry(*program_id).or_default();
+            entry.matches += item.matches;
+            entry.candidates += item.candidates;
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<(u32, MinerTargetStatsItem)> {
+        self.items.iter().map(|(program_id, item)| (*program_id, *item)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loda_rust_core::util::BigIntVecFromI64;
+
+    const INPUT_STRIPPED_SEQUENCE_MOCKDATA: &str = r#"
+# OEIS Sequence Data (http://oeis.org/stripped.gz)
+A000040 ,2,3,5,7,11,13,17,19,23,29,31,37,41,43,47,53,59,61,67,71,
+A000045 ,0,1,1,2,3,5,8,13,21,34,55,89,144,233,377,610,987,1597,2584,4181,
+A112088 ,2,3,5,7,11,16,24,36,54,81,122,183,274,411,617,925,1388,2082,3123,4684,
+A117093 ,2,3,5,7,11,13,16,17,18,19,23,28,29,30,31,37,38,39,40,41,
+A000001 ,1,2,3,
+"#;
+
+    fn find_ids(target: &MinerTarget) -> Vec<String> {
+        let mut reader = BufReader::new(INPUT_STRIPPED_SEQUENCE_MOCKDATA.as_bytes());
+        let rows: Vec<StrippedRow> = target.find_rows(&mut reader);
+        rows.iter().map(|row| row.oeis_id().a_number()).collect()
+    }
+
+    #[test]
+    fn test_10000_parse_oeis_ids() -> anyhow::Result<()> {
+        let target: MinerTarget = MinerTarget::parse_oeis_ids("A000045, 40,A112088")?;
+        assert_eq!(target, MinerTarget::OeisIds(vec![OeisId::from(45), OeisId::from(40), OeisId::from(112088)]));
+        assert!(MinerTarget::parse_oeis_ids("A00004x").is_err());
+        assert!(MinerTarget::parse_oeis_ids("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_10001_parse_term_pattern() -> anyhow::Result<()> {
+        let target: MinerTarget = MinerTarget::parse_term_pattern("1, 2,-3")?;
+        assert_eq!(target, MinerTarget::TermPattern(BigIntVec::from_i64array(&[1, 2, -3])));
+        assert!(MinerTarget::parse_term_pattern("1,,2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_20000_find_rows_by_oeis_ids() -> anyhow::Result<()> {
+        let target: MinerTarget = MinerTarget::parse_oeis_ids("A000045,A117093,A000001,A999999")?;
+        assert_eq!(find_ids(&target), vec!["A000045", "A117093"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_20001_find_rows_by_term_pattern() -> anyhow::Result<()> {
+        let target: MinerTarget = MinerTarget::parse_term_pattern("5,7,11,13")?;
+        assert_eq!(find_ids(&target), vec!["A000040", "A117093"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_30000_stats_merge() {
+        let mut stats0 = MinerTargetStats::new();
+        stats0.record_match(&[45, 40]);
+        stats0.record_candidate(&[45]);
+        let mut stats1 = MinerTargetStats::new();
+        stats1.record_match(&[45]);
+        stats1.merge(&stats0.to_vec());
+        let expected: Vec<(u32, MinerTargetStatsItem)> = vec![
+            (40, MinerTargetStatsItem { matches: 1, candidates: 0 }),
+            (45, MinerTargetStatsItem { matches: 2, candidates: 1 }),
+        ];
+        assert_eq!(stats1.to_vec(), expected);
+    }
+}
@@ -1,10 +1,11 @@
 use crate::config::Config;
 use super::{ExecuteBatchResult, RunMinerLoop, MetricEvent};
-use super::{Funnel, GenomeMutateContext, PreventFlooding};
+use super::{Funnel, GenomeMutateContext, MutationScheduler, PreventFlooding};
 use super::CoordinatorWorkerQuestion;
 use crate::oeis::TermsToProgramIdSet;
 use loda_rust_core::control::{DependencyManager, DependencyManagerFileSystemMode, ExecuteProfile};
 use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
+use loda_rust_core::execute::ExecutionEngine;
 use bastion::prelude::*;
 use std::fmt;
 use std::path::PathBuf;
@@ -21,14 +22,16 @@ pub struct MinerWorkerMessageWithAnalytics {
     funnel: Funnel,
     genome_mutate_context: GenomeMutateContext,
     terms_to_program_id_arc: Arc<TermsToProgramIdSet>,
+    targeted: bool,
 }
 
 impl MinerWorkerMessageWithAnalytics {
-    pub fn new(funnel: Funnel, genome_mutate_context: GenomeMutateContext, terms_to_program_id_arc: Arc<TermsToProgramIdSet>) -> Self {
+    pub fn new(funnel: Funnel, genome_mutate_context: GenomeMutateContext, terms_to_program_id_arc: Arc<TermsToProgramIdSet>, targeted: bool) -> Self {
         Self {
             funnel,
             genome_mutate_context,
             terms_to_program_id_arc,
+            targeted,
         }
     }
 }
@@ -48,6 +51,7 @@ pub enum MinerWorkerQuestion {
 pub async fn miner_worker(
     ctx: BastionContext,
     prevent_flooding: Arc<Mutex<PreventFlooding>>,
+    mutation_scheduler: Arc<Mutex<MutationScheduler>>,
     config: Config,
 ) -> Result<(), ()> {
     debug!("miner_worker - started, {:?}", ctx.current().id());
@@ -64,6 +68,7 @@ pub async fn miner_worker(
     let mut rml = RunMinerLoop::new(
         &config,
         prevent_flooding,
+        mutation_scheduler,
         initial_random_seed,
     );
     let callback = move |metric_event: MetricEvent| {
@@ -99,6 +104,7 @@ pub async fn miner_worker(
                 rml.set_funnel(message.funnel.clone());
                 rml.set_genome_mutate_context(message.genome_mutate_context.clone());
                 rml.set_terms_to_program_id(message.terms_to_program_id_arc.clone());
+                rml.set_targeted(message.targeted, &config);
                 match sender.reply("miner_worker_updated_ok".to_string()) {
                     Ok(value) => {
                         debug!("miner_worker: reply ok: {:?}", value);
@@ -141,6 +147,7 @@ pub async fn miner_worker(
                 UnofficialFunctionRegistry::new(),
             );
             dependency_manager.set_execute_profile(ExecuteProfile::SmallLimits);
+            dependency_manager.set_execution_engine(ExecutionEngine::Bytecode);
         
             let result: ExecuteBatchResult = match rml.execute_batch(&mut dependency_manager) {
                 Ok(value) => value,
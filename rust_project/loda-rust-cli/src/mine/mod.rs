@@ -6,6 +6,9 @@ mod create_funnel;
 mod create_genome_mutate_context;
 mod create_prevent_flooding;
 mod cronjob_worker;
+mod distributed_client;
+mod distributed_coordinator;
+mod distributed_protocol;
 mod funnel;
 mod funnel_config;
 mod genome;
@@ -18,8 +21,11 @@ mod metrics_run_miner_loop;
 mod metrics_worker;
 mod mine_event_directory_state;
 mod miner_sync_execute;
+mod miner_sync_import;
+mod miner_target;
 mod miner_worker;
 mod moving_average;
+mod mutation_stats;
 mod performance_classifier;
 mod popular_program_container;
 mod postmine_worker;
@@ -33,6 +39,7 @@ mod suggest_line;
 mod suggest_source;
 mod suggest_target;
 mod term_computer;
+mod upload_outbox;
 mod upload_worker;
 mod wildcard_checker;
 
@@ -45,6 +52,9 @@ pub use create_prevent_flooding::create_prevent_flooding;
 
 #[allow(unused_imports)]
 pub use cronjob_worker::{cronjob_worker, CronjobWorkerMessage};
+pub use distributed_client::{DistributedCandidateSender, DistributedClient};
+pub use distributed_coordinator::{DistributedCoordinator, DistributedCoordinatorSettings};
+pub use distributed_protocol::{read_message, write_message, DistributedRequest, DistributedResponse, DistributedSnapshotFile, TargetShard, MAX_REQUEST_LENGTH, MAX_RESPONSE_LENGTH};
 pub use funnel::Funnel;
 pub use funnel_config::FunnelConfig;
 pub use genome_mutate_context::{GenomeMutateContext, GenomeMutateContextBuilder};
@@ -58,10 +68,13 @@ pub use metrics_prometheus::MetricsPrometheus;
 pub use metrics_worker::MetricsWorker;
 pub use mine_event_directory_state::MineEventDirectoryState;
 pub use miner_sync_execute::{MinerSyncExecute, MinerSyncExecuteStatus};
+pub use miner_sync_import::MinerSyncImport;
+pub use miner_target::{MinerTarget, MinerTargetStats, MinerTargetStatsItem};
 
 #[allow(unused_imports)]
 pub use miner_worker::{miner_worker, MinerWorkerMessage, MinerWorkerMessageWithAnalytics, MinerWorkerQuestion};
 pub use moving_average::MovingAverage;
+pub use mutation_stats::{MutationOutcome, MutationScheduler, MutationStats, MutationStatsItem};
 pub use performance_classifier::{PerformanceClassifier, PerformanceClassifierResult};
 pub use popular_program_container::PopularProgramContainer;
 pub use postmine_worker::{postmine_worker, PostmineWorkerMessage};
@@ -71,11 +84,12 @@ pub use prevent_flooding::{PreventFlooding, PreventFloodingError};
 pub use random_indexes_with_distance::random_indexes_with_distance;
 pub use recent_program_container::RecentProgramContainer;
 pub use run_miner_loop::{ExecuteBatchResult, RunMinerLoop};
-pub use save_candidate_program::save_candidate_program;
+pub use save_candidate_program::{save_candidate_program, save_candidate_program_with_suffix};
 pub use suggest_instruction::SuggestInstruction;
 pub use suggest_line::{SuggestLine, LineValue};
 pub use suggest_source::{SuggestSource, SourceValue};
 pub use suggest_target::{SuggestTarget, TargetValue};
 pub use term_computer::TermComputer;
-pub use upload_worker::{upload_worker, UploadWorkerItem};
+pub use upload_outbox::{UploadOutbox, UploadOutboxCounts, UploadOutboxItem, UploadRetryPolicy};
+pub use upload_worker::{upload_program, upload_worker, UploadWorkerItem, UploadWorkerSink};
 pub use wildcard_checker::WildcardChecker;
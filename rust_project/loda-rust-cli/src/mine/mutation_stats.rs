@@ -0,0 +1,398 @@
+use super::MutateGenome;
+use crate::common::{create_csv_file, parse_csv_file, save_file_atomic, FileLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How far the candidate program got, after a mutation was applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MutationOutcome {
+    Attempt,
+    NoChange,
+    Funnel10,
+    Funnel20,
+    Funnel30,
+    Funnel40,
+    Candidate,
+    Accepted,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MutationStatsItem {
+    pub attempts: u64,
+    pub no_change: u64,
+    pub funnel10: u64,
+    pub funnel20: u64,
+    pub funnel30: u64,
+    pub funnel40: u64,
+    pub candidate: u64,
+    pub accepted: u64,
+}
+
+impl MutationStatsItem {
+    fn record(&mut self, outcome: MutationOutcome) {
+        match outcome {
+            MutationOutcome::Attempt => self.attempts += 1,
+            MutationOutcome::NoChange => self.no_change += 1,
+            MutationOutcome::Funnel10 => self.funnel10 += 1,
+            MutationOutcome::Funnel20 => self.funnel20 += 1,
+            MutationOutcome::Funnel30 => self.funnel30 += 1,
+            MutationOutcome::Funnel40 => self.funnel40 += 1,
+            MutationOutcome::Candidate => self.candidate += 1,
+            MutationOutcome::Accepted => self.accepted += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &MutationStatsItem) {
+        self.attempts += other.attempts;
+        self.no_change += other.no_change;
+        self.funnel10 += other.funnel10;
+        self.funnel20 += other.funnel20;
+        self.funnel30 += other.funnel30;
+        self.funnel40 += other.funnel40;
+        self.candidate += other.candidate;
+        self.accepted += other.accepted;
+    }
+
+    /// Passing a funnel stage is common, so it's worth little.
+    /// Finding a program that gets accepted by `postmine` is rare, so it's worth a lot.
+    fn reward(&self) -> f64 {
+        let funnel: u64 = self.funnel10 + self.funnel20 + self.funnel30 + self.funnel40;
+        (funnel as f64) + (self.candidate as f64) * 10.0 + (self.accepted as f64) * 100.0
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct RecordMutationStats {
+    mutation: String,
+    attempts: u64,
+    #[serde(rename = "no change")]
+    no_change: u64,
+    funnel10: u64,
+    funnel20: u64,
+    funnel30: u64,
+    funnel40: u64,
+    candidate: u64,
+    accepted: u64,
+}
+
+/// Counts how well each mutation operator performs.
+///
+/// This is saved as a `mutation-stats.csv` file, with this format:
+///
+/// ```csv
+/// mutation;attempts;no change;funnel10;funnel20;funnel30;funnel40;candidate;accepted
+/// CallRecentProgram;8123450;130512;40876;5120;2011;1530;62;3
+/// CopyLine;271220;19302;1203;140;51;38;1;0
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MutationStats {
+    items: HashMap<MutateGenome, MutationStatsItem>,
+}
+
+impl MutationStats {
+    /// Assume this many attempts at the average reward rate, before trusting the observed reward rate.
+    const PSEUDO_ATTEMPTS: f64 = 1000.0;
+
+    const MINIMUM_LIFT: f64 = 0.1;
+    const MAXIMUM_LIFT: f64 = 10.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn record(&mut self, mutation: MutateGenome, outcome: MutationOutcome) {
+        self.items.entry(mutation).or_default().record(outcome);
+    }
+
+    pub fn merge(&mut self, other: &MutationStats) {
+        for (mutation, item) in &other.items {
+            self.items.entry(*mutation).or_default().merge(item);
+        }
+    }
+
+    pub fn item(&self, mutation: MutateGenome) -> MutationStatsItem {
+        self.items.get(&mutation).cloned().unwrap_or_default()
+    }
+
+    /// All the counters sorted by name.
+    pub fn to_vec(&self) -> Vec<(MutateGenome, MutationStatsItem)> {
+        let mut items: Vec<(MutateGenome, MutationStatsItem)> = self.items.iter()
+            .map(|(mutation, item)| (*mutation, item.clone()))
+            .collect();
+        items.sort_unstable_by_key(|(mutation, _item)| mutation.name());
+        items
+    }
+
+    /// The hand tuned weights, with the weights from the config file applied on top.
+    ///
+    /// A mutation with a weight of zero is disabled, and stays disabled regardless of the stats.
+    pub fn prior_weights(overrides: &HashMap<String, usize>) -> Vec<(MutateGenome, usize)> {
+        MutateGenome::default_weights().into_iter().map(|(mutation, weight)| {
+            let weight: usize = overrides.get(&mutation.name()).cloned().unwrap_or(weight);
+            (mutation, weight)
+        }).collect()
+    }
+
+    /// Multi-armed bandit style weights for picking the next mutation.
+    ///
+    /// The prior weight is multiplied by how much better or worse the mutation performs,
+    /// compared to the average of all the mutations.
+    /// Rarely used mutations stay close to their prior weight, until enough attempts have been made.
+    pub fn weights(&self, prior_weights: &[(MutateGenome, usize)]) -> Vec<(MutateGenome, f64)> {
+        let mut total_attempts: u64 = 0;
+        let mut total_reward: f64 = 0.0;
+        for item in self.items.values() {
+            total_attempts += item.attempts;
+            total_reward += item.reward();
+        }
+        let mean_reward: f64 = if total_attempts > 0 {
+            total_reward / (total_attempts as f64)
+        } else {
+            0.0
+        };
+        prior_weights.iter().map(|(mutation, prior_weight)| {
+            let mutation: MutateGenome = *mutation;
+            let prior_weight: f64 = *prior_weight as f64;
+            if mean_reward <= 0.0 {
+                return (mutation, prior_weight);
+            }
+            let item: MutationStatsItem = self.item(mutation);
+            let observed: f64 = item.reward() + Self::PSEUDO_ATTEMPTS * mean_reward;
+            let expected: f64 = ((item.attempts as f64) + Self::PSEUDO_ATTEMPTS) * mean_reward;
+            let lift: f64 = (observed / expected).clamp(Self::MINIMUM_LIFT, Self::MAXIMUM_LIFT);
+            (mutation, prior_weight * lift)
+        }).collect()
+    }
+
+    /// Find the mutation that created the candidate program.
+    ///
+    /// The miner appends comments like `; mutate: CallRecentProgram` to the candidate program.
+    /// The last mutation that changed the genome, is the one that created the program.
+    pub fn mutation_from_candidate_program(contents: &str) -> Option<MutateGenome> {
+        contents.lines().rev()
+            .filter_map(|line| line.trim().strip_prefix("; mutate: "))
+            .find_map(MutateGenome::from_name)
+    }
+
+    /// Returns an empty instance when the file doesn't exist.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut instance = Self::new();
+        if !path.is_file() {
+            return Ok(instance);
+        }
+        let records: Vec<RecordMutationStats> = parse_csv_file(path)
+            .map_err(|e| anyhow::anyhow!("Unable to load mutation stats file: {:?} error: {:?}", path, e))?;
+        for record in records {
+            let mutation: MutateGenome = match MutateGenome::from_name(&record.mutation) {
+                Some(value) => value,
+                None => {
+                    debug!("Ignoring unknown mutation: {:?}", record.mutation);
+                    continue;
+                }
+            };
+            let item = MutationStatsItem {
+                attempts: record.attempts,
+                no_change: record.no_change,
+                funnel10: record.funnel10,
+                funnel20: record.funnel20,
+                funnel30: record.funnel30,
+                funnel40: record.funnel40,
+                candidate: record.candidate,
+                accepted: record.accepted,
+            };
+            instance.items.entry(mutation).or_default().merge(&item);
+        }
+        Ok(instance)
+    }
+
+    /// Replaces the file, without interfering with processes that are reading the old file.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let records: Vec<RecordMutationStats> = self.to_vec().into_iter().map(|(mutation, item)| {
+            RecordMutationStats {
+                mutation: mutation.name(),
+                attempts: item.attempts,
+                no_change: item.no_change,
+                funnel10: item.funnel10,
+                funnel20: item.funnel20,
+                funnel30: item.funnel30,
+                funnel40: item.funnel40,
+                candidate: item.candidate,
+                accepted: item.accepted,
+            }
+        }).collect();
+        save_file_atomic(path, |tmp_path| {
+            create_csv_file(&records, tmp_path)
+                .map_err(|e| anyhow::anyhow!("Unable to save mutation stats file: {:?} error: {:?}", path, e))
+        })
+    }
+
+    /// Add the counters to the ones already on disk.
+    ///
+    /// Both the miner and `postmine` updates the file, so it's reloaded and saved while holding a lock file.
+    pub fn append_to_file(&self, path: &Path) -> anyhow::Result<MutationStats> {
+        let _lock = FileLock::acquire(path)?;
+        let mut stats: MutationStats = MutationStats::load(path)?;
+        stats.merge(self);
+        stats.save(path)?;
+        Ok(stats)
+    }
+}
+
+/// The mutation stats shared by all the miner workers.
+///
+/// New stats are saved to disk with an interval, so they survive a restart of the miner.
+pub struct MutationScheduler {
+    path: PathBuf,
+    prior_weights: Vec<(MutateGenome, usize)>,
+    saved: MutationStats,
+    unsaved: MutationStats,
+    last_save: Instant,
+}
+
+impl MutationScheduler {
+    const INTERVAL_BETWEEN_SAVES: Duration = Duration::from_secs(60);
+
+    /// Continue with the stats from a previous run. A broken file is treated as empty.
+    pub fn load(path: &Path, prior_weights: Vec<(MutateGenome, usize)>) -> Self {
+        let saved: MutationStats = match MutationStats::load(path) {
+            Ok(value) => value,
+            Err(error) => {
+                error!("MutationScheduler: {:?}", error);
+                MutationStats::new()
+            }
+        };
+        Self {
+            path: PathBuf::from(path),
+            prior_weights,
+            saved,
+            unsaved: MutationStats::new(),
+            last_save: Instant::now(),
+        }
+    }
+
+    pub fn merge(&mut self, stats: &MutationStats) {
+        self.unsaved.merge(stats);
+    }
+
+    pub fn weights(&self) -> Vec<(MutateGenome, f64)> {
+        let mut stats: MutationStats = self.saved.clone();
+        stats.merge(&self.unsaved);
+        stats.weights(&self.prior_weights)
+    }
+
+    pub fn save_if_expired(&mut self) {
+        if self.last_save.elapsed() < Self::INTERVAL_BETWEEN_SAVES {
+            return;
+        }
+        if let Err(error) = self.save() {
+            error!("MutationScheduler: Unable to save. {:?}", error);
+        }
+    }
+
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        self.last_save = Instant::now();
+        if self.unsaved.is_empty() {
+            return Ok(());
+        }
+        self.saved = self.unsaved.append_to_file(&self.path)?;
+        self.unsaved = MutationStats::new();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn weight_of(weights: &[(MutateGenome, f64)], mutation: MutateGenome) -> f64 {
+        weights.iter().find(|(m, _)| *m == mutation).unwrap().1
+    }
+
+    fn default_prior_weights() -> Vec<(MutateGenome, usize)> {
+        MutationStats::prior_weights(&HashMap::new())
+    }
+
+    #[test]
+    fn test_10000_weights_without_stats() {
+        let stats = MutationStats::new();
+        let weights: Vec<(MutateGenome, f64)> = stats.weights(&default_prior_weights());
+        assert_eq!(weights.len(), MutateGenome::default_weights().len());
+        assert_eq!(weight_of(&weights, MutateGenome::CallRecentProgram), 300.0);
+        assert_eq!(weight_of(&weights, MutateGenome::CopyLine), 10.0);
+        assert_eq!(weight_of(&weights, MutateGenome::DisableLoop), 0.0);
+    }
+
+    #[test]
+    fn test_10001_weights_adapt() {
+        let mut stats = MutationStats::new();
+        for _ in 0..100000 {
+            stats.record(MutateGenome::CopyLine, MutationOutcome::Attempt);
+            stats.record(MutateGenome::SwapRows, MutationOutcome::Attempt);
+        }
+        for _ in 0..1000 {
+            stats.record(MutateGenome::CopyLine, MutationOutcome::Funnel10);
+        }
+        for _ in 0..10 {
+            stats.record(MutateGenome::SwapRows, MutationOutcome::Funnel10);
+        }
+        let weights: Vec<(MutateGenome, f64)> = stats.weights(&default_prior_weights());
+        assert!(weight_of(&weights, MutateGenome::CopyLine) > 15.0);
+        assert!(weight_of(&weights, MutateGenome::SwapRows) < 5.0);
+        // Mutations that hasn't been tried, keeps the hand tuned weight
+        assert_eq!(weight_of(&weights, MutateGenome::SwapAdjacentRows), 10.0);
+        // Mutations with a hand tuned weight of zero, stays disabled
+        assert_eq!(weight_of(&weights, MutateGenome::DisableLoop), 0.0);
+    }
+
+    #[test]
+    fn test_10002_prior_weights_from_config() {
+        let mut overrides = HashMap::<String, usize>::new();
+        overrides.insert("CopyLine".to_string(), 0);
+        overrides.insert("DisableLoop".to_string(), 25);
+        let prior_weights: Vec<(MutateGenome, usize)> = MutationStats::prior_weights(&overrides);
+        let weights: Vec<(MutateGenome, f64)> = MutationStats::new().weights(&prior_weights);
+        assert_eq!(weight_of(&weights, MutateGenome::CopyLine), 0.0);
+        assert_eq!(weight_of(&weights, MutateGenome::DisableLoop), 25.0);
+        assert_eq!(weight_of(&weights, MutateGenome::InsertLoopBeginEnd), 0.0);
+    }
+
+    #[test]
+    fn test_20000_mutation_from_candidate_program() {
+        let contents = "; 1,2,3\n\nmov $0,1\n\n; template 40\n; mutate: CopyLine\n; mutate: SwapRows, no change\n; mutate: mutate_inline_seq\n; keep: performance NewProgramIsAlwaysFaster than 40\n";
+        assert_eq!(MutationStats::mutation_from_candidate_program(contents), Some(MutateGenome::CopyLine));
+        assert_eq!(MutationStats::mutation_from_candidate_program("mov $0,1\n"), None);
+    }
+
+    #[test]
+    fn test_30000_save_load_roundtrip() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let path: PathBuf = PathBuf::from(&tempdir.path()).join("mutation-stats.csv");
+        let mut stats = MutationStats::new();
+        stats.record(MutateGenome::CopyLine, MutationOutcome::Attempt);
+        stats.record(MutateGenome::CopyLine, MutationOutcome::Funnel10);
+        stats.record(MutateGenome::CallRecentProgram, MutationOutcome::Accepted);
+        stats.save(&path).expect("save");
+
+        // Act
+        let mut delta = MutationStats::new();
+        delta.record(MutateGenome::CopyLine, MutationOutcome::Attempt);
+        let merged: MutationStats = delta.append_to_file(&path).expect("append");
+        let loaded: MutationStats = MutationStats::load(&path).expect("load");
+
+        // Assert
+        assert_eq!(loaded.item(MutateGenome::CopyLine).attempts, 2);
+        assert_eq!(loaded.item(MutateGenome::CopyLine).funnel10, 1);
+        assert_eq!(loaded.item(MutateGenome::CallRecentProgram).accepted, 1);
+        assert_eq!(merged.to_vec(), loaded.to_vec());
+        let contents: String = fs::read_to_string(&path).expect("file");
+        assert!(contents.starts_with("mutation;attempts;no change;funnel10;funnel20;funnel30;funnel40;candidate;accepted\nCallRecentProgram;0;0;0;0;0;0;0;1\n"));
+    }
+}
@@ -1,33 +1,32 @@
-use super::{Genome, GenomeItem, GenomeMutateContext, save_candidate_program, ToGenomeItemVec};
+use super::{Genome, GenomeItem, GenomeMutateContext, MutateGenome, save_candidate_program, ToGenomeItemVec};
 use super::{CreateFunnel, Funnel};
 use super::{PreventFlooding, TermComputer};
+use super::{DistributedCandidateSender, DistributedClient};
+use super::{MinerTargetStats, MinerTargetStatsItem};
 use super::{PerformanceClassifierResult, PerformanceClassifier};
 use super::MetricEvent;
+use super::{MutationOutcome, MutationScheduler, MutationStats};
 use super::metrics_run_miner_loop::MetricsRunMinerLoop;
+use crate::common::attach_persistent_term_cache;
 use crate::oeis::TermsToProgramIdSet;
-use crate::config::{Config, MinerFilterMode};
+use crate::config::{Config, MinerFilterMode, MinerTuning};
 use loda_rust_core::control::DependencyManager;
 use loda_rust_core::execute::{ProgramCache, ProgramId, ProgramRunner, ProgramSerializer};
 use loda_rust_core::util::{BigIntVec, BigIntVecToString};
-use loda_rust_core::parser::ParsedProgram;
+use loda_rust_core::parser::{analyze_program, ParsedProgram, ProgramAnalysisIssue};
 use std::collections::HashSet;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use std::rc::Rc;
 use std::time::Instant;
 use rand::seq::SliceRandom;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 use std::sync::{Arc, Mutex};
 
 const EXECUTE_BATCH_TIME_LIMIT: u128 = 2000;
 const INTERVAL_UNTIL_NEXT_METRIC_SYNC: u128 = 100;
-const MINIMUM_PROGRAM_LENGTH: usize = 6;
 const LOAD_INITIAL_GENOME_MINIMUM_PROGRAM_LENGTH: usize = 8;
 const LOAD_INITIAL_GENOME_RETRIES: usize = 1000;
-const MINER_CACHE_CAPACITY: usize = 1500;
-const ITERATIONS_BETWEEN_PICKING_A_NEW_INITIAL_GENOME: usize = 300;
-const ITERATIONS_BETWEEN_RELOADING_CURRENT_GENOME: usize = 5;
 
 #[derive(Clone, Debug)]
 pub struct ExecuteBatchResult {
@@ -60,30 +59,44 @@ impl ExecuteBatchResult {
     }
 }
 
+/// A genome that the mutations starts out from, whenever the genome gets reloaded.
+#[derive(Clone, Default)]
+struct PopulationItem {
+    program_id: u64,
+    genome_vec: Vec<GenomeItem>,
+    message_vec: Vec<String>,
+}
+
 pub struct RunMinerLoop {
     metrics_callback: Option<Box<dyn Fn(MetricEvent) + Send>>,
     funnel: Funnel,
     mine_event_dir: PathBuf,
     cache: ProgramCache,
     prevent_flooding: Arc<Mutex<PreventFlooding>>,
+    mutation_scheduler: Arc<Mutex<MutationScheduler>>,
+    mutation_stats: MutationStats,
+    mutation_weight_vec: Vec<(MutateGenome, f64)>,
     context: GenomeMutateContext,
     genome: Genome,
     rng: StdRng,
     metric: MetricsRunMinerLoop,
     current_program_id: u64,
-    current_genome_vec: Vec<GenomeItem>,
-    current_message_vec: Vec<String>,
+    population: Vec<PopulationItem>,
     iteration: usize,
     reload: bool,
     term_computer: TermComputer,
     terms_to_program_id: Arc<TermsToProgramIdSet>,
     suppress_low_priority_programs: bool,
+    tuning: MinerTuning,
+    distributed_candidate_sender: Option<DistributedCandidateSender>,
+    target_stats: Option<MinerTargetStats>,
 }
 
 impl RunMinerLoop {
     pub fn new(
         config: &Config,
         prevent_flooding: Arc<Mutex<PreventFlooding>>,
+        mutation_scheduler: Arc<Mutex<MutationScheduler>>,
         initial_random_seed: u64,
     ) -> Self {
         let rng: StdRng = StdRng::seed_from_u64(initial_random_seed);
@@ -95,25 +108,42 @@ impl RunMinerLoop {
             MinerFilterMode::New => true
         };
     
-        let capacity = NonZeroUsize::new(MINER_CACHE_CAPACITY).unwrap();
+        let tuning: MinerTuning = config.miner_tuning().clone();
+        let capacity = NonZeroUsize::new(tuning.cache_capacity).unwrap();
+        let mut cache = ProgramCache::with_capacity(capacity);
+        attach_persistent_term_cache(&mut cache, config.persistent_term_cache_dir().as_deref());
+        let mutation_weight_vec: Vec<(MutateGenome, f64)> = mutation_scheduler.lock().unwrap().weights();
+        let distributed_candidate_sender: Option<DistributedCandidateSender> = config.miner_coordinator_address().map(|address| {
+            let worker_name: String = DistributedClient::worker_name_from_config(config).unwrap_or_else(|error| {
+                error!("Unable to obtain the worker name. error: {:?}", error);
+                "unknown".to_string()
+            });
+            let client = DistributedClient::new(address, config.miner_coordinator_secret(), worker_name);
+            DistributedCandidateSender::spawn(client, mine_event_dir.clone())
+        });
         Self {
             metrics_callback: None,
             funnel: Funnel::create_empty_funnel(),
             mine_event_dir: PathBuf::from(mine_event_dir),
-            cache: ProgramCache::with_capacity(capacity),
+            cache,
             prevent_flooding: prevent_flooding,
+            mutation_scheduler,
+            mutation_stats: MutationStats::new(),
+            mutation_weight_vec,
             context: GenomeMutateContext::default(),
             genome: Genome::new(),
             rng: rng,
             metric: MetricsRunMinerLoop::new(),
             current_program_id: 0,
-            current_genome_vec: vec!(),
-            current_message_vec: vec!(),
+            population: vec!(),
             iteration: 0,
             reload: true,
-            term_computer: TermComputer::new(),
+            term_computer: TermComputer::with_tuning(&tuning),
             terms_to_program_id: Arc::new(TermsToProgramIdSet::new()),
             suppress_low_priority_programs: suppress_low_priority_programs,
+            tuning,
+            distributed_candidate_sender,
+            target_stats: None,
         }
     }
 
@@ -142,6 +172,19 @@ impl RunMinerLoop {
         self.metrics_callback = Some(Box::new(c));
     }
 
+    /// Reject programs with instructions that cannot contribute to the output,
+    /// before spending time executing them.
+    fn is_junk(parsed_program: &ParsedProgram) -> bool {
+        let issues: Vec<ProgramAnalysisIssue> = match analyze_program(parsed_program) {
+            Ok(value) => value,
+            Err(_error) => {
+                // Unbalanced loops are rejected later on, when the program gets parsed
+                return false;
+            }
+        };
+        issues.iter().any(|issue| matches!(issue, ProgramAnalysisIssue::NoEffect { .. } | ProgramAnalysisIssue::LoopCounterNeverDecremented { .. }))
+    }
+
     fn submit_metric_event(&mut self, metric_event: MetricEvent) {
         match &self.metrics_callback {
             Some(callback) => {
@@ -163,6 +206,7 @@ impl RunMinerLoop {
             cannot_load: self.metric.number_of_failed_genome_loads,
             cannot_parse: self.metric.number_of_programs_that_cannot_parse,
             too_short: self.metric.number_of_too_short_programs,
+            junk: self.metric.number_of_junk_programs,
             no_output: self.metric.number_of_programs_without_output,
             no_mutation: self.metric.number_of_failed_mutations,
             compute_error: self.metric.number_of_compute_errors,
@@ -181,6 +225,33 @@ impl RunMinerLoop {
         self.funnel.reset_metrics();
         self.cache.reset_metrics();
         self.metric.reset_metrics();
+        self.sync_mutation_stats();
+        self.submit_target_stats();
+    }
+
+    fn submit_target_stats(&mut self) {
+        let stats: Vec<(u32, MinerTargetStatsItem)> = match &self.target_stats {
+            Some(target_stats) if !target_stats.is_empty() => target_stats.to_vec(),
+            _ => return
+        };
+        self.submit_metric_event(MetricEvent::Target { stats });
+        self.target_stats = Some(MinerTargetStats::new());
+    }
+
+    /// Share the outcome of the recent mutations with the other miner workers,
+    /// and pick up the weights that are based on what all the workers have learned.
+    fn sync_mutation_stats(&mut self) {
+        {
+            let mut mutation_scheduler = self.mutation_scheduler.lock().unwrap();
+            mutation_scheduler.merge(&self.mutation_stats);
+            mutation_scheduler.save_if_expired();
+            self.mutation_weight_vec = mutation_scheduler.weights();
+        }
+        self.submit_metric_event(MetricEvent::Mutation {
+            stats: self.mutation_stats.to_vec(),
+            weights: self.mutation_weight_vec.clone(),
+        });
+        self.mutation_stats = MutationStats::new();
     }
 
     fn submit_metrics_for_dependency_manager(&mut self, dependency_manager: &mut DependencyManager) {
@@ -203,6 +274,20 @@ impl RunMinerLoop {
         self.terms_to_program_id = terms_to_program_id;
     }
 
+    /// When mining targeted, the funnel only contains the target sequences.
+    /// 
+    /// Improvements to existing programs are kept, since the target sequences have been picked by hand.
+    /// The progress for each target sequence is submitted as metrics.
+    pub fn set_targeted(&mut self, targeted: bool, config: &Config) {
+        if targeted {
+            self.suppress_low_priority_programs = false;
+            self.target_stats = Some(MinerTargetStats::new());
+        } else {
+            self.suppress_low_priority_programs = config.miner_filter_mode() == MinerFilterMode::New;
+            self.target_stats = None;
+        }
+    }
+
     pub fn load_initial_genome_program(&mut self, dependency_manager: &mut DependencyManager) -> anyhow::Result<()> {
         for _ in 0..LOAD_INITIAL_GENOME_RETRIES {
             let program_id: u32 = match self.context.choose_initial_genome_program(&mut self.rng) {
@@ -250,23 +335,53 @@ impl RunMinerLoop {
                 ];
             }
 
-            self.current_genome_vec = genome_vec;
-            self.current_message_vec = message_vec;
+            let item = PopulationItem {
+                program_id: program_id as u64,
+                genome_vec,
+                message_vec,
+            };
+            self.population.push(item);
+            while self.population.len() > self.tuning.population_size {
+                self.population.remove(0);
+            }
             return Ok(());
         }
         return Err(anyhow::anyhow!("Unable to pick among available programs"));
     }
 
+    /// Pick a random genome from the population.
+    ///
+    /// Sometimes the genome is combined with another genome from the population.
+    fn pick_from_population(&mut self) -> PopulationItem {
+        let length: usize = self.population.len();
+        if length == 0 {
+            return PopulationItem::default();
+        }
+        let index: usize = self.rng.gen_range(0..length);
+        let mut item: PopulationItem = self.population[index].clone();
+        if length < 2 || self.rng.gen_range(0..100) >= self.tuning.crossover_percent {
+            return item;
+        }
+        let donor_index: usize = (index + self.rng.gen_range(1..length)) % length;
+        let donor: &PopulationItem = &self.population[donor_index];
+        if Genome::crossover(&mut self.rng, &mut item.genome_vec, &donor.genome_vec) {
+            item.message_vec.push(format!("crossover: template {}", donor.program_id));
+        } else {
+            item.message_vec.push(format!("crossover: template {}, no change", donor.program_id));
+        }
+        item
+    }
+
     fn execute_one_iteration(
         &mut self, 
         dependency_manager: &mut DependencyManager, 
         execute_batch_result: &mut ExecuteBatchResult
     ) {
         self.metric.number_of_iterations += 1;
-        if (self.iteration % ITERATIONS_BETWEEN_RELOADING_CURRENT_GENOME) == 0 {
+        if (self.iteration % self.tuning.iterations_between_reloading_current_genome) == 0 {
             self.reload = true;
         }
-        if (self.iteration % ITERATIONS_BETWEEN_PICKING_A_NEW_INITIAL_GENOME) == 0 {
+        if (self.iteration % self.tuning.iterations_between_picking_a_new_initial_genome) == 0 {
             match self.load_initial_genome_program(dependency_manager) {
                 Ok(_) => {},
                 Err(error) => {
@@ -276,14 +391,18 @@ impl RunMinerLoop {
             }
         }
         if self.reload {
-            self.genome.set_message_vec(self.current_message_vec.clone());
-            self.genome.set_genome_vec(self.current_genome_vec.clone());
+            let item: PopulationItem = self.pick_from_population();
+            self.genome.set_message_vec(item.message_vec);
+            self.genome.set_genome_vec(item.genome_vec);
             self.reload = false;
         }
 
         self.iteration += 1;
         
-        if !self.genome.mutate(&mut self.rng, &self.context) {
+        let mutation: MutateGenome = self.mutation_weight_vec.choose_weighted(&mut self.rng, |item| item.1).unwrap().0;
+        self.mutation_stats.record(mutation, MutationOutcome::Attempt);
+        if !self.genome.mutate_with(&mut self.rng, &self.context, mutation) {
+            self.mutation_stats.record(mutation, MutationOutcome::NoChange);
             self.metric.number_of_failed_mutations += 1;
             return;
         }
@@ -291,11 +410,16 @@ impl RunMinerLoop {
         // println!("#{} Current genome\n{}", iteration, self.genome);
     
         let genome_parsed_program: ParsedProgram = self.genome.to_parsed_program();
-        if genome_parsed_program.instruction_vec.len() < MINIMUM_PROGRAM_LENGTH {
+        if genome_parsed_program.instruction_vec.len() < self.tuning.minimum_program_length {
             self.metric.number_of_too_short_programs += 1;
             self.reload = true;
             return;
         }
+        if Self::is_junk(&genome_parsed_program) {
+            self.metric.number_of_junk_programs += 1;
+            self.reload = true;
+            return;
+        }
 
         // Create program from genome
         let result_parse = dependency_manager.parse_stage2(
@@ -326,6 +450,7 @@ impl RunMinerLoop {
         if !self.funnel.check10(terms10) {
             return;
         }
+        self.mutation_stats.record(mutation, MutationOutcome::Funnel10);
 
         match self.term_computer.compute(&mut self.cache, &runner, 20) {
             Ok(_) => {},
@@ -346,6 +471,7 @@ impl RunMinerLoop {
                 return;
             }
         }
+        self.mutation_stats.record(mutation, MutationOutcome::Funnel20);
 
         match self.term_computer.compute(&mut self.cache, &runner, 30) {
             Ok(_) => {},
@@ -366,6 +492,7 @@ impl RunMinerLoop {
                 return;
             }
         }
+        self.mutation_stats.record(mutation, MutationOutcome::Funnel30);
 
         match self.term_computer.compute(&mut self.cache, &runner, 40) {
             Ok(_) => {},
@@ -399,18 +526,33 @@ impl RunMinerLoop {
         }
         let terms40_wildcard: &BigIntVec = &funnel40terms;
 
+        // lookup in stripped.zip and find the corresponding program_ids.
+        // This rejects the false positives in the bloomfilter funnel, before doing the time consuming checks.
+        let terms_to_program_id: Arc<TermsToProgramIdSet> = self.terms_to_program_id.clone();
+        let key: String = terms40_wildcard.to_compact_comma_string();
+        let corresponding_program_id_set: &HashSet<u32> = match terms_to_program_id.get(&key) {
+            Some(value) => value,
+            None => {
+                debug!("Ignoring false-positive in bloomfilter funnel. Could not find the candiate in the oeis stripped file. funnel20_number_of_wildcards: {:?} funnel30_number_of_wildcards: {:?} funnel40_number_of_wildcards: {:?} key: {:?}", funnel20_number_of_wildcards, funnel30_number_of_wildcards, funnel40_number_of_wildcards, key);
+                self.metric.number_of_bloomfilter_false_positive += 1;
+                self.reload = true;
+                return
+            }
+        };
+        self.mutation_stats.record(mutation, MutationOutcome::Funnel40);
+
         // Reject, if it's identical to one of the programs that this program depends on
         let depends_on_program_ids: HashSet<u32> = self.genome.depends_on_program_ids();
         let mut reject_self_dependency = false;
         for program_id in &depends_on_program_ids {
-            let program_runner: Rc::<ProgramRunner> = match dependency_manager.load(*program_id as u64) {
+            let program_runner: Arc::<ProgramRunner> = match dependency_manager.load(*program_id as u64) {
                 Ok(value) => value,
                 Err(error) => {
                     error!("Cannot verify, failed to load program id {}, {:?}", program_id, error);
                     continue;
                 }
             };
-            let mut verify_term_computer = TermComputer::new();
+            let mut verify_term_computer = TermComputer::with_tuning(&self.tuning);
             match verify_term_computer.compute(&mut self.cache, &program_runner, 40) {
                 Ok(_) => {},
                 Err(error) => {
@@ -433,17 +575,6 @@ impl RunMinerLoop {
             return;
         }
 
-        // lookup in stripped.zip and find the corresponding program_ids
-        let key: String = terms40_wildcard.to_compact_comma_string();
-        let corresponding_program_id_set: &HashSet<u32> = match self.terms_to_program_id.get(&key) {
-            Some(value) => value,
-            None => {
-                debug!("Ignoring false-positive in bloomfilter funnel. Could not find the candiate in the oeis stripped file. funnel20_number_of_wildcards: {:?} funnel30_number_of_wildcards: {:?} funnel40_number_of_wildcards: {:?} key: {:?}", funnel20_number_of_wildcards, funnel30_number_of_wildcards, funnel40_number_of_wildcards, key);
-                self.metric.number_of_bloomfilter_false_positive += 1;
-                self.reload = true;
-                return
-            }
-        };
         let intersection: HashSet<&u32> = depends_on_program_ids.intersection(corresponding_program_id_set).collect();
         if !intersection.is_empty() {
             debug!("Ignoring self-dependency. There is this intersection: {:?}", intersection);
@@ -451,6 +582,10 @@ impl RunMinerLoop {
             self.reload = true;
             return
         }
+        let target_program_ids: Vec<u32> = corresponding_program_id_set.iter().cloned().collect();
+        if let Some(target_stats) = &mut self.target_stats {
+            target_stats.record_match(&target_program_ids);
+        }
         debug!("Found corresponding program_id's: {:?} funnel20_number_of_wildcards: {:?} funnel30_number_of_wildcards: {:?} funnel40_number_of_wildcards: {:?}", corresponding_program_id_set, funnel20_number_of_wildcards, funnel30_number_of_wildcards, funnel40_number_of_wildcards);
 
         let steps: &Vec<u64> = &self.term_computer.steps;
@@ -466,7 +601,7 @@ impl RunMinerLoop {
                 maybe_a_new_program = true;
                 break;
             }
-            let program_runner: Rc::<ProgramRunner> = match dependency_manager.load(*program_id as u64) {
+            let program_runner: Arc::<ProgramRunner> = match dependency_manager.load(*program_id as u64) {
                 Ok(value) => value,
                 Err(error) => {
                     debug!("Keep. Maybe a new program. Cannot verify, failed to load program id {}, {:?}", program_id, error);
@@ -477,7 +612,7 @@ impl RunMinerLoop {
                     break;
                 }
             };
-            let mut verify_term_computer = TermComputer::new();
+            let mut verify_term_computer = TermComputer::with_tuning(&self.tuning);
             match verify_term_computer.compute(&mut self.cache, &program_runner, 40) {
                 Ok(_) => {},
                 Err(error) => {
@@ -586,12 +721,29 @@ impl RunMinerLoop {
         serializer.append_empty_line();
         let candidate_program: String = serializer.to_string();
 
+        // When mining distributed, the head node saves the program to its `mine-event` dir.
+        // If the head node cannot be reached, then the program is saved to the local `mine-event` dir.
+        if let Some(distributed_candidate_sender) = &self.distributed_candidate_sender {
+            let high_priority: bool = priority == ProgramCandidatePriority::High;
+            distributed_candidate_sender.send(&terms40_original, &candidate_program, high_priority, self.iteration);
+            self.metric.number_of_candidate_programs += 1;
+            self.mutation_stats.record(mutation, MutationOutcome::Candidate);
+            if let Some(target_stats) = &mut self.target_stats {
+                target_stats.record_candidate(&target_program_ids);
+            }
+            return;
+        }
+
         if let Err(error) = save_candidate_program(&self.mine_event_dir, self.iteration, &candidate_program) {
             println!("; GENOME\n{}", self.genome);
             error!("Unable to save candidate program: {:?}", error);
             return;
         }
         self.metric.number_of_candidate_programs += 1;
+        self.mutation_stats.record(mutation, MutationOutcome::Candidate);
+        if let Some(target_stats) = &mut self.target_stats {
+            target_stats.record_candidate(&target_program_ids);
+        }
 
         match priority {
             ProgramCandidatePriority::Low => {
@@ -8,10 +8,21 @@ pub fn save_candidate_program(
     iteration: usize,
     content: &String,
 ) -> std::io::Result<()> 
+{
+    save_candidate_program_with_suffix(mine_event_dir, &iteration.to_string(), content)
+}
+
+/// Same as `save_candidate_program`, but with a custom suffix,
+/// such as for the candidate programs that the head node receives from the worker nodes.
+pub fn save_candidate_program_with_suffix(
+    mine_event_dir: &Path,
+    suffix: &str,
+    content: &String,
+) -> std::io::Result<()> 
 {
     // Format filename as "19841231-235959-1234.asm"
     let now: DateTime<Utc> = Utc::now();
-    let filename: String = format!("{}-{}.asm", now.format("%Y%m%d-%H%M%S"), iteration);
+    let filename: String = format!("{}-{}.asm", now.format("%Y%m%d-%H%M%S"), suffix);
 
     // Write the file to the output dir
     let path = mine_event_dir.join(Path::new(&filename));
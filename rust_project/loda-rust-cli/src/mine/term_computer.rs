@@ -1,3 +1,4 @@
+use crate::config::{MinerTermLimit, MinerTermLimits, MinerTuning};
 use loda_rust_core::execute::{NodeLoopLimit, ProgramCache, ProgramRunner, RegisterValue, RunMode};
 use loda_rust_core::execute::NodeRegisterLimit;
 use loda_rust_core::util::BigIntVec;
@@ -6,14 +7,17 @@ pub struct TermComputer {
     pub terms: BigIntVec,
     pub steps: Vec<u64>,
     pub step_count: u64,
+    term_limits: MinerTermLimits,
 }
 
 impl TermComputer {
-    pub fn new() -> Self {
+    /// Use the loop/step limits from the `[miner_tuning]` section of the config file.
+    pub fn with_tuning(tuning: &MinerTuning) -> Self {
         Self {
             terms: Vec::with_capacity(40),
             steps: Vec::with_capacity(40),
             step_count: 0,
+            term_limits: tuning.term_limits(),
         }
     }
 
@@ -24,26 +28,9 @@ impl TermComputer {
             if length >= count {
                 break;
             }
-            let node_loop_limit: NodeLoopLimit;
-            if length <= 10 {
-                node_loop_limit = NodeLoopLimit::LimitCount(4000);
-            } else {
-                if length <= 20 {
-                    node_loop_limit = NodeLoopLimit::LimitCount(8000);
-                } else {
-                    node_loop_limit = NodeLoopLimit::LimitCount(32000);
-                }
-            }
-            let step_count_limit: u64;
-            if length <= 10 {
-                step_count_limit = 40000;
-            } else {
-                if length <= 20 {
-                    step_count_limit = 80000;
-                } else {
-                    step_count_limit = 320000;
-                }
-            }
+            let term_limit: MinerTermLimit = self.term_limits.term_limit(length);
+            let node_loop_limit = NodeLoopLimit::LimitCount(term_limit.loop_limit);
+            let step_count_limit: u64 = term_limit.step_limit;
             let index = length as i64;
             let input = RegisterValue::from_i64(index);
             let output: RegisterValue = runner.run(
@@ -0,0 +1,454 @@
+use crate::common::{create_csv_file, parse_csv_file, save_file_atomic, FileLock};
+use loda_rust_core::control::{fnv1a_hash, FNV_OFFSET_BASIS};
+use loda_rust_core::oeis::OeisId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+enum UploadOutboxStatus {
+    #[serde(rename = "pending")]
+    Pending,
+
+    /// Gave up after too many failed attempts. Only the `loda-rust upload-outbox` command retries it.
+    #[serde(rename = "failed")]
+    Failed,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct RecordUploadOutbox {
+    key: String,
+    #[serde(rename = "oeis id")]
+    oeis_id: u32,
+    status: UploadOutboxStatus,
+    #[serde(default)]
+    attempts: u32,
+    #[serde(rename = "next attempt", default)]
+    next_attempt: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadOutboxItem {
+    pub key: String,
+    pub oeis_id: OeisId,
+    pub upload_content: String,
+}
+
+/// Number of programs in each state.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UploadOutboxCounts {
+    pub pending: u64,
+    pub uploaded: u64,
+    pub failed: u64,
+}
+
+/// How long to wait before the next attempt, after a failed upload.
+#[derive(Clone, Copy, Debug)]
+pub struct UploadRetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay_seconds: u64,
+    pub max_delay_seconds: u64,
+}
+
+impl UploadRetryPolicy {
+    /// Exponential backoff. The delay doubles after each failed attempt.
+    pub fn delay_seconds(&self, attempts: u32) -> u64 {
+        let exponent: u32 = attempts.saturating_sub(1).min(63);
+        let factor: u64 = 1u64 << exponent;
+        self.initial_delay_seconds.saturating_mul(factor).min(self.max_delay_seconds)
+    }
+}
+
+/// Programs that are waiting to be uploaded.
+///
+/// The programs are kept on disk until the server has accepted them,
+/// so they survive network outages, server errors and restarts of the miner.
+/// When mining without network access, the programs stay here until the `loda-rust upload-outbox` command is run.
+///
+/// There is one file per program in the `upload-outbox` dir, named after the hash of the upload content,
+/// like this: `upload-outbox/a1b2c3d4e5f60718.asm`.
+/// The hash is also used as idempotency key, so the server can ignore a program that it has already received.
+///
+/// The `manifest.csv` file keeps track of the programs that have not yet been uploaded, with this format:
+///
+/// ```csv
+/// key;oeis id;status;attempts;next attempt
+/// 0f1e2d3c4b5a6978;112088;pending;2;1700000120
+/// 1a2b3c4d5e6f7081;40;failed;10;1700003600
+/// ```
+///
+/// The `next attempt` is a unix timestamp in seconds.
+///
+/// When a program has been uploaded, it's removed from the manifest, and its key is appended to the
+/// `uploaded-keys.txt` file, one key per line.
+///
+/// When the same program is added again, it's ignored. Also after it has been uploaded.
+///
+/// Both the miner and the `loda-rust upload-outbox` command may update the outbox at the same time.
+/// Every change is made while holding the `manifest.csv.lock` file, after reloading the files from disk.
+pub struct UploadOutbox {
+    dir: PathBuf,
+    records: Vec<RecordUploadOutbox>,
+    uploaded_keys: HashSet<String>,
+
+    /// The number of bytes of the `uploaded-keys.txt` file that have been read, so only new keys are read on reload.
+    uploaded_keys_file_length: u64,
+}
+
+impl UploadOutbox {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)
+            .map_err(|e| anyhow::anyhow!("Unable to create upload outbox dir: {:?} error: {:?}", dir, e))?;
+        let mut instance = Self {
+            dir: PathBuf::from(dir),
+            records: vec!(),
+            uploaded_keys: HashSet::new(),
+            uploaded_keys_file_length: 0,
+        };
+        instance.reload()?;
+        Ok(instance)
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dir.join("manifest.csv")
+    }
+
+    fn uploaded_keys_path(&self) -> PathBuf {
+        self.dir.join("uploaded-keys.txt")
+    }
+
+    /// Pick up the changes made by other processes.
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        self.read_uploaded_keys()?;
+        let path: PathBuf = self.manifest_path();
+        let records: Vec<RecordUploadOutbox> = match path.is_file() {
+            true => {
+                parse_csv_file(&path)
+                    .map_err(|e| anyhow::anyhow!("Unable to load upload outbox manifest: {:?} error: {:?}", path, e))?
+            },
+            false => vec!()
+        };
+        self.records.clear();
+        for record in records {
+            if self.uploaded_keys.contains(&record.key) {
+                continue;
+            }
+            self.records.push(record);
+        }
+        Ok(())
+    }
+
+    /// Read the keys that have been appended since the last read.
+    fn read_uploaded_keys(&mut self) -> anyhow::Result<()> {
+        let path: PathBuf = self.uploaded_keys_path();
+        let mut file: File = match File::open(&path) {
+            Ok(value) => value,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Ok(());
+            },
+            Err(error) => {
+                return Err(anyhow::anyhow!("Unable to open uploaded keys: {:?} error: {:?}", path, error));
+            }
+        };
+        file.seek(SeekFrom::Start(self.uploaded_keys_file_length))
+            .map_err(|e| anyhow::anyhow!("Unable to read uploaded keys: {:?} error: {:?}", path, e))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .map_err(|e| anyhow::anyhow!("Unable to read uploaded keys: {:?} error: {:?}", path, e))?;
+        // Only consume complete lines, in case another process is in the middle of appending a key.
+        let complete_length: usize = match contents.rfind('\n') {
+            Some(position) => position + 1,
+            None => 0
+        };
+        for key in contents[..complete_length].lines() {
+            self.uploaded_keys.insert(key.to_string());
+        }
+        self.uploaded_keys_file_length += complete_length as u64;
+        Ok(())
+    }
+
+    fn append_uploaded_keys(&mut self, keys: &[String]) -> anyhow::Result<()> {
+        let path: PathBuf = self.uploaded_keys_path();
+        let mut contents = String::new();
+        for key in keys {
+            contents.push_str(key);
+            contents.push('\n');
+        }
+        let mut file: File = OpenOptions::new().create(true).append(true).open(&path)
+            .map_err(|e| anyhow::anyhow!("Unable to open uploaded keys: {:?} error: {:?}", path, e))?;
+        file.write_all(contents.as_bytes())
+            .map_err(|e| anyhow::anyhow!("Unable to append to uploaded keys: {:?} error: {:?}", path, e))?;
+        self.read_uploaded_keys()
+    }
+
+    /// Reload the outbox, apply the change, and save the manifest, while holding the lock.
+    fn update<F, R>(&mut self, change: F) -> anyhow::Result<R>
+    where
+        F: FnOnce(&mut Self) -> anyhow::Result<R>
+    {
+        let _lock = FileLock::acquire(&self.manifest_path())?;
+        self.reload()?;
+        let result: R = change(self)?;
+        self.save_manifest()?;
+        Ok(result)
+    }
+
+    /// The key is the same across runs and across machines, so the same program is only uploaded once.
+    pub fn key_for_content(upload_content: &str) -> String {
+        let hash: u64 = fnv1a_hash(FNV_OFFSET_BASIS, upload_content.as_bytes());
+        format!("{:016x}", hash)
+    }
+
+    fn path_to_file(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.asm", key))
+    }
+
+    /// Returns `false` when the program is already in the outbox.
+    pub fn add(&mut self, oeis_id: OeisId, upload_content: &str) -> anyhow::Result<bool> {
+        let key: String = Self::key_for_content(upload_content);
+        self.update(|outbox| {
+            if outbox.uploaded_keys.contains(&key) || outbox.records.iter().any(|record| record.key == key) {
+                return Ok(false);
+            }
+            let path: PathBuf = outbox.path_to_file(&key);
+            fs::write(&path, upload_content)
+                .map_err(|e| anyhow::anyhow!("Unable to write program to upload outbox: {:?} error: {:?}", path, e))?;
+            outbox.records.push(RecordUploadOutbox {
+                key,
+                oeis_id: oeis_id.raw(),
+                status: UploadOutboxStatus::Pending,
+                attempts: 0,
+                next_attempt: 0,
+            });
+            Ok(true)
+        })
+    }
+
+    /// The programs that have not yet been uploaded, including the ones that have failed too many times.
+    pub fn pending(&self) -> anyhow::Result<Vec<UploadOutboxItem>> {
+        self.items_where(|_record| true)
+    }
+
+    /// The programs that are ready for another upload attempt, at the unix timestamp `now`.
+    pub fn due(&self, now: u64) -> anyhow::Result<Vec<UploadOutboxItem>> {
+        self.items_where(|record| record.status == UploadOutboxStatus::Pending && record.next_attempt <= now)
+    }
+
+    fn items_where<F: Fn(&RecordUploadOutbox) -> bool>(&self, predicate: F) -> anyhow::Result<Vec<UploadOutboxItem>> {
+        let mut items: Vec<UploadOutboxItem> = vec!();
+        for record in &self.records {
+            if !predicate(record) {
+                continue;
+            }
+            let path: PathBuf = self.path_to_file(&record.key);
+            let upload_content: String = fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Unable to read program from upload outbox: {:?} error: {:?}", path, e))?;
+            items.push(UploadOutboxItem {
+                key: record.key.clone(),
+                oeis_id: OeisId::from(record.oeis_id),
+                upload_content,
+            });
+        }
+        Ok(items)
+    }
+
+    /// Remember that the program has been uploaded, so it's not uploaded again.
+    pub fn mark_uploaded(&mut self, key: &str) -> anyhow::Result<()> {
+        self.update(|outbox| {
+            if outbox.uploaded_keys.contains(key) {
+                // Another process has uploaded the program in the meantime.
+                return Ok(());
+            }
+            let index: usize = match outbox.records.iter().position(|record| record.key == key) {
+                Some(value) => value,
+                None => {
+                    return Err(anyhow::anyhow!("The upload outbox doesn't contain the key: {:?}", key));
+                }
+            };
+            outbox.append_uploaded_keys(&[key.to_string()])?;
+            outbox.records.remove(index);
+            Ok(())
+        })?;
+        let path: PathBuf = self.path_to_file(key);
+        if path.is_file() {
+            if let Err(error) = fs::remove_file(&path) {
+                error!("Unable to remove uploaded program from upload outbox: {:?} error: {:?}", path, error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Postpone the next attempt, according to the retry policy.
+    ///
+    /// Returns `true` when giving up, because the max number of attempts has been reached.
+    pub fn mark_failed_attempt(&mut self, key: &str, now: u64, policy: &UploadRetryPolicy) -> anyhow::Result<bool> {
+        self.update(|outbox| {
+            if outbox.uploaded_keys.contains(key) {
+                // Another process has uploaded the program in the meantime.
+                return Ok(false);
+            }
+            let record: &mut RecordUploadOutbox = match outbox.records.iter_mut().find(|record| record.key == key) {
+                Some(value) => value,
+                None => {
+                    return Err(anyhow::anyhow!("The upload outbox doesn't contain the key: {:?}", key));
+                }
+            };
+            record.attempts += 1;
+            record.next_attempt = now + policy.delay_seconds(record.attempts);
+            let give_up: bool = record.attempts >= policy.max_attempts;
+            if give_up {
+                record.status = UploadOutboxStatus::Failed;
+            }
+            Ok(give_up)
+        })
+    }
+
+    pub fn counts(&self) -> UploadOutboxCounts {
+        let mut counts = UploadOutboxCounts {
+            uploaded: self.uploaded_keys.len() as u64,
+            ..Default::default()
+        };
+        for record in &self.records {
+            match record.status {
+                UploadOutboxStatus::Pending => counts.pending += 1,
+                UploadOutboxStatus::Failed => counts.failed += 1,
+            }
+        }
+        counts
+    }
+
+    /// Replaces the file, so a crash never leaves a partially written manifest behind.
+    fn save_manifest(&self) -> anyhow::Result<()> {
+        let path: PathBuf = self.manifest_path();
+        save_file_atomic(&path, |tmp_path| {
+            create_csv_file(&self.records, tmp_path)
+                .map_err(|e| anyhow::anyhow!("Unable to save upload outbox manifest: {:?} error: {:?}", path, e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_10000_add_pending_uploaded() -> anyhow::Result<()> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10000_add_pending_uploaded");
+        let mut outbox = UploadOutbox::load(&dir)?;
+
+        // Act
+        assert!(outbox.add(OeisId::from(45), "mov $0,1\n")?);
+        assert!(outbox.add(OeisId::from(40), "mov $0,2\n")?);
+        assert!(!outbox.add(OeisId::from(45), "mov $0,1\n")?);
+        let key: String = UploadOutbox::key_for_content("mov $0,1\n");
+        outbox.mark_uploaded(&key)?;
+        assert!(!outbox.add(OeisId::from(45), "mov $0,1\n")?);
+
+        // Assert
+        let outbox = UploadOutbox::load(&dir)?;
+        let items: Vec<UploadOutboxItem> = outbox.pending()?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].oeis_id, OeisId::from(40));
+        assert_eq!(items[0].upload_content, "mov $0,2\n");
+        assert!(!dir.join(format!("{}.asm", key)).is_file());
+        let manifest: String = fs::read_to_string(dir.join("manifest.csv"))?;
+        assert!(manifest.starts_with("key;oeis id;status;attempts;next attempt\n"));
+        assert!(!manifest.contains(&key));
+        let uploaded_keys: String = fs::read_to_string(dir.join("uploaded-keys.txt"))?;
+        assert_eq!(uploaded_keys, format!("{}\n", key));
+        let expected = UploadOutboxCounts { pending: 1, uploaded: 1, failed: 0 };
+        assert_eq!(outbox.counts(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_10001_several_writers() -> anyhow::Result<()> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10001_several_writers");
+        let mut miner = UploadOutbox::load(&dir)?;
+        let mut command = UploadOutbox::load(&dir)?;
+        let key45: String = UploadOutbox::key_for_content("mov $0,1\n");
+
+        // Act
+        assert!(miner.add(OeisId::from(45), "mov $0,1\n")?);
+        assert!(command.add(OeisId::from(40), "mov $0,2\n")?);
+        assert!(!command.add(OeisId::from(45), "mov $0,1\n")?);
+        command.mark_uploaded(&key45)?;
+        assert!(miner.add(OeisId::from(112088), "mov $0,3\n")?);
+        assert!(!miner.add(OeisId::from(45), "mov $0,1\n")?);
+        miner.mark_uploaded(&key45)?;
+
+        // Assert
+        let outbox = UploadOutbox::load(&dir)?;
+        let oeis_ids: Vec<OeisId> = outbox.pending()?.iter().map(|item| item.oeis_id).collect();
+        assert_eq!(oeis_ids, vec![OeisId::from(40), OeisId::from(112088)]);
+        let expected = UploadOutboxCounts { pending: 2, uploaded: 1, failed: 0 };
+        assert_eq!(outbox.counts(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_20000_delay_seconds() {
+        let policy = UploadRetryPolicy {
+            max_attempts: 5,
+            initial_delay_seconds: 60,
+            max_delay_seconds: 200,
+        };
+        assert_eq!(policy.delay_seconds(1), 60);
+        assert_eq!(policy.delay_seconds(2), 120);
+        assert_eq!(policy.delay_seconds(3), 200);
+        assert_eq!(policy.delay_seconds(100), 200);
+    }
+
+    #[test]
+    fn test_20001_failed_attempts() -> anyhow::Result<()> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_20001_failed_attempts");
+        let mut outbox = UploadOutbox::load(&dir)?;
+        outbox.add(OeisId::from(45), "mov $0,1\n")?;
+        let key: String = UploadOutbox::key_for_content("mov $0,1\n");
+        let policy = UploadRetryPolicy {
+            max_attempts: 2,
+            initial_delay_seconds: 10,
+            max_delay_seconds: 1000,
+        };
+
+        // Act
+        assert_eq!(outbox.due(1000)?.len(), 1);
+        assert!(!outbox.mark_failed_attempt(&key, 1000, &policy)?);
+        assert_eq!(outbox.due(1009)?.len(), 0);
+        assert_eq!(outbox.due(1010)?.len(), 1);
+        assert!(outbox.mark_failed_attempt(&key, 1010, &policy)?);
+
+        // Assert
+        let outbox = UploadOutbox::load(&dir)?;
+        assert_eq!(outbox.due(5000)?.len(), 0);
+        assert_eq!(outbox.pending()?.len(), 1);
+        let expected = UploadOutboxCounts { pending: 0, uploaded: 0, failed: 1 };
+        assert_eq!(outbox.counts(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_20002_manifest_without_attempts() -> anyhow::Result<()> {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_20002_manifest_without_attempts");
+        fs::create_dir(&dir)?;
+        fs::write(dir.join("manifest.csv"), "key;oeis id;status\nabc;45;pending\n")?;
+        fs::write(dir.join("abc.asm"), "mov $0,1\n")?;
+
+        // Act
+        let outbox = UploadOutbox::load(&dir)?;
+
+        // Assert
+        assert_eq!(outbox.due(0)?.len(), 1);
+        Ok(())
+    }
+}
@@ -1,7 +1,18 @@
+use super::{MetricEvent, UploadOutbox, UploadOutboxCounts, UploadOutboxItem, UploadRetryPolicy};
 use loda_rust_core::oeis::OeisId;
 use bastion::prelude::*;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const UPLOAD_MINER_PROFILE_LODA_RUST: &'static str = "\n; Miner Profile: loda-rust\n";
+const UPLOAD_WORKER_RETRY_INTERVAL_SECONDS: u64 = 30;
+
+/// Retry for about a day, before giving up on a program.
+const UPLOAD_WORKER_RETRY_POLICY: UploadRetryPolicy = UploadRetryPolicy {
+    max_attempts: 12,
+    initial_delay_seconds: 60,
+    max_delay_seconds: 4 * 60 * 60,
+};
 
 #[derive(Clone, Debug)]
 pub struct UploadWorkerItem {
@@ -9,11 +20,140 @@ pub struct UploadWorkerItem {
     pub oeis_id: OeisId,
 }
 
-pub async fn upload_worker(ctx: BastionContext, upload_endpoint: String) -> Result<(), ()> {
+impl UploadWorkerItem {
+    /// The program with the miner profile appended, as it's sent to the server.
+    pub fn upload_content(&self) -> String {
+        let mut upload_content: String = self.file_content.trim_end().to_string();
+        upload_content += UPLOAD_MINER_PROFILE_LODA_RUST;
+        upload_content
+    }
+}
+
+/// Where the `upload_worker` delivers the mined programs.
+#[derive(Clone, Debug)]
+pub enum UploadWorkerSink {
+    /// Post the programs to the `miner_program_upload_endpoint`.
+    ///
+    /// The programs are queued in the `upload-outbox` dir, until the server has accepted them.
+    Endpoint { upload_endpoint: String, outbox_dir: PathBuf },
+
+    /// Save the programs to the `upload-outbox` dir, so they can be uploaded later with `loda-rust upload-outbox`.
+    Outbox { outbox_dir: PathBuf },
+}
+
+/// Post a single program to the server.
+///
+/// The `idempotency_key` is derived from the program, so the server can detect when the same program is uploaded twice.
+pub async fn upload_program(client: &reqwest::Client, upload_endpoint: &str, idempotency_key: &str, upload_content: &str) -> anyhow::Result<()> {
+    let res = client.post(upload_endpoint)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .header("Idempotency-Key", idempotency_key)
+        .body(upload_content.to_string())
+        .send()
+        .await?;
+    let upload_success: bool = res.status() == 200 || res.status() == 201;
+    if !upload_success {
+        error!("upload_program: response headers: {:#?}\n", res.headers());
+        return Err(anyhow::anyhow!("response: {:?} {}, expected status 2xx.", res.version(), res.status()));
+    }
+    Ok(())
+}
+
+fn unix_timestamp_now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0
+    }
+}
+
+/// Try uploading the programs that are due.
+///
+/// Returns the number of successful uploads and the number of failed attempts.
+async fn upload_due_programs(outbox: &mut UploadOutbox, client: &reqwest::Client, upload_endpoint: &str) -> (u64, u64) {
+    // The `loda-rust upload-outbox` command may have uploaded programs in the meantime.
+    if let Err(error) = outbox.reload() {
+        error!("upload_worker: unable to reload the upload outbox, error: {:?}", error);
+        return (0, 0);
+    }
+    let items: Vec<UploadOutboxItem> = match outbox.due(unix_timestamp_now()) {
+        Ok(value) => value,
+        Err(error) => {
+            error!("upload_worker: unable to read the upload outbox, error: {:?}", error);
+            return (0, 0);
+        }
+    };
+    let mut number_of_success: u64 = 0;
+    let mut number_of_errors: u64 = 0;
+    for item in &items {
+        if let Err(error) = upload_program(client, upload_endpoint, &item.key, &item.upload_content).await {
+            error!("upload_worker: failed program upload of {}, error: {:?}", item.oeis_id, error);
+            number_of_errors += 1;
+            match outbox.mark_failed_attempt(&item.key, unix_timestamp_now(), &UPLOAD_WORKER_RETRY_POLICY) {
+                Ok(true) => {
+                    error!("upload_worker: giving up on {}. Use the `loda-rust upload-outbox` command to retry.", item.oeis_id);
+                },
+                Ok(false) => {},
+                Err(error) => {
+                    error!("upload_worker: unable to update the upload outbox, error: {:?}", error);
+                }
+            }
+            continue;
+        }
+        number_of_success += 1;
+        if let Err(error) = outbox.mark_uploaded(&item.key) {
+            error!("upload_worker: unable to update the upload outbox, error: {:?}", error);
+        }
+    }
+    (number_of_success, number_of_errors)
+}
+
+fn submit_metrics(outbox: &UploadOutbox, success: u64, error: u64) {
+    let counts: UploadOutboxCounts = outbox.counts();
+    let metric_event = MetricEvent::Upload {
+        pending: counts.pending,
+        failed: counts.failed,
+        success,
+        error,
+    };
+    let tell_result = Distributor::named("metrics_worker").tell_everyone(metric_event);
+    if let Err(error) = tell_result {
+        error!("upload_worker: Unable to send MetricEvent to metrics_worker. error: {:?}", error);
+    }
+}
+
+pub async fn upload_worker(ctx: BastionContext, sink: UploadWorkerSink) -> Result<(), ()> {
+    let outbox_dir: PathBuf = match &sink {
+        UploadWorkerSink::Endpoint { outbox_dir, .. } => outbox_dir.clone(),
+        UploadWorkerSink::Outbox { outbox_dir } => outbox_dir.clone(),
+    };
+    let mut outbox: UploadOutbox = match UploadOutbox::load(&outbox_dir) {
+        Ok(value) => value,
+        Err(error) => {
+            error!("upload_worker: unable to load the upload outbox, error: {:?}", error);
+            return Err(());
+        }
+    };
+    let client = reqwest::Client::new();
     debug!("upload_worker is ready");
     loop {
+        if let UploadWorkerSink::Endpoint { upload_endpoint, .. } = &sink {
+            let (success, error) = upload_due_programs(&mut outbox, &client, upload_endpoint).await;
+            submit_metrics(&outbox, success, error);
+        }
+
+        let timeout = Duration::from_secs(UPLOAD_WORKER_RETRY_INTERVAL_SECONDS);
+        let message: SignedMessage = match ctx.try_recv_timeout(timeout).await {
+            Ok(message) => message,
+            Err(error) => {
+                if let ReceiveError::Timeout(_duration) = error {
+                    continue;
+                }
+                error!("upload_worker: Unknown error happened. error: {:?}", error);
+                continue;
+            }
+        };
         let mut upload_worker_item: Option<UploadWorkerItem> = None;
-        MessageHandler::new(ctx.recv().await?)
+        MessageHandler::new(message)
             .on_tell(|item: UploadWorkerItem, _| {
                 debug!(
                     "upload_worker {}, received file for upload!:\n{:?}",
@@ -30,27 +170,27 @@ pub async fn upload_worker(ctx: BastionContext, upload_endpoint: String) -> Resu
                 );
             });
         if let Some(item) = upload_worker_item {
-            let mut upload_content: String = item.file_content.trim_end().to_string();
-            upload_content += UPLOAD_MINER_PROFILE_LODA_RUST;
-            let client = reqwest::Client::new();
-            let upload_result = client.post(&upload_endpoint)
-                .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
-                .body(upload_content.clone())
-                .send()
-                .await;
-            match upload_result {
-                Ok(res) => {
-                    let upload_success: bool = res.status() == 200 || res.status() == 201;
-                    if !upload_success {
-                        error!("upload_worker: uploaded program {}. body: {:?}", item.oeis_id, upload_content);
-                        error!("upload_worker: response: {:?} {}, expected status 2xx.", res.version(), res.status());
-                        error!("upload_worker: response headers: {:#?}\n", res.headers());
+            match outbox.add(item.oeis_id, &item.upload_content()) {
+                Ok(true) => {
+                    match &sink {
+                        UploadWorkerSink::Outbox { .. } => {
+                            println!("upload_worker: saved program {} to the upload outbox", item.oeis_id);
+                        },
+                        UploadWorkerSink::Endpoint { .. } => {
+                            debug!("upload_worker: added program {} to the upload outbox", item.oeis_id);
+                        }
                     }
                 },
+                Ok(false) => {
+                    debug!("upload_worker: program {} is already in the upload outbox", item.oeis_id);
+                },
                 Err(error) => {
-                    error!("upload_worker: failed program upload of {}, error: {:?}", item.oeis_id, error);
+                    error!("upload_worker: failed saving {} to the upload outbox, error: {:?}", item.oeis_id, error);
                 }
             }
+            if let UploadWorkerSink::Outbox { .. } = &sink {
+                submit_metrics(&outbox, 0, 0);
+            }
         }
     }
 }
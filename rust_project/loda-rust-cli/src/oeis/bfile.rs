@@ -0,0 +1,121 @@
+use loda_rust_core::oeis::OeisId;
+use loda_rust_core::util::BigIntVec;
+use num_bigint::BigInt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::Context;
+
+/// The terms of an OEIS `b-file`, such as `b000045.txt`.
+///
+/// Each row has the format `n a(n)`, where the `n` are consecutive.
+/// Rows starting with `#` are comments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BFile {
+    first_index: i64,
+    terms: BigIntVec,
+}
+
+impl BFile {
+    pub fn parse<S: AsRef<str>>(content: S) -> anyhow::Result<Self> {
+        let content: &str = content.as_ref();
+        let mut first_index: Option<i64> = None;
+        let mut terms: BigIntVec = vec!();
+        for (index, line) in content.lines().enumerate() {
+            let line_number: usize = index + 1;
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (n, value) = match (parts.next(), parts.next()) {
+                (Some(n), Some(value)) => (n, value),
+                _ => {
+                    return Err(anyhow::anyhow!("b-file line {}: expected 'n a(n)', but got {:?}", line_number, line));
+                }
+            };
+            let n: i64 = n.parse::<i64>()
+                .with_context(|| format!("b-file line {}: unable to parse index {:?}", line_number, n))?;
+            let value: BigInt = BigInt::from_str(value)
+                .with_context(|| format!("b-file line {}: unable to parse term {:?}", line_number, value))?;
+            let expected_n: i64 = first_index.unwrap_or(n) + (terms.len() as i64);
+            if n != expected_n {
+                return Err(anyhow::anyhow!("b-file line {}: expected index {}, but got {}", line_number, expected_n, n));
+            }
+            if first_index.is_none() {
+                first_index = Some(n);
+            }
+            terms.push(value);
+        }
+        let first_index: i64 = match first_index {
+            Some(value) => value,
+            None => {
+                return Err(anyhow::anyhow!("b-file contains no terms"));
+            }
+        };
+        Ok(Self {
+            first_index,
+            terms,
+        })
+    }
+
+    /// The `n` of the first term, this is usually the offset of the sequence.
+    pub fn first_index(&self) -> i64 {
+        self.first_index
+    }
+
+    pub fn terms(&self) -> &BigIntVec {
+        &self.terms
+    }
+
+    /// Construct a path, like this: `/absolute/path/b000045.txt`
+    pub fn path(bfile_dir: &Path, oeis_id: OeisId) -> PathBuf {
+        bfile_dir.join(format!("b{:0>6}.txt", oeis_id.raw()))
+    }
+
+    /// Load the b-file from the `bfile_dir`.
+    pub fn load(bfile_dir: &Path, oeis_id: OeisId) -> anyhow::Result<Self> {
+        let path: PathBuf = Self::path(bfile_dir, oeis_id);
+        let content: String = fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read b-file: {:?}", path))?;
+        Self::parse(&content)
+            .with_context(|| format!("Unable to parse b-file: {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loda_rust_core::util::BigIntVecToString;
+
+    #[test]
+    fn test_10000_parse_ok() {
+        let content = "# A000045 (b-file synthesized from sequence entry)\n\n0 0\n1 1\n2 1\n3 2\n4 3\n5 5\n";
+        let bfile: BFile = BFile::parse(content).expect("bfile");
+        assert_eq!(bfile.first_index(), 0);
+        assert_eq!(bfile.terms().to_compact_comma_string(), "0,1,1,2,3,5");
+    }
+
+    #[test]
+    fn test_10001_parse_offset_and_big_values() {
+        let content = "1 1\n2   123456789012345678901234567890\n3 -7";
+        let bfile: BFile = BFile::parse(content).expect("bfile");
+        assert_eq!(bfile.first_index(), 1);
+        assert_eq!(bfile.terms().to_compact_comma_string(), "1,123456789012345678901234567890,-7");
+    }
+
+    #[test]
+    fn test_20000_parse_error() {
+        assert!(BFile::parse("").is_err());
+        assert!(BFile::parse("# only a comment").is_err());
+        assert!(BFile::parse("0 1\n2 3").is_err());
+        assert!(BFile::parse("0 1\n1").is_err());
+        assert!(BFile::parse("0 x").is_err());
+    }
+
+    #[test]
+    fn test_30000_path() {
+        let path: PathBuf = BFile::path(Path::new("/tmp/bfiles"), OeisId::from(45));
+        assert_eq!(path, PathBuf::from("/tmp/bfiles/b000045.txt"));
+    }
+}
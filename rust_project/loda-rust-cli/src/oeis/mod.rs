@@ -1,12 +1,14 @@
-//! OEIS code for A-numbers, parsing of the `stripped` file and the `names` file.
+//! OEIS code for A-numbers, parsing of the `stripped` file, the `names` file and the `b-files`.
+mod bfile;
 mod name_row;
 mod process_names_file;
 mod process_stripped_file;
 mod stripped_row;
 mod terms_to_program_id;
 
+pub use bfile::BFile;
 pub use name_row::NameRow;
 pub use process_names_file::ProcessNamesFile;
 pub use process_stripped_file::ProcessStrippedFile;
 pub use stripped_row::StrippedRow;
-pub use terms_to_program_id::{TermsToProgramIdSet, load_terms_to_program_id_set};
+pub use terms_to_program_id::{TermsToProgramIdSet, load_terms_to_program_id_set, retain_program_ids, terms_to_program_id_set_from_rows};
@@ -28,6 +28,27 @@ pub fn load_terms_to_program_id_set(
     )
 }
 
+/// Only the sequences in `rows`, such as when mining targeted.
+pub fn terms_to_program_id_set_from_rows(rows: &[StrippedRow]) -> TermsToProgramIdSet {
+    let mut terms_to_program_id = TermsToProgramIdSet::new();
+    for row in rows {
+        let key: String = row.terms().to_compact_comma_string();
+        let entry = terms_to_program_id.entry(key).or_default();
+        entry.insert(row.oeis_id().raw());
+    }
+    terms_to_program_id
+}
+
+/// Only keep the program ids that satisfy the predicate, such as the sequences in a shard when mining distributed.
+///
+/// Terms that no longer have any program ids are removed.
+pub fn retain_program_ids<F: Fn(u32) -> bool>(terms_to_program_id: &mut TermsToProgramIdSet, predicate: F) {
+    terms_to_program_id.retain(|_key, program_ids| {
+        program_ids.retain(|program_id| predicate(*program_id));
+        !program_ids.is_empty()
+    });
+}
+
 fn build_terms_to_program_id_set(
     oeis_stripped_file_reader: &mut dyn io::BufRead,
     minimum_number_of_required_terms: usize,
@@ -60,6 +81,8 @@ fn build_terms_to_program_id_set(
 mod tests {
     use super::*;
     use num_traits::Zero;
+    use loda_rust_core::oeis::OeisId;
+    use loda_rust_core::util::{BigIntVec, BigIntVecFromI64};
 
     const INPUT_STRIPPED_SEQUENCE_MOCKDATA: &str = r#"
 # OEIS Sequence Data (http://oeis.org/stripped.gz)
@@ -104,4 +127,35 @@ A117093 ,2,3,5,7,11,13,16,17,18,19,23,28,29,30,31,37,38,39,40,41,43,47,53,58,59,
         assert_eq!(lookup(&dict, "non-existing"), "no value for key");
         Ok(())
     }
+
+    #[test]
+    fn test_10001_terms_to_program_id_set_from_rows() {
+        // Arrange
+        let rows: Vec<StrippedRow> = vec![
+            StrippedRow::new(OeisId::from(40), BigIntVec::from_i64array(&[2, 3, 5, 7, 11])),
+            StrippedRow::new(OeisId::from(112088), BigIntVec::from_i64array(&[2, 3, 5, 7, 11])),
+        ];
+        // Act
+        let dict: TermsToProgramIdSet = terms_to_program_id_set_from_rows(&rows);
+        // Assert
+        assert_eq!(dict.len(), 1);
+        assert_eq!(lookup(&dict, "2,3,5,7,11"), "40,112088");
+    }
+
+    #[test]
+    fn test_10002_retain_program_ids() {
+        // Arrange
+        let rows: Vec<StrippedRow> = vec![
+            StrippedRow::new(OeisId::from(40), BigIntVec::from_i64array(&[2, 3, 5, 7, 11])),
+            StrippedRow::new(OeisId::from(112088), BigIntVec::from_i64array(&[2, 3, 5, 7, 11])),
+            StrippedRow::new(OeisId::from(45), BigIntVec::from_i64array(&[0, 1, 1, 2, 3])),
+        ];
+        let mut dict: TermsToProgramIdSet = terms_to_program_id_set_from_rows(&rows);
+        // Act
+        retain_program_ids(&mut dict, |program_id| program_id % 2 == 0);
+        // Assert
+        assert_eq!(dict.len(), 1);
+        assert_eq!(lookup(&dict, "2,3,5,7,11"), "40,112088");
+        assert_eq!(lookup(&dict, "0,1,1,2,3"), "no value for key");
+    }
 }
@@ -17,10 +17,13 @@ pub fn instruction_diff_between_constants(instruction0: &Instruction, instructio
         if parameter0.parameter_type != parameter1.parameter_type {
             return None;
         }
-        let is_same_value = parameter0.parameter_value == parameter1.parameter_value;
+        let is_same_value = parameter0 == parameter1;
         match parameter0.parameter_type {
             ParameterType::Constant => {
                 if !is_same_value {
+                    if parameter0.is_big_constant() || parameter1.is_big_constant() {
+                        return None;
+                    }
                     return Some((parameter0.parameter_value, parameter1.parameter_value));
                 }
             },
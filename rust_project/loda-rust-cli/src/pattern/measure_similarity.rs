@@ -42,7 +42,7 @@ impl ProgramSimilarity {
                     return ProgramSimilarity::NotSimilar;
                 }
 
-                let is_same_value = parameter0.parameter_value == parameter1.parameter_value;
+                let is_same_value = parameter0 == parameter1;
 
                 match parameter0.parameter_type {
                     ParameterType::Constant => {
@@ -0,0 +1,180 @@
+use crate::common::attach_persistent_term_cache;
+use crate::lodacpp::{LodaCppCheckResult, LodaCppCheckStatus};
+use crate::oeis::BFile;
+use loda_rust_core::execute::{EvalError, NodeLoopLimit, NodeRegisterLimit, ProgramCache, ProgramRunner, ProgramState, RegisterValue, RunMode};
+use num_bigint::BigInt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Check a program against all the terms of a b-file, without using the `loda-cpp` executable.
+///
+/// The output has the same format as `loda-cpp check <PATH> -b`, one `n a(n)` row per correct term.
+/// In case of a mismatch, the row is `n a(n) -> expected b(n)` followed by `error`.
+/// When all the terms are correct, the last row is `ok`.
+/// When the time limit is exceeded, then there is no status row.
+///
+/// The time limit is also enforced while computing a term, so a single slow term cannot exceed it.
+pub struct CheckBFile {
+    time_limit: Duration,
+    persistent_term_cache_dir: Option<PathBuf>,
+}
+
+impl CheckBFile {
+    /// Max number of steps for computing a single term.
+    ///
+    /// When exceeded, it's undecided whether it's a full match or partial match,
+    /// so it's treated the same way as when the time limit is exceeded.
+    const DEFAULT_STEP_COUNT_LIMIT: u64 = 1_000_000_000;
+
+    pub fn new(time_limit: Duration) -> Self {
+        Self {
+            time_limit,
+            persistent_term_cache_dir: None,
+        }
+    }
+
+    /// Reuse the terms of dependencies, that have been computed by earlier runs.
+    pub fn set_persistent_term_cache_dir(&mut self, persistent_term_cache_dir: Option<PathBuf>) {
+        self.persistent_term_cache_dir = persistent_term_cache_dir;
+    }
+
+    pub fn check_and_save_output(&self, runner: &ProgramRunner, bfile: &BFile, save_output_to_path: &Path) -> anyhow::Result<LodaCppCheckResult> {
+        let mut output = String::new();
+        let result: LodaCppCheckResult = self.check(runner, bfile, &mut output);
+        fs::write(save_output_to_path, output)
+            .map_err(|e| anyhow::anyhow!("Unable to save the output of check. path: {:?} error: {:?}", save_output_to_path, e))?;
+        Ok(result)
+    }
+
+    pub fn check(&self, runner: &ProgramRunner, bfile: &BFile, output: &mut String) -> LodaCppCheckResult {
+        let start = Instant::now();
+        let deadline: Instant = start + self.time_limit;
+        let mut cache = ProgramCache::new();
+        attach_persistent_term_cache(&mut cache, self.persistent_term_cache_dir.as_deref());
+        let mut number_of_correct_terms: u32 = 0;
+        for (index, expected) in bfile.terms().iter().enumerate() {
+            if start.elapsed() > self.time_limit {
+                return LodaCppCheckResult {
+                    status: LodaCppCheckStatus::Timeout,
+                    number_of_correct_terms,
+                };
+            }
+            let n: i64 = bfile.first_index() + (index as i64);
+            let mut step_count: u64 = 0;
+            let mut state = ProgramState::new(
+                RunMode::Silent,
+                Self::DEFAULT_STEP_COUNT_LIMIT,
+                NodeRegisterLimit::Unlimited,
+                NodeLoopLimit::Unlimited,
+            );
+            state.set_deadline(Some(deadline));
+            let result = runner.run_without_offset_with_state(
+                RegisterValue::from_i64(n),
+                state,
+                &mut step_count,
+                &mut cache,
+            );
+            let actual: BigInt = match result {
+                Ok(value) => value.0,
+                Err(error) => {
+                    if let Some(EvalError::StepCountExceededLimit | EvalError::TimeLimitExceeded) = error.downcast_ref::<EvalError>() {
+                        return LodaCppCheckResult {
+                            status: LodaCppCheckStatus::Timeout,
+                            number_of_correct_terms,
+                        };
+                    }
+                    output.push_str(&format!("{:?}\nerror\n", error));
+                    return LodaCppCheckResult {
+                        status: LodaCppCheckStatus::PartialMatch,
+                        number_of_correct_terms,
+                    };
+                }
+            };
+            if actual != *expected {
+                output.push_str(&format!("{} {} -> expected {}\nerror\n", n, actual, expected));
+                return LodaCppCheckResult {
+                    status: LodaCppCheckStatus::PartialMatch,
+                    number_of_correct_terms,
+                };
+            }
+            output.push_str(&format!("{} {}\n", n, actual));
+            number_of_correct_terms += 1;
+        }
+        output.push_str("ok\n");
+        LodaCppCheckResult {
+            status: LodaCppCheckStatus::FullMatch,
+            number_of_correct_terms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loda_rust_core::control::{DependencyManager, DependencyManagerFileSystemMode};
+    use loda_rust_core::execute::ProgramId;
+    use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
+    use std::path::PathBuf;
+
+    fn process(program: &str, bfile_content: &str, time_limit: Duration) -> (LodaCppCheckResult, String) {
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            UnofficialFunctionRegistry::new(),
+        );
+        let runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, program).expect("runner");
+        let bfile: BFile = BFile::parse(bfile_content).expect("bfile");
+        let instance = CheckBFile::new(time_limit);
+        let mut output = String::new();
+        let result: LodaCppCheckResult = instance.check(&runner, &bfile, &mut output);
+        (result, output)
+    }
+
+    #[test]
+    fn test_10000_full_match() {
+        let (result, output) = process("mul $0,2", "0 0\n1 2\n2 4\n3 6", Duration::from_secs(10));
+        assert_eq!(result.status, LodaCppCheckStatus::FullMatch);
+        assert_eq!(result.number_of_correct_terms, 4);
+        assert_eq!(output, "0 0\n1 2\n2 4\n3 6\nok\n");
+    }
+
+    #[test]
+    fn test_10001_full_match_with_offset() {
+        let (result, output) = process("#offset 1\nmul $0,2", "1 2\n2 4\n3 6", Duration::from_secs(10));
+        assert_eq!(result.status, LodaCppCheckStatus::FullMatch);
+        assert_eq!(result.number_of_correct_terms, 3);
+        assert_eq!(output, "1 2\n2 4\n3 6\nok\n");
+    }
+
+    #[test]
+    fn test_20000_partial_match() {
+        let (result, output) = process("mul $0,2", "0 0\n1 2\n2 5\n3 6", Duration::from_secs(10));
+        assert_eq!(result.status, LodaCppCheckStatus::PartialMatch);
+        assert_eq!(result.number_of_correct_terms, 2);
+        assert_eq!(output, "0 0\n1 2\n2 4 -> expected 5\nerror\n");
+
+        // The output can be parsed the same way as the output from `loda-cpp check`
+        let parsed_result = LodaCppCheckResult::parse(&output, false).expect("result");
+        assert_eq!(parsed_result.status, LodaCppCheckStatus::PartialMatch);
+        assert_eq!(parsed_result.number_of_correct_terms, 2);
+    }
+
+    #[test]
+    fn test_20001_partial_match_eval_error() {
+        let (result, output) = process("mov $1,2\nsub $1,$0\ndiv $0,$1", "0 0\n1 1\n2 1", Duration::from_secs(10));
+        assert_eq!(result.status, LodaCppCheckStatus::PartialMatch);
+        assert_eq!(result.number_of_correct_terms, 2);
+        assert!(output.ends_with("\nerror\n"));
+    }
+
+    #[test]
+    fn test_30000_timeout_inside_term() {
+        // The 4th term loops 10^10 times, and is aborted when the time limit is exceeded
+        let program = "mov $1,$0\ntrn $1,2\nmul $1,10000000000\nlpb $1\n  sub $1,1\nlpe\nmul $0,2";
+        let (result, output) = process(program, "0 0\n1 2\n2 4\n3 6", Duration::from_millis(100));
+        assert_eq!(result.status, LodaCppCheckStatus::Timeout);
+        assert_eq!(result.number_of_correct_terms, 3);
+        assert_eq!(output, "0 0\n1 2\n2 4\n");
+    }
+}
@@ -2,6 +2,7 @@
 mod batch_lookup_names;
 mod batch_lookup_terms;
 mod candidate_program;
+mod check_bfile;
 mod compare_two_programs;
 mod filter_asm_files;
 mod find_postmine_directories;
@@ -24,6 +25,7 @@ pub use batch_lookup_names::batch_lookup_names;
 pub use batch_lookup_terms::batch_lookup_terms;
 
 pub use candidate_program::{CandidateProgram, State};
+pub use check_bfile::CheckBFile;
 pub use compare_two_programs::{CompareTwoPrograms, CompareTwoProgramsResult, StatusOfExistingProgram};
 
 #[allow(unused_imports)]
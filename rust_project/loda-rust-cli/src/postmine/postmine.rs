@@ -2,13 +2,18 @@
 use crate::config::{Config, MinerFilterMode};
 use crate::common::{oeis_ids_from_program_string, OeisIdStringMap};
 use crate::common::{load_program_ids_csv_file, PendingProgramsWithPriority, SimpleLog};
-use crate::oeis::{ProcessStrippedFile, StrippedRow};
-use crate::lodacpp::{LodaCpp, LodaCppCheck, LodaCppCheckResult, LodaCppCheckStatus, LodaCppEvalTermsExecute, LodaCppEvalTerms, LodaCppMinimize};
+use crate::oeis::{BFile, ProcessStrippedFile, StrippedRow};
+use crate::lodacpp::{LodaCpp, LodaCppCheck, LodaCppCheckResult, LodaCppCheckStatus, LodaCppEvalTermsExecute, LodaCppEvalTerms};
 use crate::analytics::AnalyticsDirectory;
+use crate::mine::{MutateGenome, MutationOutcome, MutationStats};
 use super::{batch_lookup_names, terms_from_program, FormatProgram, path_for_oeis_program};
-use super::{CandidateProgram, CompareTwoPrograms, CompareTwoProgramsResult, ParentDirAndChildFile, State, StatusOfExistingProgram, ValidateSingleProgram};
+use super::{CandidateProgram, CheckBFile, CompareTwoPrograms, CompareTwoProgramsResult, ParentDirAndChildFile, State, StatusOfExistingProgram, ValidateSingleProgram};
 use super::{MineEventDirectoryMaintenance, PostmineDirectoryMaintenance};
+use loda_rust_core::control::{DependencyManager, DependencyManagerFileSystemMode, ProgramMinimizer};
+use loda_rust_core::execute::{ProgramId, ProgramRunner};
 use loda_rust_core::oeis::{OeisId, OeisIdHashSet};
+use loda_rust_core::parser::ParsedProgram;
+use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
 use loda_rust_core::util::BigIntVec;
 use loda_rust_core::util::BigIntVecToString;
 use num_bigint::{BigInt, ToBigInt};
@@ -71,7 +76,9 @@ impl PostMine {
     const EVAL_TERM_COUNT: usize = 40;
     const MINIMUM_NUMBER_OF_REQUIRED_TERMS: usize = 10;
     const LODACPP_EVAL_TIME_LIMIT_IN_SECONDS: u64 = 10;
-    const LODACPP_MINIMIZE_TIME_LIMIT_IN_SECONDS: u64 = 5;
+    const MINIMIZE_TERM_COUNT: usize = 40;
+    const MINIMIZE_STEP_COUNT_LIMIT: u64 = 10_000_000;
+    const MINIMIZE_TIME_LIMIT_IN_SECONDS: u64 = 10;
     const LODACPP_CHECK_TIME_LIMIT_IN_SECONDS: u64 = 240;
     const LODACPP_COMPARE_NUMBER_OF_TERM_COUNT: usize = 60;
     const LODACPP_STEPS_TIME_LIMIT_IN_SECONDS: u64 = 120;
@@ -431,11 +438,21 @@ impl PostMine {
             return Ok(());
         }
 
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::System,
+            self.loda_programs_oeis_dir.clone(),
+            UnofficialFunctionRegistry::new(),
+        );
+        let mut minimizer = ProgramMinimizer::new();
+        minimizer.set_term_count(Self::MINIMIZE_TERM_COUNT);
+        minimizer.set_step_count_limit(Self::MINIMIZE_STEP_COUNT_LIMIT);
+        minimizer.set_time_limit(Duration::from_secs(Self::MINIMIZE_TIME_LIMIT_IN_SECONDS));
+
         let number_of_candidate_programs: usize = candidate_programs.len();
         println!("Minimizing programs");
         let pb = ProgressBar::new(number_of_candidate_programs as u64);
         for candidate_program in candidate_programs {
-            let result = self.minimize_candidate_program(candidate_program.clone());
+            let result = Self::minimize_candidate_program(&mut dm, &minimizer, candidate_program.clone());
             match result {
                 Ok(_) => {},
                 Err(error) => {
@@ -456,10 +473,9 @@ impl PostMine {
         Ok(())
     }
 
-    fn minimize_candidate_program(&mut self, candidate_program: CandidateProgramItem) -> anyhow::Result<()> {
-        let time_limit = Duration::from_secs(Self::LODACPP_MINIMIZE_TIME_LIMIT_IN_SECONDS);
+    fn minimize_candidate_program(dm: &mut DependencyManager, minimizer: &ProgramMinimizer, candidate_program: CandidateProgramItem) -> anyhow::Result<()> {
         let path_original = PathBuf::from(candidate_program.borrow().path_original());
-        let result = self.lodacpp.minimize(&path_original, time_limit);
+        let result = Self::minimize_program_file(dm, minimizer, &path_original);
         match result {
             Ok(value) => {
                 // debug!("minimized program successfully:\n{}", value);
@@ -475,6 +491,16 @@ impl PostMine {
         Ok(())
     }
 
+    fn minimize_program_file(dm: &mut DependencyManager, minimizer: &ProgramMinimizer, path: &Path) -> anyhow::Result<String> {
+        let contents: String = fs::read_to_string(path)
+            .with_context(|| format!("minimize program. Unable to read program file: {:?}", path))?;
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(&contents)
+            .map_err(|e| anyhow::anyhow!("minimize program. Unable to parse program: {:?} error: {}", path, e))?;
+        let minimized_program: ParsedProgram = minimizer.minimize(dm, &parsed_program)
+            .with_context(|| format!("minimize program: {:?}", path))?;
+        Ok(minimized_program.to_string() + "\n")
+    }
+
     fn obtain_sequence_names(&mut self) -> anyhow::Result<()> {
         if self.candidate_programs.is_empty() {
             return Ok(());
@@ -548,6 +574,7 @@ impl PostMine {
 
         println!("Analyzing {} program ids", number_of_program_ids_to_be_analyzed);
         let pb = ProgressBar::new(number_of_program_ids_to_be_analyzed as u64);
+        let mut mutation_stats = MutationStats::new();
         for candidate_program in candidate_programs {
             let possible_ids: Vec<OeisId> = candidate_program.borrow().possible_id_vec();
             for possible_id in possible_ids {
@@ -555,10 +582,24 @@ impl PostMine {
                 pb.inc(1);
             }
 
+            // Credit the mutation that created the program, before the file gets renamed.
+            let contents: String = fs::read_to_string(candidate_program.borrow().path_original()).unwrap_or_default();
+            let mutation: Option<MutateGenome> = MutationStats::mutation_from_candidate_program(&contents);
+
             candidate_program.borrow_mut().perform_keep_or_reject_based_result()
                 .map_err(|e| anyhow::anyhow!("process_candidate_programs -> perform_keep_or_reject_based_result. error: {:?}", e))?;
+
+            if let (State::Keep, Some(mutation)) = (candidate_program.borrow().state(), mutation) {
+                mutation_stats.record(mutation, MutationOutcome::Accepted);
+            }
         }
         pb.finish_and_clear();
+
+        if !mutation_stats.is_empty() {
+            if let Err(error) = mutation_stats.append_to_file(&self.config.mutation_stats_file()) {
+                error!("process_candidate_programs: Unable to save mutation stats. error: {:?}", error);
+            }
+        }
     
         let green_bold = Style::new().green().bold();        
         println!(
@@ -750,10 +791,9 @@ impl PostMine {
         check_program_file.sync_all()?;
         // debug!("Created program file: {:?}", check_program_path);
     
-        // Execute `loda-check check <PATH> -b`
-        let time_limit = Duration::from_secs(Self::LODACPP_CHECK_TIME_LIMIT_IN_SECONDS);
+        // Check the program against the b-file
         let check_start = Instant::now();
-        let ok_error = self.lodacpp.perform_check_and_save_output(&check_program_path, time_limit, &check_output_path);
+        let ok_error = self.perform_check(&simple_log, possible_id, &file_content, &check_program_path, &check_output_path);
         simple_log.println(format!("check - elapsed {}", HumanDuration(check_start.elapsed())));
         let check_result: LodaCppCheckResult = match ok_error {
             Ok(value) => {
@@ -809,6 +849,32 @@ impl PostMine {
         Ok(())
     }
 
+    /// Check the program natively, when the b-file is available in the `oeis-bfile` dir.
+    /// 
+    /// Otherwise execute `loda-check check <PATH> -b`.
+    fn perform_check(&self, simple_log: &SimpleLog, oeis_id: OeisId, file_content: &str, check_program_path: &Path, check_output_path: &Path) -> anyhow::Result<LodaCppCheckResult> {
+        let time_limit = Duration::from_secs(Self::LODACPP_CHECK_TIME_LIMIT_IN_SECONDS);
+        let bfile_dir: PathBuf = self.config.oeis_bfile_dir();
+        let bfile: BFile = match BFile::load(&bfile_dir, oeis_id) {
+            Ok(value) => value,
+            Err(error) => {
+                simple_log.println(format!("No b-file available, will use loda-cpp for checking. error: {:?}", error));
+                return self.lodacpp.perform_check_and_save_output(check_program_path, time_limit, check_output_path);
+            }
+        };
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::System,
+            self.loda_programs_oeis_dir.clone(),
+            UnofficialFunctionRegistry::new(),
+        );
+        let runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, file_content)
+            .map_err(|e| anyhow::anyhow!("Unable to create program for checking. error: {}", e))?;
+        simple_log.println(format!("Checking natively with b-file containing {} terms", bfile.terms().len()));
+        let mut check_bfile = CheckBFile::new(time_limit);
+        check_bfile.set_persistent_term_cache_dir(self.config.persistent_term_cache_dir());
+        check_bfile.check_and_save_output(&runner, &bfile, check_output_path)
+    }
+
     fn process_full_match(
         &self, 
         simple_log: SimpleLog, 
@@ -1,10 +1,78 @@
-//! The `loda-rust dependencies` subcommand, prints dependencies of a program.
+//! The `loda-rust dependencies` subcommand, prints dependencies of a program, reverse dependencies and cycles.
 use super::{DependencyManager,DependencyManagerFileSystemMode};
+use loda_rust_core::oeis::OeisId;
 use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
+use crate::analytics::{AnalyticsDirectory, DependencyDirection, DependencyGraph};
 use crate::config::Config;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use anyhow::Context;
 
-pub fn subcommand_dependencies(program_id: u64) {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubcommandDependenciesFormat {
+    Text,
+    Dot,
+    Json,
+}
+
+#[derive(Debug)]
+pub enum SubcommandDependenciesMode {
+    /// Print all direct/indirect dependencies of a program, by loading the program and its dependencies.
+    PrintDependencies { program_id: u64 },
+
+    /// Query the dependency graph of the entire repo.
+    Graph {
+        program_id: u64,
+        direction: DependencyDirection,
+        max_depth: Option<usize>,
+        format: SubcommandDependenciesFormat,
+    },
+
+    /// Print groups of programs that depend on each other.
+    PrintCycles,
+}
+
+pub fn subcommand_dependencies(mode: SubcommandDependenciesMode) -> anyhow::Result<()> {
+    let (program_id, direction, max_depth, format) = match mode {
+        SubcommandDependenciesMode::PrintDependencies { program_id } => {
+            print_dependencies(program_id);
+            return Ok(());
+        },
+        SubcommandDependenciesMode::Graph { program_id, direction, max_depth, format } => {
+            (program_id, direction, max_depth, format)
+        },
+        SubcommandDependenciesMode::PrintCycles => {
+            let graph: DependencyGraph = load_dependency_graph()?;
+            for cycle in graph.cycles() {
+                let strings: Vec<String> = cycle.iter().map(|program_id| program_id.to_string()).collect();
+                println!("{}", strings.join(","));
+            }
+            return Ok(());
+        }
+    };
+    let program_id: u32 = u32::try_from(program_id)
+        .map_err(|e| anyhow::anyhow!("The program_id is too big. error: {:?}", e))?;
+    let graph: DependencyGraph = load_dependency_graph()?;
+    if !graph.contains(program_id) {
+        return Err(anyhow::anyhow!("The program {} is not in the dependency graph", OeisId::from(program_id).a_number()));
+    }
+    let closure: BTreeMap<u32, usize> = graph.transitive_closure(program_id, direction, max_depth);
+    match format {
+        SubcommandDependenciesFormat::Text => {
+            let strings: Vec<String> = closure.keys().map(|program_id| program_id.to_string()).collect();
+            println!("{}", strings.join(","));
+        },
+        SubcommandDependenciesFormat::Dot => {
+            println!("{}", graph.to_dot(program_id, &closure));
+        },
+        SubcommandDependenciesFormat::Json => {
+            println!("{}", graph.to_json(program_id, direction, max_depth, &closure)?);
+        }
+    }
+    Ok(())
+}
+
+fn print_dependencies(program_id: u64) {
     let config = Config::load();
     let loda_programs_oeis_dir: PathBuf = config.loda_programs_oeis_dir();
     let mut dm = DependencyManager::new(
@@ -17,3 +85,21 @@ pub fn subcommand_dependencies(program_id: u64) {
     }
     dm.print_dependencies();
 }
+
+/// Use the `dependencies.csv` and `programs_valid.csv` files from the analytics dir, when they exist.
+/// Otherwise extract the dependencies from the programs.
+fn load_dependency_graph() -> anyhow::Result<DependencyGraph> {
+    let config = Config::load();
+    let analytics_directory = AnalyticsDirectory::new(config.analytics_oeis_dir())
+        .with_context(||"unable to create AnalyticsDirectory instance")?;
+    let dependencies_file: PathBuf = analytics_directory.dependencies_file();
+    let programs_valid_file: PathBuf = analytics_directory.programs_valid_file();
+    if dependencies_file.is_file() && programs_valid_file.is_file() {
+        debug!("Loading dependencies from: {:?}", dependencies_file);
+        let mut graph = DependencyGraph::load_csv(&dependencies_file)?;
+        graph.add_programs_from_csv(&programs_valid_file)?;
+        return Ok(graph);
+    }
+    debug!("Missing file: {:?} or {:?}, scanning the programs instead", dependencies_file, programs_valid_file);
+    DependencyGraph::scan_programs(&config.loda_programs_oeis_dir())
+}
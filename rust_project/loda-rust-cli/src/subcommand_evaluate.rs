@@ -1,67 +1,105 @@
 //! The `loda-rust eval` subcommand, evaluates terms of a program.
 use loda_rust_core;
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::BufWriter;
 use std::time::Instant;
 use std::rc::Rc;
-use std::path::PathBuf;
-use loda_rust_core::control::{DependencyManager,DependencyManagerFileSystemMode};
+use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use loda_rust_core::control::{DependencyManager,DependencyManagerError,DependencyManagerFileSystemMode};
+use loda_rust_core::parser::{diagnose_program, Diagnostic};
 use loda_rust_core::execute::{NodeLoopLimit, ProgramCache, ProgramRunner, RegisterValue, RunMode};
 use loda_rust_core::execute::NodeRegisterLimit;
-use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
+use loda_rust_core::unofficial_function::{register_program_functions, UnofficialFunctionRegistry};
+use crate::common::{attach_persistent_term_cache, TraceObserver};
 use crate::config::Config;
 
 pub enum SubcommandEvaluateMode {
     PrintTerms,
     PrintSteps,
     PrintDebug,
+
+    /// Print the terms, and write a JSON lines file with one record per executed instruction.
+    PrintTrace { path: PathBuf },
 }
 
 pub fn subcommand_evaluate(
     program_id: u64, 
     number_of_terms: u64,
     mode: SubcommandEvaluateMode,
-) {
+    unofficial_functions_dir: Option<PathBuf>,
+) -> anyhow::Result<()> {
     let config = Config::load();
     let loda_programs_oeis_dir: PathBuf = config.loda_programs_oeis_dir();
 
+    let registry = UnofficialFunctionRegistry::new();
+    if let Some(dir) = unofficial_functions_dir {
+        let count: usize = register_program_functions(&registry, &dir)?;
+        debug!("registered {} unofficial functions from {:?}", count, dir);
+    }
     let mut dm = DependencyManager::new(
         DependencyManagerFileSystemMode::System,
         loda_programs_oeis_dir,
-        UnofficialFunctionRegistry::new(),
+        registry,
     );
-    let program_runner: Rc::<ProgramRunner> = match dm.load(program_id) {
+    let program_runner: Arc::<ProgramRunner> = match dm.load(program_id) {
         Ok(value) => value,
         Err(error) => {
+            if let DependencyManagerError::ParseProgram(_) = error {
+                print_diagnostics(&dm.path_to_program(program_id));
+            }
             panic!("Failed to load program: {:?}", error);
         }
     };
+    let mut cache = ProgramCache::new();
+    attach_persistent_term_cache(&mut cache, config.persistent_term_cache_dir().as_deref());
     match mode {
         SubcommandEvaluateMode::PrintTerms => {
-            program_runner.print_terms(number_of_terms);
+            program_runner.print_terms(number_of_terms, &mut cache);
         },
         SubcommandEvaluateMode::PrintSteps => {
-            program_runner.print_steps(number_of_terms);
+            program_runner.print_steps(number_of_terms, &mut cache);
         },
         SubcommandEvaluateMode::PrintDebug => {
             program_runner.print_debug(number_of_terms);
+        },
+        SubcommandEvaluateMode::PrintTrace { path } => {
+            if let Err(error) = program_runner.print_trace(number_of_terms, &path) {
+                panic!("Failed to write trace file: {:?}", error);
+            }
         }
     }
+    Ok(())
+}
+
+/// Show the syntax problems of a program file, with the offending code underlined.
+fn print_diagnostics(path: &Path) {
+    let source: String = match fs::read_to_string(path) {
+        Ok(value) => value,
+        Err(_) => return
+    };
+    let diagnostics: Vec<Diagnostic> = diagnose_program(&source);
+    for diagnostic in &diagnostics {
+        eprintln!("{}\n", diagnostic.render(&source));
+    }
 }
 
 trait PrintTermsStepsDebug {
-    fn print_terms(&self, count: u64);
-    fn print_steps(&self, count: u64);
+    fn print_terms(&self, count: u64, cache: &mut ProgramCache);
+    fn print_steps(&self, count: u64, cache: &mut ProgramCache);
     fn print_debug(&self, count: u64);
+    fn print_trace(&self, count: u64, path: &Path) -> anyhow::Result<()>;
 }
 
 impl PrintTermsStepsDebug for ProgramRunner {
-    fn print_terms(&self, count: u64) {
+    fn print_terms(&self, count: u64, cache: &mut ProgramCache) {
         if count >= 0x7fff_ffff_ffff_ffff {
             panic!("Value is too high. Cannot be converted to 64bit signed integer.");
         }
         if count < 1 {
             panic!("Expected number of terms to be 1 or greater.");
         }
-        let mut cache = ProgramCache::new();
         let step_count_limit: u64 = 1000000000;
         let mut step_count: u64 = 0;
         let start_time = Instant::now();
@@ -74,7 +112,7 @@ impl PrintTermsStepsDebug for ProgramRunner {
                 step_count_limit,
                 NodeRegisterLimit::Unlimited,
                 NodeLoopLimit::Unlimited,
-                &mut cache
+                cache
             );
             let output: RegisterValue = match result_run {
                 Ok(value) => value,
@@ -94,14 +132,13 @@ impl PrintTermsStepsDebug for ProgramRunner {
         debug!("elapsed: {:?} ms", start_time.elapsed().as_millis());
     }
 
-    fn print_steps(&self, count: u64) {
+    fn print_steps(&self, count: u64, cache: &mut ProgramCache) {
         if count >= 0x7fff_ffff_ffff_ffff {
             panic!("Value is too high. Cannot be converted to 64bit signed integer.");
         }
         if count < 1 {
             panic!("Expected number of terms to be 1 or greater.");
         }
-        let mut cache = ProgramCache::new();
         let step_count_limit: u64 = 1000000000;
         for index in 0..(count as i64) {
             let input = RegisterValue::from_i64(index);
@@ -113,7 +150,7 @@ impl PrintTermsStepsDebug for ProgramRunner {
                 step_count_limit,
                 NodeRegisterLimit::Unlimited,
                 NodeLoopLimit::Unlimited,
-                &mut cache,
+                cache,
             );
             if let Err(error) = result_run {
                 panic!("Failure while computing term {}, error: {:?}", index, error);
@@ -159,4 +196,46 @@ impl PrintTermsStepsDebug for ProgramRunner {
         }
         debug!("stats: step_count: {}", step_count);
     }
+
+    fn print_trace(&self, count: u64, path: &Path) -> anyhow::Result<()> {
+        if count >= 0x7fff_ffff_ffff_ffff {
+            panic!("Value is too high. Cannot be converted to 64bit signed integer.");
+        }
+        if count < 1 {
+            panic!("Expected number of terms to be 1 or greater.");
+        }
+        let file = File::create(path)
+            .map_err(|e| anyhow::anyhow!("Unable to create trace file: {:?} error: {:?}", path, e))?;
+        let observer = Rc::new(RefCell::new(TraceObserver::new(BufWriter::new(file))));
+        let mut cache = ProgramCache::new();
+        let step_count_limit: u64 = 1000000000;
+        let mut step_count: u64 = 0;
+        for index in 0..(count as i64) {
+            observer.borrow_mut().set_term_index(index as u64);
+            let input = RegisterValue::from_i64(index);
+            let result_run = self.run_observed(
+                input, 
+                &mut step_count, 
+                step_count_limit,
+                &mut cache,
+                observer.clone(),
+            );
+            let output: RegisterValue = match result_run {
+                Ok(value) => value,
+                Err(error) => {
+                    observer.borrow_mut().finish()?;
+                    panic!("Failure while computing term {}, error: {:?}", index, error);
+                }
+            };
+            if index == 0 {
+                print!("{}", output.0);
+                continue;
+            }
+            print!(",{}", output.0);
+        }
+        println!();
+        observer.borrow_mut().finish()?;
+        debug!("trace: {} records written to {:?}", observer.borrow().record_count(), path);
+        Ok(())
+    }
 }
@@ -1,12 +1,16 @@
 //! The `loda-rust mine` subcommand, runs the miner daemon process.
 use crate::config::{Config, NumberOfWorkers, ValidateConfig, ValidateConfigTask};
 use crate::mine::{analytics_worker, cronjob_worker, miner_worker, postmine_worker, upload_worker};
-use crate::mine::{MetricsWorker, PreventFlooding};
+use crate::mine::{MetricsWorker, MutationScheduler, MutationStats, PreventFlooding, UploadWorkerSink};
 use crate::mine::{coordinator_worker, CoordinatorWorkerMessage};
+use crate::mine::{DistributedCoordinator, DistributedCoordinatorSettings};
+use crate::mine::MinerTarget;
+use loda_rust_core::util::BigIntVecToString;
 use bastion::prelude::*;
 use anyhow::Context;
 use std::fs;
-use std::sync::{Arc, Mutex};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Duration;
 
@@ -21,15 +25,19 @@ pub struct SubcommandMine {
     number_of_workers: usize,
     config: Config,
     prevent_flooding: Arc<Mutex<PreventFlooding>>,
+    mutation_scheduler: Arc<Mutex<MutationScheduler>>,
+    analytics_ready: Arc<RwLock<bool>>,
+    miner_target: Option<MinerTarget>,
 }
 
 impl SubcommandMine {
     pub async fn run(
-        metrics_mode: SubcommandMineMetricsMode
+        metrics_mode: SubcommandMineMetricsMode,
+        miner_target: Option<MinerTarget>
     ) -> anyhow::Result<()> {
         Bastion::init();
         
-        let instance = SubcommandMine::new(metrics_mode)?;
+        let instance = SubcommandMine::new(metrics_mode, miner_target)?;
         instance.prepare_mineevent_dir()?;
         instance.print_info();
         instance.start_metrics_worker()?;
@@ -38,7 +46,10 @@ impl SubcommandMine {
         instance.start_postmine_worker()?;
         instance.start_miner_workers()?;
         instance.start_analytics_worker()?;
-        instance.start_cronjob_worker()?;
+        if !instance.config.miner_offline() {
+            instance.start_cronjob_worker()?;
+        }
+        instance.start_distributed_coordinator()?;
 
         Bastion::start();
 
@@ -69,16 +80,22 @@ impl SubcommandMine {
     }
 
     fn new(
-        metrics_mode: SubcommandMineMetricsMode
+        metrics_mode: SubcommandMineMetricsMode,
+        miner_target: Option<MinerTarget>
     ) -> anyhow::Result<Self> {
         let config = Config::load();
         config.validate_config_for_task(ValidateConfigTask::OeisMine)?;
         let number_of_workers: usize = config.resolve_number_of_miner_workers();
+        let prior_weights = MutationStats::prior_weights(&config.miner_tuning().mutation_weights);
+        let mutation_scheduler = MutationScheduler::load(&config.mutation_stats_file(), prior_weights);
         Ok(Self {
             metrics_mode: metrics_mode,
             number_of_workers: number_of_workers,
             config: config,
             prevent_flooding: Arc::new(Mutex::new(PreventFlooding::new())),
+            mutation_scheduler: Arc::new(Mutex::new(mutation_scheduler)),
+            analytics_ready: Arc::new(RwLock::new(false)),
+            miner_target,
         })
     }
 
@@ -118,6 +135,25 @@ impl SubcommandMine {
 
         println!("metrics mode: {:?}", self.metrics_mode);
         println!("number of workers: {}", self.number_of_workers);
+        if self.config.miner_offline() {
+            println!("offline mode: mined programs are saved to {:?}", self.config.upload_outbox_dir());
+        }
+        if let Some(address) = self.config.miner_coordinator_listen_address() {
+            println!("head node: listening for worker nodes on {}, number of shards: {}", address, self.config.miner_coordinator_shard_count());
+        }
+        if let Some(address) = self.config.miner_coordinator_address() {
+            println!("worker node: mining for the head node at {}", address);
+        }
+        match &self.miner_target {
+            Some(MinerTarget::OeisIds(oeis_ids)) => {
+                let a_numbers: Vec<String> = oeis_ids.iter().map(|oeis_id| oeis_id.a_number()).collect();
+                println!("targeted mining: {}", a_numbers.join(","));
+            },
+            Some(MinerTarget::TermPattern(terms)) => {
+                println!("targeted mining: sequences containing the terms {}", terms.to_compact_comma_string());
+            },
+            None => {}
+        }
 
         println!("Press CTRL-C to stop the miner.\n\n");
     }
@@ -155,18 +191,24 @@ impl SubcommandMine {
     }
     
     fn start_upload_worker(&self) -> anyhow::Result<()> {
-        let miner_program_upload_endpoint: String = self.config.miner_program_upload_endpoint().clone();
+        let sink: UploadWorkerSink = match self.config.miner_offline() {
+            true => UploadWorkerSink::Outbox { outbox_dir: self.config.upload_outbox_dir() },
+            false => UploadWorkerSink::Endpoint { 
+                upload_endpoint: self.config.miner_program_upload_endpoint().clone(),
+                outbox_dir: self.config.upload_outbox_dir(),
+            },
+        };
         Bastion::supervisor(|supervisor| {
             supervisor.children(|children| {
                 children
                     .with_redundancy(1)
                     .with_distributor(Distributor::named("upload_worker"))
                     .with_exec(move |ctx: BastionContext| {
-                        let miner_program_upload_endpoint_clone = miner_program_upload_endpoint.clone();
+                        let sink_clone: UploadWorkerSink = sink.clone();
                         async move {
                             upload_worker(
                                 ctx,
-                                miner_program_upload_endpoint_clone,
+                                sink_clone,
                             ).await
                         }
                     })
@@ -202,6 +244,7 @@ impl SubcommandMine {
     fn start_miner_workers(&self) -> anyhow::Result<()> {
         let config_original: Config = self.config.clone();
         let prevent_flooding = self.prevent_flooding.clone();
+        let mutation_scheduler = self.mutation_scheduler.clone();
         Bastion::supervisor(|supervisor| {
             supervisor.children(|children| {
                 children
@@ -209,11 +252,13 @@ impl SubcommandMine {
                     .with_distributor(Distributor::named("miner_worker"))
                     .with_exec(move |ctx: BastionContext| {
                         let prevent_flooding_clone = prevent_flooding.clone();
+                        let mutation_scheduler_clone = mutation_scheduler.clone();
                         let config_clone = config_original.clone();
                         async move {
                             miner_worker(
                                 ctx,
                                 prevent_flooding_clone,
+                                mutation_scheduler_clone,
                                 config_clone,
                             ).await
                         }
@@ -227,6 +272,8 @@ impl SubcommandMine {
     fn start_analytics_worker(&self) -> anyhow::Result<()> {
         let config_original: Config = self.config.clone();
         let prevent_flooding = self.prevent_flooding.clone();
+        let analytics_ready = self.analytics_ready.clone();
+        let miner_target: Option<MinerTarget> = self.miner_target.clone();
         Bastion::supervisor(|supervisor| {
             supervisor.children(|children| {
                 children
@@ -235,11 +282,15 @@ impl SubcommandMine {
                     .with_exec(move |ctx: BastionContext| {
                         let config_clone: Config = config_original.clone();
                         let prevent_flooding_clone = prevent_flooding.clone();
+                        let analytics_ready_clone = analytics_ready.clone();
+                        let miner_target_clone: Option<MinerTarget> = miner_target.clone();
                         async move {
                             analytics_worker(
                                 ctx,
                                 config_clone,
                                 prevent_flooding_clone,
+                                analytics_ready_clone,
+                                miner_target_clone,
                             ).await
                         }
                     })
@@ -267,4 +318,36 @@ impl SubcommandMine {
         .map_err(|e| anyhow::anyhow!("couldn't start cronjob_worker. error: {:?}", e))?;
         Ok(())
     }
+    /// When acting as a head node, serve the worker nodes on the local network.
+    fn start_distributed_coordinator(&self) -> anyhow::Result<()> {
+        let address: String = match self.config.miner_coordinator_listen_address() {
+            Some(value) => value,
+            None => {
+                return Ok(());
+            }
+        };
+        let settings = DistributedCoordinatorSettings {
+            analytics_dir: self.config.analytics_oeis_dir(),
+            oeis_stripped_file: self.config.oeis_stripped_file(),
+            mine_event_dir: self.config.mine_event_dir(),
+            shard_count: self.config.miner_coordinator_shard_count(),
+            secret: self.config.miner_coordinator_secret(),
+        };
+        let mut coordinator = DistributedCoordinator::new(
+            settings, 
+            self.prevent_flooding.clone(), 
+            self.analytics_ready.clone(),
+        );
+        coordinator.set_candidate_callback(|high_priority: bool| {
+            let tell_result = Distributor::named("coordinator_worker")
+                .tell_everyone(CoordinatorWorkerMessage::RemoteCandidateProgram { high_priority });
+            if let Err(error) = tell_result {
+                error!("Unable to send RemoteCandidateProgram to coordinator_worker. error: {:?}", error);
+            }
+        });
+        let listener = TcpListener::bind(&address)
+            .with_context(|| format!("Unable to listen for worker nodes on: {:?}", address))?;
+        coordinator.spawn(listener);
+        Ok(())
+    }
 }
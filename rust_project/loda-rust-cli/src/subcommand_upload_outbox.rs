@@ -0,0 +1,64 @@
+//! The `loda-rust upload-outbox` subcommand, uploads the programs that were mined offline.
+use crate::config::Config;
+use crate::mine::{upload_program, UploadOutbox, UploadOutboxItem};
+use std::time::Duration;
+
+pub struct SubcommandUploadOutbox;
+
+impl SubcommandUploadOutbox {
+    const MAX_ATTEMPTS: u32 = 5;
+    const INITIAL_RETRY_DELAY_MILLIS: u64 = 1000;
+
+    /// Upload all the pending programs in the `~/.loda-rust/upload-outbox` dir.
+    ///
+    /// This includes the programs that the miner has given up on, after too many failed attempts.
+    ///
+    /// A program that fails to upload, stays in the outbox, so it's retried next time the command is run.
+    pub async fn run() -> anyhow::Result<()> {
+        let config = Config::load();
+        let upload_endpoint: String = config.miner_program_upload_endpoint().clone();
+        let mut outbox = UploadOutbox::load(&config.upload_outbox_dir())?;
+        let items: Vec<UploadOutboxItem> = outbox.pending()?;
+        println!("Number of programs to upload: {}", items.len());
+        let client = reqwest::Client::new();
+        let mut number_of_failures: usize = 0;
+        for item in &items {
+            match Self::upload_with_retry(&client, &upload_endpoint, item).await {
+                Ok(()) => {
+                    println!("Uploaded {}", item.oeis_id);
+                    outbox.mark_uploaded(&item.key)?;
+                },
+                Err(error) => {
+                    error!("Unable to upload {}. error: {:?}", item.oeis_id, error);
+                    number_of_failures += 1;
+                }
+            }
+        }
+        if number_of_failures > 0 {
+            return Err(anyhow::anyhow!("Failed to upload {} of {} programs. Run the command again to retry.", number_of_failures, items.len()));
+        }
+        println!("Done. Uploaded {} programs.", items.len());
+        Ok(())
+    }
+
+    /// Retry with exponential backoff.
+    async fn upload_with_retry(client: &reqwest::Client, upload_endpoint: &str, item: &UploadOutboxItem) -> anyhow::Result<()> {
+        let mut delay = Duration::from_millis(Self::INITIAL_RETRY_DELAY_MILLIS);
+        let mut attempt: u32 = 1;
+        loop {
+            let error: anyhow::Error = match upload_program(client, upload_endpoint, &item.key, &item.upload_content).await {
+                Ok(()) => {
+                    return Ok(());
+                },
+                Err(error) => error
+            };
+            if attempt >= Self::MAX_ATTEMPTS {
+                return Err(error);
+            }
+            debug!("Attempt {} of uploading {} failed, retrying in {:?}. error: {:?}", attempt, item.oeis_id, delay, error);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
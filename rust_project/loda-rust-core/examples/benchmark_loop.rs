@@ -0,0 +1,81 @@
+//! Measure how fast programs with loops are evaluated.
+//!
+//! Run with: `cargo run --release --example benchmark_loop`
+use loda_rust_core::control::{DependencyManager, DependencyManagerFileSystemMode};
+use loda_rust_core::execute::{ExecutionEngine, NodeLoopLimit, NodeRegisterLimit, ProgramCache, ProgramId, ProgramRunner, RegisterValue, RunMode};
+use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Fibonacci numbers, a loop that touches few registers.
+const PROGRAM_FEW_REGISTERS: &str = r#"
+mov $3,1
+lpb $0
+  sub $0,1
+  mov $2,$1
+  add $1,$3
+  mov $3,$2
+lpe
+mov $0,$1
+"#;
+
+/// Partitions of n, a nested loop that touches many registers via indirect addressing.
+const PROGRAM_MANY_REGISTERS: &str = r#"
+mov $1,1
+mov $2,$0
+add $2,1
+lpb $2
+  sub $2,1
+  mov $3,$0
+  sub $3,$2
+  mov $4,$3
+  add $4,10
+  mov $$4,1
+  mov $5,$0
+  sub $5,$2
+  lpb $5
+    sub $5,1
+    mov $6,$3
+    sub $6,$5
+    add $6,10
+    mov $7,$5
+    add $7,10
+    add $$6,$$7
+  lpe
+lpe
+mov $0,$4
+add $0,0
+"#;
+
+fn measure(name: &str, source: &str, terms: i64, execution_engine: ExecutionEngine) {
+    let mut dm = DependencyManager::new(
+        DependencyManagerFileSystemMode::Virtual,
+        PathBuf::from("non-existing-dir"),
+        UnofficialFunctionRegistry::new(),
+    );
+    dm.set_execution_engine(execution_engine);
+    let runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, source).expect("runner");
+    let mut cache = ProgramCache::new();
+    let mut step_count: u64 = 0;
+    let start_time = Instant::now();
+    for index in 0..terms {
+        runner.run(
+            RegisterValue::from_i64(index),
+            RunMode::Silent,
+            &mut step_count,
+            u64::MAX,
+            NodeRegisterLimit::Unlimited,
+            NodeLoopLimit::Unlimited,
+            &mut cache,
+        ).expect("output");
+    }
+    let elapsed = start_time.elapsed();
+    println!("{:16} {:10} terms: {:5} steps: {:10} elapsed: {:?}", name, format!("{:?}", execution_engine), terms, step_count, elapsed);
+}
+
+fn main() {
+    for execution_engine in [ExecutionEngine::Tree, ExecutionEngine::Bytecode] {
+        measure("few registers", PROGRAM_FEW_REGISTERS, 2000, execution_engine);
+        measure("many registers", PROGRAM_MANY_REGISTERS, 300, execution_engine);
+    }
+}
@@ -3,10 +3,10 @@ use std::fs;
 use std::path::{Path,PathBuf};
 use std::collections::HashSet;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use crate::execute::node_calc::NodeCalcSemanticMode;
 use crate::parser::{ParsedProgram, ParseProgramError, CreateProgram};
-use crate::execute::{Program, ProgramId, ProgramRunner, ProgramRunnerManager};
+use crate::execute::{ExecutionEngine, Program, ProgramId, ProgramRunner, ProgramRunnerManager};
 use crate::execute::compiletime_error::*;
 use crate::unofficial_function::UnofficialFunctionRegistry;
 use super::ExecuteProfile;
@@ -92,6 +92,7 @@ pub struct DependencyManager {
     file_system_mode: DependencyManagerFileSystemMode,
     loda_programs_oeis_dir: PathBuf,
     execute_profile: ExecuteProfile,
+    execution_engine: ExecutionEngine,
     program_run_manager: ProgramRunnerManager,
     programids_currently_loading: HashSet<u64>,
     programid_dependencies: Vec<u64>,
@@ -107,6 +108,7 @@ impl DependencyManager {
             file_system_mode: file_system_mode,
             loda_programs_oeis_dir: loda_programs_oeis_dir,
             execute_profile: ExecuteProfile::Unlimited,
+            execution_engine: ExecutionEngine::Tree,
             program_run_manager: ProgramRunnerManager::new(),
             programids_currently_loading: HashSet::new(),
             programid_dependencies: vec!(),
@@ -121,6 +123,11 @@ impl DependencyManager {
         self.execute_profile = execute_profile;
     }
 
+    /// The engine used by the `ProgramRunner` instances created by this dependency manager.
+    pub fn set_execution_engine(&mut self, execution_engine: ExecutionEngine) {
+        self.execution_engine = execution_engine;
+    }
+
     pub fn reset(&mut self) {
         self.programid_dependencies.clear();
         self.programids_currently_loading.clear();
@@ -138,10 +145,10 @@ impl DependencyManager {
     }
 
     pub fn load(&mut self, program_id: u64) ->
-        Result<Rc::<ProgramRunner>, DependencyManagerError> 
+        Result<Arc::<ProgramRunner>, DependencyManagerError> 
     {
         self.load_inner(program_id)?;
-        let runner: Rc::<ProgramRunner> = match self.program_run_manager.get(program_id) {
+        let runner: Arc::<ProgramRunner> = match self.program_run_manager.get(program_id) {
             Some(value) => value,
             None => {
                 return Err(DependencyManagerError::LookupProgramId);
@@ -235,13 +242,38 @@ impl DependencyManager {
     
         self.load_dependencies(&mut program, &program_id)?;
 
-        let runner = ProgramRunner::new(
+        let mut runner = ProgramRunner::new(
             program_id,
             program
         );
+        runner.set_execution_engine(self.execution_engine);
+        if let ProgramId::ProgramOEIS(_) = program_id {
+            // Mining candidates are not cached, so there is no need for a hash.
+            runner.set_content_hash(self.compute_content_hash(parsed_program));
+        }
         Ok(runner)
     }
 
+    /// Hash of the program and all the programs that it depends on.
+    /// 
+    /// The hash is computed from the canonical form of the program, so comments and formatting doesn't affect it.
+    /// When a dependency changes, then the hash of all the programs that depend on it also changes.
+    fn compute_content_hash(&self, parsed_program: &ParsedProgram) -> u64 {
+        let mut hash: u64 = fnv1a_hash(FNV_OFFSET_BASIS, parsed_program.to_string().as_bytes());
+        for program_id in parsed_program.direct_dependencies() {
+            let dependency_hash: u64 = self.content_hash(program_id).unwrap_or(0);
+            hash = fnv1a_hash(hash, &dependency_hash.to_le_bytes());
+        }
+        hash
+    }
+
+    /// The content hash of a loaded program, including its dependencies.
+    /// 
+    /// Returns `None` when the program hasn't been loaded.
+    pub fn content_hash(&self, program_id: u64) -> Option<u64> {
+        self.program_run_manager.get(program_id)?.content_hash()
+    }
+
     fn load_dependencies(&mut self, program: &mut Program, program_id: &ProgramId) -> Result<(), DependencyManagerError> {
         let mut dependent_program_id_vec: Vec<u64> = vec!();
         program.accumulate_call_dependencies(&mut dependent_program_id_vec);
@@ -295,6 +327,20 @@ impl DependencyManager {
     }
 }
 
+pub const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// The 64bit FNV-1a hash, that stays the same across runs and across platforms.
+///
+/// Pass `FNV_OFFSET_BASIS` as the `initial_hash`, or the hash of the previous bytes to hash several byte slices.
+pub fn fnv1a_hash(initial_hash: u64, bytes: &[u8]) -> u64 {
+    let mut hash: u64 = initial_hash;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
 impl fmt::Debug for DependencyManager {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "DependencyManager")
@@ -304,7 +350,7 @@ impl fmt::Debug for DependencyManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::rc::Rc;
+    use crate::execute::{NodeLoopLimit, NodeRegisterLimit, PersistentProgramCache, ProgramCache, RegisterValue, RunMode};
 
     #[test]
     fn test_10000_parse_string() {
@@ -356,21 +402,21 @@ mod tests {
     #[test]
     fn test_10101_load_simple1() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/load_simple1");
-        let runner: Rc::<ProgramRunner> = dm.load(79).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(79).unwrap();
         assert_eq!(runner.inspect(10), "1,2,4,8,16,32,64,128,256,512");
     }
 
     #[test]
     fn test_10102_load_simple2() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/load_simple2");
-        let runner: Rc::<ProgramRunner> = dm.load(1).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(1).unwrap();
         assert_eq!(runner.inspect(10), "1,2,1,2,1,2,1,2,1,2");
     }
 
     #[test]
     fn test_10103_load_simple3() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/load_simple3");
-        let runner: Rc::<ProgramRunner> = dm.load(120).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(120).unwrap();
         assert_eq!(runner.inspect(10), "0,1,1,2,1,2,2,3,1,2");
     }
 
@@ -430,161 +476,312 @@ mod tests {
     #[test]
     fn test_20001_instruction_seq_with_negative_parameter1() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/instruction_seq_with_negative_parameter1");
-        let runner: Rc::<ProgramRunner> = dm.load(666).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(666).unwrap();
         assert_eq!(runner.inspect(10), "BOOM");
     }
 
     #[test]
     fn test_20002_instruction_seq_with_negative_parameter2() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/instruction_seq_with_negative_parameter2");
-        let runner: Rc::<ProgramRunner> = dm.load(666).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(666).unwrap();
         assert_eq!(runner.inspect(10), "BOOM");
     }
 
     #[test]
     fn test_20003_instruction_seq_with_parametertype_indirect() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/instruction_seq_with_parametertype_indirect");
-        let runner: Rc::<ProgramRunner> = dm.load(1).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(1).unwrap();
         assert_eq!(runner.inspect(6), "1,2,3,4,5,6");
     }
 
     #[test]
     fn test_50000_parametertype_indirect1() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect1");
-        let runner: Rc::<ProgramRunner> = dm.load(15736).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(15736).unwrap();
         assert_eq!(runner.inspect(12), "1,1,1,1,1,1,1,1,1,1,1,0");
     }
 
     #[test]
     fn test_50001_parametertype_indirect2() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect2");
-        let runner: Rc::<ProgramRunner> = dm.load(25238).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(25238).unwrap();
         assert_eq!(runner.inspect(10), "3,1,3,10,36,137,543,2219,9285,39587");
     }
 
     #[test]
     fn test_50002_parametertype_indirect3() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect3");
-        let runner: Rc::<ProgramRunner> = dm.load(159631).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(159631).unwrap();
         assert_eq!(runner.inspect(10), "1,1,1,2,1,1,1,2,2,1");
     }
 
     #[test]
     fn test_50003_parametertype_indirect4() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect4");
-        let runner: Rc::<ProgramRunner> = dm.load(41).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(41).unwrap();
         assert_eq!(runner.inspect(12), "1,1,2,3,5,7,11,15,22,30,42,56");
     }
 
     #[test]
     fn test_50004_parametertype_indirect5() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect5");
-        let runner: Rc::<ProgramRunner> = dm.load(355497).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(355497).unwrap();
         assert_eq!(runner.inspect(12), "0,4,10,11,12,13,14,15,16,17,18,19");
     }
 
     #[test]
     fn test_50005_parametertype_indirect6() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect6");
-        let runner: Rc::<ProgramRunner> = dm.load(344348).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(344348).unwrap();
         assert_eq!(runner.inspect(12), "0,0,0,0,3,2,1,0,5,4,1,9");
     }
 
     #[test]
     fn test_50006_parametertype_indirect7() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/parametertype_indirect7");
-        let runner: Rc::<ProgramRunner> = dm.load(103627).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(103627).unwrap();
         assert_eq!(runner.inspect(13), "0,1,0,1,1,1,2,1,2,3,1,3,4");
     }
 
     #[test]
     fn test_60000_instruction_lpb_with_parametertype_indirect1() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/instruction_lpb_with_parametertype_indirect1");
-        let runner: Rc::<ProgramRunner> = dm.load(1).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(1).unwrap();
         assert_eq!(runner.inspect(10), "5,5,5,5,5,5,5,5,5,5");
     }
 
     #[test]
     fn test_60001_instruction_lpb_with_parametertype_indirect2() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/instruction_lpb_with_parametertype_indirect2");
-        let runner: Rc::<ProgramRunner> = dm.load(1).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(1).unwrap();
         assert_eq!(runner.inspect(10), "5,5,5,5,5,5,5,5,5,5");
     }
     
     #[test]
     fn test_60002_instruction_lpb_with_range_direct() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/instruction_lpb_with_range_direct");
-        let runner: Rc::<ProgramRunner> = dm.load(1).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(1).unwrap();
         assert_eq!(runner.inspect(10), "5,5,5,5,5,5,5,5,5,5");
     }
 
     #[test]
     fn test_70000_offset_positive() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/offset");
-        let runner: Rc::<ProgramRunner> = dm.load(247).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(247).unwrap();
         assert_eq!(runner.inspect(10), "0,3,10,25,56,119,246,501,1012,2035");
     }
 
     #[test]
     fn test_70001_offset_positive() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/offset");
-        let runner: Rc::<ProgramRunner> = dm.load(183634).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(183634).unwrap();
         assert_eq!(runner.inspect(6), "44,136,452,1576,5684,21016");
     }
 
     #[test]
     fn test_70002_offset_positive() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/offset");
-        let runner: Rc::<ProgramRunner> = dm.load(203).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(203).unwrap();
         assert_eq!(runner.inspect(6), "1,3,4,7,6,12");
     }
 
     #[test]
     fn test_70003_offset_depends_on_another_program_with_offset() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/offset");
-        let runner: Rc::<ProgramRunner> = dm.load(223069).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(223069).unwrap();
         assert_eq!(runner.inspect(5), "16,150,1080,6627,36552");
     }
 
     #[test]
     fn test_70004_offset_depends_on_another_program_with_offset() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/offset");
-        let runner: Rc::<ProgramRunner> = dm.load(9194).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(9194).unwrap();
         assert_eq!(runner.inspect(10), "1,1,1,1,1,6,1,1,1,2");
     }
 
     #[test]
     fn test_80000_instruction_rol() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/memory_operations");
-        let runner: Rc::<ProgramRunner> = dm.load(78).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(78).unwrap();
         assert_eq!(runner.inspect(10), "0,0,0,1,1,2,4,8,15,29");
     }
 
     #[test]
     fn test_80001_instruction_rol() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/memory_operations");
-        let runner: Rc::<ProgramRunner> = dm.load(10873).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(10873).unwrap();
         assert_eq!(runner.inspect(10), "0,1,2,3,0,1,2,3,0,1");
     }
 
     #[test]
     fn test_80002_instruction_ror() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/memory_operations");
-        let runner: Rc::<ProgramRunner> = dm.load(803).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(803).unwrap();
         assert_eq!(runner.inspect(10), "0,0,8,4,8,16,24,44,80,144");
     }
 
     #[test]
     fn test_80003_instruction_fil() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/memory_operations");
-        let runner: Rc::<ProgramRunner> = dm.load(7395).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(7395).unwrap();
         assert_eq!(runner.inspect(10), "2,2,2,2,2,2,2,2,2,2");
     }
 
     #[test]
     fn test_80004_instruction_clr() {
         let mut dm: DependencyManager = dependency_manager_mock("tests/memory_operations");
-        let runner: Rc::<ProgramRunner> = dm.load(355989).unwrap();
+        let runner: Arc::<ProgramRunner> = dm.load(355989).unwrap();
         assert_eq!(runner.inspect(10), "1,3,6,30,60,420,840,7560,15120,166320");
     }
+
+    #[test]
+    fn test_90000_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<DependencyManager>();
+        assert_send_sync::<ProgramRunner>();
+        assert_send_sync::<Arc::<ProgramRunner>>();
+    }
+
+    #[test]
+    fn test_90001_evaluate_from_many_threads() {
+        // Arrange
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            UnofficialFunctionRegistry::new(),
+        );
+        dm.virtual_filesystem_insert_file(45, "mov $3,1\nlpb $0\n  sub $0,1\n  mov $2,$1\n  add $1,$3\n  mov $3,$2\nlpe\nmov $0,$1".to_string());
+        dm.virtual_filesystem_insert_file(1, "seq $0,45\nmul $0,2".to_string());
+        let runner: Arc::<ProgramRunner> = dm.load(1).unwrap();
+
+        // Act
+        let results: Vec<String> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| {
+                let runner: Arc::<ProgramRunner> = Arc::clone(&runner);
+                scope.spawn(move || {
+                    // Each thread has its own cache
+                    let mut cache = ProgramCache::new();
+                    let mut terms: Vec<String> = vec!();
+                    for index in 0..10 {
+                        let mut step_count: u64 = 0;
+                        let value: RegisterValue = runner.run(
+                            RegisterValue::from_i64(index),
+                            RunMode::Silent,
+                            &mut step_count,
+                            1000,
+                            NodeRegisterLimit::Unlimited,
+                            NodeLoopLimit::Unlimited,
+                            &mut cache,
+                        ).expect("value");
+                        terms.push(value.to_string());
+                    }
+                    terms.join(",")
+                })
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().expect("thread")).collect()
+        });
+
+        // Assert
+        for result in results {
+            assert_eq!(result, "0,2,2,4,6,10,16,26,42,68");
+        }
+    }
+
+    #[test]
+    fn test_90002_persistent_cache() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let cache_dir = PathBuf::from(&tempdir.path()).join("test_90002_persistent_cache");
+        let create_dm = |program45: &str| {
+            let mut dm = DependencyManager::new(
+                DependencyManagerFileSystemMode::Virtual,
+                PathBuf::from("non-existing-dir"),
+                UnofficialFunctionRegistry::new(),
+            );
+            dm.virtual_filesystem_insert_file(45, program45.to_string());
+            dm.virtual_filesystem_insert_file(1, "seq $0,45\nmul $0,2".to_string());
+            dm
+        };
+        let create_cache = || {
+            let mut cache = ProgramCache::new();
+            cache.set_persistent_cache(PersistentProgramCache::new(&cache_dir).expect("persistent cache"));
+            cache
+        };
+        let program45 = "mov $3,1\nlpb $0\n  sub $0,1\n  mov $2,$1\n  add $1,$3\n  mov $3,$2\nlpe\nmov $0,$1";
+        {
+            let runner: Arc::<ProgramRunner> = create_dm(program45).load(1).unwrap();
+            let mut cache = create_cache();
+            assert_eq!(runner.inspect_advanced(10, &mut cache), "0,2,2,4,6,10,16,26,42,68");
+            assert_eq!(cache.metric_hit(), 0);
+        }
+
+        // Act
+        let runner: Arc::<ProgramRunner> = create_dm(program45).load(1).unwrap();
+        let mut cache = create_cache();
+        let actual: String = runner.inspect_advanced(10, &mut cache);
+
+        // Assert
+        assert_eq!(actual, "0,2,2,4,6,10,16,26,42,68");
+        assert_eq!(cache.metric_hit(), 10);
+        assert_eq!(cache.metric_miss_for_program_oeis(), 0);
+
+        // When the program changes, then the persisted terms are discarded.
+        let runner: Arc::<ProgramRunner> = create_dm("mov $0,7").load(45).unwrap();
+        let mut cache = create_cache();
+        assert_eq!(runner.inspect_advanced(3, &mut cache), "7,7,7");
+        assert_eq!(cache.metric_hit(), 0);
+    }
+
+    #[test]
+    fn test_90003_content_hash() {
+        let content_hashes = |program45: &str| -> (u64, u64) {
+            let mut dm = DependencyManager::new(
+                DependencyManagerFileSystemMode::Virtual,
+                PathBuf::from("non-existing-dir"),
+                UnofficialFunctionRegistry::new(),
+            );
+            dm.virtual_filesystem_insert_file(45, program45.to_string());
+            dm.virtual_filesystem_insert_file(1, "seq $0,45\nmul $0,2".to_string());
+            assert_eq!(dm.content_hash(1), None, "not loaded yet");
+            dm.load(1).unwrap();
+            (dm.content_hash(1).expect("hash"), dm.content_hash(45).expect("hash"))
+        };
+        let (hash1_a, hash45_a) = content_hashes("mov $1,1\nlpb $0\n  sub $0,1\n  mul $1,2\nlpe\nmov $0,$1");
+
+        // Comments and formatting doesn't affect the hash
+        let (hash1_b, hash45_b) = content_hashes("; powers of 2\nmov $1,1\nlpb $0\n    sub $0,1 ; decrement\n    mul $1,2\nlpe\nmov $0,$1\n");
+        assert_eq!(hash1_a, hash1_b);
+        assert_eq!(hash45_a, hash45_b);
+
+        // When a dependency changes, then the programs that depend on it also changes
+        let (hash1_c, hash45_c) = content_hashes("mov $1,1\nlpb $0\n  sub $0,1\n  mul $1,3\nlpe\nmov $0,$1");
+        assert_ne!(hash1_a, hash1_c);
+        assert_ne!(hash45_a, hash45_c);
+        assert_ne!(hash1_a, hash45_a);
+    }
+
+    #[test]
+    fn test_90004_changed_dependency_invalidates_cache() {
+        // Arrange
+        let create_runner = |program45: &str| -> Arc::<ProgramRunner> {
+            let mut dm = DependencyManager::new(
+                DependencyManagerFileSystemMode::Virtual,
+                PathBuf::from("non-existing-dir"),
+                UnofficialFunctionRegistry::new(),
+            );
+            dm.virtual_filesystem_insert_file(45, program45.to_string());
+            dm.virtual_filesystem_insert_file(1, "seq $0,45\nmul $0,2".to_string());
+            dm.load(1).unwrap()
+        };
+        let mut cache = ProgramCache::new();
+        let runner: Arc::<ProgramRunner> = create_runner("mov $0,5");
+        assert_eq!(runner.inspect_advanced(3, &mut cache), "10,10,10");
+
+        // Act
+        let runner: Arc::<ProgramRunner> = create_runner("mov $0,7");
+        let actual: String = runner.inspect_advanced(3, &mut cache);
+
+        // Assert
+        assert_eq!(actual, "14,14,14", "the same cache must not return outdated terms");
+    }
 }
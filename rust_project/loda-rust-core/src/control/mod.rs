@@ -1,6 +1,8 @@
-//! Loading of programs, manage dependencies between programs, caching of programs.
+//! Loading of programs, manage dependencies between programs, caching of programs, minimizing programs.
 mod dependency_manager;
 mod execute_profile;
+mod program_minimizer;
 
-pub use dependency_manager::{DependencyManager, DependencyManagerError, DependencyManagerFileSystemMode};
+pub use dependency_manager::{DependencyManager, DependencyManagerError, DependencyManagerFileSystemMode, fnv1a_hash, FNV_OFFSET_BASIS};
 pub use execute_profile::ExecuteProfile;
+pub use program_minimizer::ProgramMinimizer;
@@ -0,0 +1,294 @@
+use super::DependencyManager;
+use crate::execute::{NodeLoopLimit, NodeRegisterLimit, ProgramCache, ProgramId, ProgramRunner, RegisterValue, RunMode};
+use crate::parser::{Instruction, InstructionId, InstructionParameter, ParameterType, ParsedProgram};
+use num_bigint::BigInt;
+use std::time::{Duration, Instant};
+
+/// Remove and simplify instructions, while preserving the first terms of the program.
+///
+/// A candidate is only accepted if it computes exactly the same terms as the original program,
+/// using no more steps than the original program. So the result is never slower than the original program.
+///
+/// The minimizer stops when the time limit is exceeded, and returns the smallest program found so far.
+///
+/// The following is attempted repeatedly, until no further improvements can be made:
+/// - Remove an entire loop, the `lpb`, the body and the `lpe`.
+/// - Remove a single instruction.
+/// - Remove the `lpb` and `lpe`, and keep the loop body.
+/// - Merge adjacent `add`, `sub` or `mul` instructions with constants, e.g. `add $1,2` + `add $1,3` into `add $1,5`.
+pub struct ProgramMinimizer {
+    term_count: usize,
+    step_count_limit: u64,
+    time_limit: Duration,
+}
+
+impl ProgramMinimizer {
+    pub const DEFAULT_TERM_COUNT: usize = 40;
+    pub const DEFAULT_STEP_COUNT_LIMIT: u64 = 10_000_000;
+    pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+    pub fn new() -> Self {
+        Self {
+            term_count: Self::DEFAULT_TERM_COUNT,
+            step_count_limit: Self::DEFAULT_STEP_COUNT_LIMIT,
+            time_limit: Self::DEFAULT_TIME_LIMIT,
+        }
+    }
+
+    /// The number of terms that must be preserved.
+    pub fn set_term_count(&mut self, term_count: usize) {
+        self.term_count = term_count;
+    }
+
+    /// The max number of steps for computing all the terms of a program.
+    pub fn set_step_count_limit(&mut self, step_count_limit: u64) {
+        self.step_count_limit = step_count_limit;
+    }
+
+    /// The max time spent on minimizing a single program.
+    pub fn set_time_limit(&mut self, time_limit: Duration) {
+        self.time_limit = time_limit;
+    }
+
+    /// Returns the minimized program.
+    ///
+    /// Returns an error if the original program cannot compute the terms within the step budget.
+    pub fn minimize(&self, dm: &mut DependencyManager, parsed_program: &ParsedProgram) -> anyhow::Result<ParsedProgram> {
+        let start_time = Instant::now();
+        let mut cache = ProgramCache::new();
+        let (expected_terms, expected_step_count) = match self.compute_terms(dm, &mut cache, parsed_program, self.step_count_limit) {
+            Some(value) => value,
+            None => {
+                return Err(anyhow::anyhow!("ProgramMinimizer unable to compute {} terms of the original program within {} steps", self.term_count, self.step_count_limit));
+            }
+        };
+
+        // A candidate that uses more steps than the original program is rejected.
+        let candidate_step_count_limit: u64 = expected_step_count.saturating_add(1);
+        let mut current: ParsedProgram = parsed_program.clone();
+        loop {
+            let mut changed = false;
+            for candidate in Self::candidates(&current) {
+                if start_time.elapsed() > self.time_limit {
+                    debug!("ProgramMinimizer exceeded the time limit of {:?}", self.time_limit);
+                    return Ok(current);
+                }
+                let result = self.compute_terms(dm, &mut cache, &candidate, candidate_step_count_limit);
+                let (terms, step_count) = match result {
+                    Some(value) => value,
+                    None => continue
+                };
+                if terms == expected_terms && step_count <= expected_step_count {
+                    current = candidate;
+                    changed = true;
+                    break;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        Ok(current)
+    }
+
+    /// Returns the terms and the total number of steps for computing them.
+    /// 
+    /// Returns `None` if the program cannot be created or fails to compute all the terms.
+    fn compute_terms(&self, dm: &mut DependencyManager, cache: &mut ProgramCache, parsed_program: &ParsedProgram, step_count_limit: u64) -> Option<(Vec<BigInt>, u64)> {
+        let runner: ProgramRunner = dm.parse_stage2(ProgramId::ProgramWithoutId, parsed_program).ok()?;
+        let mut step_count: u64 = 0;
+        let mut terms: Vec<BigInt> = Vec::with_capacity(self.term_count);
+        for index in 0..self.term_count {
+            let output: RegisterValue = runner.run(
+                RegisterValue::from_i64(index as i64),
+                RunMode::Silent,
+                &mut step_count,
+                step_count_limit,
+                NodeRegisterLimit::Unlimited,
+                NodeLoopLimit::Unlimited,
+                cache,
+            ).ok()?;
+            terms.push(output.0);
+        }
+        Some((terms, step_count))
+    }
+
+    /// Variants of the program that are smaller or simpler, the most promising first.
+    fn candidates(parsed_program: &ParsedProgram) -> Vec<ParsedProgram> {
+        let instruction_vec: &Vec<Instruction> = &parsed_program.instruction_vec;
+        let mut result: Vec<ParsedProgram> = vec!();
+        let with_instructions = |instruction_vec: Vec<Instruction>| -> ParsedProgram {
+            ParsedProgram {
+                optional_offset: parsed_program.optional_offset,
+                instruction_vec,
+            }
+        };
+
+        // Remove entire loops
+        for (begin_index, end_index) in Self::loop_ranges(instruction_vec) {
+            let mut instructions: Vec<Instruction> = instruction_vec.clone();
+            instructions.drain(begin_index..=end_index);
+            result.push(with_instructions(instructions));
+        }
+
+        // Remove single instructions, starting from the bottom
+        for (index, instruction) in instruction_vec.iter().enumerate().rev() {
+            match instruction.instruction_id {
+                InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract | InstructionId::LoopEnd => continue,
+                _ => {}
+            }
+            let mut instructions: Vec<Instruction> = instruction_vec.clone();
+            instructions.remove(index);
+            result.push(with_instructions(instructions));
+        }
+
+        // Remove loops, but keep the loop body
+        for (begin_index, end_index) in Self::loop_ranges(instruction_vec) {
+            let mut instructions: Vec<Instruction> = instruction_vec.clone();
+            instructions.remove(end_index);
+            instructions.remove(begin_index);
+            result.push(with_instructions(instructions));
+        }
+
+        // Merge adjacent instructions with constants
+        for index in 1..instruction_vec.len() {
+            if let Some(instruction) = Self::merge_constants(&instruction_vec[index - 1], &instruction_vec[index]) {
+                let mut instructions: Vec<Instruction> = instruction_vec.clone();
+                instructions[index - 1] = instruction;
+                instructions.remove(index);
+                result.push(with_instructions(instructions));
+            }
+        }
+        result
+    }
+
+    /// The indexes of the matching `lpb` and `lpe` instructions.
+    fn loop_ranges(instruction_vec: &[Instruction]) -> Vec<(usize, usize)> {
+        let mut result: Vec<(usize, usize)> = vec!();
+        let mut stack: Vec<usize> = vec!();
+        for (index, instruction) in instruction_vec.iter().enumerate() {
+            match instruction.instruction_id {
+                InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract => {
+                    stack.push(index);
+                },
+                InstructionId::LoopEnd => {
+                    if let Some(begin_index) = stack.pop() {
+                        result.push((begin_index, index));
+                    }
+                },
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Combine two instructions such as `add $1,2` and `add $1,3` into `add $1,5`.
+    fn merge_constants(instruction0: &Instruction, instruction1: &Instruction) -> Option<Instruction> {
+        if instruction0.instruction_id != instruction1.instruction_id {
+            return None;
+        }
+        if instruction0.parameter_vec.len() != 2 || instruction1.parameter_vec.len() != 2 {
+            return None;
+        }
+        let target0 = &instruction0.parameter_vec[0];
+        let target1 = &instruction1.parameter_vec[0];
+        if target0.parameter_type != ParameterType::Direct || target0 != target1 {
+            return None;
+        }
+        let source0 = &instruction0.parameter_vec[1];
+        let source1 = &instruction1.parameter_vec[1];
+        let value0: BigInt = source0.constant_value()?;
+        let value1: BigInt = source1.constant_value()?;
+        let value: BigInt = match instruction0.instruction_id {
+            InstructionId::Add | InstructionId::Subtract => value0 + value1,
+            InstructionId::Multiply => value0 * value1,
+            _ => return None
+        };
+        let mut instruction: Instruction = instruction0.clone();
+        instruction.parameter_vec[1] = InstructionParameter::new_constant(value);
+        Some(instruction)
+    }
+}
+
+impl Default for ProgramMinimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::DependencyManagerFileSystemMode;
+    use crate::unofficial_function::UnofficialFunctionRegistry;
+    use std::path::PathBuf;
+
+    fn process(input: &str) -> String {
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            UnofficialFunctionRegistry::new(),
+        );
+        dm.virtual_filesystem_insert_file(45, "mov $1,1\nlpb $0\n  sub $0,1\n  mov $2,$1\n  add $1,$3\n  mov $3,$2\nlpe\nmov $0,$3".to_string());
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(input).expect("parsed program");
+        let mut minimizer = ProgramMinimizer::new();
+        minimizer.set_term_count(20);
+        minimizer.set_step_count_limit(100000);
+        match minimizer.minimize(&mut dm, &parsed_program) {
+            Ok(value) => value.to_string(),
+            Err(_) => "ERROR".to_string()
+        }
+    }
+
+    #[test]
+    fn test_10000_remove_instructions() {
+        assert_eq!(process("mov $1,7\nmul $0,2\nadd $0,0\nmov $2,$1"), "mul $0,2");
+    }
+
+    #[test]
+    fn test_10001_already_minimal() {
+        let program = "mov $1,1\nlpb $0\n  sub $0,1\n  mul $1,2\nlpe\nmov $0,$1";
+        assert_eq!(process(program), "mov $1,1\nlpb $0\nsub $0,1\nmul $1,2\nlpe\nmov $0,$1");
+    }
+
+    #[test]
+    fn test_10002_preserve_offset() {
+        assert_eq!(process("#offset 1\nmov $1,$0\nmov $0,$1"), "#offset 1");
+    }
+
+    #[test]
+    fn test_20000_remove_loop() {
+        let program = "mov $2,$0\nlpb $2\n  sub $2,1\n  add $3,1\nlpe\nmul $0,3";
+        assert_eq!(process(program), "mul $0,3");
+    }
+
+    #[test]
+    fn test_20001_remove_loop_but_keep_body() {
+        let program = "mov $2,1\nlpb $2\n  add $0,5\n  sub $2,1\nlpe";
+        assert_eq!(process(program), "add $0,5");
+    }
+
+    #[test]
+    fn test_30000_merge_constants() {
+        assert_eq!(process("add $0,2\nadd $0,3"), "add $0,5");
+        assert_eq!(process("mul $0,2\nmul $0,3\nsub $0,1\nsub $0,1"), "mul $0,6\nsub $0,2");
+        assert_eq!(process("mul $0,4611686018427387904\nmul $0,4"), "mul $0,18446744073709551616");
+    }
+
+    #[test]
+    fn test_40000_seq() {
+        assert_eq!(process("seq $0,45\nmov $1,$0\nmov $0,$1"), "seq $0,45");
+    }
+
+    #[test]
+    fn test_40001_reject_slower_program() {
+        // Removing the `min $1,1` yields the same terms, but with more loop cycles.
+        let program = "mov $1,$0\nmin $1,1\nlpb $1\n  mov $2,$1\n  sub $1,1\nlpe\nmov $0,$2";
+        assert_eq!(process(program), "mov $1,$0\nmin $1,1\nmov $2,$1\nmov $0,$2");
+    }
+
+    #[test]
+    fn test_50000_error_exceeding_step_limit() {
+        assert_eq!(process("mov $1,$0\npow $1,5\nlpb $1\n  sub $1,1\nlpe"), "ERROR");
+    }
+}
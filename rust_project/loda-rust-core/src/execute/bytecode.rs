@@ -0,0 +1,474 @@
+use super::{EvalError, NodeLoopLimit, PerformCheckValue, Program, ProgramCache, ProgramRunner, ProgramState, RegisterCell, RegisterValue, LOOP_RANGE_MAX_BITS};
+use super::node_calc::{NodeCalc, NodeCalcSemanticMode};
+use crate::parser::{InstructionId, InstructionParameter, ParameterType};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BytecodeCompileError {
+    /// The node cannot be represented as bytecode, such as `lpb` with an indirect parameter.
+    UnsupportedNode,
+
+    /// The parameter refers to a register with a negative address.
+    NegativeRegister,
+
+    /// A `seq` node that has not been linked with the program it depends on.
+    MissingLink,
+
+    /// A constant beyond 64bit, these are only supported by the tree engine.
+    BigConstant,
+}
+
+impl fmt::Display for BytecodeCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedNode => write!(f, "The node has no bytecode representation"),
+            Self::NegativeRegister => write!(f, "Register with a negative address"),
+            Self::MissingLink => write!(f, "No link have been established to the program that is being called"),
+            Self::BigConstant => write!(f, "Constant that doesn't fit inside an i64"),
+        }
+    }
+}
+
+/// A parameter where the register address has been resolved at compile time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BytecodeOperand {
+    Constant(i64),
+    Direct(u64),
+    Indirect(u64),
+}
+
+impl BytecodeOperand {
+    pub fn from_parameter(parameter: &InstructionParameter) -> Result<Self, BytecodeCompileError> {
+        if parameter.is_big_constant() {
+            return Err(BytecodeCompileError::BigConstant);
+        }
+        let value: i64 = parameter.parameter_value;
+        match parameter.parameter_type {
+            ParameterType::Constant => {
+                return Ok(Self::Constant(value));
+            },
+            ParameterType::Direct | ParameterType::Indirect => {}
+        }
+        if value < 0 {
+            return Err(BytecodeCompileError::NegativeRegister);
+        }
+        match parameter.parameter_type {
+            ParameterType::Direct => Ok(Self::Direct(value as u64)),
+            _ => Ok(Self::Indirect(value as u64))
+        }
+    }
+}
+
+/// How many registers that a loop compares, when determining if the loop should continue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BytecodeLoopRange {
+    /// The `lpb $0` and `lpb $0,5` instructions.
+    Constant(u64),
+
+    /// The `lpb $0,$1` instruction, where the range length is the smallest value seen in the register.
+    Register(u64),
+}
+
+pub enum BytecodeInstruction {
+    Calc {
+        semantic_mode: NodeCalcSemanticMode,
+        instruction_id: InstructionId,
+        target: BytecodeOperand,
+        source: BytecodeOperand,
+    },
+    Seq {
+        target: BytecodeOperand,
+        program_runner: Arc<ProgramRunner>,
+    },
+    LoopBegin {
+        range: BytecodeLoopRange,
+    },
+    LoopEnd {
+        /// Where to jump when the loop continues with another cycle.
+        loop_begin_index: usize,
+        register_start: u64,
+        range: BytecodeLoopRange,
+    },
+}
+
+impl BytecodeInstruction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Calc { .. } => "calc",
+            Self::Seq { .. } => "seq",
+            Self::LoopBegin { .. } => "lpb",
+            Self::LoopEnd { .. } => "lpe",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BytecodeCompiler {
+    instruction_vec: Vec<BytecodeInstruction>,
+}
+
+impl BytecodeCompiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, instruction: BytecodeInstruction) {
+        self.instruction_vec.push(instruction);
+    }
+
+    /// Append a loop, where the body of the loop is the `program`.
+    pub fn push_loop(&mut self, register_start: u64, range: BytecodeLoopRange, program: &Program) -> Result<(), BytecodeCompileError> {
+        let loop_begin_index: usize = self.instruction_vec.len();
+        self.push(BytecodeInstruction::LoopBegin { range });
+        program.compile(self)?;
+        self.push(BytecodeInstruction::LoopEnd { loop_begin_index, register_start, range });
+        Ok(())
+    }
+}
+
+/// The state of a loop that is currently running.
+struct LoopFrame {
+    cycles: u32,
+    range_length: u64,
+}
+
+/// A `Program` flattened into an array of instructions, where loops are jumps.
+///
+/// Evaluating the bytecode gives the same result and step count as `Program::run`,
+/// without the recursive `Node::eval` calls.
+///
+/// Only used with `RunMode::Silent`, and when there is no `ProgramObserver` attached.
+pub struct BytecodeProgram {
+    instruction_vec: Vec<BytecodeInstruction>,
+}
+
+impl BytecodeProgram {
+    pub fn compile(program: &Program) -> Result<Self, BytecodeCompileError> {
+        let mut compiler = BytecodeCompiler::new();
+        program.compile(&mut compiler)?;
+        let instance = Self {
+            instruction_vec: compiler.instruction_vec,
+        };
+        Ok(instance)
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruction_vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruction_vec.is_empty()
+    }
+
+    pub fn run(&self, state: &mut ProgramState, cache: &mut ProgramCache) -> anyhow::Result<()> {
+        let mut program_counter: usize = 0;
+        match self.run_inner(state, cache, &mut program_counter) {
+            Ok(()) => Ok(()),
+            Err(error) => {
+                let name: &str = self.instruction_vec.get(program_counter).map(|instruction| instruction.name()).unwrap_or("");
+                Err(error.context(format!("BytecodeProgram instruction {} at index {}", name, program_counter)))
+            }
+        }
+    }
+
+    fn run_inner(&self, state: &mut ProgramState, cache: &mut ProgramCache, program_counter: &mut usize) -> anyhow::Result<()> {
+        let limit: NodeLoopLimit = state.node_loop_limit().clone();
+        let mut loop_frame_vec: Vec<LoopFrame> = vec!();
+        let length: usize = self.instruction_vec.len();
+        while *program_counter < length {
+            match &self.instruction_vec[*program_counter] {
+                BytecodeInstruction::Calc { semantic_mode, instruction_id, target, source } => {
+                    Self::eval_calc(state, *semantic_mode, *instruction_id, target, source)?;
+                    state.increment_step_count()?;
+                },
+                BytecodeInstruction::Seq { target, program_runner } => {
+                    Self::eval_seq(state, cache, target, program_runner)?;
+                    state.increment_step_count()?;
+                },
+                BytecodeInstruction::LoopBegin { range } => {
+                    let range_length: u64 = match range {
+                        BytecodeLoopRange::Constant(range_length) => {
+                            if *range_length >= (1 << LOOP_RANGE_MAX_BITS) {
+                                return Err(EvalError::LoopRangeLengthExceededLimit.into());
+                            }
+                            *range_length
+                        },
+                        BytecodeLoopRange::Register(address) => {
+                            Self::range_length_from_register(state, *address)?
+                        }
+                    };
+                    loop_frame_vec.push(LoopFrame { cycles: 0, range_length });
+                    state.checkpoint_begin();
+                },
+                BytecodeInstruction::LoopEnd { loop_begin_index, register_start, range } => {
+                    let frame: &mut LoopFrame = match loop_frame_vec.last_mut() {
+                        Some(value) => value,
+                        None => {
+                            return Err(anyhow::anyhow!("BytecodeProgram lpe without a matching lpb"));
+                        }
+                    };
+                    if let BytecodeLoopRange::Register(address) = range {
+                        let range_length: u64 = Self::range_length_from_register(state, *address)?;
+                        frame.range_length = u64::min(range_length, frame.range_length);
+                    }
+                    let is_less: bool = state.is_less_than_checkpoint_range(
+                        *register_start,
+                        *register_start,
+                        frame.range_length
+                    );
+                    if !is_less {
+                        // When the loop reaches its end, the previous state is restored.
+                        state.checkpoint_rollback();
+                        loop_frame_vec.pop();
+                        state.increment_step_count()?;
+                    } else {
+                        state.checkpoint_commit();
+
+                        // Prevent looping for too long
+                        if let NodeLoopLimit::LimitCount(limit_count) = limit {
+                            frame.cycles += 1;
+                            if frame.cycles > limit_count {
+                                return Err(EvalError::LoopCountExceededLimit.into());
+                            }
+                        }
+                        state.checkpoint_begin();
+                        *program_counter = *loop_begin_index + 1;
+                        continue;
+                    }
+                }
+            }
+            *program_counter += 1;
+        }
+        Ok(())
+    }
+
+    fn eval_calc(state: &mut ProgramState, semantic_mode: NodeCalcSemanticMode, instruction_id: InstructionId, target: &BytecodeOperand, source: &BytecodeOperand) -> Result<(), EvalError> {
+        let address: u64 = Self::address(state, target)?;
+        let target_cell: &RegisterCell = state.get_cell(address);
+        let source_cell: Cow<RegisterCell> = Self::get(state, source)?;
+        if let (RegisterCell::Small(target_value), RegisterCell::Small(source_value)) = (target_cell, source_cell.as_ref()) {
+            if let Some(value) = NodeCalc::calc_small(instruction_id, *target_value, *source_value) {
+                return state.set_u64_cell(address, RegisterCell::Small(value));
+            }
+        }
+        let value: BigInt = NodeCalc::calc(
+            semantic_mode,
+            instruction_id,
+            &target_cell.as_bigint(),
+            &source_cell.as_bigint()
+        )?;
+        state.set_u64_cell(address, RegisterCell::from_bigint(value))
+    }
+
+    fn eval_seq(state: &mut ProgramState, cache: &mut ProgramCache, target: &BytecodeOperand, program_runner: &ProgramRunner) -> anyhow::Result<()> {
+        let address: u64 = Self::address(state, target)?;
+        let input: BigInt = state.get_cell(address).as_bigint().into_owned();
+        if input.is_negative() {
+            return Err(EvalError::EvalSequenceWithNegativeParameter.into());
+        }
+
+        // Abort if the input value is beyond the limit (optional)
+        state.check_value().input(&input)?;
+
+        let mut step_count: u64 = state.step_count();
+        let mut inner_state = ProgramState::new(
+            state.run_mode(),
+            state.step_count_limit(),
+            state.node_register_limit().clone(),
+            state.node_loop_limit().clone(),
+        );
+        inner_state.set_deadline(state.deadline());
+        let run_result = program_runner.run_without_offset_with_state(
+            RegisterValue(input),
+            inner_state,
+            &mut step_count,
+            cache,
+        );
+
+        // Update statistics, no matter if run succeeded or failed
+        state.set_step_count(step_count);
+        let output: RegisterValue = run_result?;
+
+        // Abort if the output value is beyond the limit (optional)
+        state.check_value().output(&output.0)?;
+
+        state.set_u64_cell(address, RegisterCell::from_bigint(output.0))?;
+        Ok(())
+    }
+
+    fn range_length_from_register(state: &ProgramState, address: u64) -> Result<u64, EvalError> {
+        let value: &RegisterCell = state.get_cell(address);
+        if value.is_negative() {
+            // Value is negative. Clamp to 0 length.
+            return Ok(0);
+        }
+        // Same bit length check as `NodeLoopRegister`.
+        match value.to_i64() {
+            Some(value) if ((u64::BITS - (value as u64).leading_zeros()) as u64) < LOOP_RANGE_MAX_BITS => Ok(value as u64),
+            _ => Err(EvalError::LoopRangeLengthExceededLimit)
+        }
+    }
+
+    /// The address of a register operand, following indirect addressing.
+    fn address(state: &ProgramState, operand: &BytecodeOperand) -> Result<u64, EvalError> {
+        match operand {
+            BytecodeOperand::Constant(_) => Err(EvalError::CannotSetValueOfConstant),
+            BytecodeOperand::Direct(address) => Ok(*address),
+            BytecodeOperand::Indirect(address) => {
+                match state.get_cell(*address) {
+                    RegisterCell::Small(value) if *value >= 0 => Ok(*value as u64),
+                    cell => cell.as_bigint().to_u64().ok_or(EvalError::CannotConvertBigIntToAddress)
+                }
+            }
+        }
+    }
+
+    fn get<'a>(state: &'a ProgramState, operand: &BytecodeOperand) -> Result<Cow<'a, RegisterCell>, EvalError> {
+        match operand {
+            BytecodeOperand::Constant(value) => Ok(Cow::Owned(RegisterCell::Small(*value))),
+            _ => {
+                let address: u64 = Self::address(state, operand)?;
+                Ok(Cow::Borrowed(state.get_cell(address)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::{DependencyManager, DependencyManagerFileSystemMode};
+    use crate::execute::{ExecutionEngine, NodeRegisterLimit, ProgramId, RunMode};
+    use crate::unofficial_function::UnofficialFunctionRegistry;
+    use std::path::PathBuf;
+
+    fn dependency_manager() -> DependencyManager {
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            UnofficialFunctionRegistry::new(),
+        );
+        dm.virtual_filesystem_insert_file(45, "mov $3,1\nlpb $0\n  sub $0,1\n  mov $2,$1\n  add $1,$3\n  mov $3,$2\nlpe\nmov $0,$1".to_string());
+        dm
+    }
+
+    /// Evaluate the program with both engines, and verify that the terms and step counts are the same.
+    fn compare_engines(source: &str, count: i64) -> String {
+        let mut dm = dependency_manager();
+        let runner_tree: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, source).expect("runner");
+        let mut runner_bytecode: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, source).expect("runner");
+        runner_bytecode.set_execution_engine(ExecutionEngine::Bytecode);
+        assert_eq!(runner_bytecode.execution_engine(), ExecutionEngine::Bytecode);
+        let mut terms: Vec<String> = vec!();
+        for index in 0..count {
+            let mut results: Vec<String> = vec!();
+            for runner in [&runner_tree, &runner_bytecode] {
+                let mut cache = ProgramCache::new();
+                let mut step_count: u64 = 0;
+                let result = runner.run(
+                    RegisterValue::from_i64(index),
+                    RunMode::Silent,
+                    &mut step_count,
+                    1000,
+                    NodeRegisterLimit::LimitBits(64),
+                    NodeLoopLimit::LimitCount(100),
+                    &mut cache,
+                );
+                match result {
+                    Ok(value) => results.push(format!("{} steps:{}", value, step_count)),
+                    Err(_) => results.push(format!("ERROR steps:{}", step_count))
+                }
+            }
+            assert_eq!(results[0], results[1], "index: {}", index);
+            terms.push(results[0].split(' ').next().unwrap_or("").to_string());
+        }
+        terms.join(",")
+    }
+
+    #[test]
+    fn test_10000_loop_simple() {
+        let source = "mov $1,1\nlpb $0\n  sub $0,1\n  mul $1,3\nlpe\nmov $0,$1";
+        assert_eq!(compare_engines(source, 6), "1,3,9,27,81,243");
+    }
+
+    #[test]
+    fn test_10001_loop_nested() {
+        let source = "lpb $0\n  mov $2,$0\n  lpb $2\n    sub $2,1\n    add $1,1\n  lpe\n  sub $0,1\nlpe\nmov $0,$1";
+        assert_eq!(compare_engines(source, 6), "0,1,3,6,10,15");
+    }
+
+    #[test]
+    fn test_10002_loop_range_register() {
+        let source = "mov $1,2\nlpb $0,$1\n  sub $1,1\n  add $2,$0\n  trn $0,1\nlpe\nmov $0,$2";
+        assert_eq!(compare_engines(source, 6), "0,1,2,3,4,5");
+    }
+
+    #[test]
+    fn test_10003_indirect_and_overflow() {
+        let source = "mov $1,10\nmov $$1,$0\nmov $2,9223372036854775807\nadd $2,$10\nmov $0,$2\ndiv $0,2";
+        assert_eq!(compare_engines(source, 3), "4611686018427387903,4611686018427387904,4611686018427387904");
+    }
+
+    #[test]
+    fn test_10004_seq() {
+        let source = "seq $0,45\nmul $0,2";
+        assert_eq!(compare_engines(source, 8), "0,2,2,4,6,10,16,26");
+    }
+
+    #[test]
+    fn test_10005_errors() {
+        // Division by zero
+        assert_eq!(compare_engines("div $0,0", 2), "ERROR,ERROR");
+        // The loop counter isn't decreasing, so the loop is rolled back
+        assert_eq!(compare_engines("lpb $0\n  add $1,1\nlpe", 2), "0,1");
+        // Loop count exceeded
+        assert_eq!(compare_engines("add $0,1000\nlpb $0\n  sub $0,1\nlpe", 2), "ERROR,ERROR");
+        // Loop range length exceeded
+        assert_eq!(compare_engines("mov $1,200\nlpb $0,$1\n  sub $0,1\nlpe", 2), "ERROR,ERROR");
+        assert_eq!(compare_engines("mov $1,127\nlpb $0,$1\n  sub $0,1\nlpe", 2), "0,0");
+    }
+
+    #[test]
+    fn test_20000_unsupported_node_falls_back() {
+        // Arrange
+        let mut dm = dependency_manager();
+        let mut runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, "lpb $0\n  clr $0,2\nlpe").expect("runner");
+
+        // Act
+        runner.set_execution_engine(ExecutionEngine::Bytecode);
+
+        // Assert
+        assert_eq!(runner.execution_engine(), ExecutionEngine::Tree);
+        assert_eq!(runner.inspect(3), "0,0,0");
+    }
+
+    #[test]
+    fn test_20001_big_constant_falls_back() {
+        // Arrange
+        let mut dm = dependency_manager();
+        let mut runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, "mul $0,123456789012345678901234567890\nsub $0,-1").expect("runner");
+
+        // Act
+        runner.set_execution_engine(ExecutionEngine::Bytecode);
+
+        // Assert
+        assert_eq!(runner.execution_engine(), ExecutionEngine::Tree);
+        assert_eq!(runner.inspect(3), "1,123456789012345678901234567891,246913578024691357802469135781");
+    }
+
+    #[test]
+    fn test_20002_compile_error() {
+        let mut program = Program::new();
+        program.push(super::super::NodeMemoryOp::new(
+            crate::parser::InstructionId::Clear,
+            InstructionParameter::new(ParameterType::Direct, 0),
+            InstructionParameter::new(ParameterType::Constant, 1),
+        ));
+        let result = BytecodeProgram::compile(&program);
+        assert!(matches!(result, Err(BytecodeCompileError::UnsupportedNode)));
+    }
+}
@@ -11,6 +11,7 @@ pub enum CreateInstructionErrorType {
     ParameterMustBeConstant,
     ParameterMustBeDirect,
     ConstantMustBeNonNegative,
+    ConstantIsTooHigh,
     LoopWithConstantRangeIsTooHigh,
     RegisterIndexMustBeNonNegative,
     RegisterIndexTooHigh,
@@ -0,0 +1,359 @@
+use super::{ObserverAction, ObserverEvent, ProgramId, ProgramObserver};
+use num_bigint::BigInt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegisterComparison {
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+}
+
+/// Pause when a register satisfies a comparison, such as `$1 > 100`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakpointCondition {
+    pub register: u64,
+    pub comparison: RegisterComparison,
+    pub value: BigInt,
+}
+
+impl BreakpointCondition {
+    pub fn is_satisfied(&self, register_value: &BigInt) -> bool {
+        match self.comparison {
+            RegisterComparison::Equal => register_value == &self.value,
+            RegisterComparison::NotEqual => register_value != &self.value,
+            RegisterComparison::LessThan => register_value < &self.value,
+            RegisterComparison::GreaterThan => register_value > &self.value,
+        }
+    }
+}
+
+/// Breakpoints applies to the root program only, not to the programs called via `seq`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    /// Pause before the instruction on this line gets evaluated.
+    Line { line_number: usize },
+
+    /// Pause before the instruction on this line gets evaluated, when the condition is satisfied.
+    ConditionalLine { line_number: usize, condition: BreakpointCondition },
+
+    /// Pause before any instruction gets evaluated, when the condition is satisfied.
+    Condition { condition: BreakpointCondition },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebuggerPauseReason {
+    Breakpoint { index: usize },
+    Step,
+}
+
+/// Snapshot of the execution, at the moment the debugger paused.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebuggerPause {
+    pub reason: DebuggerPauseReason,
+    pub program_id: ProgramId,
+    pub call_depth: usize,
+    pub loop_depth: usize,
+    pub node_index: usize,
+    pub line_number: usize,
+    pub instruction: String,
+    pub step_count: u64,
+
+    /// The current values of the watched registers.
+    pub watches: Vec<(u64, BigInt)>,
+}
+
+/// What to do after the debugger has paused.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebuggerCommand {
+    /// Run until the next breakpoint.
+    Continue,
+
+    /// Pause at the next instruction, also when it's inside a program called via `seq`.
+    StepInto,
+
+    /// Pause at the next instruction, skipping over the instructions inside `seq` calls.
+    StepOver,
+
+    /// Stop executing the program.
+    Abort,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DebuggerStepMode {
+    Run,
+    StepInto,
+    StepOver { call_depth: usize },
+}
+
+pub type DebuggerPauseHandler = Box<dyn FnMut(&DebuggerPause) -> DebuggerCommand>;
+
+/// Step-through debugger with breakpoints and register watches.
+///
+/// The pause handler decides how to proceed whenever the debugger pauses.
+/// A command line tool may wait for user input inside the handler.
+/// An interactive tool that cannot block, may record the `DebuggerPause`, abort and rerun the program.
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<u64>,
+    step_mode: DebuggerStepMode,
+    pause_handler: DebuggerPauseHandler,
+    pause_count: usize,
+}
+
+impl Debugger {
+    pub fn new(pause_handler: DebuggerPauseHandler) -> Self {
+        Self {
+            breakpoints: vec!(),
+            watches: vec!(),
+            step_mode: DebuggerStepMode::Run,
+            pause_handler,
+            pause_count: 0,
+        }
+    }
+
+    /// Returns the index of the breakpoint.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_all_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn add_watch(&mut self, register: u64) {
+        if !self.watches.contains(&register) {
+            self.watches.push(register);
+        }
+    }
+
+    /// Pause before the very first instruction.
+    pub fn set_step_into(&mut self) {
+        self.step_mode = DebuggerStepMode::StepInto;
+    }
+
+    pub fn pause_count(&self) -> usize {
+        self.pause_count
+    }
+
+    fn find_breakpoint(&self, event: &ObserverEvent) -> Option<usize> {
+        if event.call_depth > 0 {
+            return None;
+        }
+        for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+            let hit: bool = match breakpoint {
+                Breakpoint::Line { line_number } => {
+                    *line_number == event.line_number
+                },
+                Breakpoint::ConditionalLine { line_number, condition } => {
+                    *line_number == event.line_number &&
+                        condition.is_satisfied(&event.state.get_u64(condition.register))
+                },
+                Breakpoint::Condition { condition } => {
+                    condition.is_satisfied(&event.state.get_u64(condition.register))
+                },
+            };
+            if hit {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    fn pause_reason(&self, event: &ObserverEvent) -> Option<DebuggerPauseReason> {
+        match self.step_mode {
+            DebuggerStepMode::Run => {},
+            DebuggerStepMode::StepInto => {
+                return Some(DebuggerPauseReason::Step);
+            },
+            DebuggerStepMode::StepOver { call_depth } => {
+                if event.call_depth <= call_depth {
+                    return Some(DebuggerPauseReason::Step);
+                }
+            }
+        }
+        self.find_breakpoint(event).map(|index| DebuggerPauseReason::Breakpoint { index })
+    }
+}
+
+impl ProgramObserver for Debugger {
+    fn before_eval(&mut self, event: &ObserverEvent) -> ObserverAction {
+        let reason: DebuggerPauseReason = match self.pause_reason(event) {
+            Some(value) => value,
+            None => return ObserverAction::Continue
+        };
+        let watches: Vec<(u64, BigInt)> = self.watches.iter().map(|register| {
+            (*register, event.state.get_u64(*register).into_owned())
+        }).collect();
+        let pause = DebuggerPause {
+            reason,
+            program_id: event.program_id,
+            call_depth: event.call_depth,
+            loop_depth: event.loop_depth,
+            node_index: event.node_index,
+            line_number: event.line_number,
+            instruction: event.node.formatted_instruction(),
+            step_count: event.state.step_count(),
+            watches,
+        };
+        self.pause_count += 1;
+        let command: DebuggerCommand = (self.pause_handler)(&pause);
+        match command {
+            DebuggerCommand::Continue => {
+                self.step_mode = DebuggerStepMode::Run;
+            },
+            DebuggerCommand::StepInto => {
+                self.step_mode = DebuggerStepMode::StepInto;
+            },
+            DebuggerCommand::StepOver => {
+                self.step_mode = DebuggerStepMode::StepOver { call_depth: event.call_depth };
+            },
+            DebuggerCommand::Abort => {
+                return ObserverAction::Abort;
+            }
+        }
+        ObserverAction::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::{DependencyManager, DependencyManagerFileSystemMode};
+    use crate::execute::{ProgramCache, ProgramObserverRef, ProgramRunner, RegisterValue};
+    use crate::unofficial_function::UnofficialFunctionRegistry;
+    use num_bigint::ToBigInt;
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    const PROGRAM_A000045: &str = r#"
+mov $3,1
+lpb $0
+  sub $0,1
+  mov $2,$1
+  add $1,$3
+  mov $3,$2
+lpe
+mov $0,$1
+"#;
+
+    const PROGRAM_CALLER: &str = r#"
+seq $0,45
+mul $0,2
+"#;
+
+    fn load(program_id: u64) -> Arc<ProgramRunner> {
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            UnofficialFunctionRegistry::new(),
+        );
+        dm.virtual_filesystem_insert_file(45, PROGRAM_A000045.to_string());
+        dm.virtual_filesystem_insert_file(1, PROGRAM_CALLER.to_string());
+        dm.load(program_id).expect("runner")
+    }
+
+    /// Runs the program with a debugger and returns the pauses that happened.
+    fn run_with_debugger(program_id: u64, input: i64, setup: impl FnOnce(&mut Debugger), commands: Vec<DebuggerCommand>) -> (anyhow::Result<RegisterValue>, Vec<DebuggerPause>) {
+        let runner: Arc<ProgramRunner> = load(program_id);
+        let pauses: Rc<RefCell<Vec<DebuggerPause>>> = Rc::new(RefCell::new(vec!()));
+        let pauses_clone = pauses.clone();
+        let mut command_iter = commands.into_iter();
+        let handler: DebuggerPauseHandler = Box::new(move |pause: &DebuggerPause| {
+            pauses_clone.borrow_mut().push(pause.clone());
+            command_iter.next().unwrap_or(DebuggerCommand::Continue)
+        });
+        let mut debugger = Debugger::new(handler);
+        setup(&mut debugger);
+        let observer: ProgramObserverRef = Rc::new(RefCell::new(debugger));
+        let mut cache = ProgramCache::new();
+        let mut step_count: u64 = 0;
+        let result = runner.run_observed(
+            RegisterValue::from_i64(input),
+            &mut step_count,
+            10000,
+            &mut cache,
+            observer,
+        );
+        let pauses_vec: Vec<DebuggerPause> = pauses.borrow().clone();
+        (result, pauses_vec)
+    }
+
+    #[test]
+    fn test_10000_line_breakpoint() {
+        // Act
+        let (result, pauses) = run_with_debugger(45, 5, |debugger| {
+            debugger.add_breakpoint(Breakpoint::Line { line_number: 5 });
+            debugger.add_watch(1);
+        }, vec!());
+
+        // Assert
+        assert_eq!(result.expect("output"), RegisterValue::from_i64(5));
+        assert_eq!(pauses.len(), 6);
+        let pause: &DebuggerPause = &pauses[0];
+        assert_eq!(pause.reason, DebuggerPauseReason::Breakpoint { index: 0 });
+        assert_eq!(pause.instruction, "mov $2,$1");
+        assert_eq!(pause.loop_depth, 1);
+        assert_eq!(pause.call_depth, 0);
+        assert_eq!(pause.watches, vec![(1, 0.to_bigint().unwrap())]);
+        let values: Vec<String> = pauses.iter().map(|pause| pause.watches[0].1.to_string()).collect();
+        assert_eq!(values.join(","), "0,1,1,2,3,5");
+    }
+
+    #[test]
+    fn test_10001_conditional_breakpoint() {
+        // Act
+        let condition = BreakpointCondition {
+            register: 1,
+            comparison: RegisterComparison::GreaterThan,
+            value: 2.to_bigint().unwrap(),
+        };
+        let (result, pauses) = run_with_debugger(45, 7, |debugger| {
+            debugger.add_breakpoint(Breakpoint::ConditionalLine { line_number: 4, condition });
+            debugger.add_watch(1);
+        }, vec![DebuggerCommand::Abort]);
+
+        // Assert
+        assert!(result.is_err());
+        assert_eq!(pauses.len(), 1);
+        assert_eq!(pauses[0].watches, vec![(1, 3.to_bigint().unwrap())]);
+    }
+
+    #[test]
+    fn test_20000_step_into_seq() {
+        // Act
+        let (result, pauses) = run_with_debugger(1, 2, |debugger| {
+            debugger.set_step_into();
+        }, vec![DebuggerCommand::StepInto, DebuggerCommand::StepInto, DebuggerCommand::Continue]);
+
+        // Assert
+        assert_eq!(result.expect("output"), RegisterValue::from_i64(2));
+        assert_eq!(pauses.len(), 3);
+        assert_eq!(pauses[0].instruction, "seq $0,45");
+        assert_eq!(pauses[0].program_id, ProgramId::ProgramOEIS(1));
+        assert_eq!(pauses[1].instruction, "mov $3,1");
+        assert_eq!(pauses[1].program_id, ProgramId::ProgramOEIS(45));
+        assert_eq!(pauses[1].call_depth, 1);
+        assert_eq!(pauses[2].instruction, "lpb $0");
+    }
+
+    #[test]
+    fn test_20001_step_over_seq() {
+        // Act
+        let (result, pauses) = run_with_debugger(1, 2, |debugger| {
+            debugger.set_step_into();
+        }, vec![DebuggerCommand::StepOver, DebuggerCommand::StepOver]);
+
+        // Assert
+        assert_eq!(result.expect("output"), RegisterValue::from_i64(2));
+        let instructions: Vec<String> = pauses.iter().map(|pause| pause.instruction.clone()).collect();
+        assert_eq!(instructions, vec!["seq $0,45", "mul $0,2"]);
+        assert_eq!(pauses[1].line_number, 3);
+        // A000045 uses 15 steps for n=2, the loop body runs 3 times, and the last cycle gets rolled back.
+        // The `seq` instruction itself is 1 step.
+        assert_eq!(pauses[1].step_count, 16);
+    }
+}
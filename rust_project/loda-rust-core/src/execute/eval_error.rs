@@ -59,6 +59,12 @@ pub enum EvalError {
     /// Unofficial function
     UnofficialFunctionOutputVectorHasIncorrectLength,
     UnofficialFunctionCannotSetOutputValue,
+
+    /// A `ProgramObserver` requested that execution stops, such as a debugger.
+    AbortedByObserver,
+
+    /// The deadline of the `ProgramState` has passed.
+    TimeLimitExceeded,
 }
 
 impl fmt::Display for EvalError {
@@ -112,6 +118,10 @@ impl fmt::Display for EvalError {
                 write!(f, "Unofficial function output vector has incorrect length"),
             Self::UnofficialFunctionCannotSetOutputValue => 
                 write!(f, "Unofficial function cannot set output value"),
+            Self::AbortedByObserver => 
+                write!(f, "Aborted by observer"),
+            Self::TimeLimitExceeded => 
+                write!(f, "Time limit exceeded"),
         }
     }
 }
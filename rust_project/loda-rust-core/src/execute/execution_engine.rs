@@ -0,0 +1,11 @@
+/// How a `ProgramRunner` evaluates its program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutionEngine {
+    /// Recursive `Node::eval` calls on the tree of nodes.
+    Tree,
+
+    /// Flat `BytecodeProgram` evaluated by an interpreter loop.
+    /// 
+    /// Programs with nodes that have no bytecode representation, are evaluated with `Tree`.
+    Bytecode,
+}
@@ -1,18 +1,24 @@
 //! Instruction execution.
+mod bytecode;
 mod check_value;
+mod debugger;
 mod eval_error;
+mod execution_engine;
 mod node;
 mod node_loop_shared;
 mod node_memory_op;
 mod node_register_limit;
 mod node_unofficial_function;
 mod node_unofficial_loop_subtract;
+mod persistent_program_cache;
 mod program;
 mod program_cache;
 mod program_id;
+mod program_observer;
 mod program_runner;
 mod program_runner_manager;
 mod program_state;
+mod register_file;
 mod register_index;
 mod register_index_and_type;
 mod register_type;
@@ -26,22 +32,28 @@ mod semantic_power;
 mod semantic_simple;
 
 use check_value::*;
+pub use bytecode::{BytecodeCompileError, BytecodeCompiler, BytecodeInstruction, BytecodeLoopRange, BytecodeOperand, BytecodeProgram};
+pub use debugger::{Breakpoint, BreakpointCondition, Debugger, DebuggerCommand, DebuggerPause, DebuggerPauseHandler, DebuggerPauseReason, RegisterComparison};
 pub use program::Program;
 pub use program_id::ProgramId;
+pub use program_observer::{ObserverAction, ObserverContext, ObserverEvent, ProgramObserver, ProgramObserverRef};
 pub use program_runner::ProgramRunner;
 pub use program_runner_manager::ProgramRunnerManager;
 pub use program_state::ProgramState;
+pub use persistent_program_cache::PersistentProgramCache;
 pub use program_cache::{CacheValue, ProgramCache};
 pub use program_serializer::ProgramSerializer;
 pub use program_serializer_context::ProgramSerializerContext;
 pub use run_mode::RunMode;
 pub use eval_error::{EvalError, ValidateCallError};
+pub use execution_engine::ExecutionEngine;
 pub use node::{BoxNode, Node};
 pub use node_loop_shared::{NodeLoopLimit, LOOP_RANGE_MAX_BITS};
 pub use node_memory_op::NodeMemoryOp;
 pub use node_register_limit::NodeRegisterLimit;
 pub use node_unofficial_function::NodeUnofficialFunction;
 pub use node_unofficial_loop_subtract::NodeUnofficialLoopSubtract;
+pub use register_file::{RegisterCell, RegisterFile};
 pub use register_index::RegisterIndex;
 pub use register_index_and_type::RegisterIndexAndType;
 pub use register_type::RegisterType;
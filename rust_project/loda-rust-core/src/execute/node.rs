@@ -1,6 +1,6 @@
-use super::{ProgramCache, ProgramRunnerManager, ProgramSerializer, ProgramSerializerContext, ProgramState, ValidateCallError};
+use super::{BytecodeCompileError, BytecodeCompiler, ProgramCache, ProgramRunnerManager, ProgramSerializer, ProgramSerializerContext, ProgramState, ValidateCallError};
 
-pub trait Node {
+pub trait Node: Send + Sync {
     fn formatted_instruction(&self) -> String;
 
     fn formatted_instruction_advanced(&self, _context: &dyn ProgramSerializerContext) -> Option<String> {
@@ -42,6 +42,13 @@ pub trait Node {
     fn validate_call_nodes(&self) -> Result<(), ValidateCallError> {
         Ok(())
     }
+
+    /// Append the node to a flat `BytecodeProgram`.
+    /// Nodes without a bytecode representation returns an error, 
+    /// in which case the program is evaluated via `eval` instead.
+    fn compile(&self, _compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        Err(BytecodeCompileError::UnsupportedNode)
+    }
 }
 
 pub type BoxNode = Box<dyn Node>;
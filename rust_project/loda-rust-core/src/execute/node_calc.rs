@@ -1,4 +1,4 @@
-use super::{EvalError, ProgramCache, Node, ProgramState};
+use super::{BytecodeCompileError, BytecodeCompiler, BytecodeInstruction, BytecodeOperand, EvalError, ProgramCache, Node, ProgramState};
 use super::{Semantics, SemanticsWithoutLimits, SemanticsWithSmallLimits};
 use crate::parser::{InstructionId, InstructionParameter};
 use num_bigint::BigInt;
@@ -26,19 +26,34 @@ impl NodeCalc {
         }
     }
 
-    fn calc(&self, target: &BigInt, source: &BigInt) -> Result<BigInt, EvalError> {
-        match self.semantic_mode {
+    pub fn calc(semantic_mode: NodeCalcSemanticMode, instruction_id: InstructionId, target: &BigInt, source: &BigInt) -> Result<BigInt, EvalError> {
+        match semantic_mode {
             NodeCalcSemanticMode::Unlimited => {
-                return self.calc_with_semantics::<SemanticsWithoutLimits>(target, source)
+                Self::calc_with_semantics::<SemanticsWithoutLimits>(instruction_id, target, source)
             },
             NodeCalcSemanticMode::SmallLimits => {
-                return self.calc_with_semantics::<SemanticsWithSmallLimits>(target, source)
+                Self::calc_with_semantics::<SemanticsWithSmallLimits>(instruction_id, target, source)
             },
         }
     }
 
-    fn calc_with_semantics<S: Semantics>(&self, target: &BigInt, source: &BigInt) -> Result<BigInt, EvalError> {
-        match self.instruction_id {
+    /// Fast path for values that fits inside an `i64`, without allocating a `BigInt`.
+    /// 
+    /// Returns `None` when the result must be computed with `BigInt`, such as on overflow.
+    /// The results are within the `SemanticsWithSmallLimits` range, so both semantic modes agree.
+    pub fn calc_small(instruction_id: InstructionId, target: i64, source: i64) -> Option<i64> {
+        match instruction_id {
+            InstructionId::Move     => Some(source),
+            InstructionId::Add      => target.checked_add(source),
+            InstructionId::Subtract => target.checked_sub(source),
+            InstructionId::Truncate => target.checked_sub(source).map(|value| value.max(0)),
+            InstructionId::Multiply => target.checked_mul(source),
+            _ => None
+        }
+    }
+
+    fn calc_with_semantics<S: Semantics>(instruction_id: InstructionId, target: &BigInt, source: &BigInt) -> Result<BigInt, EvalError> {
+        match instruction_id {
             InstructionId::Move            => Ok(source.clone()),
             InstructionId::Add             => S::add(target, source),
             InstructionId::Subtract        => S::subtract(target, source),
@@ -68,7 +83,7 @@ impl NodeCalc {
             InstructionId::LargestExponent => S::largestexponent(target, source),
             InstructionId::Factorial       => S::factorial(target, source),
             _ => {
-                error!("unsupported instruction: {:?}", self.instruction_id);
+                error!("unsupported instruction: {:?}", instruction_id);
                 return Err(EvalError::UnsupportedInstruction);
             }            
         }
@@ -81,11 +96,29 @@ impl Node for NodeCalc {
     }
 
     fn eval(&self, state: &mut ProgramState, _cache: &mut ProgramCache) -> anyhow::Result<()> {
+        if let (Some(target), Some(source)) = (state.get_small(&self.target), state.get_small(&self.source)) {
+            if let Some(value) = Self::calc_small(self.instruction_id, target, source) {
+                state.set_small(&self.target, value)?;
+                state.increment_step_count()?;
+                return Ok(());
+            }
+        }
         let target: BigInt = state.get(&self.target, false)?;
         let source: BigInt = state.get(&self.source, false)?;
-        let value: BigInt = self.calc(&target, &source)?;
+        let value: BigInt = Self::calc(self.semantic_mode, self.instruction_id, &target, &source)?;
         state.set(&self.target, value)?;
         state.increment_step_count()?;
         Ok(())
     }
+
+    fn compile(&self, compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        let instruction = BytecodeInstruction::Calc {
+            semantic_mode: self.semantic_mode,
+            instruction_id: self.instruction_id,
+            target: BytecodeOperand::from_parameter(&self.target)?,
+            source: BytecodeOperand::from_parameter(&self.source)?,
+        };
+        compiler.push(instruction);
+        Ok(())
+    }
 }
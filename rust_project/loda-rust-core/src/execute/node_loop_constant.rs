@@ -1,6 +1,6 @@
 use anyhow::Context;
 
-use super::{EvalError, Node, NodeLoopLimit, Program, ProgramCache, ProgramSerializer, ProgramState, ProgramRunnerManager, RegisterIndex, RunMode, ValidateCallError, LOOP_RANGE_MAX_BITS};
+use super::{BytecodeCompileError, BytecodeCompiler, BytecodeLoopRange, EvalError, Node, NodeLoopLimit, Program, ProgramCache, ProgramSerializer, ProgramState, ProgramRunnerManager, RegisterIndex, RunMode, ValidateCallError, LOOP_RANGE_MAX_BITS};
 
 pub struct NodeLoopConstant {
     register_start: RegisterIndex,
@@ -50,12 +50,12 @@ impl Node for NodeLoopConstant {
         let limit: NodeLoopLimit = state.node_loop_limit().clone();
         let mut cycles = 0;
         loop {
-            let old_state: ProgramState = state.clone();
+            state.checkpoint_begin();
 
             self.program.run(state, cache)?;
 
-            let is_less: bool = state.is_less_range(
-                &old_state, 
+            let is_less: bool = state.is_less_than_checkpoint_range(
+                self.register_start.0, 
                 self.register_start.0, 
                 self.range_length
             );
@@ -67,11 +67,10 @@ impl Node for NodeLoopConstant {
                 }
 
                 // When the loop reaches its end, the previous state is restored.
-                let mut new_state: ProgramState = old_state.clone();
-                new_state.set_step_count(state.step_count());
-                *state = new_state;
+                state.checkpoint_rollback();
                 break;
             }
+            state.checkpoint_commit();
 
             // Prevent looping for too long
             match limit {
@@ -104,4 +103,8 @@ impl Node for NodeLoopConstant {
     fn validate_call_nodes(&self) -> Result<(), ValidateCallError> {
         self.program.validate_call_nodes()
     }
+
+    fn compile(&self, compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        compiler.push_loop(self.register_start.0, BytecodeLoopRange::Constant(self.range_length), &self.program)
+    }
 }
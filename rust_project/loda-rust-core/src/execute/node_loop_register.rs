@@ -1,4 +1,4 @@
-use super::{EvalError, Node, NodeLoopLimit, Program, ProgramCache, ProgramSerializer, ProgramState, ProgramRunnerManager, RegisterIndex, RunMode, ValidateCallError, LOOP_RANGE_MAX_BITS};
+use super::{BytecodeCompileError, BytecodeCompiler, BytecodeLoopRange, EvalError, Node, NodeLoopLimit, Program, ProgramCache, ProgramSerializer, ProgramState, ProgramRunnerManager, RegisterIndex, RunMode, ValidateCallError, LOOP_RANGE_MAX_BITS};
 use anyhow::Context;
 use num_bigint::BigInt;
 use num_traits::{ToPrimitive, Signed};
@@ -39,7 +39,7 @@ impl Node for NodeLoopRegister {
             println!("{:12} {} => {}", instruction, snapshot, snapshot);
         }
 
-        let initial_value_inner: &BigInt = state.get_u64(self.register_with_range_length.0);
+        let initial_value_inner: &BigInt = &state.get_u64(self.register_with_range_length.0);
         let initial_range_length: u64;
         if initial_value_inner.is_positive() {
             if initial_value_inner.bits() >= LOOP_RANGE_MAX_BITS {
@@ -69,11 +69,15 @@ impl Node for NodeLoopRegister {
         let limit: NodeLoopLimit = state.node_loop_limit().clone();
         let mut cycles = 0;
         loop {
-            let old_state: ProgramState = state.clone();
+            let old_snapshot: Option<String> = match state.run_mode() {
+                RunMode::Verbose => Some(state.memory_full_to_string()),
+                RunMode::Silent => None
+            };
+            state.checkpoint_begin();
 
             self.program.run(state, cache)?;
 
-            let value_inner: &BigInt = state.get_u64(self.register_with_range_length.0);
+            let value_inner: &BigInt = &state.get_u64(self.register_with_range_length.0);
             let range_length: u64;
             if value_inner.is_positive() {
                 if value_inner.bits() >= LOOP_RANGE_MAX_BITS {
@@ -103,8 +107,8 @@ impl Node for NodeLoopRegister {
                 currently_smallest_range_length
             );
 
-            let is_less: bool = state.is_less_range(
-                &old_state, 
+            let is_less: bool = state.is_less_than_checkpoint_range(
+                self.register_start.0,
                 self.register_start.0,
                 currently_smallest_range_length
             );
@@ -114,18 +118,16 @@ impl Node for NodeLoopRegister {
 
             if !is_less {
 
-                if state.run_mode() == RunMode::Verbose {
+                if let Some(after) = &old_snapshot {
                     let before = state.memory_full_to_string();
-                    let after = old_state.memory_full_to_string();
                     println!("{:12} {} => {}  break", "lpe", before, after);
                 }
 
                 // When the loop reaches its end, the previous state is restored.
-                let mut new_state: ProgramState = old_state.clone();
-                new_state.set_step_count(state.step_count());
-                *state = new_state;
+                state.checkpoint_rollback();
                 break;
             }
+            state.checkpoint_commit();
 
             // Prevent looping for too long
             match limit {
@@ -138,9 +140,8 @@ impl Node for NodeLoopRegister {
                     }
                 }
             }
-            if state.run_mode() == RunMode::Verbose {
+            if let Some(after) = &old_snapshot {
                 let before = state.memory_full_to_string();
-                let after = old_state.memory_full_to_string();
                 println!("{:12} {} => {}  continue", "lpe", before, after);
             }
         }
@@ -160,4 +161,8 @@ impl Node for NodeLoopRegister {
     fn validate_call_nodes(&self) -> Result<(), ValidateCallError> {
         self.program.validate_call_nodes()
     }
+
+    fn compile(&self, compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        compiler.push_loop(self.register_start.0, BytecodeLoopRange::Register(self.register_with_range_length.0), &self.program)
+    }
 }
@@ -1,6 +1,6 @@
 use anyhow::Context;
 
-use super::{EvalError, Node, NodeLoopLimit, ProgramCache, Program, ProgramRunnerManager, ProgramSerializer, ProgramState, RegisterIndex, RunMode, ValidateCallError};
+use super::{BytecodeCompileError, BytecodeCompiler, BytecodeLoopRange, EvalError, Node, NodeLoopLimit, ProgramCache, Program, ProgramRunnerManager, ProgramSerializer, ProgramState, RegisterIndex, RunMode, ValidateCallError};
 
 pub struct NodeLoopSimple {
     register: RegisterIndex,
@@ -39,13 +39,14 @@ impl Node for NodeLoopSimple {
         let limit: NodeLoopLimit = state.node_loop_limit().clone();
         let mut cycles = 0;
         loop {
-            let old_state: ProgramState = state.clone();
+            state.checkpoint_begin();
 
             self.program.run(state, cache)?;
 
-            let is_less: bool = state.is_less_single(
-                &old_state, 
-                self.register.0
+            let is_less: bool = state.is_less_than_checkpoint_range(
+                self.register.0, 
+                self.register.0, 
+                1
             );
 
             if !is_less {
@@ -54,11 +55,10 @@ impl Node for NodeLoopSimple {
                 }
 
                 // When the loop reaches its end, the previous state is restored.
-                let mut new_state: ProgramState = old_state.clone();
-                new_state.set_step_count(state.step_count());
-                *state = new_state;
+                state.checkpoint_rollback();
                 break;
             }
+            state.checkpoint_commit();
 
             // Prevent looping for too long
             match limit {
@@ -91,4 +91,8 @@ impl Node for NodeLoopSimple {
     fn validate_call_nodes(&self) -> Result<(), ValidateCallError> {
         self.program.validate_call_nodes()
     }
+
+    fn compile(&self, compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        compiler.push_loop(self.register.0, BytecodeLoopRange::Constant(1), &self.program)
+    }
 }
@@ -57,7 +57,11 @@ impl Node for NodeLoopSlow {
         let limit: NodeLoopLimit = state.node_loop_limit().clone();
         let mut cycles = 0;
         loop {
-            let old_state: ProgramState = state.clone();
+            let old_snapshot: Option<String> = match state.run_mode() {
+                RunMode::Verbose => Some(state.memory_full_to_string()),
+                RunMode::Silent => None
+            };
+            state.checkpoint_begin();
 
             let old_target: BigInt = state.get(&self.target, true)?;
             let old_target_u64: u64 = match old_target.to_u64() {
@@ -124,12 +128,11 @@ impl Node for NodeLoopSlow {
                 println!("LOOP: old_target={}, old_range={}", old_target_u64, old_range_length);
                 println!("LOOP: new_target={}, new_range={}", new_target_u64, new_range_length);
 
-                let snapshot0 = old_state.memory_full_to_string();
+                let snapshot0: String = old_snapshot.clone().unwrap_or_default();
                 let snapshot1 = state.memory_full_to_string();
                 println!("LOOP: old={} new={}", snapshot0, snapshot1);
             }
-            let is_less: bool = state.is_less_twostartindexes_range(
-                &old_state, 
+            let is_less: bool = state.is_less_than_checkpoint_range(
                 new_target_u64, 
                 old_target_u64, 
                 range_length
@@ -141,11 +144,10 @@ impl Node for NodeLoopSlow {
                 }
 
                 // When the loop reaches its end, the previous state is restored.
-                let mut new_state: ProgramState = old_state.clone();
-                new_state.set_step_count(state.step_count());
-                *state = new_state;
+                state.checkpoint_rollback();
                 break;
             }
+            state.checkpoint_commit();
 
             // Prevent looping for too long
             match limit {
@@ -83,10 +83,10 @@ impl NodeMemoryOp {
             return Ok(());
         }
 
-        let leftmost: BigInt = state.get_i64(first)?.clone();
+        let leftmost: BigInt = state.get_i64(first)?.into_owned();
         
         for i in first..(second - 1) {
-            let next_value: BigInt = state.get_i64(i + 1)?.clone();
+            let next_value: BigInt = state.get_i64(i + 1)?.into_owned();
             state.set_i64(i, next_value)?;
         }
         
@@ -106,10 +106,10 @@ impl NodeMemoryOp {
             return Ok(());
         }
 
-        let rightmost: BigInt = state.get_i64(second - 1)?.clone();
+        let rightmost: BigInt = state.get_i64(second - 1)?.into_owned();
         
         for i in (first + 1..second).rev() {
-            let prev_value: BigInt = state.get_i64(i - 1)?.clone();
+            let prev_value: BigInt = state.get_i64(i - 1)?.into_owned();
             state.set_i64(i, prev_value)?;
         }
         
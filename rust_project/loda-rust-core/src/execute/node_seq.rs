@@ -1,5 +1,5 @@
-use std::rc::Rc;
-use super::{EvalError, ProgramSerializerContext, ProgramCache, Node, RegisterValue, Program, ProgramId, ProgramState, ProgramRunner, ProgramRunnerManager, ValidateCallError};
+use std::sync::Arc;
+use super::{BytecodeCompileError, BytecodeCompiler, BytecodeInstruction, BytecodeOperand, EvalError, ObserverContext, ProgramSerializerContext, ProgramCache, Node, RegisterValue, Program, ProgramId, ProgramState, ProgramRunner, ProgramRunnerManager, ValidateCallError};
 use super::PerformCheckValue;
 use crate::parser::InstructionParameter;
 use anyhow::Context;
@@ -9,7 +9,7 @@ use num_traits::Signed;
 pub struct NodeSeq {
     target: InstructionParameter,
     program_id: u64,
-    program_runner_rc: Rc::<ProgramRunner>,
+    program_runner_arc: Arc::<ProgramRunner>,
     link_established: bool,
 }
 
@@ -20,12 +20,12 @@ impl NodeSeq {
             ProgramId::ProgramWithoutId,
             dummy_program
         );
-        let program_runner_rc = Rc::new(program_runner);
+        let program_runner_arc = Arc::new(program_runner);
 
         Self {
             target: target,
             program_id: program_id,
-            program_runner_rc: program_runner_rc,
+            program_runner_arc,
             link_established: false,
         }
     }
@@ -66,15 +66,29 @@ impl Node for NodeSeq {
         let step_count_limit: u64 = state.step_count_limit();
         let mut step_count: u64 = state.step_count();
 
+        // Pass on the observer, so it can follow the execution into the called program
+        let observer_context: Option<ObserverContext> = state.observer_context().map(|parent| {
+            ObserverContext::new(
+                parent.observer().clone(), 
+                self.program_runner_arc.program_id(), 
+                parent.call_depth() + 1
+            )
+        });
+
         // Invoke the actual run() function
         let input_value = RegisterValue(input);
-        let run_result = self.program_runner_rc.run_without_offset(
-            input_value, 
+        let mut inner_state = ProgramState::new(
             state.run_mode(), 
-            &mut step_count, 
             step_count_limit,
             state.node_register_limit().clone(),
             state.node_loop_limit().clone(),
+        );
+        inner_state.set_observer_context(observer_context);
+        inner_state.set_deadline(state.deadline());
+        let run_result = self.program_runner_arc.run_without_offset_with_state(
+            input_value, 
+            inner_state,
+            &mut step_count, 
             cache,
         );
 
@@ -104,14 +118,14 @@ impl Node for NodeSeq {
         }
         let program_id: u64 = self.program_id;
 
-        let program_runner: Rc::<ProgramRunner> = match program_manager.get(program_id) {
+        let program_runner: Arc::<ProgramRunner> = match program_manager.get(program_id) {
             Some(value) => value,
             None => {
                 panic!("NodeCall. Unable to get program_id: {}", program_id);
             }
         };
 
-        self.program_runner_rc = program_runner;
+        self.program_runner_arc = program_runner;
         self.link_established = true;
         //trace!("NodeCall: update_call. program_id: {}", program_id);
     }
@@ -128,4 +142,16 @@ impl Node for NodeSeq {
         }
         Ok(())
     }
+
+    fn compile(&self, compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        if !self.link_established {
+            return Err(BytecodeCompileError::MissingLink);
+        }
+        let instruction = BytecodeInstruction::Seq {
+            target: BytecodeOperand::from_parameter(&self.target)?,
+            program_runner: self.program_runner_arc.clone(),
+        };
+        compiler.push(instruction);
+        Ok(())
+    }
 }
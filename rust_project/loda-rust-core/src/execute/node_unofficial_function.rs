@@ -50,18 +50,19 @@ impl Node for NodeUnofficialFunction {
         let mut input_vec: Vec<BigInt> = vec!();
         for i in 0..self.input_count {
             let address = start_address + (i as u64);
-            let value: &BigInt = state.get_u64(address);
+            let value: BigInt = state.get_u64(address).into_owned();
             // debug!("input#{} = address {} = value {:?}", i, address, value);
 
             // Abort if the input value is beyond the limit (optional)
             state.check_value().input(&input)?;
 
-            input_vec.push(value.clone());
+            input_vec.push(value);
         }
 
-        // Run the function
-        let run_result = self.unofficial_function.run(input_vec);
-        let output_vec: Vec<BigInt> = run_result.context("NodeUnofficialFunction.eval run returned error")?;
+        // Run the function, with the remaining steps as its budget
+        let step_count_limit: u64 = state.step_count_limit().saturating_sub(state.step_count());
+        let run_result = self.unofficial_function.run_with_step_count_limit(input_vec, step_count_limit, state.node_register_limit(), state.node_loop_limit());
+        let (output_vec, step_count) = run_result.context("NodeUnofficialFunction.eval run returned error")?;
         
         // Output from the function
         if output_vec.len() != (self.output_count as usize) {
@@ -78,7 +79,7 @@ impl Node for NodeUnofficialFunction {
             state.set_u64(address, value.clone())
                 .with_context(|| format!("NodeUnofficialFunction.eval Cannot set output value into state. address: {}", address))?;
         }
-        state.increment_step_count()?;
+        state.increase_step_count(step_count.max(1))?;
         Ok(())
     }
 
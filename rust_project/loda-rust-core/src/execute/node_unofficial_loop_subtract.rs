@@ -39,7 +39,7 @@ impl Node for NodeUnofficialLoopSubtract {
 
         let mut current_counter: BigInt;
         {
-            let counter: &BigInt = state.get_u64(self.register.0);
+            let counter: &BigInt = &state.get_u64(self.register.0);
             if !counter.is_positive() {
                 state.increment_step_count()?;
                 return Ok(())
@@ -54,7 +54,7 @@ impl Node for NodeUnofficialLoopSubtract {
             state.increment_step_count()?;
 
             {
-                let counter: &BigInt = state.get_u64(self.register.0);
+                let counter: &BigInt = &state.get_u64(self.register.0);
                 let has_counter_been_modified: bool = counter != &current_counter;
                 if has_counter_been_modified {
                     if state.run_mode() == RunMode::Verbose {
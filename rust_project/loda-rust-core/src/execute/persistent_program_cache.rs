@@ -0,0 +1,291 @@
+use super::CacheValue;
+use crate::control::{fnv1a_hash, FNV_OFFSET_BASIS};
+use cached::{Cached, SizedCache};
+use num_bigint::BigInt;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use anyhow::Context;
+
+/// The number of programs that are kept in memory. The least recently used program is evicted.
+const DEFAULT_FILE_CAPACITY: usize = 1000;
+
+/// The rows are written to disk, when this many rows have been buffered.
+const FLUSH_ROW_COUNT: usize = 200;
+
+/// The rows are written to disk, when the oldest buffered row is older than this.
+const FLUSH_INTERVAL_SECONDS: u64 = 10;
+
+/// Terms that survive a process restart, so expensive programs are computed once per machine.
+///
+/// There is one text file per program content, like this: `dir/045/A045123_0000000000000abc.txt`.
+/// The suffix is the hash of the program content. When the program changes,
+/// then the hash changes and the terms are saved to another file. Files for outdated programs
+/// are not removed, and can safely be deleted.
+///
+/// The rows are formatted as `index value step_count checksum`, and each row starts with a newline.
+///
+/// The file is only appended to. The rows are buffered in memory and appended in batches,
+/// with `flush`, or when the cache is dropped.
+/// Several processes may append to the same file, since they compute the same terms for the same program content.
+/// When a process gets killed while appending, then the last row is truncated. The row appended by 
+/// the next writer starts with a newline, so it's kept apart from the truncated row.
+/// A row with a checksum mismatch is ignored, so a truncated row is harmless, and a duplicate row is harmless.
+pub struct PersistentProgramCache {
+    dir: PathBuf,
+    files: SizedCache<(u64, u64), HashMap<BigInt, CacheValue>>,
+    pending_rows: HashMap<(u64, u64), String>,
+    pending_row_count: usize,
+    pending_since: Option<Instant>,
+}
+
+impl PersistentProgramCache {
+    pub fn new(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("PersistentProgramCache cannot create dir: {:?}", dir))?;
+        let instance = Self {
+            dir: PathBuf::from(dir),
+            files: SizedCache::with_size(DEFAULT_FILE_CAPACITY),
+            pending_rows: HashMap::new(),
+            pending_row_count: 0,
+            pending_since: None,
+        };
+        Ok(instance)
+    }
+
+    /// Construct a path, like this: `dir/045/A045123_0000000000000abc.txt`
+    fn path_to_file(&self, program_id: u64, content_hash: u64) -> PathBuf {
+        let dir_index: String = format!("{:0>3}", program_id / 1000);
+        let filename: String = format!("A{:0>6}_{:016x}.txt", program_id, content_hash);
+        self.dir.join(dir_index).join(filename)
+    }
+
+    /// Load the file, if it isn't already in memory.
+    ///
+    /// When the file is missing, then it's empty.
+    fn values(&mut self, program_id: u64, content_hash: u64) -> &mut HashMap<BigInt, CacheValue> {
+        let path: PathBuf = self.path_to_file(program_id, content_hash);
+        self.files.cache_get_or_set_with((program_id, content_hash), || {
+            let contents: String = fs::read_to_string(&path).unwrap_or_default();
+            parse_file(&contents)
+        })
+    }
+
+    pub fn get(&mut self, program_id: u64, content_hash: u64, index: &BigInt) -> Option<CacheValue> {
+        self.values(program_id, content_hash).get(index).cloned()
+    }
+
+    /// The term is available right away. It's written to disk with the next `flush`.
+    pub fn set(&mut self, program_id: u64, content_hash: u64, index: BigInt, value: BigInt, step_count: u64) -> anyhow::Result<()> {
+        let row: String = format_row(&index, &value, step_count);
+        let cache_value = CacheValue {
+            value,
+            step_count,
+        };
+        self.values(program_id, content_hash).insert(index, cache_value);
+
+        self.pending_rows.entry((program_id, content_hash)).or_default().push_str(&row);
+        self.pending_row_count += 1;
+        let pending_since: Instant = *self.pending_since.get_or_insert_with(Instant::now);
+        if self.pending_row_count >= FLUSH_ROW_COUNT || pending_since.elapsed() >= Duration::from_secs(FLUSH_INTERVAL_SECONDS) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Append the buffered rows to the files.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        self.pending_row_count = 0;
+        self.pending_since = None;
+        let pending_rows: HashMap<(u64, u64), String> = std::mem::take(&mut self.pending_rows);
+        for ((program_id, content_hash), rows) in pending_rows {
+            let path: PathBuf = self.path_to_file(program_id, content_hash);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("PersistentProgramCache cannot create dir: {:?}", parent))?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)
+                .with_context(|| format!("PersistentProgramCache cannot open file: {:?}", path))?;
+            file.write_all(rows.as_bytes())
+                .with_context(|| format!("PersistentProgramCache cannot append to file: {:?}", path))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PersistentProgramCache {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush() {
+            error!("Unable to save terms to persistent cache. error: {:?}", error);
+        }
+    }
+}
+
+fn row_checksum(row_without_checksum: &str) -> u64 {
+    fnv1a_hash(FNV_OFFSET_BASIS, row_without_checksum.as_bytes())
+}
+
+/// Construct a row, like this: `\n3 7 42 3f2a0c9e5b1d7f80`
+fn format_row(index: &BigInt, value: &BigInt, step_count: u64) -> String {
+    let row_without_checksum: String = format!("{} {} {}", index, value, step_count);
+    let checksum: u64 = row_checksum(&row_without_checksum);
+    format!("\n{} {:016x}", row_without_checksum, checksum)
+}
+
+fn parse_file(contents: &str) -> HashMap<BigInt, CacheValue> {
+    let mut values = HashMap::<BigInt, CacheValue>::new();
+    for row in contents.lines() {
+        let (row_without_checksum, checksum) = match row.rsplit_once(' ') {
+            Some(value) => value,
+            None => continue
+        };
+        if u64::from_str_radix(checksum, 16).ok() != Some(row_checksum(row_without_checksum)) {
+            continue;
+        }
+        let mut iter = row_without_checksum.split(' ');
+        let index: Option<BigInt> = iter.next().and_then(|s| BigInt::from_str(s).ok());
+        let value: Option<BigInt> = iter.next().and_then(|s| BigInt::from_str(s).ok());
+        let step_count: Option<u64> = iter.next().and_then(|s| s.parse::<u64>().ok());
+        if let (Some(index), Some(value), Some(step_count), None) = (index, value, step_count, iter.next()) {
+            values.insert(index, CacheValue { value, step_count });
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_10000_survives_restart() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10000_survives_restart");
+        {
+            let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+            cache.set(40, 0xabc, BigInt::from(3), BigInt::from(7), 42).expect("ok");
+            cache.set(40, 0xabc, BigInt::from(4), BigInt::from(11), 50).expect("ok");
+        }
+
+        // Act
+        let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+
+        // Assert
+        let value: CacheValue = cache.get(40, 0xabc, &BigInt::from(4)).expect("value");
+        assert_eq!(value.value, BigInt::from(11));
+        assert_eq!(value.step_count, 50);
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(5)), None);
+        assert_eq!(cache.get(41, 0xabc, &BigInt::from(4)), None);
+        let contents: String = fs::read_to_string(dir.join("000").join("A000040_0000000000000abc.txt")).expect("file");
+        assert_eq!(contents, format!("{}{}", format_row(&BigInt::from(3), &BigInt::from(7), 42), format_row(&BigInt::from(4), &BigInt::from(11), 50)));
+    }
+
+    #[test]
+    fn test_10001_changed_program_discards_terms() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10001_changed_program_discards_terms");
+        {
+            let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+            cache.set(40, 0xabc, BigInt::from(3), BigInt::from(7), 42).expect("ok");
+        }
+
+        // Act
+        let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+        assert_eq!(cache.get(40, 0xdef, &BigInt::from(3)), None);
+        cache.set(40, 0xdef, BigInt::from(4), BigInt::from(8), 1).expect("ok");
+        cache.flush().expect("ok");
+
+        // Assert
+        let contents: String = fs::read_to_string(dir.join("000").join("A000040_0000000000000def.txt")).expect("file");
+        assert_eq!(contents, format_row(&BigInt::from(4), &BigInt::from(8), 1));
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(3)).expect("value").value, BigInt::from(7));
+    }
+
+    #[test]
+    fn test_10002_several_writers() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10002_several_writers");
+        let mut cache0 = PersistentProgramCache::new(&dir).expect("cache");
+        let mut cache1 = PersistentProgramCache::new(&dir).expect("cache");
+
+        // Act
+        cache0.set(40, 0xabc, BigInt::from(3), BigInt::from(7), 42).expect("ok");
+        cache1.set(40, 0xdef, BigInt::from(3), BigInt::from(8), 1).expect("ok");
+        cache0.flush().expect("ok");
+        cache0.set(40, 0xabc, BigInt::from(4), BigInt::from(11), 50).expect("ok");
+        cache1.set(40, 0xabc, BigInt::from(5), BigInt::from(13), 60).expect("ok");
+        cache1.flush().expect("ok");
+        cache0.flush().expect("ok");
+
+        // Assert
+        let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(3)).expect("value").value, BigInt::from(7));
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(4)).expect("value").value, BigInt::from(11));
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(5)).expect("value").value, BigInt::from(13));
+        assert_eq!(cache.get(40, 0xdef, &BigInt::from(3)).expect("value").value, BigInt::from(8));
+        assert_eq!(cache.get(40, 0xdef, &BigInt::from(4)), None);
+    }
+
+    #[test]
+    fn test_10003_buffered_until_flush() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10003_buffered_until_flush");
+        let path: PathBuf = dir.join("000").join("A000040_0000000000000abc.txt");
+        let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+
+        // Act
+        cache.set(40, 0xabc, BigInt::from(3), BigInt::from(7), 42).expect("ok");
+        let exists_before_flush: bool = path.is_file();
+        cache.flush().expect("ok");
+
+        // Assert
+        assert_eq!(exists_before_flush, false);
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(3)).expect("value").value, BigInt::from(7));
+        assert_eq!(fs::read_to_string(&path).expect("file"), format_row(&BigInt::from(3), &BigInt::from(7), 42));
+    }
+
+    #[test]
+    fn test_10004_truncated_row_followed_by_another_writer() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = PathBuf::from(&tempdir.path()).join("test_10004_truncated_row_followed_by_another_writer");
+        let path: PathBuf = dir.join("000").join("A000040_0000000000000abc.txt");
+        fs::create_dir_all(path.parent().unwrap()).expect("dir");
+        // A writer got killed in the middle of a row, the step count is cut short.
+        let row: String = format_row(&BigInt::from(3), &BigInt::from(7), 4242);
+        let truncated_row: &str = &row[0..(row.len() - 19)];
+        assert_eq!(truncated_row, "\n3 7 42");
+        fs::write(&path, truncated_row).expect("write");
+
+        // Act
+        {
+            let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+            cache.set(40, 0xabc, BigInt::from(4), BigInt::from(11), 50).expect("ok");
+        }
+
+        // Assert
+        let mut cache = PersistentProgramCache::new(&dir).expect("cache");
+        assert_eq!(cache.get(40, 0xabc, &BigInt::from(3)), None);
+        let value: CacheValue = cache.get(40, 0xabc, &BigInt::from(4)).expect("value");
+        assert_eq!(value.value, BigInt::from(11));
+        assert_eq!(value.step_count, 50);
+    }
+
+    #[test]
+    fn test_20000_parse_file_ignore_junk() {
+        let row0: String = format_row(&BigInt::from(1), &BigInt::from(2), 3);
+        let row1: String = format_row(&BigInt::from(-1), &BigInt::from(-2), 7);
+        let contents: String = format!("{}\n4 5\njunk\n1 2 3\n1 2 3 4{}\n1 2 3 0000000000000000\n5 6", row0, row1);
+        let values: HashMap<BigInt, CacheValue> = parse_file(&contents);
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.get(&BigInt::from(-1)).expect("value").step_count, 7);
+        assert!(parse_file("").is_empty());
+    }
+}
@@ -1,9 +1,11 @@
-use super::{BoxNode, Node, ProgramCache, ProgramRunnerManager, ProgramSerializer, ProgramState, RunMode, ValidateCallError};
+use super::{BoxNode, BytecodeCompileError, BytecodeCompiler, EvalError, Node, ObserverAction, ObserverEvent, ProgramCache, ProgramObserverRef, ProgramRunnerManager, ProgramSerializer, ProgramState, RunMode, ValidateCallError};
+use anyhow::Context;
 
 type BoxNodeVec = Vec<BoxNode>;
 
 pub struct Program {
     node_vec: BoxNodeVec,
+    line_number_vec: Vec<usize>,
     offset: Option<i32>,
 }
 
@@ -11,6 +13,7 @@ impl Program {
     pub fn new() -> Self {
         Program {
             node_vec: vec!(),
+            line_number_vec: vec!(),
             offset: None,
         }
     }
@@ -25,11 +28,25 @@ impl Program {
 
     pub fn push<T: Node + 'static>(&mut self, node: T) {
         let node_wrapped = Box::new(node);
-        self.node_vec.push(node_wrapped);
+        self.push_boxed(node_wrapped);
     }
 
     pub fn push_boxed(&mut self, node_wrapped: BoxNode) {
         self.node_vec.push(node_wrapped);
+        self.line_number_vec.push(0);
+    }
+
+    /// Remember where in the source code the most recently added node comes from.
+    /// 
+    /// The line numbers are reported to a `ProgramObserver`, so breakpoints can be set on lines.
+    pub fn assign_line_number_to_last_node(&mut self, line_number: usize) {
+        if let Some(value) = self.line_number_vec.last_mut() {
+            *value = line_number;
+        }
+    }
+
+    pub fn line_number(&self, node_index: usize) -> Option<usize> {
+        self.line_number_vec.get(node_index).copied()
     }
 
     pub fn serialize(&self, serializer: &mut ProgramSerializer) {
@@ -38,7 +55,17 @@ impl Program {
         }
     }
 
+    pub fn compile(&self, compiler: &mut BytecodeCompiler) -> Result<(), BytecodeCompileError> {
+        for node in &self.node_vec {
+            node.compile(compiler)?;
+        }
+        Ok(())
+    }
+
     pub fn run(&self, state: &mut ProgramState, cache: &mut ProgramCache) -> anyhow::Result<()> {
+        if state.observer_context().is_some() {
+            return self.run_observed(state, cache);
+        }
         match state.run_mode() {
             RunMode::Verbose => self.run_verbose(state, cache),
             RunMode::Silent => self.run_silent(state, cache),
@@ -70,6 +97,56 @@ impl Program {
         Ok(())
     }
 
+    /// Invokes the `ProgramObserver` before and after each node is evaluated.
+    pub fn run_observed(&self, state: &mut ProgramState, cache: &mut ProgramCache) -> anyhow::Result<()> {
+        for (node_index, node) in self.node_vec.iter().enumerate() {
+            let line_number: usize = self.line_number_vec[node_index];
+            let action: ObserverAction = Self::notify_observer(state, node.as_ref(), node_index, line_number, true);
+            if action == ObserverAction::Abort {
+                let error = Err(EvalError::AbortedByObserver);
+                return error.context("Program.run_observed aborted before eval");
+            }
+
+            if let Some(context) = state.observer_context_mut() {
+                context.enter_node();
+            }
+            node.eval(state, cache)?;
+            if let Some(context) = state.observer_context_mut() {
+                context.leave_node();
+            }
+
+            let action: ObserverAction = Self::notify_observer(state, node.as_ref(), node_index, line_number, false);
+            if action == ObserverAction::Abort {
+                let error = Err(EvalError::AbortedByObserver);
+                return error.context("Program.run_observed aborted after eval");
+            }
+        }
+        Ok(())
+    }
+
+    fn notify_observer(state: &ProgramState, node: &dyn Node, node_index: usize, line_number: usize, is_before: bool) -> ObserverAction {
+        let context = match state.observer_context() {
+            Some(value) => value,
+            None => return ObserverAction::Continue
+        };
+        let observer: ProgramObserverRef = context.observer().clone();
+        let event = ObserverEvent {
+            program_id: context.program_id(),
+            call_depth: context.call_depth(),
+            loop_depth: context.loop_depth(),
+            node_index,
+            line_number,
+            node,
+            state,
+        };
+        let mut observer_mut = observer.borrow_mut();
+        if is_before {
+            observer_mut.before_eval(&event)
+        } else {
+            observer_mut.after_eval(&event)
+        }
+    }
+
     pub fn update_call(&mut self, program_manager: &mut ProgramRunnerManager) {
         for node in &mut self.node_vec {
             node.update_call(program_manager);
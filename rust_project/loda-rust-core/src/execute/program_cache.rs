@@ -1,3 +1,4 @@
+use super::PersistentProgramCache;
 use num_bigint::BigInt;
 use std::num::NonZeroUsize;
 use cached::{SizedCache, Cached};
@@ -7,6 +8,7 @@ const DEFAULT_CACHE_CAPACITY: usize = 1500;
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 struct CacheKey {
     program_id: u64,
+    content_hash: Option<u64>,
     index: BigInt,
 }
 
@@ -18,6 +20,7 @@ pub struct CacheValue {
 
 pub struct ProgramCache {
     cache: SizedCache<CacheKey, CacheValue>,
+    persistent_cache: Option<PersistentProgramCache>,
     metric_hit: u64,
     metric_miss_for_program_oeis: u64,
     metric_miss_for_program_without_id: u64,
@@ -33,12 +36,22 @@ impl ProgramCache {
         let cache: SizedCache<CacheKey, CacheValue> = SizedCache::with_size(capacity.get());
         Self {
             cache: cache,
+            persistent_cache: None,
             metric_hit: 0,
             metric_miss_for_program_oeis: 0,
             metric_miss_for_program_without_id: 0,
         }
     }
 
+    /// Lookup terms on disk, for the terms that are not in memory.
+    pub fn set_persistent_cache(&mut self, persistent_cache: PersistentProgramCache) {
+        self.persistent_cache = Some(persistent_cache);
+    }
+
+    pub fn has_persistent_cache(&self) -> bool {
+        self.persistent_cache.is_some()
+    }
+
     pub fn register_cache_hit(&mut self) {
         self.metric_hit += 1;
     }
@@ -73,22 +86,43 @@ impl ProgramCache {
         format!("hit:{} miss:{},{}", self.metric_hit, self.metric_miss_for_program_oeis, self.metric_miss_for_program_without_id)
     }
 
-    pub fn get(&mut self, program_id: u64, index: &BigInt) -> Option<&CacheValue> {
+    /// Lookup a computed term in memory, and then in the persistent cache.
+    /// 
+    /// The `content_hash` identifies the program content and the content of its dependencies,
+    /// so terms computed by an outdated program are not returned.
+    /// The persistent cache is only used when there is a `content_hash`.
+    pub fn get(&mut self, program_id: u64, content_hash: Option<u64>, index: &BigInt) -> Option<CacheValue> {
         let key = CacheKey {
             program_id: program_id,
+            content_hash,
             index: index.clone(),
         };
-        self.cache.cache_get(&key)
+        if let Some(value) = self.cache.cache_get(&key) {
+            return Some(value.clone());
+        }
+        let content_hash: u64 = content_hash?;
+        let value: CacheValue = self.persistent_cache.as_mut()?.get(program_id, content_hash, index)?;
+        self.cache.cache_set(key, value.clone());
+        Some(value)
     }
 
-    pub fn set(&mut self, program_id: u64, index: BigInt, value: BigInt, step_count: u64) {
+    /// Save a computed term in memory, and in the persistent cache.
+    pub fn set(&mut self, program_id: u64, content_hash: Option<u64>, index: BigInt, value: BigInt, step_count: u64) {
+        if let (Some(persistent_cache), Some(content_hash)) = (self.persistent_cache.as_mut(), content_hash) {
+            let result = persistent_cache.set(program_id, content_hash, index.clone(), value.clone(), step_count);
+            if let Err(error) = result {
+                // The computed value is still usable, even though it cannot be saved.
+                error!("Unable to save term to persistent cache. program_id: {} error: {:?}", program_id, error);
+            }
+        }
         let key = CacheKey {
-            program_id: program_id,
-            index: index,
+            program_id,
+            content_hash,
+            index,
         };
         let value = CacheValue {
-            value: value,
-            step_count: step_count,
+            value,
+            step_count,
         };
         self.cache.cache_set(key, value);
     }
@@ -105,21 +139,35 @@ mod tests {
         // Arrange
         let capacity = NonZeroUsize::new(2).unwrap();
         let mut cache = ProgramCache::with_capacity(capacity);
-        assert_eq!(cache.get(40, &0u8.to_bigint().unwrap()), None, "initially the cache is empty");
-        assert_eq!(cache.get(40, &1u8.to_bigint().unwrap()), None, "initially the cache is empty");
-        assert_eq!(cache.get(40, &2u8.to_bigint().unwrap()), None, "initially the cache is empty");
-        cache.set(40, 0u8.to_bigint().unwrap(), 2u8.to_bigint().unwrap(), 1);
-        cache.set(40, 1u8.to_bigint().unwrap(), 3u8.to_bigint().unwrap(), 1);
-        assert_ne!(cache.get(40, &0u8.to_bigint().unwrap()), None, "has data");
-        assert_ne!(cache.get(40, &1u8.to_bigint().unwrap()), None, "has data");
-        assert_eq!(cache.get(40, &2u8.to_bigint().unwrap()), None, "empty");
+        assert_eq!(cache.get(40, None, &0u8.to_bigint().unwrap()), None, "initially the cache is empty");
+        assert_eq!(cache.get(40, None, &1u8.to_bigint().unwrap()), None, "initially the cache is empty");
+        assert_eq!(cache.get(40, None, &2u8.to_bigint().unwrap()), None, "initially the cache is empty");
+        cache.set(40, None, 0u8.to_bigint().unwrap(), 2u8.to_bigint().unwrap(), 1);
+        cache.set(40, None, 1u8.to_bigint().unwrap(), 3u8.to_bigint().unwrap(), 1);
+        assert_ne!(cache.get(40, None, &0u8.to_bigint().unwrap()), None, "has data");
+        assert_ne!(cache.get(40, None, &1u8.to_bigint().unwrap()), None, "has data");
+        assert_eq!(cache.get(40, None, &2u8.to_bigint().unwrap()), None, "empty");
+
+        // Act
+        cache.set(40, None, 2u8.to_bigint().unwrap(), 5u8.to_bigint().unwrap(), 1);
+
+        // Assert
+        assert_eq!(cache.get(40, None, &0u8.to_bigint().unwrap()), None, "empty, removed oldest data");
+        assert_ne!(cache.get(40, None, &1u8.to_bigint().unwrap()), None, "has data");
+        assert_ne!(cache.get(40, None, &2u8.to_bigint().unwrap()), None, "has data");
+    }
+
+    #[test]
+    fn test_10001_content_hash_is_part_of_key() {
+        // Arrange
+        let mut cache = ProgramCache::new();
 
         // Act
-        cache.set(40, 2u8.to_bigint().unwrap(), 5u8.to_bigint().unwrap(), 1);
+        cache.set(40, Some(1), BigInt::from(5), BigInt::from(11), 1);
 
         // Assert
-        assert_eq!(cache.get(40, &0u8.to_bigint().unwrap()), None, "empty, removed oldest data");
-        assert_ne!(cache.get(40, &1u8.to_bigint().unwrap()), None, "has data");
-        assert_ne!(cache.get(40, &2u8.to_bigint().unwrap()), None, "has data");
+        assert_ne!(cache.get(40, Some(1), &BigInt::from(5)), None, "has data");
+        assert_eq!(cache.get(40, Some(2), &BigInt::from(5)), None, "the program has changed");
+        assert_eq!(cache.get(40, None, &BigInt::from(5)), None, "unknown program content");
     }
 }
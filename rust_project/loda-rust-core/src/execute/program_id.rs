@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ProgramId {
     ProgramWithoutId,
     ProgramOEIS(u64),
@@ -0,0 +1,92 @@
+use super::{Node, ProgramId, ProgramState};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Information about the node that is about to be evaluated, or that has just been evaluated.
+pub struct ObserverEvent<'a> {
+    /// The program being executed. Programs invoked via `seq` have their OEIS id here.
+    pub program_id: ProgramId,
+
+    /// Number of nested `seq` calls. The root program has depth 0.
+    pub call_depth: usize,
+
+    /// Number of nested loops inside the current program. Outside any loop the depth is 0.
+    pub loop_depth: usize,
+
+    /// Position of the node inside its enclosing `Program`.
+    pub node_index: usize,
+
+    /// Line number in the source code, or 0 when the program wasn't created from source code.
+    pub line_number: usize,
+
+    pub node: &'a dyn Node,
+    pub state: &'a ProgramState,
+}
+
+/// What to do after an observer has been invoked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObserverAction {
+    Continue,
+
+    /// Stop executing the program, `run` returns `EvalError::AbortedByObserver`.
+    Abort,
+}
+
+/// Hook that gets invoked by `Program::run` before and after each `Node::eval`.
+///
+/// Used for building debuggers and tracers, without parsing the output of `RunMode::Verbose`.
+pub trait ProgramObserver {
+    fn before_eval(&mut self, event: &ObserverEvent) -> ObserverAction;
+
+    fn after_eval(&mut self, _event: &ObserverEvent) -> ObserverAction {
+        ObserverAction::Continue
+    }
+}
+
+pub type ProgramObserverRef = Rc<RefCell<dyn ProgramObserver>>;
+
+/// Keeps track of where in the program the execution is, while an observer is attached.
+#[derive(Clone)]
+pub struct ObserverContext {
+    observer: ProgramObserverRef,
+    program_id: ProgramId,
+    call_depth: usize,
+    loop_depth: usize,
+}
+
+impl ObserverContext {
+    pub fn new(observer: ProgramObserverRef, program_id: ProgramId, call_depth: usize) -> Self {
+        Self {
+            observer,
+            program_id,
+            call_depth,
+            loop_depth: 0,
+        }
+    }
+
+    pub fn observer(&self) -> &ProgramObserverRef {
+        &self.observer
+    }
+
+    pub fn program_id(&self) -> ProgramId {
+        self.program_id
+    }
+
+    pub fn call_depth(&self) -> usize {
+        self.call_depth
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    /// Nodes with a child program, such as loops, evaluate their children one level deeper.
+    pub fn enter_node(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    pub fn leave_node(&mut self) {
+        assert!(self.loop_depth > 0);
+        self.loop_depth -= 1;
+    }
+}
@@ -1,4 +1,4 @@
-use super::{NodeLoopLimit, ProgramCache, Program, ProgramId, ProgramSerializer, ProgramState, RegisterValue, RunMode};
+use super::{BytecodeProgram, ExecutionEngine, NodeLoopLimit, ObserverContext, ProgramCache, Program, ProgramId, ProgramObserverRef, ProgramSerializer, ProgramState, RegisterValue, RunMode};
 use super::NodeRegisterLimit;
 use anyhow::Context;
 use num_bigint::BigInt;
@@ -7,6 +7,8 @@ use std::fmt;
 pub struct ProgramRunner {
     program_id: ProgramId,
     program: Program,
+    bytecode: Option<BytecodeProgram>,
+    content_hash: Option<u64>,
 }
 
 impl ProgramRunner {
@@ -14,13 +16,67 @@ impl ProgramRunner {
         Self {
             program_id: program_id,
             program: program,
+            bytecode: None,
+            content_hash: None,
         }
     }
 
+    /// Identifies the program content and the content of its dependencies, so cached terms 
+    /// are discarded when the program or one of its dependencies changes.
+    /// 
+    /// Without a content hash, the terms are not saved to the persistent cache.
+    pub fn set_content_hash(&mut self, content_hash: u64) {
+        self.content_hash = Some(content_hash);
+    }
+
+    pub fn content_hash(&self) -> Option<u64> {
+        self.content_hash
+    }
+
+    /// Choose how `run` evaluates the program.
+    /// 
+    /// With `ExecutionEngine::Bytecode` the program is compiled. If the program 
+    /// contains nodes that cannot be compiled, then it stays with `ExecutionEngine::Tree`.
+    pub fn set_execution_engine(&mut self, execution_engine: ExecutionEngine) {
+        self.bytecode = None;
+        if execution_engine == ExecutionEngine::Bytecode {
+            match BytecodeProgram::compile(&self.program) {
+                Ok(value) => {
+                    self.bytecode = Some(value);
+                },
+                Err(error) => {
+                    debug!("ProgramRunner {:?} falls back to ExecutionEngine::Tree. {}", self.program_id, error);
+                }
+            }
+        }
+    }
+
+    /// The engine that is used for evaluating the program.
+    pub fn execution_engine(&self) -> ExecutionEngine {
+        match self.bytecode {
+            Some(_) => ExecutionEngine::Bytecode,
+            None => ExecutionEngine::Tree
+        }
+    }
+
+    fn run_program(&self, state: &mut ProgramState, cache: &mut ProgramCache) -> anyhow::Result<()> {
+        // The bytecode has no support for printing the verbose output or for notifying an observer.
+        if let Some(bytecode) = &self.bytecode {
+            if state.run_mode() == RunMode::Silent && state.observer_context().is_none() {
+                return bytecode.run(state, cache);
+            }
+        }
+        self.program.run(state, cache)
+    }
+
     pub fn program(&self) -> &Program {
         &self.program
     }
 
+    pub fn program_id(&self) -> ProgramId {
+        self.program_id
+    }
+
     pub fn run(
         &self, 
         input: RegisterValue,
@@ -44,6 +100,31 @@ impl ProgramRunner {
         )
     }
 
+    /// Same as `run`, but with a `ProgramObserver` that gets invoked before and after each instruction.
+    /// 
+    /// The observer is also invoked for the instructions inside programs called via `seq`.
+    /// 
+    /// There is no limit on the registers and loops, the same as when running `eval`.
+    pub fn run_observed(
+        &self, 
+        input: RegisterValue,
+        step_count: &mut u64, 
+        step_count_limit: u64,
+        cache: &mut ProgramCache,
+        observer: ProgramObserverRef,
+    ) -> anyhow::Result<RegisterValue> {
+        let value_adjusted: BigInt = input.0 + self.program.offset().unwrap_or(0);
+        let input_adjusted = RegisterValue(value_adjusted);
+        let mut state = ProgramState::new(
+            RunMode::Silent, 
+            step_count_limit, 
+            NodeRegisterLimit::Unlimited,
+            NodeLoopLimit::Unlimited,
+        );
+        state.set_observer_context(Some(ObserverContext::new(observer, self.program_id, 0)));
+        self.run_without_offset_with_state(input_adjusted, state, step_count, cache)
+    }
+
     pub fn run_without_offset(
         &self, 
         input: RegisterValue,
@@ -53,13 +134,36 @@ impl ProgramRunner {
         node_register_limit: NodeRegisterLimit, 
         node_loop_limit: NodeLoopLimit,
         cache: &mut ProgramCache
+    ) -> anyhow::Result<RegisterValue> {
+        let state = ProgramState::new(
+            run_mode, 
+            step_count_limit, 
+            node_register_limit,
+            node_loop_limit,
+        );
+        self.run_without_offset_with_state(input, state, step_count, cache)
+    }
+
+    /// Same as `run_without_offset`, but the caller provides the initial `ProgramState` with the limits,
+    /// and optionally an `ObserverContext`.
+    pub fn run_without_offset_with_state(
+        &self, 
+        input: RegisterValue,
+        mut state: ProgramState,
+        step_count: &mut u64, 
+        cache: &mut ProgramCache,
     ) -> anyhow::Result<RegisterValue> {
         let step_count_before: u64 = *step_count;
 
         // Lookup (programid+input) in cache
         // No need to compute anything if it has been computed recently
+        // When an observer is attached, then the program is executed, so the observer can step into it.
         if let ProgramId::ProgramOEIS(program_oeis) = self.program_id {
-            if let Some(cache_value) = cache.get(program_oeis, &(input.0)) {
+            let cached_value = match state.observer_context() {
+                Some(_) => None,
+                None => cache.get(program_oeis, self.content_hash, &(input.0))
+            };
+            if let Some(cache_value) = cached_value {
                 let value = RegisterValue(cache_value.value.clone());
                 *step_count = step_count_before + cache_value.step_count;
                 cache.register_cache_hit();
@@ -68,17 +172,11 @@ impl ProgramRunner {
         }
 
         // Initial state
-        let mut state = ProgramState::new(
-            run_mode, 
-            step_count_limit, 
-            node_register_limit,
-            node_loop_limit,
-        );
         state.set_step_count(step_count_before);
         state.set_input_value(&input);
 
         // Invoke the actual run() function
-        let run_result = self.program.run(&mut state, cache);
+        let run_result = self.run_program(&mut state, cache);
 
         // Update statistics, no matter if run succeeded or failed
         let step_count_after: u64 = state.step_count();
@@ -100,7 +198,7 @@ impl ProgramRunner {
                 let computed_step_count: u64 = step_count_after - step_count_before;
 
                 // Cache the computed value.
-                cache.set(program_oeis, input.0, output.0.clone(), computed_step_count);
+                cache.set(program_oeis, self.content_hash, input.0, output.0.clone(), computed_step_count);
                 cache.register_cache_miss_for_program_oeis();
             },
             ProgramId::ProgramWithoutId => {
@@ -144,7 +242,7 @@ impl ProgramRunner {
         }
 
         // Invoke the actual run() function
-        let run_result = self.program.run(&mut state, cache);
+        let run_result = self.run_program(&mut state, cache);
 
         // Update statistics, no matter if run succeeded or failed
         let step_count_after: u64 = state.step_count();
@@ -156,7 +254,7 @@ impl ProgramRunner {
         // Output vector
         let mut output_vec = Vec::<BigInt>::with_capacity(output_count as usize);
         for index in 0..output_count {
-            let value: BigInt = state.get_u64(index as u64).clone();
+            let value: BigInt = state.get_u64(index as u64).into_owned();
             output_vec.push(value);
         }
 
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use super::ProgramRunner;
 
 pub struct ProgramRunnerManager {
-    registered_program_runners: HashMap<u64, Rc::<ProgramRunner>>
+    registered_program_runners: HashMap<u64, Arc::<ProgramRunner>>
 }
 
 impl ProgramRunnerManager {
@@ -15,18 +15,14 @@ impl ProgramRunnerManager {
 
     pub fn register(&mut self, program_id: u64, program_runner: ProgramRunner) {
         // TODO: abort if the program is already registered
-        let program_runner_rc = Rc::new(program_runner);
-        self.registered_program_runners.insert(program_id, program_runner_rc);
+        let program_runner_arc = Arc::new(program_runner);
+        self.registered_program_runners.insert(program_id, program_runner_arc);
     }
 
-    pub fn get(&mut self, program_id: u64) -> Option<Rc::<ProgramRunner>> {
+    pub fn get(&self, program_id: u64) -> Option<Arc::<ProgramRunner>> {
         match self.registered_program_runners.get(&program_id) {
-            Some(value) => {
-                return Some(Rc::clone(value));
-            },
-            None => {
-                return None;
-            }
+            Some(value) => Some(Arc::clone(value)),
+            None => None
         }
     }
 
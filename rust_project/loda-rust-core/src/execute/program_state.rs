@@ -1,17 +1,19 @@
 use super::{EvalError, NodeLoopLimit, RegisterIndex, RegisterValue, RunMode};
 use super::NodeRegisterLimit;
 use super::BoxCheckValue;
+use super::ObserverContext;
+use super::{RegisterCell, RegisterFile};
+use super::register_file::REGISTER_CELL_ZERO;
 use crate::parser::{InstructionParameter, ParameterType};
 use num_bigint::{BigInt, ToBigInt};
-use num_traits::{Signed, ToPrimitive, Zero};
+use num_traits::{ToPrimitive, Zero};
+use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::iter::FromIterator;
-use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashSet};
+use std::time::Instant;
 
-lazy_static! {
-    static ref BIGINT_ZERO: BigInt = BigInt::zero();
-}
+/// The deadline is checked every time the step count passes a multiple of this, since reading the clock is slow.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
 
 /// The register 0 is for input data.
 const INPUT_REGISTER: u64 = 0;
@@ -22,16 +24,30 @@ const OUTPUT_REGISTER: u64 = 0;
 
 const MAX_NUMBER_OF_REGISTERS: u64 = 10000;
 
+/// The value a register had before it was modified inside a loop cycle.
+#[derive(Clone)]
+struct JournalEntry {
+    address: u64,
+    previous_value: Option<RegisterCell>,
+}
 
 #[derive(Clone)]
 pub struct ProgramState {
-    memory_full: HashMap<u64, BigInt>,
+    memory_full: RegisterFile,
     step_count: u64,
     run_mode: RunMode,
     step_count_limit: u64,
     node_register_limit: NodeRegisterLimit,
     node_loop_limit: NodeLoopLimit,
     check_value: BoxCheckValue,
+    observer_context: Option<ObserverContext>,
+    deadline: Option<Instant>,
+
+    /// Undo log of register writes, only recorded while a loop cycle is running.
+    journal: Vec<JournalEntry>,
+
+    /// For each nested loop cycle, the length of the journal when the cycle began.
+    checkpoint_vec: Vec<usize>,
 }
 
 impl ProgramState {
@@ -44,13 +60,17 @@ impl ProgramState {
         let check_value: BoxCheckValue = node_register_limit.create_boxed_check_value();
 
         Self {
-            memory_full: HashMap::new(),
+            memory_full: RegisterFile::new(),
             step_count: 0,
             run_mode: run_mode,
             step_count_limit: step_count_limit,
             node_register_limit: node_register_limit,
             node_loop_limit: node_loop_limit,
             check_value: check_value,
+            observer_context: None,
+            deadline: None,
+            journal: vec!(),
+            checkpoint_vec: vec!(),
         }
     }
 
@@ -70,21 +90,47 @@ impl ProgramState {
         self.run_mode
     }
 
-    pub fn get_u64(&self, address: u64) -> &BigInt {
-        match self.memory_full.get(&address) {
-            Some(value) => { return value; },
-            None => { return &BIGINT_ZERO; }
+    pub fn observer_context(&self) -> Option<&ObserverContext> {
+        self.observer_context.as_ref()
+    }
+
+    pub fn observer_context_mut(&mut self) -> Option<&mut ObserverContext> {
+        self.observer_context.as_mut()
+    }
+
+    pub fn set_observer_context(&mut self, observer_context: Option<ObserverContext>) {
+        self.observer_context = observer_context;
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Abort with `EvalError::TimeLimitExceeded` when the deadline has passed, no matter how many steps are left.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Read a register without converting it to a `BigInt`.
+    pub fn get_cell(&self, address: u64) -> &RegisterCell {
+        match self.memory_full.get(address) {
+            Some(value) => value,
+            None => &REGISTER_CELL_ZERO
         }
     }
 
-    pub fn get_i64(&self, address: i64) -> Result<&BigInt, EvalError> {
+    pub fn get_u64(&self, address: u64) -> Cow<'_, BigInt> {
+        self.get_cell(address).as_bigint()
+    }
+
+    pub fn get_i64(&self, address: i64) -> Result<Cow<'_, BigInt>, EvalError> {
         if address < 0 {
             return Err(EvalError::CannotConvertI64ToAddress);
         }
         Ok(self.get_u64(address as u64))
     }
 
-    pub fn get_bigint(&self, address: &BigInt) -> Result<&BigInt, EvalError> {
+    pub fn get_bigint(&self, address: &BigInt) -> Result<Cow<'_, BigInt>, EvalError> {
         let address_u64: u64 = match address.to_u64() {
             Some(value) => value,
             None => {
@@ -100,6 +146,9 @@ impl ProgramState {
                 if get_address {
                     return Err(EvalError::CannotGetAddressOfConstant);
                 }
+                if let Some(value) = &parameter.parameter_big_value {
+                    return Ok(value.clone());
+                }
                 match parameter.parameter_value.to_bigint() {
                     Some(value) => { return Ok(value); },
                     None => { return Err(EvalError::CannotConvertParameterValueToBigInt); }
@@ -112,40 +161,94 @@ impl ProgramState {
                         None => { return Err(EvalError::CannotConvertParameterValueToBigInt); }
                     }
                 }
-                let inner_value: &BigInt = self.get_i64(parameter.parameter_value)?;
-                return Ok(inner_value.clone());
+                let inner_value: Cow<BigInt> = self.get_i64(parameter.parameter_value)?;
+                Ok(inner_value.into_owned())
             },
             ParameterType::Indirect => {
-                let inner_value: &BigInt = self.get_i64(parameter.parameter_value)?;
+                let inner_value: Cow<BigInt> = self.get_i64(parameter.parameter_value)?;
                 if get_address {
-                    return Ok(inner_value.clone());
+                    return Ok(inner_value.into_owned());
+                }
+                let inner_value2: Cow<BigInt> = self.get_bigint(&inner_value)?;
+                Ok(inner_value2.into_owned())
+            }
+        }
+    }
+
+    /// Same as `get()` for a value, but without allocating a `BigInt`.
+    /// 
+    /// Returns `None` when the value doesn't fit inside an `i64`, or when `get()` would fail.
+    pub fn get_small(&self, parameter: &InstructionParameter) -> Option<i64> {
+        match parameter.parameter_type {
+            ParameterType::Constant => {
+                if parameter.is_big_constant() {
+                    return None;
+                }
+                Some(parameter.parameter_value)
+            },
+            ParameterType::Direct => {
+                if parameter.parameter_value < 0 {
+                    return None;
                 }
-                let inner_value2: &BigInt = self.get_bigint(inner_value)?;
-                return Ok(inner_value2.clone());
+                self.get_cell(parameter.parameter_value as u64).to_i64()
+            },
+            ParameterType::Indirect => {
+                if parameter.parameter_value < 0 {
+                    return None;
+                }
+                let address: i64 = self.get_cell(parameter.parameter_value as u64).to_i64()?;
+                if address < 0 {
+                    return None;
+                }
+                self.get_cell(address as u64).to_i64()
             }
         }
     }
     
     pub fn set(&mut self, parameter: &InstructionParameter, set_value: BigInt) -> Result<(), EvalError> {
-        match parameter.parameter_type {
+        self.set_cell(parameter, RegisterCell::from_bigint(set_value))
+    }
+
+    /// Same as `set()`, but without allocating a `BigInt`.
+    pub fn set_small(&mut self, parameter: &InstructionParameter, set_value: i64) -> Result<(), EvalError> {
+        self.set_cell(parameter, RegisterCell::Small(set_value))
+    }
+
+    fn set_cell(&mut self, parameter: &InstructionParameter, set_value: RegisterCell) -> Result<(), EvalError> {
+        let address: u64 = match parameter.parameter_type {
             ParameterType::Constant => {
                 return Err(EvalError::CannotSetValueOfConstant);
             },
             ParameterType::Direct => {
-                self.set_i64(parameter.parameter_value, set_value)?;
-                return Ok(());
+                if parameter.parameter_value < 0 {
+                    return Err(EvalError::AddressWithNegativeValue);
+                }
+                parameter.parameter_value as u64
             },
             ParameterType::Indirect => {
-                let address_ref: &BigInt = self.get_i64(parameter.parameter_value)?;
-                let address: BigInt = address_ref.clone();
-                self.set_bigint(&address, set_value)?;
-                return Ok(());
+                if parameter.parameter_value < 0 {
+                    return Err(EvalError::CannotConvertI64ToAddress);
+                }
+                match self.get_cell(parameter.parameter_value as u64) {
+                    RegisterCell::Small(value) if *value >= 0 => *value as u64,
+                    cell => {
+                        match cell.as_bigint().to_u64() {
+                            Some(value) => value,
+                            None => {
+                                return Err(EvalError::CannotConvertBigIntToAddress);
+                            }
+                        }
+                    }
+                }
             }
-        }
+        };
+        self.set_u64_cell(address, set_value)
     }
 
-    /// Read the value of register 0, the output reference to its `BigInt`.
-    pub fn get_output_value_bigint(&self) -> &BigInt {
+    /// Read the value of register 0, the output register.
+    /// 
+    /// Values that fits inside an `i64` are borrowed as a temporary `BigInt`.
+    pub fn get_output_value_bigint(&self) -> Cow<'_, BigInt> {
         self.get_u64(OUTPUT_REGISTER)
     }
     
@@ -155,8 +258,8 @@ impl ProgramState {
     /// If you are going to discard the `ProgramState` immediately after getting the output,
     /// then consider using the `remove_output_value` function, that does no `BigInt.clone()`.
     pub fn get_output_value(&self) -> RegisterValue {
-        let output_value: &BigInt = self.get_output_value_bigint();
-        RegisterValue(output_value.clone())
+        let output_value: Cow<BigInt> = self.get_output_value_bigint();
+        RegisterValue(output_value.into_owned())
     }
 
     /// Take ownership of the content in register `$0`, and removes it from the internal `memory_full`.
@@ -167,13 +270,9 @@ impl ProgramState {
     /// Why use `remove_output_value()`?
     /// This eliminates one `BigInt.clone()` operation.
     pub fn remove_output_value(&mut self) -> RegisterValue {
-        match self.memory_full.remove(&OUTPUT_REGISTER) {
-            Some(value) => { 
-                return RegisterValue(value); 
-            },
-            None => { 
-                return RegisterValue(BIGINT_ZERO.clone()); 
-            }
+        match self.memory_remove(OUTPUT_REGISTER) {
+            Some(value) => RegisterValue(value.into_bigint()),
+            None => RegisterValue(BigInt::zero())
         }
     }
 
@@ -195,16 +294,20 @@ impl ProgramState {
     }
 
     pub fn set_u64(&mut self, address: u64, value: BigInt) -> Result<(), EvalError> {
+        self.set_u64_cell(address, RegisterCell::from_bigint(value))
+    }
+
+    pub fn set_u64_cell(&mut self, address: u64, value: RegisterCell) -> Result<(), EvalError> {
         if address >= MAX_NUMBER_OF_REGISTERS {
             return Err(EvalError::AddressIsOutsideMaxCapacity);
         }
-        self.memory_full.insert(address, value);
+        self.memory_insert(address, value);
         Ok(())
     }
 
     /// Write a value to register 0, the input register.
     pub fn set_input_value(&mut self, register_value: &RegisterValue) {
-        self.memory_full.insert(INPUT_REGISTER, register_value.0.clone());
+        self.memory_insert(INPUT_REGISTER, RegisterCell::from_bigint(register_value.0.clone()));
     }
    
     pub fn set_register_range_to_zero(&mut self, register_index: RegisterIndex, count: u64) -> Result<(), EvalError> {
@@ -214,34 +317,138 @@ impl ProgramState {
             return Err(EvalError::AddressIsOutsideMaxCapacity);
         }
         for _ in 0..count {
-            self.memory_full.remove(&index);
+            self.memory_remove(index);
             index += 1;
         }
         Ok(())
     }
 
-    /// Make the internal state human readable
-    pub fn memory_full_to_string(&self) -> String {
-        let key_refs: Vec<&u64> = Vec::from_iter(self.memory_full.keys());
-        let mut keys: Vec<u64> = key_refs.iter().map(|&key| *key).collect();
-        keys.sort();
+    fn is_journal_enabled(&self) -> bool {
+        !self.checkpoint_vec.is_empty()
+    }
+
+    fn memory_insert(&mut self, address: u64, value: RegisterCell) {
+        let previous_value: Option<RegisterCell> = self.memory_full.insert(address, value);
+        if self.is_journal_enabled() {
+            self.journal.push(JournalEntry { address, previous_value });
+        }
+    }
+
+    fn memory_remove(&mut self, address: u64) -> Option<RegisterCell> {
+        let previous_value: Option<RegisterCell> = self.memory_full.remove(address);
+        if self.is_journal_enabled() {
+            self.journal.push(JournalEntry { address, previous_value: previous_value.clone() });
+        }
+        previous_value
+    }
+
+    /// Begin a loop cycle. All register writes after this point can be undone with `checkpoint_rollback()`.
+    /// 
+    /// This replaces cloning the entire `ProgramState` for every loop cycle,
+    /// so the cost of a loop cycle depends on the registers it modifies, not on the number of registers in use.
+    /// 
+    /// Checkpoints can be nested, for nested loops.
+    pub fn checkpoint_begin(&mut self) {
+        self.checkpoint_vec.push(self.journal.len());
+    }
+
+    /// End the loop cycle and keep the modified registers.
+    /// 
+    /// The journal entries are kept, in case an outer loop cycle needs to roll back.
+    /// The outer loop cycle only needs the first write to each register, so the rest are discarded.
+    /// This way the journal doesn't grow with the number of inner loop cycles.
+    pub fn checkpoint_commit(&mut self) {
+        self.checkpoint_vec.pop();
+        let journal_length: usize = match self.checkpoint_vec.last() {
+            Some(value) => *value,
+            None => {
+                self.journal.clear();
+                return;
+            }
+        };
+        self.compact_journal(journal_length);
+    }
+
+    /// Keep only the first journal entry for each register, after `journal_length`.
+    fn compact_journal(&mut self, journal_length: usize) {
+        if self.journal.len() <= journal_length + 1 {
+            return;
+        }
+        let mut seen_addresses = HashSet::<u64>::new();
+        let mut entries: Vec<JournalEntry> = self.journal.split_off(journal_length);
+        entries.retain(|entry| seen_addresses.insert(entry.address));
+        self.journal.append(&mut entries);
+    }
 
-        let mut strings = Vec::<String>::new();
-        for key in keys {
-            match self.memory_full.get(&key) {
+    /// End the loop cycle and restore the registers to their values when the cycle began.
+    /// 
+    /// The `step_count` is not restored.
+    pub fn checkpoint_rollback(&mut self) {
+        let journal_length: usize = match self.checkpoint_vec.pop() {
+            Some(value) => value,
+            None => {
+                return;
+            }
+        };
+        while self.journal.len() > journal_length {
+            let entry: JournalEntry = match self.journal.pop() {
+                Some(value) => value,
+                None => break
+            };
+            match entry.previous_value {
                 Some(value) => {
-                    strings.push(format!("{}:{}", key, value));
+                    self.memory_full.insert(entry.address, value);
                 },
                 None => {
-                    strings.push(format!("{}:N/A", key));
+                    self.memory_full.remove(entry.address);
                 }
             }
         }
+    }
+
+    /// The value that a register had when the current loop cycle began.
+    pub fn get_u64_at_checkpoint(&self, address: u64) -> Cow<'_, BigInt> {
+        self.get_cell_at_checkpoint(address).as_bigint()
+    }
+
+    fn get_cell_at_checkpoint(&self, address: u64) -> &RegisterCell {
+        let journal_length: usize = match self.checkpoint_vec.last() {
+            Some(value) => *value,
+            None => {
+                return self.get_cell(address);
+            }
+        };
+        // The first write to the register, after the checkpoint, holds the value at the checkpoint.
+        for entry in &self.journal[journal_length..] {
+            if entry.address != address {
+                continue;
+            }
+            match &entry.previous_value {
+                Some(value) => { return value; },
+                None => { return &REGISTER_CELL_ZERO; }
+            }
+        }
+        self.get_cell(address)
+    }
 
+    /// Make the internal state human readable
+    pub fn memory_full_to_string(&self) -> String {
+        let strings: Vec<String> = self.memory_full.sorted_items().iter()
+            .map(|(key, value)| format!("{}:{}", key, value))
+            .collect();
         let strings_joined: String = strings.join(",");
         format!("[{}]", strings_joined)
     }
 
+    /// Copy of the registers that have been assigned a value, sorted by register index.
+    pub fn memory_full_snapshot(&self) -> BTreeMap<u64, BigInt> {
+        let mut result = BTreeMap::<u64, BigInt>::new();
+        for (key, value) in self.memory_full.sorted_items() {
+            result.insert(key, value.as_bigint().into_owned());
+        }
+        result
+    }
+
     /// Compare a range of registers.
     /// 
     /// Returns `true` if the range of registers have a lower value.
@@ -251,13 +458,37 @@ impl ProgramState {
     /// Returns `false` if a register is encountered with a negative value.
     pub fn is_less_twostartindexes_range(&self, other_state: &ProgramState, start_index0: u64, start_index1: u64, range_length: u64) -> bool {
         for i in 0..range_length {
-            let a_value: &BigInt = self.get_u64(start_index0 + i);
+            let a_value: &RegisterCell = self.get_cell(start_index0 + i);
             if a_value.is_negative() {
                 // Negative value encountered
                 return false;
             }
-            let b_value: &BigInt = other_state.get_u64(start_index1 + i);
-            let ordering: Ordering = a_value.cmp(&b_value);
+            let b_value: &RegisterCell = other_state.get_cell(start_index1 + i);
+            let ordering: Ordering = a_value.cmp(b_value);
+            match ordering {
+                Ordering::Less => return true,
+                Ordering::Greater => return false,
+                Ordering::Equal => continue,
+            }
+        }
+        false
+    }
+
+    /// Same as `is_less_twostartindexes_range()`, but compares with the values 
+    /// that the registers had when the current loop cycle began.
+    /// 
+    /// The `start_index0` is the start of the range in the current state.
+    /// 
+    /// The `start_index1` is the start of the range at the checkpoint.
+    pub fn is_less_than_checkpoint_range(&self, start_index0: u64, start_index1: u64, range_length: u64) -> bool {
+        for i in 0..range_length {
+            let a_value: &RegisterCell = self.get_cell(start_index0 + i);
+            if a_value.is_negative() {
+                // Negative value encountered
+                return false;
+            }
+            let b_value: &RegisterCell = self.get_cell_at_checkpoint(start_index1 + i);
+            let ordering: Ordering = a_value.cmp(b_value);
             match ordering {
                 Ordering::Less => return true,
                 Ordering::Greater => return false,
@@ -289,12 +520,36 @@ impl ProgramState {
     }
 
     pub fn increment_step_count(&mut self) -> Result<(), EvalError> {
-        let count: u64 = self.step_count + 1;
+        let count_before: u64 = self.step_count;
+        let count: u64 = count_before + 1;
+        self.step_count = count;
+
+        if count >= self.step_count_limit {
+            return Err(EvalError::StepCountExceededLimit);
+        }
+        self.check_deadline(count_before, count)
+    }
+
+    /// Same as `increment_step_count`, for an instruction that counts as several steps.
+    pub fn increase_step_count(&mut self, increment: u64) -> Result<(), EvalError> {
+        let count_before: u64 = self.step_count;
+        let count: u64 = count_before.saturating_add(increment);
         self.step_count = count;
 
         if count >= self.step_count_limit {
             return Err(EvalError::StepCountExceededLimit);
         }
+        self.check_deadline(count_before, count)
+    }
+
+    #[inline]
+    fn check_deadline(&self, count_before: u64, count: u64) -> Result<(), EvalError> {
+        if let Some(deadline) = self.deadline {
+            let passed_interval: bool = (count_before / DEADLINE_CHECK_INTERVAL) != (count / DEADLINE_CHECK_INTERVAL);
+            if passed_interval && Instant::now() >= deadline {
+                return Err(EvalError::TimeLimitExceeded);
+            }
+        }
         Ok(())
     }
 
@@ -363,6 +618,15 @@ mod tests {
         assert_eq!(state.memory_full_to_string(), "[]")
     }
 
+    #[test]
+    fn test_10003_memory_full_snapshot() {
+        let state = mock_program_state();
+        let snapshot: BTreeMap<u64, BigInt> = state.memory_full_snapshot();
+        let keys: Vec<u64> = snapshot.keys().copied().collect();
+        assert_eq!(keys, vec![0, 1, 2, 3]);
+        assert_eq!(snapshot[&3], 103.to_bigint().unwrap());
+    }
+
     #[test]
     fn test_20001_set_register_range_to_zero() {
         {
@@ -644,6 +908,108 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_45000_checkpoint_rollback() {
+        // Arrange
+        let mut state = mock_program_state();
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 1, 50);
+        set_value_not_failable(&mut state, 1, 40);
+        set_value_not_failable(&mut state, 7, 7);
+        state.set_register_range_to_zero(RegisterIndex(2), 1).expect("should not fail");
+        state.increment_step_count().expect("should not fail");
+        assert_eq!(state.memory_full_to_string(), "[0:100,1:40,3:103,7:7]");
+
+        // Act
+        state.checkpoint_rollback();
+
+        // Assert
+        assert_eq!(state.memory_full_to_string(), "[0:100,1:101,2:102,3:103]");
+        assert_eq!(state.step_count(), 1);
+    }
+
+    #[test]
+    fn test_45001_checkpoint_commit() {
+        // Arrange
+        let mut state = mock_program_state();
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 1, 50);
+
+        // Act
+        state.checkpoint_commit();
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 1, 40);
+        state.checkpoint_rollback();
+
+        // Assert
+        assert_eq!(state.memory_full_to_string(), "[0:100,1:50,2:102,3:103]");
+    }
+
+    #[test]
+    fn test_45002_checkpoint_nested() {
+        // Arrange
+        let mut state = mock_program_state();
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 0, 99);
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 1, 50);
+        state.checkpoint_commit();
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 1, 40);
+        set_value_not_failable(&mut state, 2, 30);
+        assert_eq!(state.get_u64_at_checkpoint(1).to_i64(), Some(50));
+
+        // Act
+        state.checkpoint_rollback();
+        assert_eq!(state.memory_full_to_string(), "[0:99,1:50,2:102,3:103]");
+        assert_eq!(state.get_u64_at_checkpoint(1).to_i64(), Some(101));
+        state.checkpoint_rollback();
+
+        // Assert
+        assert_eq!(state.memory_full_to_string(), "[0:100,1:101,2:102,3:103]");
+    }
+
+    #[test]
+    fn test_45003_checkpoint_nested_commit_compacts_journal() {
+        // Arrange
+        let mut state = mock_program_state();
+        state.checkpoint_begin();
+        set_value_not_failable(&mut state, 0, 99);
+
+        // Act
+        for i in 0..100 {
+            state.checkpoint_begin();
+            set_value_not_failable(&mut state, 1, i);
+            set_value_not_failable(&mut state, 2, i);
+            set_value_not_failable(&mut state, 1, i + 1);
+            state.checkpoint_commit();
+        }
+
+        // Assert
+        assert_eq!(state.journal.len(), 3);
+        assert_eq!(state.memory_full_to_string(), "[0:99,1:100,2:99,3:103]");
+        assert_eq!(state.get_u64_at_checkpoint(1).to_i64(), Some(101));
+        state.checkpoint_rollback();
+        assert_eq!(state.memory_full_to_string(), "[0:100,1:101,2:102,3:103]");
+    }
+
+    #[test]
+    fn test_45004_is_less_than_checkpoint_range() {
+        let mut state = mock_program_state();
+        state.checkpoint_begin();
+        assert_eq!(state.is_less_than_checkpoint_range(0, 0, 1), false);
+        set_value_not_failable(&mut state, 0, 99);
+        assert_eq!(state.is_less_than_checkpoint_range(0, 0, 1), true);
+        set_value_not_failable(&mut state, 0, -1);
+        assert_eq!(state.is_less_than_checkpoint_range(0, 0, 1), false);
+        set_value_not_failable(&mut state, 0, 100);
+        set_value_not_failable(&mut state, 10, 5);
+        assert_eq!(state.is_less_than_checkpoint_range(0, 0, 2), false);
+        set_value_not_failable(&mut state, 1, 100);
+        assert_eq!(state.is_less_than_checkpoint_range(0, 0, 2), true);
+        assert_eq!(state.is_less_than_checkpoint_range(10, 0, 1), true);
+    }
+
     #[test]
     fn test_50000_remove_output_value_some() {
         // Arrange
@@ -673,4 +1039,70 @@ mod tests {
         assert_eq!(state.memory_full_to_string(), "[1:100]");
         assert_eq!(output, RegisterValue(BigInt::zero()));
     }
+
+    #[test]
+    fn test_60000_get_small() {
+        let mut state = mock_program_state();
+        set_value_not_failable(&mut state, 5, -1);
+        state.set_u64(6, BigInt::from(u64::MAX)).expect("should not fail");
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Constant, -7)), Some(-7));
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Direct, 3)), Some(103));
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Direct, 4)), Some(0));
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Direct, 6)), None);
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Indirect, 5)), None);
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Indirect, 6)), None);
+        set_value_not_failable(&mut state, 5, 2);
+        assert_eq!(state.get_small(&InstructionParameter::new(ParameterType::Indirect, 5)), Some(102));
+    }
+
+    #[test]
+    fn test_60001_set_small() {
+        let mut state = mock_program_state();
+        state.set_small(&InstructionParameter::new(ParameterType::Direct, 1), 5).expect("should not fail");
+        set_value_not_failable(&mut state, 4, 2);
+        state.set_small(&InstructionParameter::new(ParameterType::Indirect, 4), 6).expect("should not fail");
+        assert_eq!(state.memory_full_to_string(), "[0:100,1:5,2:6,3:103,4:2]");
+        set_value_not_failable(&mut state, 4, -2);
+        let result = state.set_small(&InstructionParameter::new(ParameterType::Indirect, 4), 6);
+        assert!(matches!(result, Err(EvalError::CannotConvertBigIntToAddress)));
+    }
+
+    #[test]
+    fn test_60002_small_and_big_values() {
+        let mut state = empty_program_state();
+        let big: BigInt = BigInt::from(i64::MAX) + 1;
+        state.set_u64(1, big.clone()).expect("should not fail");
+        state.set_u64(2, big.clone() - 1).expect("should not fail");
+        assert_eq!(*state.get_u64(1), big);
+        assert_eq!(state.get_u64(2).to_i64(), Some(i64::MAX));
+        assert_eq!(state.is_less_range(&state, 1, 1), false);
+        assert_eq!(state.memory_full_to_string(), "[1:9223372036854775808,2:9223372036854775807]");
+    }
+
+    #[test]
+    fn test_70000_deadline() {
+        // Arrange
+        let mut state = ProgramState::new(
+            RunMode::Silent, 
+            u64::MAX, 
+            NodeRegisterLimit::Unlimited,
+            NodeLoopLimit::Unlimited,
+        );
+        state.set_deadline(Some(Instant::now()));
+
+        // Act
+        let mut result: Result<(), EvalError> = Ok(());
+        for _ in 0..DEADLINE_CHECK_INTERVAL {
+            result = state.increment_step_count();
+            if result.is_err() {
+                break;
+            }
+        }
+
+        // Assert
+        assert!(matches!(result, Err(EvalError::TimeLimitExceeded)));
+        assert_eq!(state.step_count(), DEADLINE_CHECK_INTERVAL);
+        state.set_deadline(None);
+        assert!(state.increase_step_count(DEADLINE_CHECK_INTERVAL).is_ok());
+    }
 }
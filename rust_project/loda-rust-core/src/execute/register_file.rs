@@ -0,0 +1,199 @@
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Registers below this address are stored in a `Vec`, the remaining registers in a `HashMap`.
+///
+/// Most programs only access the low registers directly,
+/// high registers are typically accessed via indirect addressing.
+const DENSE_REGISTER_COUNT: u64 = 32;
+
+/// The value of a register.
+///
+/// Values that fits inside an `i64` are stored inline, avoiding the heap allocation of a `BigInt`.
+/// A `Big` value is never within the `i64` range.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegisterCell {
+    Small(i64),
+    Big(BigInt),
+}
+
+pub static REGISTER_CELL_ZERO: RegisterCell = RegisterCell::Small(0);
+
+impl RegisterCell {
+    pub fn from_bigint(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value_i64) => RegisterCell::Small(value_i64),
+            None => RegisterCell::Big(value)
+        }
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            RegisterCell::Small(value) => Some(*value),
+            RegisterCell::Big(_) => None
+        }
+    }
+
+    pub fn as_bigint(&self) -> Cow<'_, BigInt> {
+        match self {
+            RegisterCell::Small(value) => Cow::Owned(BigInt::from(*value)),
+            RegisterCell::Big(value) => Cow::Borrowed(value)
+        }
+    }
+
+    pub fn into_bigint(self) -> BigInt {
+        match self {
+            RegisterCell::Small(value) => BigInt::from(value),
+            RegisterCell::Big(value) => value
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        match self {
+            RegisterCell::Small(value) => *value < 0,
+            RegisterCell::Big(value) => value.is_negative()
+        }
+    }
+}
+
+impl fmt::Display for RegisterCell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegisterCell::Small(value) => write!(f, "{}", value),
+            RegisterCell::Big(value) => write!(f, "{}", value)
+        }
+    }
+}
+
+impl Ord for RegisterCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (RegisterCell::Small(a), RegisterCell::Small(b)) => a.cmp(b),
+            _ => self.as_bigint().cmp(&other.as_bigint())
+        }
+    }
+}
+
+impl PartialOrd for RegisterCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Storage for the registers of a `ProgramState`.
+///
+/// Hybrid representation: a dense `Vec` for the low registers, and a sparse `HashMap` for the high registers.
+#[derive(Clone, Debug, Default)]
+pub struct RegisterFile {
+    dense: Vec<Option<RegisterCell>>,
+    sparse: HashMap<u64, RegisterCell>,
+}
+
+impl RegisterFile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, address: u64) -> Option<&RegisterCell> {
+        if address < DENSE_REGISTER_COUNT {
+            match self.dense.get(address as usize) {
+                Some(value) => return value.as_ref(),
+                None => return None
+            }
+        }
+        self.sparse.get(&address)
+    }
+
+    /// Assign a value to a register, and return the previous value.
+    pub fn insert(&mut self, address: u64, value: RegisterCell) -> Option<RegisterCell> {
+        if address < DENSE_REGISTER_COUNT {
+            let index = address as usize;
+            if index >= self.dense.len() {
+                self.dense.resize(index + 1, None);
+            }
+            return self.dense[index].replace(value);
+        }
+        self.sparse.insert(address, value)
+    }
+
+    /// Clear a register, and return the previous value.
+    pub fn remove(&mut self, address: u64) -> Option<RegisterCell> {
+        if address < DENSE_REGISTER_COUNT {
+            match self.dense.get_mut(address as usize) {
+                Some(value) => return value.take(),
+                None => return None
+            }
+        }
+        self.sparse.remove(&address)
+    }
+
+    /// The registers that have been assigned a value, sorted by address.
+    pub fn sorted_items(&self) -> Vec<(u64, &RegisterCell)> {
+        let mut items: Vec<(u64, &RegisterCell)> = vec!();
+        for (index, value) in self.dense.iter().enumerate() {
+            if let Some(value) = value {
+                items.push((index as u64, value));
+            }
+        }
+        let mut sparse_items: Vec<(u64, &RegisterCell)> = self.sparse.iter()
+            .map(|(key, value)| (*key, value))
+            .collect();
+        sparse_items.sort_by_key(|(key, _)| *key);
+        items.extend(sparse_items);
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_10000_register_cell_from_bigint() {
+        assert_eq!(RegisterCell::from_bigint(BigInt::from(-42)), RegisterCell::Small(-42));
+        assert_eq!(RegisterCell::from_bigint(BigInt::from(i64::MAX)), RegisterCell::Small(i64::MAX));
+        let big: BigInt = BigInt::from(i64::MAX) + 1;
+        assert_eq!(RegisterCell::from_bigint(big.clone()), RegisterCell::Big(big));
+    }
+
+    #[test]
+    fn test_10001_register_cell_ordering() {
+        let big = RegisterCell::from_bigint(BigInt::from(i64::MAX) * 2);
+        let big_negative = RegisterCell::from_bigint(BigInt::from(i64::MIN) * 2);
+        assert!(RegisterCell::Small(1) < RegisterCell::Small(2));
+        assert!(RegisterCell::Small(i64::MAX) < big);
+        assert!(big_negative < RegisterCell::Small(i64::MIN));
+        assert!(big_negative.is_negative());
+    }
+
+    #[test]
+    fn test_20000_register_file_insert_remove() {
+        let mut registers = RegisterFile::new();
+        assert_eq!(registers.insert(3, RegisterCell::Small(3)), None);
+        assert_eq!(registers.insert(5000, RegisterCell::Small(5000)), None);
+        assert_eq!(registers.insert(3, RegisterCell::Small(33)), Some(RegisterCell::Small(3)));
+        assert_eq!(registers.get(3), Some(&RegisterCell::Small(33)));
+        assert_eq!(registers.get(4), None);
+        assert_eq!(registers.get(31), None);
+        assert_eq!(registers.get(5000), Some(&RegisterCell::Small(5000)));
+        assert_eq!(registers.remove(5000), Some(RegisterCell::Small(5000)));
+        assert_eq!(registers.remove(5000), None);
+        assert_eq!(registers.remove(31), None);
+    }
+
+    #[test]
+    fn test_20001_register_file_sorted_items() {
+        let mut registers = RegisterFile::new();
+        registers.insert(200, RegisterCell::Small(2));
+        registers.insert(100, RegisterCell::Small(1));
+        registers.insert(7, RegisterCell::Small(7));
+        registers.insert(0, RegisterCell::Small(0));
+        registers.remove(7);
+        let addresses: Vec<u64> = registers.sorted_items().iter().map(|(key, _)| *key).collect();
+        assert_eq!(addresses, vec![0, 100, 200]);
+    }
+}
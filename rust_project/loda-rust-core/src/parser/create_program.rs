@@ -12,6 +12,7 @@ use crate::execute::compiletime_error::*;
 use crate::execute::NodeUnofficialFunction;
 use crate::execute::NodeUnofficialLoopSubtract;
 use crate::unofficial_function::{UnofficialFunction, UnofficialFunctionId, UnofficialFunctionRegistry};
+use num_traits::Signed;
 use std::sync::Arc;
 
 impl Instruction {
@@ -73,6 +74,31 @@ impl Instruction {
     }
 }
 
+/// The value of a constant, that must be non-negative and fit inside an `i64`.
+/// 
+/// The parameters of `seq`, `fNM` and `lpb` cannot be constants beyond 64bit.
+fn non_negative_small_constant(instruction: &Instruction, parameter: &InstructionParameter) -> Result<u64, CreateInstructionError> {
+    let is_negative: bool = match &parameter.parameter_big_value {
+        Some(value) => value.is_negative(),
+        None => parameter.parameter_value < 0
+    };
+    if is_negative {
+        let err = CreateInstructionError::new(
+            instruction.line_number,
+            CreateInstructionErrorType::ConstantMustBeNonNegative,
+        );
+        return Err(err);
+    }
+    if parameter.is_big_constant() {
+        let err = CreateInstructionError::new(
+            instruction.line_number,
+            CreateInstructionErrorType::ConstantIsTooHigh,
+        );
+        return Err(err);
+    }
+    Ok(parameter.parameter_value as u64)
+}
+
 fn create_node_seq(instruction: &Instruction) -> Result<BoxNode, CreateInstructionError> {
     instruction.expect_two_parameters()?;
 
@@ -91,14 +117,7 @@ fn create_node_seq(instruction: &Instruction) -> Result<BoxNode, CreateInstructi
         );
         return Err(err);
     }
-    if parameter1.parameter_value < 0 {
-        let err = CreateInstructionError::new(
-            instruction.line_number,
-            CreateInstructionErrorType::ConstantMustBeNonNegative,
-        );
-        return Err(err);
-    }
-    let program_id = parameter1.parameter_value as u64;
+    let program_id: u64 = non_negative_small_constant(instruction, parameter1)?;
 
     let node = NodeSeq::new(
         parameter0.clone(),
@@ -148,14 +167,7 @@ fn create_node_unofficial_function(
         );
         return Err(err);
     }
-    if parameter1.parameter_value < 0 {
-        let err = CreateInstructionError::new(
-            instruction.line_number,
-            CreateInstructionErrorType::ConstantMustBeNonNegative,
-        );
-        return Err(err);
-    }
-    let function_id_u64 = parameter1.parameter_value as u64;
+    let function_id_u64: u64 = non_negative_small_constant(instruction, parameter1)?;
     if function_id_u64 >= (u32::MAX as u64) {
         let err = CreateInstructionError::new(
             instruction.line_number,
@@ -216,14 +228,7 @@ enum LoopType {
 }
 
 fn node_loop_range_parameter_constant(instruction: &Instruction, parameter: &InstructionParameter) -> Result<LoopType, CreateInstructionError> {
-    if parameter.parameter_value < 0 {
-        let err = CreateInstructionError::new(
-            instruction.line_number,
-            CreateInstructionErrorType::ConstantMustBeNonNegative,
-        );
-        return Err(err);
-    }
-    let range_length: u64 = parameter.parameter_value as u64;
+    let range_length: u64 = non_negative_small_constant(instruction, parameter)?;
     if range_length >= (2 ^ LOOP_RANGE_MAX_BITS) {
         let err = CreateInstructionError::new(
             instruction.line_number,
@@ -266,6 +271,7 @@ fn node_loop_range_parameter(instruction: &Instruction, parameter: &InstructionP
 struct LoopScope {
     register: RegisterIndex,
     loop_type: LoopType,
+    line_number: usize,
 }
 
 fn process_loopbegin(instruction: &Instruction) -> Result<LoopScope, CreateInstructionError> {
@@ -279,6 +285,7 @@ fn process_loopbegin(instruction: &Instruction) -> Result<LoopScope, CreateInstr
         let ls = LoopScope {
             register: register_index0,
             loop_type: LoopType::Slow { instruction: instruction.clone() },
+            line_number: instruction.line_number,
         };
         return Ok(ls)
     }
@@ -301,6 +308,7 @@ fn process_loopbegin(instruction: &Instruction) -> Result<LoopScope, CreateInstr
     let ls = LoopScope {
         register: register_index0,
         loop_type: loop_type,
+        line_number: instruction.line_number,
     };
     Ok(ls)
 }
@@ -323,6 +331,7 @@ fn process_unofficial_loopbeginsubtract(instruction: &Instruction) -> Result<Loo
     let ls = LoopScope {
         register: register_index0,
         loop_type: LoopType::UnofficialLoopSubtract,
+        line_number: instruction.line_number,
     };
     Ok(ls)
 }
@@ -351,11 +360,13 @@ impl CreateProgram {
                     let loopscope: LoopScope = process_loopbegin(&instruction)?;
                     stack_vec.push((program, loopscope));
                     program = Program::new();
+                    continue;
                 },
                 InstructionId::UnofficialLoopBeginSubtract => {
                     let loopscope: LoopScope = process_unofficial_loopbeginsubtract(&instruction)?;
                     stack_vec.push((program, loopscope));
                     program = Program::new();
+                    continue;
                 },
                 InstructionId::LoopEnd => {
                     instruction.expect_zero_parameters()?;
@@ -369,6 +380,7 @@ impl CreateProgram {
                     let loopscope: LoopScope = stack_item.1;
     
                     let loop_register: RegisterIndex = loopscope.register;
+                    let loop_line_number: usize = loopscope.line_number;
                     let program_child: Program = program;
                     program = program_parent;
     
@@ -390,6 +402,8 @@ impl CreateProgram {
                             program.push(NodeUnofficialLoopSubtract::new(loop_register, program_child));
                         }
                     }
+                    program.assign_line_number_to_last_node(loop_line_number);
+                    continue;
                 },
                 InstructionId::Move => {
                     let node = self.create_node_calc(&instruction)?;
@@ -533,6 +547,7 @@ impl CreateProgram {
                     program.push_boxed(node);
                 }
             }
+            program.assign_line_number_to_last_node(instruction.line_number);
         }
     
         Ok(program)
@@ -0,0 +1,382 @@
+use super::{extract_offset, remove_comment, Instruction, ParsedProgram, ParseProgramError};
+use super::parse_program::parse_instruction_row;
+use std::fmt;
+
+/// An instruction row, with the formatting of the original source code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstInstruction {
+    pub instruction: Instruction,
+
+    /// Whitespace before the instruction, usually the loop indentation.
+    indentation: String,
+
+    /// The instruction exactly as it was written, such as `add  $0 , 1`.
+    original_code: String,
+
+    /// The instruction that `original_code` was parsed into.
+    original_instruction: Instruction,
+
+    /// Whitespace after the instruction and the trailing comment, such as ` ; fibonacci`.
+    trailing: String,
+}
+
+impl CstInstruction {
+    /// Create a new instruction without a comment.
+    pub fn new(instruction: Instruction, indentation: &str) -> Self {
+        Self {
+            original_code: instruction.to_string(),
+            original_instruction: instruction.clone(),
+            instruction,
+            indentation: indentation.to_string(),
+            trailing: String::new(),
+        }
+    }
+
+    pub fn indentation(&self) -> &str {
+        &self.indentation
+    }
+
+    /// The trailing comment without the `;` prefix, such as ` fibonacci`.
+    pub fn comment(&self) -> Option<&str> {
+        self.trailing.find(';').map(|index| &self.trailing[index + 1..])
+    }
+
+    /// Replace the trailing comment. With `None` the comment is removed.
+    pub fn set_comment(&mut self, comment: Option<&str>) {
+        self.trailing = match comment {
+            Some(comment) => format!(" ;{}", comment),
+            None => String::new(),
+        };
+    }
+
+    /// Determines if the instruction has been changed since it was parsed.
+    ///
+    /// The line number is ignored, since it changes when rows are inserted or removed.
+    fn is_modified(&self) -> bool {
+        self.instruction.instruction_id != self.original_instruction.instruction_id ||
+        self.instruction.parameter_vec != self.original_instruction.parameter_vec
+    }
+}
+
+impl fmt::Display for CstInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_modified() {
+            // The original spacing is lost, when the instruction has been changed.
+            write!(f, "{}{}{}", self.indentation, self.instruction, self.trailing)
+        } else {
+            write!(f, "{}{}{}", self.indentation, self.original_code, self.trailing)
+        }
+    }
+}
+
+/// A single row of source code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CstLine {
+    /// A line that is empty or contains only whitespace.
+    Blank { text: String },
+
+    /// A line that contains only a comment, such as `; A000045: Fibonacci numbers`.
+    Comment { text: String },
+
+    /// The `#offset` line.
+    Offset { offset: i32, text: String },
+
+    Instruction(CstInstruction),
+}
+
+impl fmt::Display for CstLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blank { text } => write!(f, "{}", text),
+            Self::Comment { text } => write!(f, "{}", text),
+            Self::Offset { text, .. } => write!(f, "{}", text),
+            Self::Instruction(instruction) => write!(f, "{}", instruction),
+        }
+    }
+}
+
+/// Concrete syntax tree of a LODA program.
+///
+/// Unlike `ParsedProgram`, the comments, blank lines and whitespace are preserved,
+/// so that a program can be modified without destroying the annotations made by its author.
+///
+/// Round-trip guarantee: For any input that can be parsed, `to_string()` returns the input unchanged.
+/// Only the instructions that have been modified are reformatted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstProgram {
+    lines: Vec<CstLine>,
+}
+
+impl CstProgram {
+    /// Accepts the same input as `ParsedProgram::parse_program` and rejects it with the same errors.
+    pub fn parse(raw_input: &str) -> Result<CstProgram, ParseProgramError> {
+        let mut lines: Vec<CstLine> = vec!();
+        let mut has_offset = false;
+        let mut has_instructions = false;
+        for (index, raw_input_line) in raw_input.split('\n').enumerate() {
+            let line_number: usize = index + 1;
+
+            let (_, extracted_offset) = extract_offset(raw_input_line, line_number)?;
+            if let Some(offset) = extracted_offset {
+                if has_offset || has_instructions {
+                    // The `#offset` line must occur only once, and before the instructions.
+                    return Err(ParseProgramError::SyntaxError(line_number));
+                }
+                has_offset = true;
+                lines.push(CstLine::Offset { offset, text: raw_input_line.to_string() });
+                continue;
+            }
+
+            if raw_input_line.trim().is_empty() {
+                lines.push(CstLine::Blank { text: raw_input_line.to_string() });
+                continue;
+            }
+
+            let code_with_whitespace: String = remove_comment(raw_input_line).to_string();
+            let code: &str = code_with_whitespace.trim();
+            if code.is_empty() {
+                lines.push(CstLine::Comment { text: raw_input_line.to_string() });
+                continue;
+            }
+
+            let instruction: Instruction = parse_instruction_row(code_with_whitespace.trim_end(), line_number)?;
+            has_instructions = true;
+            let indentation_length: usize = code_with_whitespace.len() - code_with_whitespace.trim_start().len();
+            let code_end: usize = indentation_length + code.len();
+            let cst_instruction = CstInstruction {
+                original_instruction: instruction.clone(),
+                instruction,
+                indentation: raw_input_line[..indentation_length].to_string(),
+                original_code: code.to_string(),
+                trailing: raw_input_line[code_end..].to_string(),
+            };
+            lines.push(CstLine::Instruction(cst_instruction));
+        }
+        Ok(Self { lines })
+    }
+
+    pub fn lines(&self) -> &Vec<CstLine> {
+        &self.lines
+    }
+
+    pub fn lines_mut(&mut self) -> &mut Vec<CstLine> {
+        &mut self.lines
+    }
+
+    pub fn offset(&self) -> Option<i32> {
+        self.lines.iter().find_map(|line| match line {
+            CstLine::Offset { offset, .. } => Some(*offset),
+            _ => None,
+        })
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.instructions().count()
+    }
+
+    pub fn instructions(&self) -> impl Iterator<Item = &CstInstruction> {
+        self.lines.iter().filter_map(|line| match line {
+            CstLine::Instruction(instruction) => Some(instruction),
+            _ => None,
+        })
+    }
+
+    /// Access the n'th instruction, ignoring comments and blank lines.
+    pub fn instruction_mut(&mut self, index: usize) -> Option<&mut CstInstruction> {
+        self.lines.iter_mut().filter_map(|line| match line {
+            CstLine::Instruction(instruction) => Some(instruction),
+            _ => None,
+        }).nth(index)
+    }
+
+    /// Remove the n'th instruction together with its trailing comment.
+    ///
+    /// The comment lines above the instruction are kept.
+    pub fn remove_instruction(&mut self, index: usize) -> Option<CstInstruction> {
+        let line_index: usize = self.line_index_of_instruction(index)?;
+        match self.lines.remove(line_index) {
+            CstLine::Instruction(instruction) => Some(instruction),
+            _ => None,
+        }
+    }
+
+    /// Insert an instruction, so it becomes the n'th instruction.
+    ///
+    /// The indentation is copied from the instruction that currently has that position,
+    /// or from the last instruction when appending.
+    pub fn insert_instruction(&mut self, index: usize, instruction: Instruction) {
+        if let Some(line_index) = self.line_index_of_instruction(index) {
+            let indentation: String = self.indentation_of_line(line_index);
+            self.lines.insert(line_index, CstLine::Instruction(CstInstruction::new(instruction, &indentation)));
+            return;
+        }
+        let last_instruction_line_index: Option<usize> = self.lines.iter().rposition(|line| matches!(line, CstLine::Instruction(_)));
+        match last_instruction_line_index {
+            Some(line_index) => {
+                let indentation: String = self.indentation_of_line(line_index);
+                self.lines.insert(line_index + 1, CstLine::Instruction(CstInstruction::new(instruction, &indentation)));
+            },
+            None => {
+                self.lines.push(CstLine::Instruction(CstInstruction::new(instruction, "")));
+            }
+        }
+    }
+
+    fn line_index_of_instruction(&self, index: usize) -> Option<usize> {
+        self.lines.iter().enumerate()
+            .filter(|(_, line)| matches!(line, CstLine::Instruction(_)))
+            .nth(index)
+            .map(|(line_index, _)| line_index)
+    }
+
+    fn indentation_of_line(&self, line_index: usize) -> String {
+        match &self.lines[line_index] {
+            CstLine::Instruction(instruction) => instruction.indentation.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// Discard the comments and formatting.
+    ///
+    /// The line numbers of the instructions reflect the current position of the rows.
+    pub fn to_parsed_program(&self) -> ParsedProgram {
+        let mut instruction_vec: Vec<Instruction> = vec!();
+        for (index, line) in self.lines.iter().enumerate() {
+            if let CstLine::Instruction(cst_instruction) = line {
+                let mut instruction: Instruction = cst_instruction.instruction.clone();
+                instruction.line_number = index + 1;
+                instruction_vec.push(instruction);
+            }
+        }
+        ParsedProgram {
+            optional_offset: self.offset(),
+            instruction_vec,
+        }
+    }
+}
+
+impl fmt::Display for CstProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = self.lines.iter().map(|line| line.to_string()).collect();
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{InstructionId, InstructionParameter, ParameterType};
+
+    const PROGRAM: &str = "; A000045: Fibonacci numbers\n; Submitted by John Doe\n; 0,1,1,2,3,5,8\n\n#offset 0\n\nmov  $1,1 ; initial value\nlpb $0\n  sub $0,1\n\n  ; swap\n  mov $2,$1\n  add $1,$3\n  mov $3,$2\nlpe\nmov $0,$3\n";
+
+    #[test]
+    fn test_10000_round_trip() {
+        let inputs: [&str; 9] = [
+            "",
+            "\n\n",
+            "  \t ",
+            "; comment only",
+            PROGRAM,
+            "add $0,1\r\nmul $0,2 ; comment\r\n",
+            "\t add   $0 , 1\t;\t comment ; with ; semicolons   ",
+            "lpb $0\n  sub $0,1\n;\nlpe",
+            "#offset -3\nf11 $0,1200",
+        ];
+        for input in inputs {
+            let cst: CstProgram = CstProgram::parse(input).expect("cst");
+            assert_eq!(cst.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_10001_same_as_parsed_program() {
+        let cst: CstProgram = CstProgram::parse(PROGRAM).expect("cst");
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(PROGRAM).expect("parsed program");
+        assert_eq!(cst.to_parsed_program(), parsed_program);
+        assert_eq!(cst.offset(), Some(0));
+        assert_eq!(cst.instruction_count(), 8);
+    }
+
+    #[test]
+    fn test_10002_line_kinds() {
+        let cst: CstProgram = CstProgram::parse("; header\n \n#offset 1\nadd $0,1 ; plus one").expect("cst");
+        let lines: &Vec<CstLine> = cst.lines();
+        assert_eq!(lines[0], CstLine::Comment { text: "; header".to_string() });
+        assert_eq!(lines[1], CstLine::Blank { text: " ".to_string() });
+        assert_eq!(lines[2], CstLine::Offset { offset: 1, text: "#offset 1".to_string() });
+        match &lines[3] {
+            CstLine::Instruction(instruction) => {
+                assert_eq!(instruction.comment(), Some(" plus one"));
+                assert_eq!(instruction.instruction.to_string(), "add $0,1");
+            },
+            _ => panic!("expected instruction")
+        }
+    }
+
+    #[test]
+    fn test_10003_same_errors_as_parsed_program() {
+        let inputs: [&str; 5] = [
+            "Add",
+            "mov $x",
+            "#offset 007",
+            "add $0,1\n#offset 1",
+            "; comment\nboom $1",
+        ];
+        for input in inputs {
+            let expected: ParseProgramError = ParsedProgram::parse_program(input).expect_err("error");
+            let actual: ParseProgramError = CstProgram::parse(input).expect_err("error");
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_20000_modify_instruction_keeps_comments() {
+        let mut cst: CstProgram = CstProgram::parse(PROGRAM).expect("cst");
+        {
+            let instruction: &mut CstInstruction = cst.instruction_mut(0).expect("instruction");
+            instruction.instruction.parameter_vec[1] = InstructionParameter::new(ParameterType::Constant, 2);
+        }
+        let expected: String = PROGRAM.replace("mov  $1,1 ; initial value", "mov $1,2 ; initial value");
+        assert_eq!(cst.to_string(), expected);
+    }
+
+    #[test]
+    fn test_20001_set_comment() {
+        let mut cst: CstProgram = CstProgram::parse("  add $0,1 ; old").expect("cst");
+        cst.instruction_mut(0).expect("instruction").set_comment(Some(" new"));
+        assert_eq!(cst.to_string(), "  add $0,1 ; new");
+        cst.instruction_mut(0).expect("instruction").set_comment(None);
+        assert_eq!(cst.to_string(), "  add $0,1");
+    }
+
+    #[test]
+    fn test_30000_remove_instruction() {
+        let mut cst: CstProgram = CstProgram::parse("; header\nmov $1,7 ; unused\n; keep me\nmul $0,2").expect("cst");
+        let removed: CstInstruction = cst.remove_instruction(0).expect("removed");
+        assert_eq!(removed.comment(), Some(" unused"));
+        assert_eq!(cst.to_string(), "; header\n; keep me\nmul $0,2");
+        assert!(cst.remove_instruction(1).is_none());
+    }
+
+    #[test]
+    fn test_30001_insert_instruction() {
+        let mut cst: CstProgram = CstProgram::parse("lpb $0\n  sub $0,1 ; decrement\nlpe\n; footer").expect("cst");
+        let instruction = Instruction {
+            instruction_id: InstructionId::Add,
+            parameter_vec: vec![
+                InstructionParameter::new(ParameterType::Direct, 1),
+                InstructionParameter::new(ParameterType::Constant, 1),
+            ],
+            line_number: 0,
+        };
+        cst.insert_instruction(1, instruction.clone());
+        assert_eq!(cst.to_string(), "lpb $0\n  add $1,1\n  sub $0,1 ; decrement\nlpe\n; footer");
+        cst.insert_instruction(4, instruction);
+        assert_eq!(cst.to_string(), "lpb $0\n  add $1,1\n  sub $0,1 ; decrement\nlpe\nadd $1,1\n; footer");
+
+        // Line numbers are assigned from the current position
+        let parsed_program: ParsedProgram = cst.to_parsed_program();
+        let line_numbers: Vec<usize> = parsed_program.instruction_vec.iter().map(|instruction| instruction.line_number).collect();
+        assert_eq!(line_numbers, vec![1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,383 @@
+use super::{extract_offset, parse_parameters, remove_comment, InstructionId, ParseInstructionId, ParseParametersError, EXTRACT_ROW_RE};
+use serde::Serialize;
+use std::fmt;
+
+/// The mnemonics recognized by `ParseInstructionId`, excluding the `fNM` unofficial functions.
+const KNOWN_MNEMONICS: [&str; 36] = [
+    "add", "ban", "bin", "bor", "bxo", "clr", "cmp", "dgr", "dgs", "div", "dif", "dir",
+    "equ", "fac", "fil", "gcd", "geq", "lex", "log", "lpb", "lpe", "leq", "lps", "max",
+    "min", "mod", "mov", "mul", "neq", "nrt", "pow", "rol", "ror", "seq", "sub", "trn",
+];
+
+/// Max number of "did you mean" suggestions for an unrecognized mnemonic.
+const MAX_MNEMONIC_SUGGESTIONS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for DiagnosticSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A range of characters within a single line.
+///
+/// The `line_number` and `column` are 1-based. The `length` is counted in characters.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct DiagnosticSpan {
+    pub line_number: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+/// A problem found in the source code, with a human readable explanation and optional fix-it suggestions.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub span: DiagnosticSpan,
+
+    /// The offending source code, such as `mvo`.
+    pub token: String,
+    pub message: String,
+
+    /// Ways to fix the problem, such as "did you mean `mov`?".
+    pub suggestions: Vec<String>,
+}
+
+impl Diagnostic {
+    fn error(line: &str, line_number: usize, byte_start: usize, token: &str, message: String) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Error,
+            span: DiagnosticSpan {
+                line_number,
+                column: line[..byte_start].chars().count() + 1,
+                length: token.chars().count().max(1),
+            },
+            token: token.to_string(),
+            message,
+            suggestions: vec!(),
+        }
+    }
+
+    fn with_suggestion(mut self, suggestion: String) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Show the offending line with the span underlined, like this:
+    ///
+    /// ```text
+    /// error: unrecognized instruction `mvo`
+    ///  --> line 3, column 1
+    ///   |
+    /// 3 | mvo $0,1
+    ///   | ^^^
+    ///   = help: did you mean `mov`?
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let line: &str = source.split('\n').nth(self.span.line_number.saturating_sub(1)).unwrap_or("");
+        let line: &str = line.trim_end_matches('\r');
+        let gutter: String = " ".repeat(self.span.line_number.to_string().len());
+        let underline: String = format!("{}{}", " ".repeat(self.span.column - 1), "^".repeat(self.span.length));
+        let mut rows: Vec<String> = vec![
+            format!("{}: {}", self.severity, self.message),
+            format!("{}--> line {}, column {}", gutter, self.span.line_number, self.span.column),
+            format!("{} |", gutter),
+            format!("{} | {}", self.span.line_number, line.replace('\t', " ")),
+            format!("{} | {}", gutter, underline),
+        ];
+        for suggestion in &self.suggestions {
+            rows.push(format!("{} = help: {}", gutter, suggestion));
+        }
+        rows.join("\n")
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} at line {}, column {}", self.severity, self.message, self.span.line_number, self.span.column)
+    }
+}
+
+/// Find all the syntax problems in a program.
+///
+/// Unlike `ParsedProgram::parse_program`, this doesn't stop at the first problem,
+/// and it also reports unbalanced loops. Returns an empty vec when there are no problems.
+pub fn diagnose_program(source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec!();
+    let mut offset_line_number: Option<usize> = None;
+    let mut has_instructions = false;
+    let mut loop_stack: Vec<Diagnostic> = vec!();
+    for (index, raw_line) in source.split('\n').enumerate() {
+        let line_number: usize = index + 1;
+        let line: &str = raw_line.trim_end_matches('\r');
+        let indentation: usize = line.len() - line.trim_start().len();
+
+        if line.trim_start().starts_with("#offset") {
+            let token: &str = line.trim();
+            if extract_offset(line, line_number).is_err() {
+                let mut diagnostic = Diagnostic::error(line, line_number, indentation, token, "invalid #offset syntax".to_string());
+                if let Some(fixed) = fix_offset(line) {
+                    diagnostic = diagnostic.with_suggestion(format!("write it as `{}`", fixed));
+                }
+                diagnostics.push(diagnostic);
+            } else if let Some(first_line_number) = offset_line_number {
+                let diagnostic = Diagnostic::error(line, line_number, indentation, token, "duplicate #offset".to_string())
+                    .with_suggestion(format!("remove this line, the offset is already defined in line {}", first_line_number));
+                diagnostics.push(diagnostic);
+            } else if has_instructions {
+                let diagnostic = Diagnostic::error(line, line_number, indentation, token, "#offset after the first instruction".to_string())
+                    .with_suggestion("move the #offset line before the instructions".to_string());
+                diagnostics.push(diagnostic);
+            }
+            offset_line_number = offset_line_number.or(Some(line_number));
+            continue;
+        }
+
+        let code_with_whitespace = remove_comment(line);
+        let code: &str = code_with_whitespace.trim();
+        if code.is_empty() {
+            continue;
+        }
+        has_instructions = true;
+        let mnemonic: &str = code.split([' ', '\t']).next().unwrap_or(code);
+
+        let captures = match EXTRACT_ROW_RE.captures(code_with_whitespace.trim_end()) {
+            Some(value) => value,
+            None => {
+                diagnostics.push(diagnose_syntax_error(line, line_number, indentation, code, mnemonic));
+                continue;
+            }
+        };
+
+        let instruction_id: InstructionId = match InstructionId::parse(mnemonic, line_number) {
+            Ok(value) => value,
+            Err(_) => {
+                let mut diagnostic = Diagnostic::error(line, line_number, indentation, mnemonic, format!("unrecognized instruction `{}`", mnemonic));
+                for candidate in similar_mnemonics(mnemonic) {
+                    diagnostic = diagnostic.with_suggestion(format!("did you mean `{}`?", candidate));
+                }
+                diagnostics.push(diagnostic);
+                continue;
+            }
+        };
+
+        if let Some(parameters) = captures.get(2) {
+            diagnose_parameters(&mut diagnostics, line, line_number, parameters.start(), parameters.as_str());
+        }
+
+        match instruction_id {
+            InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract => {
+                let diagnostic = Diagnostic::error(line, line_number, indentation, mnemonic, format!("`{}` without matching `lpe`", mnemonic))
+                    .with_suggestion("insert `lpe` after the loop body".to_string());
+                loop_stack.push(diagnostic);
+            },
+            InstructionId::LoopEnd if loop_stack.pop().is_none() => {
+                let diagnostic = Diagnostic::error(line, line_number, indentation, mnemonic, "`lpe` without matching `lpb`".to_string())
+                    .with_suggestion("remove this `lpe`, or insert `lpb` before it".to_string());
+                diagnostics.push(diagnostic);
+            },
+            _ => {}
+        }
+    }
+    diagnostics.extend(loop_stack);
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.span.line_number, diagnostic.span.column));
+    diagnostics
+}
+
+fn diagnose_syntax_error(line: &str, line_number: usize, indentation: usize, code: &str, mnemonic: &str) -> Diagnostic {
+    let mut diagnostic = Diagnostic::error(line, line_number, indentation, mnemonic, "expected an instruction, such as `add $0,1`".to_string());
+    let lowercase: String = mnemonic.to_lowercase();
+    if lowercase != mnemonic && KNOWN_MNEMONICS.contains(&lowercase.as_str()) {
+        return diagnostic.with_suggestion(format!("instructions are lowercase, write `{}`", lowercase));
+    }
+    if let Some(position) = mnemonic.find('$') {
+        let prefix: &str = &mnemonic[..position];
+        if KNOWN_MNEMONICS.contains(&prefix) {
+            let fixed: String = format!("{} {}", prefix, &code[position..]);
+            return diagnostic.with_suggestion(format!("insert a space after the instruction, `{}`", fixed));
+        }
+    }
+    if mnemonic.len() > 5 {
+        diagnostic = diagnostic.with_suggestion("instructions are between 2 and 5 characters long".to_string());
+    }
+    diagnostic
+}
+
+fn diagnose_parameters(diagnostics: &mut Vec<Diagnostic>, line: &str, line_number: usize, byte_start: usize, parameters: &str) {
+    let mut position: usize = byte_start;
+    for item in parameters.split(',') {
+        let item_start: usize = position + (item.len() - item.trim_start().len());
+        position += item.len() + 1;
+        let token: &str = item.trim();
+        let result = match token.is_empty() {
+            true => Err(ParseParametersError::EmptyParameter(line_number)),
+            false => parse_parameters(token, line_number),
+        };
+        let error: ParseParametersError = match result {
+            Ok(_) => continue,
+            Err(error) => error,
+        };
+        let diagnostic: Diagnostic = match error {
+            ParseParametersError::EmptyParameter(_) => {
+                let position_of_comma: usize = item_start.min(line.len());
+                Diagnostic::error(line, line_number, position_of_comma, "", "empty parameter".to_string())
+                    .with_suggestion("remove the extra `,`".to_string())
+            },
+            ParseParametersError::UnrecognizedParameter(_) | ParseParametersError::UnrecognizedParameterValue(_) => {
+                Diagnostic::error(line, line_number, item_start, token, format!("unrecognized parameter `{}`", token))
+                    .with_suggestion("expected a constant such as `5`, a register such as `$5`, or an indirect register such as `$$5`".to_string())
+            },
+            ParseParametersError::UnrecognizedParameterType(_) => {
+                Diagnostic::error(line, line_number, item_start, token, format!("too many `$` in parameter `{}`", token))
+                    .with_suggestion("use at most two `$`".to_string())
+            },
+            ParseParametersError::StrictIncorrectParameterValue(_) => {
+                let prefix: &str = token.trim_end_matches(|c: char| c.is_ascii_digit() || c == '-');
+                let value: &str = &token[prefix.len()..];
+                let mut diagnostic = Diagnostic::error(line, line_number, item_start, token, format!("non-canonical number `{}`", value));
+                if let Ok(number) = value.parse::<num_bigint::BigInt>() {
+                    diagnostic = diagnostic.with_suggestion(format!("write it as `{}{}`", prefix, number));
+                }
+                diagnostic
+            },
+            ParseParametersError::NegativeValueNotAllowedForThisParameterType(_) => {
+                Diagnostic::error(line, line_number, item_start, token, format!("negative register index `{}`", token))
+                    .with_suggestion("register indexes must be 0 or greater".to_string())
+            },
+        };
+        diagnostics.push(diagnostic);
+    }
+}
+
+/// Rewrite an invalid `#offset` line into the canonical form, if possible.
+fn fix_offset(line: &str) -> Option<String> {
+    let code = remove_comment(line);
+    let value: &str = code.trim().strip_prefix("#offset")?.trim();
+    let offset: i32 = value.parse::<i32>().ok()?;
+    Some(format!("#offset {}", offset))
+}
+
+fn similar_mnemonics(mnemonic: &str) -> Vec<&'static str> {
+    KNOWN_MNEMONICS.iter()
+        .filter(|candidate| edit_distance(mnemonic, candidate) <= 1)
+        .take(MAX_MNEMONIC_SUGGESTIONS)
+        .copied()
+        .collect()
+}
+
+/// Optimal string alignment distance, where swapping two adjacent characters counts as 1 edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d: Vec<Vec<usize>> = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, value) in d[0].iter_mut().enumerate() {
+        *value = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost: usize = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut value: usize = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                value = value.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = value;
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParsedProgram;
+
+    fn process(source: &str) -> Vec<String> {
+        diagnose_program(source).iter().map(|diagnostic| {
+            let mut s: String = diagnostic.to_string();
+            for suggestion in &diagnostic.suggestions {
+                s += &format!(" | {}", suggestion);
+            }
+            s
+        }).collect()
+    }
+
+    #[test]
+    fn test_10000_no_problems() {
+        assert!(diagnose_program("").is_empty());
+        assert!(diagnose_program("; A000045\n#offset 1\nlpb $0\n  sub $0,1 ; comment\nlpe\nf11 $0,1200").is_empty());
+    }
+
+    #[test]
+    fn test_10001_same_programs_rejected_as_parse_program() {
+        let inputs: [&str; 8] = ["Add", "mvo $0", "mov $x", "mov $$$3,4", "mov $-3,0", "mov $0,00", "#offset 007", "#offset 1\n#offset 1"];
+        for input in inputs {
+            assert!(ParsedProgram::parse_program(input).is_err());
+            assert!(!diagnose_program(input).is_empty(), "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_20000_unrecognized_instruction() {
+        assert_eq!(process("add $0,1\n  mvo $0,1"), vec!["error: unrecognized instruction `mvo` at line 2, column 3 | did you mean `mov`?"]);
+        assert_eq!(process("xyz $0"), vec!["error: unrecognized instruction `xyz` at line 1, column 1"]);
+    }
+
+    #[test]
+    fn test_20001_syntax_error() {
+        assert_eq!(process("ADD $0,1"), vec!["error: expected an instruction, such as `add $0,1` at line 1, column 1 | instructions are lowercase, write `add`"]);
+        assert_eq!(process("mov$0,1"), vec!["error: expected an instruction, such as `add $0,1` at line 1, column 1 | insert a space after the instruction, `mov $0,1`"]);
+    }
+
+    #[test]
+    fn test_20002_parameters() {
+        assert_eq!(process("mov $0, 007"), vec!["error: non-canonical number `007` at line 1, column 9 | write it as `7`"]);
+        assert_eq!(process("mov $-3,$$00"), vec![
+            "error: negative register index `$-3` at line 1, column 5 | register indexes must be 0 or greater",
+            "error: non-canonical number `00` at line 1, column 9 | write it as `$$0`",
+        ]);
+        assert_eq!(process("mov $0,x"), vec!["error: unrecognized parameter `x` at line 1, column 8 | expected a constant such as `5`, a register such as `$5`, or an indirect register such as `$$5`"]);
+        assert_eq!(process("mov $$$0,1"), vec!["error: too many `$` in parameter `$$$0` at line 1, column 5 | use at most two `$`"]);
+        assert_eq!(process("mov $0,,1"), vec!["error: empty parameter at line 1, column 8 | remove the extra `,`"]);
+    }
+
+    #[test]
+    fn test_20003_offset() {
+        assert_eq!(process("#offset 007"), vec!["error: invalid #offset syntax at line 1, column 1 | write it as `#offset 7`"]);
+        assert_eq!(process("#offset 1\n#offset 2"), vec!["error: duplicate #offset at line 2, column 1 | remove this line, the offset is already defined in line 1"]);
+        assert_eq!(process("add $0,1\n#offset 2"), vec!["error: #offset after the first instruction at line 2, column 1 | move the #offset line before the instructions"]);
+    }
+
+    #[test]
+    fn test_20004_unbalanced_loops() {
+        assert_eq!(process("lpe"), vec!["error: `lpe` without matching `lpb` at line 1, column 1 | remove this `lpe`, or insert `lpb` before it"]);
+        assert_eq!(process("lpb $0\n  lpb $1\n  lpe"), vec!["error: `lpb` without matching `lpe` at line 1, column 1 | insert `lpe` after the loop body"]);
+    }
+
+    #[test]
+    fn test_30000_render() {
+        let source = "mov $1,2\nlpb $0\n\tmvo $0,1 ; comment\nlpe";
+        let diagnostics: Vec<Diagnostic> = diagnose_program(source);
+        assert_eq!(diagnostics.len(), 1);
+        let expected = "error: unrecognized instruction `mvo`\n --> line 3, column 2\n  |\n3 |  mvo $0,1 ; comment\n  |  ^^^\n  = help: did you mean `mov`?";
+        assert_eq!(diagnostics[0].render(source), expected);
+    }
+
+    #[test]
+    fn test_40000_edit_distance() {
+        assert_eq!(edit_distance("mov", "mov"), 0);
+        assert_eq!(edit_distance("mvo", "mov"), 1);
+        assert_eq!(edit_distance("ad", "add"), 1);
+        assert_eq!(edit_distance("xyz", "add"), 3);
+    }
+}
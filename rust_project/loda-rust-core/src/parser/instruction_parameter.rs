@@ -1,23 +1,96 @@
 use std::fmt;
 use super::ParameterType;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct InstructionParameter {
     pub parameter_type: ParameterType,
     pub parameter_value: i64,
+
+    /// A constant that doesn't fit inside an `i64`, such as `mul $0,123456789012345678901234567890`.
+    /// 
+    /// Only used with `ParameterType::Constant`, and then the `parameter_value` is zero.
+    pub parameter_big_value: Option<BigInt>,
 }
 
 impl InstructionParameter {
     pub fn new(parameter_type: ParameterType, parameter_value: i64) -> Self {
         Self {
             parameter_type: parameter_type,
-            parameter_value: parameter_value
+            parameter_value,
+            parameter_big_value: None,
+        }
+    }
+
+    /// Create a constant, that may be beyond 64bit.
+    pub fn new_constant(value: BigInt) -> Self {
+        match value.to_i64() {
+            Some(value) => Self::new(ParameterType::Constant, value),
+            None => {
+                Self {
+                    parameter_type: ParameterType::Constant,
+                    parameter_value: 0,
+                    parameter_big_value: Some(value),
+                }
+            }
+        }
+    }
+
+    /// Determines if this is a constant that doesn't fit inside an `i64`.
+    pub fn is_big_constant(&self) -> bool {
+        self.parameter_big_value.is_some()
+    }
+
+    /// The value of a constant, also when it's beyond 64bit.
+    /// 
+    /// Returns `None` for parameters that are not constants.
+    pub fn constant_value(&self) -> Option<BigInt> {
+        if self.parameter_type != ParameterType::Constant {
+            return None;
+        }
+        match &self.parameter_big_value {
+            Some(value) => Some(value.clone()),
+            None => Some(BigInt::from(self.parameter_value))
         }
     }
 }
 
 impl fmt::Display for InstructionParameter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}{}", self.parameter_type.prefix(), self.parameter_value)
+        match &self.parameter_big_value {
+            Some(value) => write!(f, "{}{}", self.parameter_type.prefix(), value),
+            None => write!(f, "{}{}", self.parameter_type.prefix(), self.parameter_value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_10000_new_constant() {
+        {
+            let parameter = InstructionParameter::new_constant(BigInt::from(-42));
+            assert_eq!(parameter, InstructionParameter::new(ParameterType::Constant, -42));
+            assert_eq!(parameter.is_big_constant(), false);
+            assert_eq!(parameter.to_string(), "-42");
+        }
+        {
+            let value = BigInt::from_str("-123456789012345678901234567890").unwrap();
+            let parameter = InstructionParameter::new_constant(value.clone());
+            assert_eq!(parameter.is_big_constant(), true);
+            assert_eq!(parameter.parameter_value, 0);
+            assert_eq!(parameter.constant_value(), Some(value));
+            assert_eq!(parameter.to_string(), "-123456789012345678901234567890");
+        }
+    }
+
+    #[test]
+    fn test_10001_constant_value() {
+        assert_eq!(InstructionParameter::new(ParameterType::Constant, 7).constant_value(), Some(BigInt::from(7)));
+        assert_eq!(InstructionParameter::new(ParameterType::Direct, 7).constant_value(), None);
     }
 }
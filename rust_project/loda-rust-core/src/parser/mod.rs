@@ -1,5 +1,7 @@
 //! Translate from LODA source code to a program instance.
 mod create_program;
+mod cst_program;
+mod diagnostic;
 mod extract_offset;
 mod extract_row_re;
 mod instruction;
@@ -10,9 +12,12 @@ mod parse_error;
 mod parse_instruction_id;
 mod parse_parameters;
 mod parse_program;
+mod program_analysis;
 mod remove_comment;
 
 pub use create_program::CreateProgram;
+pub use cst_program::{CstInstruction, CstLine, CstProgram};
+pub use diagnostic::{diagnose_program, Diagnostic, DiagnosticSeverity, DiagnosticSpan};
 pub use extract_offset::{extract_offset, ExtractOffsetError};
 pub use extract_row_re::EXTRACT_ROW_RE;
 pub use instruction::Instruction;
@@ -23,6 +28,7 @@ pub use parse_error::ParseError;
 pub use parse_instruction_id::{ParseInstructionId, ParseInstructionIdError};
 pub use parse_parameters::{ParseParametersError, parse_parameters};
 pub use parse_program::{ParsedProgram, ParseProgramError};
+pub use program_analysis::{analyze_program, ProgramAnalysisIssue};
 pub use remove_comment::remove_comment;
 
 pub mod extract_parameter_re;
@@ -2,6 +2,7 @@ use std::fmt;
 use std::str::FromStr;
 use super::extract_parameter_re::EXTRACT_PARAMETER_RE;
 use super::{InstructionParameter, ParameterType};
+use num_bigint::BigInt;
 
 #[derive(Debug, PartialEq)]
 pub enum ParseParametersError {
@@ -67,7 +68,21 @@ pub fn parse_parameters(parameter_string_raw: &str, line_number: usize)
         let parameter_value: i64 = match i64::from_str(capture2) {
             Ok(value) => value,
             _ => {
-                return Err(ParseParametersError::UnrecognizedParameterValue(line_number));
+                if parameter_type != ParameterType::Constant {
+                    return Err(ParseParametersError::UnrecognizedParameterValue(line_number));
+                }
+                // Constants beyond 64bit, such as `123456789012345678901234567890`.
+                let big_value: BigInt = match BigInt::from_str(capture2) {
+                    Ok(value) => value,
+                    _ => {
+                        return Err(ParseParametersError::UnrecognizedParameterValue(line_number));
+                    }
+                };
+                if big_value.to_string() != capture2 {
+                    return Err(ParseParametersError::StrictIncorrectParameterValue(line_number));
+                }
+                parameter_vec.push(InstructionParameter::new_constant(big_value));
+                continue;
             }
         };
 
@@ -87,10 +102,7 @@ pub fn parse_parameters(parameter_string_raw: &str, line_number: usize)
             return Err(ParseParametersError::NegativeValueNotAllowedForThisParameterType(line_number));
         }
 
-        let parameter = InstructionParameter {
-            parameter_type: parameter_type,
-            parameter_value: parameter_value,
-        };
+        let parameter = InstructionParameter::new(parameter_type, parameter_value);
         parameter_vec.push(parameter);
     }
 
@@ -192,4 +204,14 @@ mod tests {
         assert_eq!(process("$-12"), "NegativeValueNotAllowedForThisParameterType(1)");
         assert_eq!(process("$$-12"), "NegativeValueNotAllowedForThisParameterType(1)");
     }
+
+    #[test]
+    fn test_10009_big_constants() {
+        assert_eq!(process("9223372036854775807"), "9223372036854775807");
+        assert_eq!(process("9223372036854775808"), "9223372036854775808");
+        assert_eq!(process("$0, -123456789012345678901234567890"), "$0,-123456789012345678901234567890");
+        assert_eq!(process("000123456789012345678901234567890"), "StrictIncorrectParameterValue(1)");
+        assert_eq!(process("$123456789012345678901234567890"), "UnrecognizedParameterValue(1)");
+        assert_eq!(process("$$123456789012345678901234567890"), "UnrecognizedParameterValue(1)");
+    }
 }
@@ -68,12 +68,36 @@ impl From<ExtractOffsetError> for ParseProgramError {
     }
 }
 
+/// Parse a row of code, that has no comment, such as `add $0,1`.
+pub(crate) fn parse_instruction_row(row: &str, line_number: usize) -> Result<Instruction, ParseProgramError> {
+    let captures = match EXTRACT_ROW_RE.captures(row) {
+        Some(value) => value,
+        None => {
+            return Err(ParseProgramError::SyntaxError(line_number));
+        }
+    };
+    let instruction_raw: &str = captures.get(1).map_or("", |m| m.as_str());
+    let parameter_string: &str = captures.get(2).map_or("", |m| m.as_str());
+
+    let instruction_id: InstructionId = 
+        InstructionId::parse(instruction_raw, line_number)?;
+
+    let parameter_vec: Vec<InstructionParameter> = 
+        parse_parameters(parameter_string, line_number)?;
+
+    let instruction = Instruction {
+        instruction_id,
+        parameter_vec,
+        line_number,
+    };
+    Ok(instruction)
+}
+
 impl ParsedProgram {
     /// Returns `Ok` if the program can be parsed.
     /// 
     /// Returns `Err` if there is a problem during parsing.
     pub fn parse_program(raw_input: &str) -> Result<ParsedProgram, ParseProgramError> {
-        let re = &EXTRACT_ROW_RE;
         let mut optional_offset: Option<i32> = None;
         let mut instruction_vec: Vec<Instruction> = vec!();
         for (index, raw_input_line) in raw_input.split("\n").enumerate() {
@@ -104,27 +128,7 @@ impl ParsedProgram {
                 continue;
             }
     
-            // Parse the instruction
-            let captures = match re.captures(line2) {
-                Some(value) => value,
-                None => {
-                    return Err(ParseProgramError::SyntaxError(line_number));
-                }
-            };
-            let instruction_raw: &str = captures.get(1).map_or("", |m| m.as_str());
-            let parameter_string: &str = captures.get(2).map_or("", |m| m.as_str());
-    
-            let instruction_id: InstructionId = 
-                InstructionId::parse(instruction_raw, line_number)?;
-    
-            let parameter_vec: Vec<InstructionParameter> = 
-                parse_parameters(parameter_string, line_number)?;
-    
-            let instruction = Instruction {
-                instruction_id: instruction_id,
-                parameter_vec: parameter_vec,
-                line_number: line_number,
-            };
+            let instruction: Instruction = parse_instruction_row(line2, line_number)?;
             instruction_vec.push(instruction);
         }
     
@@ -138,7 +142,6 @@ impl ParsedProgram {
     /// The direct dependencies that this program depends on.
     /// 
     /// This doesn't include the indirect dependencies.
-    #[allow(dead_code)]
     pub fn direct_dependencies(&self) -> Vec<u64> {
         let mut program_ids: Vec<u64> = vec!();
         for instruction in &self.instruction_vec {
@@ -149,7 +152,7 @@ impl ParsedProgram {
                 continue;
             }
             let param: &InstructionParameter = &(instruction.parameter_vec[1]);
-            if param.parameter_type != ParameterType::Constant {
+            if param.parameter_type != ParameterType::Constant || param.is_big_constant() {
                 continue;
             }
             let program_id: i64 = param.parameter_value;
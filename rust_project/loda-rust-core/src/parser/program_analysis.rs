@@ -0,0 +1,515 @@
+//! Static analysis of a `ParsedProgram`, without executing it.
+//!
+//! The analysis is conservative. When a program uses indirect addressing or memory operations,
+//! then it's unknown what registers are accessed, and no issues are reported for those registers.
+use std::collections::BTreeSet;
+use std::fmt;
+use super::{Instruction, InstructionId, InstructionParameter, ParameterType, ParsedProgram};
+use super::validate_loops::{validate_loops, ValidateLoopError};
+
+/// The register 0 is for input data and output data.
+const INPUT_OUTPUT_REGISTER: u64 = 0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgramAnalysisIssue {
+    /// The value written to the register is never read.
+    DeadStore { line_number: usize, register: u64 },
+
+    /// The register is read before any value has been written to it, so its value is zero.
+    ReadBeforeWrite { line_number: usize, register: u64 },
+
+    /// The instruction doesn't change any registers, such as `add $1,0` or `mul $1,1`.
+    NoEffect { line_number: usize },
+
+    /// The loop counter is never decremented, so the loop body is evaluated once and then rolled back.
+    LoopCounterNeverDecremented { line_number: usize, register: u64 },
+}
+
+impl ProgramAnalysisIssue {
+    pub fn line_number(&self) -> usize {
+        match self {
+            Self::DeadStore { line_number, .. } => *line_number,
+            Self::ReadBeforeWrite { line_number, .. } => *line_number,
+            Self::NoEffect { line_number } => *line_number,
+            Self::LoopCounterNeverDecremented { line_number, .. } => *line_number,
+        }
+    }
+}
+
+impl fmt::Display for ProgramAnalysisIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DeadStore { line_number, register } =>
+                write!(f, "line {}: the value written to ${} is never read", line_number, register),
+            Self::ReadBeforeWrite { line_number, register } =>
+                write!(f, "line {}: ${} is read before it has been written", line_number, register),
+            Self::NoEffect { line_number } =>
+                write!(f, "line {}: the instruction has no effect", line_number),
+            Self::LoopCounterNeverDecremented { line_number, register } =>
+                write!(f, "line {}: the loop counter ${} is never decremented", line_number, register),
+        }
+    }
+}
+
+/// A set of registers, that can also represent all registers.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct RegisterSet {
+    all: bool,
+    registers: BTreeSet<u64>,
+}
+
+impl RegisterSet {
+    fn contains(&self, register: u64) -> bool {
+        self.all || self.registers.contains(&register)
+    }
+
+    fn insert_all(&mut self, other: &RegisterSet) {
+        self.all |= other.all;
+        self.registers.extend(other.registers.iter());
+    }
+}
+
+/// The registers that an instruction reads and writes.
+#[derive(Debug, Default)]
+struct InstructionEffect {
+    uses: RegisterSet,
+
+    /// Registers that are always overwritten by the instruction.
+    defs: BTreeSet<u64>,
+
+    /// The instruction may write to registers that are unknown at compile time.
+    writes_unknown: bool,
+}
+
+impl InstructionEffect {
+    /// Register an operand that is being read.
+    fn read(&mut self, parameter: &InstructionParameter) {
+        match parameter.parameter_type {
+            ParameterType::Constant => {},
+            ParameterType::Direct => {
+                if parameter.parameter_value >= 0 {
+                    self.uses.registers.insert(parameter.parameter_value as u64);
+                }
+            },
+            ParameterType::Indirect => {
+                self.uses.all = true;
+            }
+        }
+    }
+
+    /// Register an operand that is being written.
+    fn write(&mut self, parameter: &InstructionParameter) {
+        match parameter.parameter_type {
+            ParameterType::Constant => {},
+            ParameterType::Direct => {
+                if parameter.parameter_value >= 0 {
+                    self.defs.insert(parameter.parameter_value as u64);
+                }
+            },
+            ParameterType::Indirect => {
+                // The address register is read, and the destination is unknown.
+                if parameter.parameter_value >= 0 {
+                    self.uses.registers.insert(parameter.parameter_value as u64);
+                }
+                self.writes_unknown = true;
+            }
+        }
+    }
+
+    fn read_range(&mut self, start: &InstructionParameter, count: u64) {
+        if start.parameter_type != ParameterType::Direct || start.parameter_value < 0 {
+            self.read(start);
+            return;
+        }
+        for i in 0..count {
+            self.uses.registers.insert((start.parameter_value as u64) + i);
+        }
+    }
+
+    fn write_range(&mut self, start: &InstructionParameter, count: u64) {
+        if start.parameter_type != ParameterType::Direct || start.parameter_value < 0 {
+            self.write(start);
+            return;
+        }
+        for i in 0..count {
+            self.defs.insert((start.parameter_value as u64) + i);
+        }
+    }
+}
+
+fn loop_counter_range(instruction: &Instruction) -> Option<u64> {
+    match instruction.parameter_vec.get(1) {
+        None => Some(1),
+        Some(parameter) => {
+            if parameter.parameter_type != ParameterType::Constant || parameter.is_big_constant() {
+                return None;
+            }
+            Some(parameter.parameter_value.max(1) as u64)
+        }
+    }
+}
+
+fn instruction_effect(instruction: &Instruction) -> InstructionEffect {
+    let mut effect = InstructionEffect::default();
+    let parameters: &Vec<InstructionParameter> = &instruction.parameter_vec;
+    match instruction.instruction_id {
+        InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract => {
+            // The loop counter is compared at the end of each cycle.
+            if let Some(target) = parameters.first() {
+                match loop_counter_range(instruction) {
+                    Some(count) => effect.read_range(target, count),
+                    None => {
+                        // The range length is stored in a register, so it's unknown which registers are compared.
+                        effect.read(target);
+                        effect.uses.all = true;
+                    }
+                }
+            }
+            for parameter in parameters.iter().skip(1) {
+                effect.read(parameter);
+            }
+        },
+        InstructionId::LoopEnd => {},
+        InstructionId::Clear | InstructionId::Fill | InstructionId::RotateLeft | InstructionId::RotateRight => {
+            // The range of registers is unknown.
+            effect.uses.all = true;
+            effect.writes_unknown = true;
+        },
+        InstructionId::EvalSequence => {
+            if let Some(target) = parameters.first() {
+                effect.read(target);
+                effect.write(target);
+            }
+        },
+        InstructionId::UnofficialFunction { input_count, output_count } => {
+            if let Some(target) = parameters.first() {
+                effect.read_range(target, input_count as u64);
+                effect.write_range(target, output_count as u64);
+            }
+        },
+        InstructionId::Move => {
+            if let Some(source) = parameters.get(1) {
+                effect.read(source);
+            }
+            if let Some(target) = parameters.first() {
+                effect.write(target);
+            }
+        },
+        _ => {
+            for parameter in parameters {
+                effect.read(parameter);
+            }
+            if let Some(target) = parameters.first() {
+                effect.write(target);
+            }
+        }
+    }
+    effect
+}
+
+/// Determines if the instruction doesn't change the target register, such as `add $1,0`.
+fn is_no_effect(instruction: &Instruction) -> bool {
+    let target: &InstructionParameter = match instruction.parameter_vec.first() {
+        Some(value) => value,
+        None => return false
+    };
+    let source: &InstructionParameter = match instruction.parameter_vec.get(1) {
+        Some(value) => value,
+        None => return false
+    };
+    if target.parameter_type == ParameterType::Constant {
+        return false;
+    }
+    if source.parameter_type == ParameterType::Constant {
+        if source.is_big_constant() {
+            return false;
+        }
+        let value: i64 = source.parameter_value;
+        return match instruction.instruction_id {
+            InstructionId::Add | InstructionId::Subtract => value == 0,
+            InstructionId::BitwiseOr | InstructionId::BitwiseXor => value == 0,
+            InstructionId::Multiply | InstructionId::Divide | InstructionId::DivideIf => value == 1,
+            InstructionId::Power => value == 1,
+            InstructionId::Clear | InstructionId::Fill => value == 0,
+            _ => false
+        };
+    }
+    if source != target {
+        return false;
+    }
+    matches!(
+        instruction.instruction_id,
+        InstructionId::Move | InstructionId::Min | InstructionId::Max | InstructionId::BitwiseAnd | InstructionId::BitwiseOr
+    )
+}
+
+/// For each instruction, the index of the matching `lpb` or `lpe` instruction.
+fn loop_partners(instruction_vec: &[Instruction]) -> Vec<Option<usize>> {
+    let mut partners: Vec<Option<usize>> = vec![None; instruction_vec.len()];
+    let mut stack: Vec<usize> = vec!();
+    for (index, instruction) in instruction_vec.iter().enumerate() {
+        match instruction.instruction_id {
+            InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract => {
+                stack.push(index);
+            },
+            InstructionId::LoopEnd => {
+                if let Some(begin_index) = stack.pop() {
+                    partners[begin_index] = Some(index);
+                    partners[index] = Some(begin_index);
+                }
+            },
+            _ => {}
+        }
+    }
+    partners
+}
+
+/// The instructions that can be executed after an instruction.
+///
+/// The index `instruction_vec.len()` is the end of the program.
+///
+/// When a loop ends, the state from the beginning of the last cycle is restored,
+/// so the loop is exited from the `lpb` instruction rather than from the `lpe` instruction.
+fn successors(instruction_vec: &[Instruction], partners: &[Option<usize>], index: usize) -> Vec<usize> {
+    let instruction: &Instruction = &instruction_vec[index];
+    match (instruction.instruction_id, partners[index]) {
+        (InstructionId::LoopBegin, Some(end_index)) => vec![index + 1, end_index + 1],
+        // The `lps` instruction doesn't restore the state when exiting.
+        (InstructionId::UnofficialLoopBeginSubtract, Some(end_index)) => vec![index + 1, end_index + 1],
+        (InstructionId::LoopEnd, Some(begin_index)) => {
+            if instruction_vec[begin_index].instruction_id == InstructionId::UnofficialLoopBeginSubtract {
+                return vec![begin_index, index + 1];
+            }
+            vec![begin_index]
+        },
+        _ => vec![index + 1]
+    }
+}
+
+/// The registers that are live after each instruction, meaning that their value may be read later on.
+fn live_out(instruction_vec: &[Instruction], effects: &[InstructionEffect], partners: &[Option<usize>]) -> Vec<RegisterSet> {
+    let count: usize = instruction_vec.len();
+
+    // The output register is read after the program has ended.
+    let mut live_end = RegisterSet::default();
+    live_end.registers.insert(INPUT_OUTPUT_REGISTER);
+
+    let mut live_in: Vec<RegisterSet> = vec![RegisterSet::default(); count];
+    let mut live_out: Vec<RegisterSet> = vec![RegisterSet::default(); count];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in (0..count).rev() {
+            let mut out = RegisterSet::default();
+            for successor in successors(instruction_vec, partners, index) {
+                if successor >= count {
+                    out.insert_all(&live_end);
+                } else {
+                    out.insert_all(&live_in[successor]);
+                }
+            }
+            let mut input: RegisterSet = out.clone();
+            for register in &effects[index].defs {
+                input.registers.remove(register);
+            }
+            input.insert_all(&effects[index].uses);
+            if input != live_in[index] || out != live_out[index] {
+                live_in[index] = input;
+                live_out[index] = out;
+                changed = true;
+            }
+        }
+    }
+    live_out
+}
+
+fn analyze_dead_stores(instruction_vec: &[Instruction], effects: &[InstructionEffect], live_out: &[RegisterSet], issues: &mut Vec<ProgramAnalysisIssue>) {
+    for (index, instruction) in instruction_vec.iter().enumerate() {
+        match instruction.instruction_id {
+            InstructionId::LoopBegin | InstructionId::UnofficialLoopBeginSubtract | InstructionId::LoopEnd => continue,
+            _ => {}
+        }
+        for register in &effects[index].defs {
+            if !live_out[index].contains(*register) {
+                issues.push(ProgramAnalysisIssue::DeadStore { line_number: instruction.line_number, register: *register });
+            }
+        }
+    }
+}
+
+fn analyze_read_before_write(instruction_vec: &[Instruction], effects: &[InstructionEffect], issues: &mut Vec<ProgramAnalysisIssue>) {
+    let mut written = BTreeSet::<u64>::new();
+    written.insert(INPUT_OUTPUT_REGISTER);
+    for (index, instruction) in instruction_vec.iter().enumerate() {
+        let effect: &InstructionEffect = &effects[index];
+        for register in &effect.uses.registers {
+            if written.insert(*register) {
+                issues.push(ProgramAnalysisIssue::ReadBeforeWrite { line_number: instruction.line_number, register: *register });
+            }
+        }
+        if effect.writes_unknown {
+            // Any register may have been written.
+            return;
+        }
+        written.extend(effect.defs.iter());
+    }
+}
+
+fn analyze_loop_counters(instruction_vec: &[Instruction], effects: &[InstructionEffect], partners: &[Option<usize>], issues: &mut Vec<ProgramAnalysisIssue>) {
+    for (begin_index, instruction) in instruction_vec.iter().enumerate() {
+        if instruction.instruction_id != InstructionId::LoopBegin {
+            continue;
+        }
+        let end_index: usize = match partners[begin_index] {
+            Some(value) => value,
+            None => continue
+        };
+        let target: &InstructionParameter = match instruction.parameter_vec.first() {
+            Some(value) => value,
+            None => continue
+        };
+        if target.parameter_type != ParameterType::Direct || target.parameter_value < 0 {
+            continue;
+        }
+        let range_length: u64 = match loop_counter_range(instruction) {
+            Some(value) => value,
+            None => continue
+        };
+        let counter_start = target.parameter_value as u64;
+        let counter_end: u64 = counter_start + range_length;
+        let mut may_decrement = false;
+        for index in (begin_index + 1)..end_index {
+            let effect: &InstructionEffect = &effects[index];
+            if effect.writes_unknown {
+                may_decrement = true;
+                break;
+            }
+            if !effect.defs.iter().any(|register| *register >= counter_start && *register < counter_end) {
+                continue;
+            }
+            // Adding a non-negative constant cannot decrement the counter.
+            let body_instruction: &Instruction = &instruction_vec[index];
+            let is_increment: bool = body_instruction.instruction_id == InstructionId::Add &&
+                body_instruction.parameter_vec.get(1).is_some_and(|parameter| {
+                    parameter.parameter_type == ParameterType::Constant && parameter.parameter_value >= 0 &&
+                        parameter.parameter_big_value.as_ref().is_none_or(|value| value.sign() != num_bigint::Sign::Minus)
+                });
+            if !is_increment {
+                may_decrement = true;
+                break;
+            }
+        }
+        if !may_decrement {
+            issues.push(ProgramAnalysisIssue::LoopCounterNeverDecremented { line_number: instruction.line_number, register: counter_start });
+        }
+    }
+}
+
+/// Find dead stores, registers read before written, instructions without effect,
+/// and loops where the counter is never decremented.
+///
+/// The issues are sorted by line number.
+///
+/// Returns an error if the `lpb` and `lpe` instructions are unbalanced.
+pub fn analyze_program(parsed_program: &ParsedProgram) -> Result<Vec<ProgramAnalysisIssue>, ValidateLoopError> {
+    let instruction_vec: &Vec<Instruction> = &parsed_program.instruction_vec;
+    validate_loops(instruction_vec)?;
+    let effects: Vec<InstructionEffect> = instruction_vec.iter().map(instruction_effect).collect();
+    let partners: Vec<Option<usize>> = loop_partners(instruction_vec);
+    let live_out: Vec<RegisterSet> = live_out(instruction_vec, &effects, &partners);
+
+    let mut issues: Vec<ProgramAnalysisIssue> = vec!();
+    analyze_dead_stores(instruction_vec, &effects, &live_out, &mut issues);
+    analyze_read_before_write(instruction_vec, &effects, &mut issues);
+    for instruction in instruction_vec {
+        if is_no_effect(instruction) {
+            issues.push(ProgramAnalysisIssue::NoEffect { line_number: instruction.line_number });
+        }
+    }
+    analyze_loop_counters(instruction_vec, &effects, &partners, &mut issues);
+    issues.sort_by_key(|issue| issue.line_number());
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(input: &str) -> String {
+        let parsed_program: ParsedProgram = match ParsedProgram::parse_program(input) {
+            Ok(value) => value,
+            Err(error) => {
+                return format!("PARSE ERROR: {:?}", error);
+            }
+        };
+        let issues: Vec<ProgramAnalysisIssue> = match analyze_program(&parsed_program) {
+            Ok(value) => value,
+            Err(error) => {
+                return format!("ANALYZE ERROR: {:?}", error);
+            }
+        };
+        let strings: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        strings.join("\n")
+    }
+
+    #[test]
+    fn test_10000_no_issues() {
+        assert_eq!(process(""), "");
+        assert_eq!(process("mul $0,2"), "");
+        let program = "mov $1,1\nlpb $0\n  sub $0,1\n  mul $1,3\nlpe\nmov $0,$1";
+        assert_eq!(process(program), "");
+    }
+
+    #[test]
+    fn test_10001_unbalanced_loops() {
+        assert_eq!(process("lpb $0"), "ANALYZE ERROR: Unbalanced");
+    }
+
+    #[test]
+    fn test_20000_dead_store() {
+        assert_eq!(process("mov $1,5\nmov $0,7"), "line 1: the value written to $1 is never read");
+        assert_eq!(process("mov $0,5\nmov $0,7"), "line 1: the value written to $0 is never read");
+        assert_eq!(process("mov $1,$0\nmul $1,2"), "line 2: the value written to $1 is never read");
+    }
+
+    #[test]
+    fn test_20001_dead_store_inside_loop() {
+        // The value of $2 is read in the next cycle
+        assert_eq!(process("mov $1,1\nmov $2,1\nlpb $0\n  sub $0,1\n  add $1,$2\n  mov $2,$1\nlpe\nmov $0,$1"), "");
+        // The value of $2 is never read
+        assert_eq!(process("lpb $0\n  sub $0,1\n  mov $2,3\nlpe"), "line 3: the value written to $2 is never read");
+    }
+
+    #[test]
+    fn test_20002_dead_store_indirect() {
+        // With indirect addressing, any register may be read
+        assert_eq!(process("mov $1,5\nmov $2,1\nmov $0,$$2"), "");
+    }
+
+    #[test]
+    fn test_30000_read_before_write() {
+        assert_eq!(process("add $0,$1"), "line 1: $1 is read before it has been written");
+        assert_eq!(process("add $1,$0\nmov $0,$1"), "line 1: $1 is read before it has been written");
+        assert_eq!(process("mov $1,$0\nmov $0,$1"), "");
+    }
+
+    #[test]
+    fn test_40000_no_effect() {
+        assert_eq!(process("add $0,0"), "line 1: the instruction has no effect");
+        assert_eq!(process("mul $0,1"), "line 1: the instruction has no effect");
+        assert_eq!(process("mov $0,$0"), "line 1: the instruction has no effect");
+        assert_eq!(process("max $0,$0"), "line 1: the instruction has no effect");
+        assert_eq!(process("add $0,$0"), "");
+        assert_eq!(process("trn $0,0"), "");
+    }
+
+    #[test]
+    fn test_50000_loop_counter_never_decremented() {
+        let expected = "line 1: the loop counter $0 is never decremented\nline 2: $1 is read before it has been written";
+        assert_eq!(process("lpb $0\n  add $1,1\nlpe"), expected);
+        assert_eq!(process("lpb $0\n  add $0,1\nlpe"), "line 1: the loop counter $0 is never decremented");
+        assert_eq!(process("lpb $0\n  sub $0,1\nlpe"), "");
+        assert_eq!(process("lpb $0,2\n  sub $1,1\nlpe"), "line 1: $1 is read before it has been written");
+        assert_eq!(process("lpb $0\n  mov $1,0\n  mov $$1,0\nlpe"), "");
+    }
+}
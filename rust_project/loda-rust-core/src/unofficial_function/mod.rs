@@ -2,7 +2,9 @@
 
 mod assert;
 mod debug;
+mod number_theory;
 mod product;
+mod program_function;
 mod register_common_functions;
 mod sort;
 mod sum;
@@ -14,8 +16,10 @@ mod unofficial_function_registry;
 
 pub use assert::{AssertFunction, AssertFunctionMode};
 pub use debug::DebugFunction;
+pub use number_theory::{NumberTheoryFunction, NumberTheoryFunctionMode};
 pub use product::ProductFunction;
-pub use register_common_functions::register_common_functions;
+pub use program_function::{ProgramFunction, register_program_functions};
+pub use register_common_functions::{is_builtin_function_id, register_common_functions};
 pub use sort::SortFunction;
 pub use sum::SumFunction;
 pub use test_util::perform_run;
@@ -0,0 +1,655 @@
+use super::{UnofficialFunction, UnofficialFunctionId};
+use crate::execute::{EvalError, NodeLoopLimit, NodeRegisterLimit};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub enum NumberTheoryFunctionMode {
+    /// `1` when the input is a prime, otherwise `0`.
+    IsPrime,
+
+    /// The n'th prime, where `a(1) = 2`.
+    NthPrime,
+
+    /// Euler's totient function, `phi(n)`.
+    EulerPhi,
+
+    /// Number of divisors, `sigma0(n)`.
+    DivisorCount,
+
+    /// Sum of divisors, `sigma1(n)`.
+    DivisorSum,
+
+    /// Moebius function, `mu(n)`.
+    Moebius,
+
+    /// Smallest prime factor, with `a(1) = 1`.
+    SmallestPrimeFactor,
+
+    /// Greatest prime factor, with `a(1) = 1`.
+    GreatestPrimeFactor,
+
+    /// Number of prime factors counted with multiplicity, `bigomega(n)`.
+    PrimeFactorCount,
+
+    /// Modular inverse of input[0] modulo input[1].
+    ModularInverse,
+}
+
+pub struct NumberTheoryFunction {
+    id: u32,
+    mode: NumberTheoryFunctionMode,
+}
+
+impl NumberTheoryFunction {
+    pub fn new(id: u32, mode: NumberTheoryFunctionMode) -> Self {
+        Self {
+            id,
+            mode,
+        }
+    }
+
+    fn run_inner(&self, input: &[BigInt], step_counter: &mut StepCounter) -> anyhow::Result<BigInt> {
+        let input0: &BigInt = &input[0];
+        match self.mode {
+            NumberTheoryFunctionMode::IsPrime => {
+                if is_prime(input0, step_counter)? {
+                    return Ok(BigInt::one());
+                }
+                Ok(BigInt::zero())
+            },
+            NumberTheoryFunctionMode::NthPrime => {
+                nth_prime(input0, step_counter)
+            },
+            NumberTheoryFunctionMode::EulerPhi => {
+                let mut result = BigInt::one();
+                for (prime, exponent) in factorize(input0, step_counter)? {
+                    result *= prime.pow(exponent - 1) * (&prime - 1);
+                }
+                Ok(result)
+            },
+            NumberTheoryFunctionMode::DivisorCount => {
+                let mut result = BigInt::one();
+                for (_prime, exponent) in factorize(input0, step_counter)? {
+                    result *= exponent + 1;
+                }
+                Ok(result)
+            },
+            NumberTheoryFunctionMode::DivisorSum => {
+                let mut result = BigInt::one();
+                for (prime, exponent) in factorize(input0, step_counter)? {
+                    result *= (prime.pow(exponent + 1) - 1) / (&prime - 1);
+                }
+                Ok(result)
+            },
+            NumberTheoryFunctionMode::Moebius => {
+                let factors: Vec<(BigInt, u32)> = factorize(input0, step_counter)?;
+                if factors.iter().any(|(_prime, exponent)| *exponent > 1) {
+                    return Ok(BigInt::zero());
+                }
+                if factors.len() % 2 == 1 {
+                    return Ok(-BigInt::one());
+                }
+                Ok(BigInt::one())
+            },
+            NumberTheoryFunctionMode::SmallestPrimeFactor => {
+                let factors: Vec<(BigInt, u32)> = factorize(input0, step_counter)?;
+                Ok(factors.first().map_or_else(BigInt::one, |(prime, _exponent)| prime.clone()))
+            },
+            NumberTheoryFunctionMode::GreatestPrimeFactor => {
+                let factors: Vec<(BigInt, u32)> = factorize(input0, step_counter)?;
+                Ok(factors.last().map_or_else(BigInt::one, |(prime, _exponent)| prime.clone()))
+            },
+            NumberTheoryFunctionMode::PrimeFactorCount => {
+                let factors: Vec<(BigInt, u32)> = factorize(input0, step_counter)?;
+                let count: u32 = factors.iter().map(|(_prime, exponent)| *exponent).sum();
+                Ok(BigInt::from(count))
+            },
+            NumberTheoryFunctionMode::ModularInverse => {
+                modular_inverse(input0, &input[1])
+            },
+        }
+    }
+}
+
+impl UnofficialFunction for NumberTheoryFunction {
+    fn id(&self) -> UnofficialFunctionId {
+        let inputs: u8 = match self.mode {
+            NumberTheoryFunctionMode::ModularInverse => 2,
+            _ => 1,
+        };
+        UnofficialFunctionId::InputOutput { id: self.id, inputs, outputs: 1 }
+    }
+
+    fn name(&self) -> String {
+        let name: &str = match self.mode {
+            NumberTheoryFunctionMode::IsPrime => "Is input[0] a prime, 1=yes 0=no.",
+            NumberTheoryFunctionMode::NthPrime => "The n'th prime.",
+            NumberTheoryFunctionMode::EulerPhi => "Euler totient function phi(n).",
+            NumberTheoryFunctionMode::DivisorCount => "Number of divisors of n.",
+            NumberTheoryFunctionMode::DivisorSum => "Sum of divisors of n.",
+            NumberTheoryFunctionMode::Moebius => "Moebius function mu(n).",
+            NumberTheoryFunctionMode::SmallestPrimeFactor => "Smallest prime factor of n.",
+            NumberTheoryFunctionMode::GreatestPrimeFactor => "Greatest prime factor of n.",
+            NumberTheoryFunctionMode::PrimeFactorCount => "Number of prime factors of n counted with multiplicity.",
+            NumberTheoryFunctionMode::ModularInverse => "Modular inverse of input[0] modulo input[1].",
+        };
+        name.to_string()
+    }
+
+    fn run(&self, input: Vec<BigInt>) -> anyhow::Result<Vec<BigInt>> {
+        let (output, _step_count) = self.run_with_step_count_limit(input, u64::MAX, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited)?;
+        Ok(output)
+    }
+
+    fn run_with_step_count_limit(&self, input: Vec<BigInt>, step_count_limit: u64, _node_register_limit: &NodeRegisterLimit, _node_loop_limit: &NodeLoopLimit) -> anyhow::Result<(Vec<BigInt>, u64)> {
+        let expected_input_count: usize = match self.id() {
+            UnofficialFunctionId::InputOutput { inputs, .. } => inputs as usize,
+        };
+        if input.len() != expected_input_count {
+            return Err(anyhow::anyhow!("Wrong number of inputs"));
+        }
+        let mut step_counter = StepCounter::new(step_count_limit);
+        let value: BigInt = self.run_inner(&input, &mut step_counter)?;
+        Ok((vec![value], step_counter.step_count.max(1)))
+    }
+}
+
+/// The work done by the primality test, the sieve, trial division and Pollard's rho,
+/// so that a huge number cannot bypass the step limit of the calling program.
+struct StepCounter {
+    step_count: u64,
+    step_count_limit: u64,
+}
+
+impl StepCounter {
+    fn new(step_count_limit: u64) -> Self {
+        Self {
+            step_count: 0,
+            step_count_limit,
+        }
+    }
+
+    fn increment(&mut self) -> anyhow::Result<()> {
+        self.add(1)
+    }
+
+    fn add(&mut self, count: u64) -> anyhow::Result<()> {
+        self.step_count = self.step_count.saturating_add(count);
+        if self.step_count >= self.step_count_limit {
+            return Err(anyhow::Error::new(EvalError::StepCountExceededLimit));
+        }
+        Ok(())
+    }
+}
+
+/// Primes below this limit are found by trial division, before trying Pollard's rho.
+const TRIAL_DIVISION_LIMIT: u32 = 10_000;
+
+/// Max number of iterations of Pollard's rho, for a single choice of the polynomial.
+const POLLARD_RHO_ITERATION_LIMIT: u32 = 1_000_000;
+
+/// The largest `n` accepted by `nth_prime`, so the table of primes stays within a few megabytes.
+const NTH_PRIME_LIMIT: u64 = 1_000_000;
+
+/// The primes found so far by `nth_prime`. The table is extended when a larger `n` is requested.
+static NTH_PRIME_TABLE: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// The small primes that are tried as divisors, before doing the probable prime tests.
+const SMALL_PRIMES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Baillie-PSW primality test, a Miller-Rabin test with base 2 followed by a strong Lucas test.
+///
+/// There is no known composite that passes both tests, and it has been verified that none exists below `2^64`.
+///
+/// The steps are proportional to the number of bits in `n`, since each test does one modular squaring per bit.
+fn is_prime(n: &BigInt, step_counter: &mut StepCounter) -> anyhow::Result<bool> {
+    if *n < BigInt::from(2) {
+        return Ok(false);
+    }
+    for prime in SMALL_PRIMES {
+        let prime = BigInt::from(prime);
+        if *n == prime {
+            return Ok(true);
+        }
+        if (n % &prime).is_zero() {
+            return Ok(false);
+        }
+    }
+    if !is_strong_probable_prime_base2(n, step_counter)? {
+        return Ok(false);
+    }
+    is_strong_lucas_probable_prime(n, step_counter)
+}
+
+/// Miller-Rabin test with base 2, for an odd `n > 2`.
+fn is_strong_probable_prime_base2(n: &BigInt, step_counter: &mut StepCounter) -> anyhow::Result<bool> {
+    let n_minus_one: BigInt = n - 1;
+    let mut d: BigInt = n_minus_one.clone();
+    let mut s: u32 = 0;
+    while d.is_even() {
+        d >>= 1;
+        s += 1;
+    }
+    step_counter.add(d.bits())?;
+    let mut x: BigInt = BigInt::from(2).modpow(&d, n);
+    if x.is_one() || x == n_minus_one {
+        return Ok(true);
+    }
+    for _ in 1..s {
+        step_counter.increment()?;
+        x = (&x * &x) % n;
+        if x == n_minus_one {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Strong Lucas test with Selfridge's parameters, for an odd `n > 2` without small prime factors.
+fn is_strong_lucas_probable_prime(n: &BigInt, step_counter: &mut StepCounter) -> anyhow::Result<bool> {
+    // A perfect square has no `D` with the Jacobi symbol -1, so the search below would never end.
+    if perfect_square_root(n).is_some() {
+        return Ok(false);
+    }
+    // Find the first `D` in 5, -7, 9, -11, 13, ... where the Jacobi symbol `(D/n)` is -1.
+    let mut d = BigInt::from(5);
+    loop {
+        step_counter.increment()?;
+        match jacobi_symbol(&d, n) {
+            -1 => break,
+            0 if d.abs() != *n => {
+                return Ok(false);
+            },
+            _ => {}
+        }
+        d = if d.is_positive() { -(d + 2u32) } else { -(d - 2u32) };
+    }
+    // P = 1, Q = (1 - D) / 4
+    let q: BigInt = (BigInt::one() - &d) / 4;
+
+    // Write n + 1 = k * 2^s, where k is odd.
+    let mut k: BigInt = n + 1;
+    let mut s: u32 = 0;
+    while k.is_even() {
+        k >>= 1;
+        s += 1;
+    }
+
+    // Compute U(k), V(k) and Q^k, by going through the bits of k, starting with the most significant bit.
+    let half = |value: BigInt| -> BigInt {
+        let value: BigInt = if value.is_odd() { value + n } else { value };
+        let value: BigInt = value >> 1usize;
+        value.mod_floor(n)
+    };
+    let mut u = BigInt::one();
+    let mut v = BigInt::one();
+    let mut q_k: BigInt = q.mod_floor(n);
+    for bit_index in (0..(k.bits() - 1)).rev() {
+        step_counter.increment()?;
+        u = (&u * &v).mod_floor(n);
+        v = (&v * &v - &q_k * 2u32).mod_floor(n);
+        q_k = (&q_k * &q_k).mod_floor(n);
+        if k.bit(bit_index) {
+            let u_next: BigInt = half(&u + &v);
+            let v_next: BigInt = half(&d * &u + &v);
+            u = u_next;
+            v = v_next;
+            q_k = (&q_k * &q).mod_floor(n);
+        }
+    }
+    if u.is_zero() || v.is_zero() {
+        return Ok(true);
+    }
+    // V(2k) = V(k)^2 - 2 Q^k
+    for _ in 1..s {
+        step_counter.increment()?;
+        v = (&v * &v - &q_k * 2u32).mod_floor(n);
+        if v.is_zero() {
+            return Ok(true);
+        }
+        q_k = (&q_k * &q_k).mod_floor(n);
+    }
+    Ok(false)
+}
+
+/// The Jacobi symbol `(a/n)` for an odd positive `n`. Returns -1, 0 or 1.
+fn jacobi_symbol(a: &BigInt, n: &BigInt) -> i8 {
+    let mut a: BigInt = a.mod_floor(n);
+    let mut n: BigInt = n.clone();
+    let mut result: i8 = 1;
+    while !a.is_zero() {
+        while a.is_even() {
+            a >>= 1;
+            let n_mod8: u32 = (&n % 8u32).to_u32().unwrap_or(0);
+            if n_mod8 == 3 || n_mod8 == 5 {
+                result = -result;
+            }
+        }
+        std::mem::swap(&mut a, &mut n);
+        let a_mod4: u32 = (&a % 4u32).to_u32().unwrap_or(0);
+        let n_mod4: u32 = (&n % 4u32).to_u32().unwrap_or(0);
+        if a_mod4 == 3 && n_mod4 == 3 {
+            result = -result;
+        }
+        a = a.mod_floor(&n);
+    }
+    if n.is_one() {
+        return result;
+    }
+    0
+}
+
+/// The n'th prime.
+///
+/// The steps are the size of the sieve needed for the n'th prime, no matter if the prime is already in the table,
+/// so the step count is the same every time.
+fn nth_prime(n: &BigInt, step_counter: &mut StepCounter) -> anyhow::Result<BigInt> {
+    let n: u64 = match n.to_u64() {
+        Some(value) if (1..=NTH_PRIME_LIMIT).contains(&value) => value,
+        _ => {
+            return Err(anyhow::anyhow!("nth_prime expected input in the range 1..={}, but got {}", NTH_PRIME_LIMIT, n));
+        }
+    };
+    step_counter.add(sieve_limit(n as usize) as u64)?;
+    let mut primes = NTH_PRIME_TABLE.lock()
+        .map_err(|e| anyhow::anyhow!("nth_prime the table of primes is poisoned. error: {:?}", e))?;
+    if primes.len() < n as usize {
+        // Grow the table by at least a factor 2, so the sieve only runs a few times.
+        let count: usize = (n as usize).max(primes.len() * 2).min(NTH_PRIME_LIMIT as usize);
+        *primes = sieve_primes(count);
+    }
+    match primes.get((n - 1) as usize) {
+        Some(value) => Ok(BigInt::from(*value)),
+        None => Err(anyhow::anyhow!("nth_prime sieve is too small for n={}", n))
+    }
+}
+
+/// Upper bound for the `count`'th prime.
+fn sieve_limit(count: usize) -> usize {
+    // Rosser's theorem, p(n) < n * (ln n + ln ln n) for n >= 6.
+    if count < 6 {
+        return 13;
+    }
+    let x = count as f64;
+    (x * (x.ln() + x.ln().ln())) as usize + 1
+}
+
+/// The first `count` primes, using the sieve of Eratosthenes.
+fn sieve_primes(count: usize) -> Vec<u32> {
+    let limit: usize = sieve_limit(count);
+    let mut is_composite: Vec<bool> = vec![false; limit + 1];
+    let mut primes: Vec<u32> = Vec::with_capacity(count);
+    for i in 2..=limit {
+        if is_composite[i] {
+            continue;
+        }
+        primes.push(i as u32);
+        if primes.len() == count {
+            break;
+        }
+        let mut j: usize = i * i;
+        while j <= limit {
+            is_composite[j] = true;
+            j += i;
+        }
+    }
+    primes
+}
+
+/// The prime factors of a positive integer, in ascending order, with their exponents.
+///
+/// Returns an error for `n < 1`, if Pollard's rho fails to find a factor, or if the step limit is exceeded.
+fn factorize(n: &BigInt, step_counter: &mut StepCounter) -> anyhow::Result<Vec<(BigInt, u32)>> {
+    if !n.is_positive() {
+        return Err(anyhow::anyhow!("factorize expected a positive integer, but got {}", n));
+    }
+    let mut factors = BTreeMap::<BigInt, u32>::new();
+    let mut remaining: BigInt = n.clone();
+    let mut divisor: u32 = 2;
+    while divisor < TRIAL_DIVISION_LIMIT {
+        let d = BigInt::from(divisor);
+        if &d * &d > remaining {
+            break;
+        }
+        step_counter.increment()?;
+        while (&remaining % &d).is_zero() {
+            remaining /= &d;
+            *factors.entry(d.clone()).or_insert(0) += 1;
+        }
+        divisor += if divisor == 2 { 1 } else { 2 };
+    }
+    let mut pending: Vec<BigInt> = vec!();
+    if !remaining.is_one() {
+        pending.push(remaining);
+    }
+    while let Some(value) = pending.pop() {
+        if is_prime(&value, step_counter)? {
+            *factors.entry(value).or_insert(0) += 1;
+            continue;
+        }
+        if let Some(root) = perfect_square_root(&value) {
+            pending.push(root.clone());
+            pending.push(root);
+            continue;
+        }
+        let factor: BigInt = pollard_rho(&value, step_counter)?;
+        pending.push(&value / &factor);
+        pending.push(factor);
+    }
+    Ok(factors.into_iter().collect())
+}
+
+fn perfect_square_root(n: &BigInt) -> Option<BigInt> {
+    let root: BigInt = n.sqrt();
+    if &root * &root == *n {
+        return Some(root);
+    }
+    None
+}
+
+/// Find a non-trivial factor of a composite number, that has no small prime factors.
+fn pollard_rho(n: &BigInt, step_counter: &mut StepCounter) -> anyhow::Result<BigInt> {
+    for c in 1..20u32 {
+        let c = BigInt::from(c);
+        let mut x = BigInt::from(2);
+        let mut y = BigInt::from(2);
+        for _ in 0..POLLARD_RHO_ITERATION_LIMIT {
+            step_counter.increment()?;
+            x = (&x * &x + &c) % n;
+            y = (&y * &y + &c) % n;
+            y = (&y * &y + &c) % n;
+            let d: BigInt = (&x - &y).abs().gcd(n);
+            if d.is_one() {
+                continue;
+            }
+            if d == *n {
+                break;
+            }
+            return Ok(d);
+        }
+    }
+    Err(anyhow::anyhow!("pollard_rho unable to find a factor of {}", n))
+}
+
+fn modular_inverse(value: &BigInt, modulus: &BigInt) -> anyhow::Result<BigInt> {
+    if !modulus.is_positive() {
+        return Err(anyhow::anyhow!("modular_inverse expected a positive modulus, but got {}", modulus));
+    }
+    let value: BigInt = value.mod_floor(modulus);
+    let extended = value.extended_gcd(modulus);
+    if !extended.gcd.is_one() {
+        return Err(anyhow::anyhow!("modular_inverse {} has no inverse modulo {}", value, modulus));
+    }
+    Ok(extended.x.mod_floor(modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::perform_run;
+    use std::str::FromStr;
+
+    fn run_sequence(mode: NumberTheoryFunctionMode, range: std::ops::RangeInclusive<i32>) -> Vec<i32> {
+        let f = NumberTheoryFunction::new(0, mode);
+        let f_boxed: Box<dyn UnofficialFunction> = Box::new(f);
+        let mut result: Vec<i32> = vec!();
+        for i in range {
+            let input: Vec<BigInt> = vec![BigInt::from(i)];
+            let output: Vec<BigInt> = f_boxed.run(input).expect("output");
+            result.push(output[0].to_i32().unwrap());
+        }
+        result
+    }
+
+    #[test]
+    fn test_10000_is_prime() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::IsPrime, -2..=13), vec![0, 0, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1]);
+        // Mersenne prime 2^127-1
+        assert!(is_prime(&BigInt::from_str("170141183460469231731687303715884105727").unwrap(), &mut StepCounter::new(u64::MAX)).unwrap());
+        // Carmichael number
+        assert!(!is_prime(&BigInt::from(561), &mut StepCounter::new(u64::MAX)).unwrap());
+    }
+
+    #[test]
+    fn test_10001_is_prime_bpsw() {
+        let is_prime_u64 = |n: u64| -> bool {
+            is_prime(&BigInt::from(n), &mut StepCounter::new(u64::MAX)).unwrap()
+        };
+        // Agrees with the sieve
+        let primes: Vec<u32> = sieve_primes(2000);
+        let largest: u64 = *primes.last().unwrap() as u64;
+        let primes_bpsw: Vec<u32> = (0..=largest).filter(|n| is_prime_u64(*n)).map(|n| n as u32).collect();
+        assert_eq!(primes_bpsw, primes);
+        // Strong pseudoprimes to base 2, that are rejected by the strong Lucas test
+        assert!(!is_prime_u64(2047));
+        assert!(!is_prime_u64(3215031751));
+        assert!(!is_prime_u64(3825123056546413051));
+        // Strong Lucas pseudoprimes, that are rejected by the Miller-Rabin test
+        assert!(!is_prime_u64(5459));
+        assert!(!is_prime_u64(5777));
+        // Perfect squares of primes
+        assert!(!is_prime_u64(43 * 43));
+        // Mersenne prime 2^521-1, and a composite Mersenne number 2^523-1
+        let two = BigInt::from(2);
+        assert!(is_prime(&(two.pow(521u32) - 1), &mut StepCounter::new(u64::MAX)).unwrap());
+        assert!(!is_prime(&(two.pow(523u32) - 1), &mut StepCounter::new(u64::MAX)).unwrap());
+    }
+
+    #[test]
+    fn test_10002_is_prime_step_count_limit() {
+        let n: BigInt = BigInt::from(2).pow(521u32) - 1;
+        let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::IsPrime);
+        let (output, step_count) = f.run_with_step_count_limit(vec![n.clone()], u64::MAX, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect("output");
+        assert_eq!(output, vec![BigInt::one()]);
+        assert!(step_count > 1000);
+        let error = f.run_with_step_count_limit(vec![n], 1000, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect_err("exceeds limit");
+        assert!(error.downcast_ref::<EvalError>().is_some());
+    }
+
+    #[test]
+    fn test_20000_nth_prime() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::NthPrime, 1..=10), vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::NthPrime, 10000..=10000), vec![104729]);
+        assert!(nth_prime(&BigInt::zero(), &mut StepCounter::new(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_20001_nth_prime_step_count_limit() {
+        let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::NthPrime);
+        let (output, step_count) = f.run_with_step_count_limit(vec![BigInt::from(10000)], u64::MAX, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect("output");
+        assert_eq!(output, vec![BigInt::from(104729)]);
+        assert_eq!(step_count, sieve_limit(10000) as u64);
+        // Same step count when the prime is already in the table
+        let (_output, step_count_again) = f.run_with_step_count_limit(vec![BigInt::from(10000)], u64::MAX, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect("output");
+        assert_eq!(step_count_again, step_count);
+        let error = f.run_with_step_count_limit(vec![BigInt::from(10000)], 1000, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect_err("exceeds limit");
+        assert!(error.downcast_ref::<EvalError>().is_some());
+    }
+
+    #[test]
+    fn test_30000_euler_phi() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::EulerPhi, 1..=12), vec![1, 1, 2, 2, 4, 2, 6, 4, 6, 4, 10, 4]);
+    }
+
+    #[test]
+    fn test_30001_divisor_count() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::DivisorCount, 1..=12), vec![1, 2, 2, 3, 2, 4, 2, 4, 3, 4, 2, 6]);
+    }
+
+    #[test]
+    fn test_30002_divisor_sum() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::DivisorSum, 1..=12), vec![1, 3, 4, 7, 6, 12, 8, 15, 13, 18, 12, 28]);
+    }
+
+    #[test]
+    fn test_30003_moebius() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::Moebius, 1..=12), vec![1, -1, -1, 0, -1, 1, -1, 0, 0, 1, -1, 0]);
+    }
+
+    #[test]
+    fn test_30004_prime_factors() {
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::SmallestPrimeFactor, 1..=12), vec![1, 2, 3, 2, 5, 2, 7, 2, 3, 2, 11, 2]);
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::GreatestPrimeFactor, 1..=12), vec![1, 2, 3, 2, 5, 3, 7, 2, 3, 5, 11, 3]);
+        assert_eq!(run_sequence(NumberTheoryFunctionMode::PrimeFactorCount, 1..=12), vec![0, 1, 1, 2, 1, 2, 1, 3, 2, 2, 1, 3]);
+    }
+
+    #[test]
+    fn test_30005_factorize_big() {
+        // 2^64+1 = 274177 * 67280421310721
+        let n = BigInt::from_str("18446744073709551617").unwrap();
+        let factors: Vec<(BigInt, u32)> = factorize(&n, &mut StepCounter::new(u64::MAX)).expect("factors");
+        assert_eq!(factors, vec![(BigInt::from(274177), 1), (BigInt::from(67280421310721u64), 1)]);
+
+        // Square of a large prime
+        let p = BigInt::from(1_000_000_007);
+        let factors: Vec<(BigInt, u32)> = factorize(&(&p * &p * 3), &mut StepCounter::new(u64::MAX)).expect("factors");
+        assert_eq!(factors, vec![(BigInt::from(3), 1), (p, 2)]);
+    }
+
+    #[test]
+    fn test_30006_factorize_error() {
+        assert!(factorize(&BigInt::zero(), &mut StepCounter::new(u64::MAX)).is_err());
+        assert!(factorize(&BigInt::from(-5), &mut StepCounter::new(u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_30007_factorize_step_count_limit() {
+        // 2^64+1 = 274177 * 67280421310721, the factor is beyond the trial division limit
+        let n = BigInt::from_str("18446744073709551617").unwrap();
+        let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::GreatestPrimeFactor);
+        let (output, step_count) = f.run_with_step_count_limit(vec![n.clone()], u64::MAX, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect("output");
+        assert_eq!(output, vec![BigInt::from(67280421310721u64)]);
+        assert!(step_count > 1000);
+        let error = f.run_with_step_count_limit(vec![n], 1000, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect_err("exceeds limit");
+        assert!(error.downcast_ref::<EvalError>().is_some());
+
+        // Small numbers are cheap
+        let (_output, step_count) = f.run_with_step_count_limit(vec![BigInt::from(12)], 1000, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited).expect("output");
+        assert_eq!(step_count, 1);
+    }
+
+    #[test]
+    fn test_40000_modular_inverse() {
+        {
+            let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::ModularInverse);
+            let v = perform_run(Box::new(f), vec![3, 11]).expect("output");
+            assert_eq!(v, vec![4]);
+        }
+        {
+            let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::ModularInverse);
+            let v = perform_run(Box::new(f), vec![-3, 11]).expect("output");
+            assert_eq!(v, vec![7]);
+        }
+        {
+            let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::ModularInverse);
+            _ = perform_run(Box::new(f), vec![4, 8]).expect_err("no inverse");
+        }
+        {
+            let f = NumberTheoryFunction::new(0, NumberTheoryFunctionMode::ModularInverse);
+            _ = perform_run(Box::new(f), vec![4, 0]).expect_err("invalid modulus");
+        }
+    }
+}
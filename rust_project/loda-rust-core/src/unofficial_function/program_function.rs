@@ -0,0 +1,287 @@
+use super::{is_builtin_function_id, UnofficialFunction, UnofficialFunctionId, UnofficialFunctionRegistry};
+use crate::execute::{NodeLoopLimit, NodeRegisterLimit, Program, ProgramCache, ProgramState, RunMode};
+use crate::execute::node_calc::NodeCalcSemanticMode;
+use crate::parser::{CreateProgram, InstructionId, ParsedProgram};
+use crate::parser::validate_loops::validate_loops;
+use num_bigint::BigInt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use anyhow::Context;
+
+lazy_static! {
+    // Extract the function signature from a filename like `f21_1100.asm`.
+    static ref EXTRACT_SIGNATURE_FROM_FILENAME: Regex = Regex::new(
+        "^f(\\d)(\\d)_(\\d+)\\.asm$"
+    ).unwrap();
+}
+
+/// An unofficial function where the body is a LODA program.
+///
+/// The N input values are placed in the registers `$0..$N-1`.
+/// After the program has run, the M output values are taken from the registers `$0..$M-1`.
+///
+/// The program cannot use `seq` or other unofficial functions.
+/// The steps used by the program are counted as steps of the calling program,
+/// and the program runs with the remaining step budget and the limits of the calling program.
+pub struct ProgramFunction {
+    id: u32,
+    inputs: u8,
+    outputs: u8,
+    name: String,
+    program: Program,
+}
+
+impl ProgramFunction {
+    /// Max number of steps for a single invocation of the function, when there is no caller with a step budget.
+    const STEP_COUNT_LIMIT: u64 = 10_000_000;
+
+    pub fn new(id: u32, inputs: u8, outputs: u8, name: String, parsed_program: ParsedProgram) -> anyhow::Result<Self> {
+        if is_builtin_function_id(id) {
+            return Err(anyhow::anyhow!("ProgramFunction {}: the id is reserved for the builtin functions, such as 1000..=1099 and 1200..=1299", id));
+        }
+        // The instruction is named `fNM`, where N and M are single digits.
+        if inputs > 9 || outputs > 9 {
+            return Err(anyhow::anyhow!("ProgramFunction {}: the number of inputs and outputs must be in the range 0..=9, but got f{}{}", id, inputs, outputs));
+        }
+        if outputs == 0 {
+            return Err(anyhow::anyhow!("ProgramFunction {}: expected 1 or more outputs", id));
+        }
+        validate_loops(&parsed_program.instruction_vec)
+            .map_err(|e| anyhow::anyhow!("ProgramFunction {}: invalid loops: {:?}", id, e))?;
+        for instruction in &parsed_program.instruction_vec {
+            match instruction.instruction_id {
+                InstructionId::EvalSequence | InstructionId::UnofficialFunction { .. } => {
+                    return Err(anyhow::anyhow!("ProgramFunction {}: line {}: the instruction {} cannot be used inside a function", id, instruction.line_number, instruction.instruction_id));
+                },
+                _ => {}
+            }
+        }
+        // Create the program when registering, so that errors are reported here and not when running.
+        let program: Program = Self::create_program(&parsed_program)
+            .with_context(|| format!("ProgramFunction {}", id))?;
+        Ok(Self {
+            id,
+            inputs,
+            outputs,
+            name,
+            program,
+        })
+    }
+
+    /// Load a function from a file named like `f21_1100.asm`, a function with 2 inputs, 1 output and the id 1100.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let filename: &str = path.file_name()
+            .and_then(|value| value.to_str())
+            .with_context(|| format!("ProgramFunction cannot obtain filename: {:?}", path))?;
+        let captures = EXTRACT_SIGNATURE_FROM_FILENAME.captures(filename)
+            .with_context(|| format!("ProgramFunction expected filename like 'f21_1100.asm', but got: {:?}", path))?;
+        let inputs: u8 = captures[1].parse::<u8>()?;
+        let outputs: u8 = captures[2].parse::<u8>()?;
+        let id: u32 = captures[3].parse::<u32>()
+            .with_context(|| format!("ProgramFunction id is out of range: {:?}", path))?;
+        let contents: String = fs::read_to_string(path)
+            .with_context(|| format!("ProgramFunction cannot read file: {:?}", path))?;
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(&contents)
+            .map_err(|e| anyhow::anyhow!("ProgramFunction cannot parse file: {:?} error: {}", path, e))?;
+        let name: String = format!("Program {}", filename);
+        Self::new(id, inputs, outputs, name, parsed_program)
+            .with_context(|| format!("ProgramFunction invalid file: {:?}", path))
+    }
+
+    fn create_program(parsed_program: &ParsedProgram) -> anyhow::Result<Program> {
+        // The program cannot use unofficial functions, so there is no need for a populated registry.
+        let registry = UnofficialFunctionRegistry::new();
+        let create_program = CreateProgram::new(NodeCalcSemanticMode::Unlimited);
+        create_program.create_program(parsed_program, &registry)
+            .map_err(|e| anyhow::anyhow!("cannot create program: {}", e))
+    }
+}
+
+impl UnofficialFunction for ProgramFunction {
+    fn id(&self) -> UnofficialFunctionId {
+        UnofficialFunctionId::InputOutput { id: self.id, inputs: self.inputs, outputs: self.outputs }
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn run(&self, input: Vec<BigInt>) -> anyhow::Result<Vec<BigInt>> {
+        let (output, _step_count) = self.run_with_step_count_limit(input, Self::STEP_COUNT_LIMIT, &NodeRegisterLimit::Unlimited, &NodeLoopLimit::Unlimited)?;
+        Ok(output)
+    }
+
+    fn run_with_step_count_limit(&self, input: Vec<BigInt>, step_count_limit: u64, node_register_limit: &NodeRegisterLimit, node_loop_limit: &NodeLoopLimit) -> anyhow::Result<(Vec<BigInt>, u64)> {
+        if input.len() != (self.inputs as usize) {
+            return Err(anyhow::anyhow!("ProgramFunction {}: expected {} inputs, but got {}", self.id, self.inputs, input.len()));
+        }
+        let mut state = ProgramState::new(
+            RunMode::Silent,
+            step_count_limit.min(Self::STEP_COUNT_LIMIT),
+            node_register_limit.clone(),
+            node_loop_limit.clone(),
+        );
+        for (index, value) in input.into_iter().enumerate() {
+            state.set_u64(index as u64, value)?;
+        }
+        let mut cache = ProgramCache::new();
+        self.program.run(&mut state, &mut cache)?;
+        let mut output: Vec<BigInt> = Vec::with_capacity(self.outputs as usize);
+        for index in 0..self.outputs {
+            output.push(state.get_u64(index as u64).into_owned());
+        }
+        Ok((output, state.step_count()))
+    }
+}
+
+/// Register all the functions in a dir, with filenames like `f21_1100.asm`.
+///
+/// Returns an error if a function has the same id and arity as an already registered function.
+///
+/// Returns the number of registered functions.
+pub fn register_program_functions(registry: &UnofficialFunctionRegistry, dir: &Path) -> anyhow::Result<usize> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .with_context(|| format!("register_program_functions cannot read dir: {:?}", dir))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "asm"))
+        .collect();
+    paths.sort();
+    for path in &paths {
+        let function = ProgramFunction::load(path)?;
+        registry.try_register(Arc::new(Box::new(function)))
+            .with_context(|| format!("register_program_functions cannot register: {:?}", path))?;
+    }
+    Ok(paths.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::perform_run;
+    use crate::control::{DependencyManager, DependencyManagerFileSystemMode};
+    use crate::execute::{EvalError, ProgramId, ProgramRunner, RegisterValue};
+    use std::path::PathBuf;
+
+    fn create_function(id: u32, inputs: u8, outputs: u8, program: &str) -> anyhow::Result<ProgramFunction> {
+        let parsed_program: ParsedProgram = ParsedProgram::parse_program(program).expect("parsed program");
+        ProgramFunction::new(id, inputs, outputs, "test".to_string(), parsed_program)
+    }
+
+    #[test]
+    fn test_10000_run() {
+        {
+            let f = create_function(1100, 2, 1, "mul $0,$1").expect("function");
+            let v = perform_run(Box::new(f), vec![7, 3]).expect("output");
+            assert_eq!(v, vec![21]);
+        }
+        {
+            // Divide and remainder
+            let f = create_function(1101, 2, 2, "mov $2,$0\ndiv $0,$1\nmod $2,$1\nmov $1,$2").expect("function");
+            let v = perform_run(Box::new(f), vec![17, 5]).expect("output");
+            assert_eq!(v, vec![3, 2]);
+        }
+    }
+
+    #[test]
+    fn test_10001_run_error() {
+        let f = create_function(1100, 2, 1, "div $0,$1").expect("function");
+        assert!(perform_run(Box::new(f), vec![7, 0]).is_err());
+    }
+
+    #[test]
+    fn test_10002_caller_step_count_limit() {
+        // Arrange
+        let registry = UnofficialFunctionRegistry::new();
+        let f = create_function(1100, 1, 1, "mov $1,$0\nlpb $1\n  sub $1,1\n  add $0,1\nlpe").expect("function");
+        registry.try_register(Arc::new(Box::new(f))).expect("ok");
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            registry,
+        );
+        let runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, "f11 $0,1100").expect("runner");
+        let run = |input: i64, step_count_limit: u64| -> (anyhow::Result<RegisterValue>, u64) {
+            let mut cache = ProgramCache::new();
+            let mut step_count: u64 = 0;
+            let result = runner.run(
+                RegisterValue::from_i64(input),
+                RunMode::Silent,
+                &mut step_count,
+                step_count_limit,
+                NodeRegisterLimit::Unlimited,
+                NodeLoopLimit::Unlimited,
+                &mut cache,
+            );
+            (result, step_count)
+        };
+
+        // Act
+        let (result0, step_count0) = run(10, 1000);
+        let (result1, _step_count1) = run(1000, 1000);
+
+        // Assert
+        assert_eq!(result0.expect("output"), RegisterValue::from_i64(20));
+        assert!(step_count0 > 20);
+        let error = result1.expect_err("exceeds the step count limit of the caller");
+        assert!(error.chain().any(|cause| matches!(cause.downcast_ref::<EvalError>(), Some(EvalError::StepCountExceededLimit))));
+    }
+
+    #[test]
+    fn test_20000_new_error() {
+        assert!(create_function(1100, 10, 1, "mov $0,1").is_err());
+        assert!(create_function(1100, 1, 0, "mov $0,1").is_err());
+        assert!(create_function(1100, 1, 1, "seq $0,40").is_err());
+        assert!(create_function(1100, 1, 1, "f11 $0,1100").is_err());
+        assert!(create_function(1100, 1, 1, "lpb $0").is_err());
+    }
+
+    #[test]
+    fn test_20001_new_error_builtin_id() {
+        assert!(create_function(1, 1, 1, "mov $0,1").is_err());
+        assert!(create_function(1000, 2, 1, "mov $0,1").is_err());
+        assert!(create_function(1035, 2, 1, "mov $0,1").is_err());
+        assert!(create_function(1200, 1, 1, "mov $0,1").is_err());
+        assert!(create_function(1199, 1, 1, "mov $0,1").is_ok());
+        assert!(create_function(2, 1, 1, "mov $0,1").is_ok());
+    }
+
+    #[test]
+    fn test_30000_load_and_register_dir() {
+        // Arrange
+        let tempdir = tempfile::tempdir().unwrap();
+        let basedir = PathBuf::from(&tempdir.path()).join("test_30000_load_and_register_dir");
+        fs::create_dir(&basedir).expect("ok");
+        fs::write(basedir.join("f21_1100.asm"), "; multiply\nmul $0,$1\n").expect("ok");
+        fs::write(basedir.join("f11_1101.asm"), "pow $0,2\n").expect("ok");
+        fs::write(basedir.join("readme.txt"), "ignored").expect("ok");
+        let registry = UnofficialFunctionRegistry::new();
+
+        // Act
+        let count: usize = register_program_functions(&registry, &basedir).expect("ok");
+
+        // Assert
+        assert_eq!(count, 2);
+        let mut dm = DependencyManager::new(
+            DependencyManagerFileSystemMode::Virtual,
+            PathBuf::from("non-existing-dir"),
+            registry.clone(),
+        );
+        let runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, "mov $1,$0\nadd $1,1\nf21 $0,1100\nf11 $0,1101").expect("runner");
+        assert_eq!(runner.inspect(5), "0,4,36,144,400");
+
+        // Registering the same functions again is a collision
+        assert!(register_program_functions(&registry, &basedir).is_err());
+    }
+
+    #[test]
+    fn test_30001_load_invalid_filename() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = PathBuf::from(&tempdir.path()).join("multiply.asm");
+        fs::write(&path, "mul $0,$1\n").expect("ok");
+        assert!(ProgramFunction::load(&path).is_err());
+    }
+}
@@ -1,6 +1,20 @@
-use super::{AssertFunction, AssertFunctionMode, DebugFunction, ProductFunction, SortFunction, SumFunction, UnofficialFunctionRegistry};
+use super::{AssertFunction, AssertFunctionMode, DebugFunction, NumberTheoryFunction, NumberTheoryFunctionMode, ProductFunction, SortFunction, SumFunction, UnofficialFunctionRegistry};
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 
+/// The ids that are reserved for the builtin functions.
+/// 
+/// Functions loaded from files cannot use these ids, so they don't shadow a builtin function.
+const BUILTIN_FUNCTION_ID_RANGES: [RangeInclusive<u32>; 3] = [
+    1..=1,
+    1000..=1099,
+    1200..=1299,
+];
+
+pub fn is_builtin_function_id(id: u32) -> bool {
+    BUILTIN_FUNCTION_ID_RANGES.iter().any(|range| range.contains(&id))
+}
+
 pub fn register_common_functions(registry: &UnofficialFunctionRegistry) {
     macro_rules! register_function {
         ($create_instance:expr) => {
@@ -40,4 +54,18 @@ pub fn register_common_functions(registry: &UnofficialFunctionRegistry) {
         register_function!(AssertFunction::new(1034, AssertFunctionMode::GreaterThan));
         register_function!(AssertFunction::new(1035, AssertFunctionMode::GreaterThanOrEqual));
     }
+
+    // Number theory functions
+    {
+        register_function!(NumberTheoryFunction::new(1200, NumberTheoryFunctionMode::IsPrime));
+        register_function!(NumberTheoryFunction::new(1201, NumberTheoryFunctionMode::NthPrime));
+        register_function!(NumberTheoryFunction::new(1202, NumberTheoryFunctionMode::EulerPhi));
+        register_function!(NumberTheoryFunction::new(1203, NumberTheoryFunctionMode::DivisorCount));
+        register_function!(NumberTheoryFunction::new(1204, NumberTheoryFunctionMode::DivisorSum));
+        register_function!(NumberTheoryFunction::new(1205, NumberTheoryFunctionMode::Moebius));
+        register_function!(NumberTheoryFunction::new(1206, NumberTheoryFunctionMode::SmallestPrimeFactor));
+        register_function!(NumberTheoryFunction::new(1207, NumberTheoryFunctionMode::GreatestPrimeFactor));
+        register_function!(NumberTheoryFunction::new(1208, NumberTheoryFunctionMode::PrimeFactorCount));
+        register_function!(NumberTheoryFunction::new(1209, NumberTheoryFunctionMode::ModularInverse));
+    }
 }
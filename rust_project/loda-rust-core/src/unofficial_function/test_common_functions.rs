@@ -221,6 +221,100 @@ mod tests {
         _ = run(program, 0).expect_err("should fail");
     }
 
+    #[test]
+    fn test_40000_is_prime() {
+        let program = "
+        f11 $0,1200 ; Is prime
+        ";
+        assert_eq!(run(program, 97).expect("output"), 1);
+        assert_eq!(run(program, 91).expect("output"), 0);
+    }
+
+    #[test]
+    fn test_40001_nth_prime() {
+        let program = "
+        f11 $0,1201 ; The n'th prime
+        ";
+        assert_eq!(run(program, 1).expect("output"), 2);
+        assert_eq!(run(program, 100).expect("output"), 541);
+    }
+
+    #[test]
+    fn test_40002_euler_phi() {
+        let program = "
+        f11 $0,1202 ; Euler totient function
+        ";
+        assert_eq!(run(program, 36).expect("output"), 12);
+    }
+
+    #[test]
+    fn test_40003_divisor_count() {
+        let program = "
+        f11 $0,1203 ; Number of divisors
+        ";
+        assert_eq!(run(program, 36).expect("output"), 9);
+    }
+
+    #[test]
+    fn test_40004_divisor_sum() {
+        let program = "
+        f11 $0,1204 ; Sum of divisors
+        ";
+        assert_eq!(run(program, 36).expect("output"), 91);
+    }
+
+    #[test]
+    fn test_40005_moebius() {
+        let program = "
+        f11 $0,1205 ; Moebius function
+        ";
+        assert_eq!(run(program, 30).expect("output"), -1);
+        assert_eq!(run(program, 36).expect("output"), 0);
+    }
+
+    #[test]
+    fn test_40006_smallest_prime_factor() {
+        let program = "
+        f11 $0,1206 ; Smallest prime factor
+        ";
+        assert_eq!(run(program, 91).expect("output"), 7);
+    }
+
+    #[test]
+    fn test_40007_greatest_prime_factor() {
+        let program = "
+        f11 $0,1207 ; Greatest prime factor
+        ";
+        assert_eq!(run(program, 91).expect("output"), 13);
+    }
+
+    #[test]
+    fn test_40008_prime_factor_count() {
+        let program = "
+        f11 $0,1208 ; Number of prime factors with multiplicity
+        ";
+        assert_eq!(run(program, 72).expect("output"), 5);
+    }
+
+    #[test]
+    fn test_40009_modular_inverse() {
+        let program = "
+        mov $1,11
+        f21 $0,1209 ; Modular inverse
+        ";
+        assert_eq!(run(program, 3).expect("output"), 4);
+        assert_eq!(run(program, 5).expect("output"), 9);
+        _ = run(program, 0).expect_err("no inverse");
+    }
+
+    #[test]
+    fn test_40010_number_theory_error() {
+        let program = "
+        f11 $0,1202 ; Euler totient function
+        ";
+        _ = run(program, 0).expect_err("phi(0) is undefined");
+    }
+
     /// Run program with 1 input and 1 output
     fn run<S: AsRef<str>>(program: S, input: i64) -> anyhow::Result<i64> {
         let program_str: &str = program.as_ref();
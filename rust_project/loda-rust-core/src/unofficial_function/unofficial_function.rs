@@ -1,8 +1,20 @@
 use super::UnofficialFunctionId;
+use crate::execute::{NodeLoopLimit, NodeRegisterLimit};
 use num_bigint::BigInt;
 
 pub trait UnofficialFunction: Send + Sync {
     fn id(&self) -> UnofficialFunctionId;
     fn name(&self) -> String;
     fn run(&self, input: Vec<BigInt>) -> anyhow::Result<Vec<BigInt>>;
+
+    /// Same as `run`, but the function is aborted when it exceeds the `step_count_limit`.
+    /// 
+    /// The register limit and the loop limit are the ones of the caller, for functions that run a program.
+    /// 
+    /// Returns the output and the number of steps used, that are added to the step count of the caller.
+    /// Most functions are cheap and count as a single step.
+    fn run_with_step_count_limit(&self, input: Vec<BigInt>, _step_count_limit: u64, _node_register_limit: &NodeRegisterLimit, _node_loop_limit: &NodeLoopLimit) -> anyhow::Result<(Vec<BigInt>, u64)> {
+        let output: Vec<BigInt> = self.run(input)?;
+        Ok((output, 1))
+    }
 }
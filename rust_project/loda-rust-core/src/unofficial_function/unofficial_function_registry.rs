@@ -37,6 +37,17 @@ impl UnofficialFunctionRegistry {
         }
     }
 
+    /// Register a function, unless there already is a function with the same id and arity.
+    pub fn try_register(&self, plugin: Arc<Box<dyn UnofficialFunction>>) -> anyhow::Result<()> {
+        let key: UnofficialFunctionId = plugin.id();
+        let mut inner = self.inner.write().expect("UnofficialFunctionRegistry.try_register() RwLock poisoned");
+        if let Some(existing) = inner.plugin_dict.get(&key) {
+            return Err(anyhow::anyhow!("UnofficialFunctionRegistry.try_register({:?}) collision with existing function: {:?}", key, existing.name()));
+        }
+        inner.plugin_dict.insert(key, plugin);
+        Ok(())
+    }
+
     pub fn lookup(&self, key: UnofficialFunctionId) -> Option<Arc<Box<dyn UnofficialFunction>>> {
         let inner = self.inner.read().expect("UnofficialFunctionRegistry.lookup() RwLock poisoned");
         if let Some(value) = inner.plugin_dict.get(&key) {
@@ -74,6 +85,16 @@ mod tests {
         assert_eq!(output_vec, expected_output_vec);
     }
 
+    #[test]
+    fn test_10001_try_register_collision() {
+        let registry = UnofficialFunctionRegistry::new();
+        registry.try_register(Arc::new(Box::new(SumFunction::new(1234, 2)))).expect("ok");
+        // Same id, different arity
+        registry.try_register(Arc::new(Box::new(SumFunction::new(1234, 3)))).expect("ok");
+        // Same id, same arity
+        assert!(registry.try_register(Arc::new(Box::new(SumFunction::new(1234, 2)))).is_err());
+    }
+
     #[test]
     fn test_20000_clone() {
         // Arrange
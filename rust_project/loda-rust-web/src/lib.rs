@@ -15,6 +15,7 @@ use loda_rust_core;
 
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::Arc;
 use core::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -22,7 +23,7 @@ use loda_rust_core::control::{DependencyManager,DependencyManagerFileSystemMode}
 use loda_rust_core::execute::{NodeLoopLimit, ProgramCache, ProgramId, ProgramRunner, RegisterValue, RunMode};
 use loda_rust_core::execute::NodeRegisterLimit;
 use loda_rust_core::unofficial_function::UnofficialFunctionRegistry;
-use loda_rust_core::parser::ParsedProgram;
+use loda_rust_core::parser::{diagnose_program, Diagnostic, ParsedProgram};
 
 
 #[derive(Clone)]
@@ -113,6 +114,32 @@ pub fn perform_selfcheck() {
     info!("Selfcheck success");
 }
 
+/// Syntax problems in the source code, for highlighting in the editor.
+/// 
+/// Returns an array of objects like this:
+/// `{ severity: "error", line: 3, column: 1, length: 3, token: "mvo", message: "...", suggestions: ["did you mean `mov`?"] }`
+#[wasm_bindgen]
+pub fn diagnose_source_code(source_code: String) -> Result<JsValue, JsValue> {
+    let diagnostics: Vec<Diagnostic> = diagnose_program(&source_code);
+    let array = js_sys::Array::new();
+    for diagnostic in &diagnostics {
+        let object = js_sys::Object::new();
+        js_sys::Reflect::set(&object, &"severity".into(), &diagnostic.severity.to_string().into())?;
+        js_sys::Reflect::set(&object, &"line".into(), &JsValue::from(diagnostic.span.line_number as u32))?;
+        js_sys::Reflect::set(&object, &"column".into(), &JsValue::from(diagnostic.span.column as u32))?;
+        js_sys::Reflect::set(&object, &"length".into(), &JsValue::from(diagnostic.span.length as u32))?;
+        js_sys::Reflect::set(&object, &"token".into(), &diagnostic.token.as_str().into())?;
+        js_sys::Reflect::set(&object, &"message".into(), &diagnostic.message.as_str().into())?;
+        let suggestions = js_sys::Array::new();
+        for suggestion in &diagnostic.suggestions {
+            suggestions.push(&suggestion.as_str().into());
+        }
+        js_sys::Reflect::set(&object, &"suggestions".into(), &suggestions)?;
+        array.push(&object);
+    }
+    Ok(array.into())
+}
+
 trait MyPrintTerms {
     fn my_print_terms(&self, count: u64);
 }
@@ -165,7 +192,7 @@ struct WebDependencyManagerInner {
     cache: ProgramCache,
     step_count: u64,
     outputted_byte_count: u64,
-    program_runner: Rc::<ProgramRunner>,
+    program_runner: Arc::<ProgramRunner>,
 }
 
 impl WebDependencyManagerInner {
@@ -179,7 +206,7 @@ impl WebDependencyManagerInner {
 
         let dummy_program: &str = "mov $1,$0";
         let dummy_runner: ProgramRunner = dm.parse(ProgramId::ProgramWithoutId, dummy_program).unwrap();
-        let program_runner: Rc::<ProgramRunner> = Rc::new(dummy_runner);
+        let program_runner: Arc::<ProgramRunner> = Arc::new(dummy_runner);
 
         WebDependencyManagerInner {
             count: 0,
@@ -197,7 +224,11 @@ impl WebDependencyManagerInner {
             Ok(value) => value,
             Err(error) => {
                 error!("Unable to parse program: {:?}", error);
-                let s = format!("{}", error);
+                let diagnostics: Vec<Diagnostic> = diagnose_program(&root_source_code);
+                let s: String = match diagnostics.first() {
+                    Some(diagnostic) => diagnostic.render(&root_source_code),
+                    None => format!("{}", error)
+                };
                 let err = JsValue::from_str(&s);
                 return Err(err);
             }
@@ -311,7 +342,7 @@ impl WebDependencyManagerInner {
                 return Err(err);
             }
         };
-        let runner: Rc::<ProgramRunner> = Rc::new(runner1);
+        let runner: Arc::<ProgramRunner> = Arc::new(runner1);
         self.program_runner = runner;
 
         self.step_count = 0;